@@ -12,11 +12,18 @@ mkdir -p plugins
 
 # Plugin 1: String Reverser
 echo ""
-echo "📦 Building plugin-example (String Reverser)..."
-cargo build --target wasm32-unknown-unknown --release -p plugin-example
-cp target/wasm32-unknown-unknown/release/plugin_example.wasm plugins/reverser.wasm
+echo "📦 Building plugin-reverser (String Reverser)..."
+cargo build --target wasm32-unknown-unknown --release -p plugin-reverser
+cp target/wasm32-unknown-unknown/release/plugin_reverser.wasm plugins/reverser.wasm
 echo "✓ reverser.wasm → plugins/reverser.wasm"
 
+# Plugin 1b: Example (reverser that also echoes env)
+echo ""
+echo "📦 Building plugin-example..."
+cargo build --target wasm32-unknown-unknown --release -p plugin-example
+cp target/wasm32-unknown-unknown/release/plugin_example.wasm plugins/example.wasm
+echo "✓ example.wasm → plugins/example.wasm"
+
 # Plugin 2: Uppercase Converter
 echo ""
 echo "📦 Building plugin-uppercase..."
@@ -45,6 +52,98 @@ cargo build --target wasm32-unknown-unknown --release -p plugin-env-reader
 cp target/wasm32-unknown-unknown/release/plugin_env_reader.wasm plugins/env-reader.wasm
 echo "✓ env-reader.wasm → plugins/env-reader.wasm"
 
+# Plugin 6: Base64
+echo ""
+echo "📦 Building plugin-base64..."
+cargo build --target wasm32-unknown-unknown --release -p plugin-base64
+cp target/wasm32-unknown-unknown/release/plugin_base64.wasm plugins/base64.wasm
+echo "✓ base64.wasm → plugins/base64.wasm"
+
+# Plugin 7: JSON Transform
+echo ""
+echo "📦 Building plugin-json-transform..."
+cargo build --target wasm32-unknown-unknown --release -p plugin-json-transform
+cp target/wasm32-unknown-unknown/release/plugin_json_transform.wasm plugins/json-transform.wasm
+echo "✓ json-transform.wasm → plugins/json-transform.wasm"
+
+# Plugin 8: Lowercase (length-return output)
+echo ""
+echo "📦 Building plugin-lowercase..."
+cargo build --target wasm32-unknown-unknown --release -p plugin-lowercase
+cp target/wasm32-unknown-unknown/release/plugin_lowercase.wasm plugins/lowercase.wasm
+echo "✓ lowercase.wasm → plugins/lowercase.wasm"
+
+# Plugin 9: Fuel Budget
+echo ""
+echo "📦 Building plugin-fuel-budget..."
+cargo build --target wasm32-unknown-unknown --release -p plugin-fuel-budget
+cp target/wasm32-unknown-unknown/release/plugin_fuel_budget.wasm plugins/fuel-budget.wasm
+echo "✓ fuel-budget.wasm → plugins/fuel-budget.wasm"
+
+# Plugin 10: SHA-256
+echo ""
+echo "📦 Building plugin-sha256..."
+cargo build --target wasm32-unknown-unknown --release -p plugin-sha256
+cp target/wasm32-unknown-unknown/release/plugin_sha256.wasm plugins/sha256.wasm
+echo "✓ sha256.wasm → plugins/sha256.wasm"
+
+# Plugin 11: Word Count
+echo ""
+echo "📦 Building plugin-wordcount..."
+cargo build --target wasm32-unknown-unknown --release -p plugin-wordcount
+cp target/wasm32-unknown-unknown/release/plugin_wordcount.wasm plugins/wordcount.wasm
+echo "✓ wordcount.wasm → plugins/wordcount.wasm"
+
+# Plugin 12: Rate Limiter
+echo ""
+echo "📦 Building plugin-rate-limiter..."
+cargo build --target wasm32-unknown-unknown --release -p plugin-rate-limiter
+cp target/wasm32-unknown-unknown/release/plugin_rate_limiter.wasm plugins/rate-limiter.wasm
+echo "✓ rate-limiter.wasm → plugins/rate-limiter.wasm"
+
+# Plugin 13: URL Encode
+echo ""
+echo "📦 Building plugin-urlencode..."
+cargo build --target wasm32-unknown-unknown --release -p plugin-urlencode
+cp target/wasm32-unknown-unknown/release/plugin_urlencode.wasm plugins/urlencode.wasm
+echo "✓ urlencode.wasm → plugins/urlencode.wasm"
+
+# Plugin 14: Multi-Output
+echo ""
+echo "📦 Building plugin-multi-output..."
+cargo build --target wasm32-unknown-unknown --release -p plugin-multi-output
+cp target/wasm32-unknown-unknown/release/plugin_multi_output.wasm plugins/multi-output.wasm
+echo "✓ multi-output.wasm → plugins/multi-output.wasm"
+
+# Plugin 15: Memory Grow
+echo ""
+echo "📦 Building plugin-memory-grow..."
+cargo build --target wasm32-unknown-unknown --release -p plugin-memory-grow
+cp target/wasm32-unknown-unknown/release/plugin_memory_grow.wasm plugins/memory-grow.wasm
+echo "✓ memory-grow.wasm → plugins/memory-grow.wasm"
+
+# Plugin 16: Hexdump
+echo ""
+echo "📦 Building plugin-hexdump..."
+cargo build --target wasm32-unknown-unknown --release -p plugin-hexdump
+cp target/wasm32-unknown-unknown/release/plugin_hexdump.wasm plugins/hexdump.wasm
+echo "✓ hexdump.wasm → plugins/hexdump.wasm"
+
+# Plugin 17: CRC-32
+echo ""
+echo "📦 Building plugin-crc32..."
+cargo build --target wasm32-unknown-unknown --release -p plugin-crc32
+cp target/wasm32-unknown-unknown/release/plugin_crc32.wasm plugins/crc32.wasm
+echo "✓ crc32.wasm → plugins/crc32.wasm"
+
+# Plugin 18: Host SHA-256 (its manifest grants the crypto capability)
+echo ""
+echo "📦 Building plugin-host-sha256..."
+cargo build --target wasm32-unknown-unknown --release -p plugin-host-sha256
+cp target/wasm32-unknown-unknown/release/plugin_host_sha256.wasm plugins/host-sha256.wasm
+cp plugin-host-sha256/host-sha256.toml plugins/host-sha256.toml
+echo "✓ host-sha256.wasm → plugins/host-sha256.wasm"
+
 
 echo ""
 echo "━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━"
@@ -0,0 +1,182 @@
+use anyhow::{Context, Result};
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use wasm_shared::{ErrorResponse, ExecuteRequest};
+
+use crate::socket_client::SocketClient;
+
+/// Outcome of a benchmark run. Latencies are the server-reported
+/// `execution_time_ms` of the successful executions.
+#[derive(Debug)]
+pub struct BenchReport {
+    pub executions: usize,
+    pub failures: usize,
+    pub elapsed: Duration,
+    pub p50_ms: u64,
+    pub p95_ms: u64,
+    pub p99_ms: u64,
+}
+
+impl BenchReport {
+    /// Completed executions, failed or not, per second of wall-clock time
+    pub fn executions_per_sec(&self) -> f64 {
+        (self.executions + self.failures) as f64 / self.elapsed.as_secs_f64().max(f64::EPSILON)
+    }
+}
+
+/// Send `request` `iterations` times over `concurrency` connections to the
+/// server at `path`, each connection running one execution at a time.
+/// Execution errors count as failures; losing a connection aborts the run.
+pub async fn run(
+    path: &Path,
+    request: ExecuteRequest,
+    iterations: usize,
+    concurrency: usize,
+) -> Result<BenchReport> {
+    let mut clients = Vec::new();
+    for _ in 0..concurrency.clamp(1, iterations.max(1)) {
+        clients.push(SocketClient::connect_to(path).await?);
+    }
+
+    let next = Arc::new(AtomicUsize::new(0));
+    let started = Instant::now();
+    let workers: Vec<_> = clients
+        .into_iter()
+        .map(|mut client| {
+            let next = next.clone();
+            let request = request.clone();
+            tokio::spawn(async move {
+                let mut latencies = Vec::new();
+                let mut failures = 0;
+                while next.fetch_add(1, Ordering::Relaxed) < iterations {
+                    match client.execute_request(request.clone()).await {
+                        Ok(response) => latencies.push(response.result.execution_time_ms),
+                        Err(e) if e.downcast_ref::<ErrorResponse>().is_some() => failures += 1,
+                        Err(e) => return Err(e),
+                    }
+                }
+                Ok((latencies, failures))
+            })
+        })
+        .collect();
+
+    let mut latencies = Vec::with_capacity(iterations);
+    let mut failures = 0;
+    for worker in workers {
+        let (worker_latencies, worker_failures) =
+            worker.await.context("Benchmark worker panicked")??;
+        latencies.extend(worker_latencies);
+        failures += worker_failures;
+    }
+    let elapsed = started.elapsed();
+
+    latencies.sort_unstable();
+    Ok(BenchReport {
+        executions: latencies.len(),
+        failures,
+        elapsed,
+        p50_ms: percentile(&latencies, 50),
+        p95_ms: percentile(&latencies, 95),
+        p99_ms: percentile(&latencies, 99),
+    })
+}
+
+/// Nearest-rank percentile of already sorted values, 0 when there are none
+fn percentile(sorted: &[u64], p: usize) -> u64 {
+    if sorted.is_empty() {
+        return 0;
+    }
+    let rank = (p * sorted.len()).div_ceil(100).max(1);
+    sorted[rank - 1]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::{SinkExt, StreamExt};
+    use tokio::net::UnixListener;
+    use tokio_util::codec::{Framed, LinesCodec};
+
+    use crate::socket_client::hello_response;
+    use uuid::Uuid;
+    use wasm_shared::*;
+
+    #[test]
+    fn test_percentile() {
+        let values: Vec<u64> = (1..=100).collect();
+        assert_eq!(percentile(&values, 50), 50);
+        assert_eq!(percentile(&values, 99), 99);
+        assert_eq!(percentile(&[7], 95), 7);
+        assert_eq!(percentile(&[], 50), 0);
+    }
+
+    #[tokio::test]
+    async fn test_tiny_benchmark() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("wasm-core.sock");
+        let listener = UnixListener::bind(&path).unwrap();
+
+        // Answer every execute with a result that took 3ms
+        tokio::spawn(async move {
+            loop {
+                let (stream, _) = listener.accept().await.unwrap();
+                tokio::spawn(async move {
+                    let mut framed = Framed::new(stream, LinesCodec::new());
+                    while let Some(Ok(line)) = framed.next().await {
+                        if let Ok(Command::Hello { .. }) = serde_json::from_str(&line) {
+                            let hello = hello_response();
+                            framed
+                                .send(serde_json::to_string(&hello).unwrap())
+                                .await
+                                .unwrap();
+                            continue;
+                        }
+                        let response = Response::Execute(Ok(ExecuteResponse {
+                            result: ExecutionResult {
+                                binary_id: Uuid::nil(),
+                                return_code: 0,
+                                output: "HI".to_string(),
+                                logs: Vec::new(),
+                                from_output_buffer: true,
+                                execution_time_ms: 3,
+                                fuel_consumed: 10,
+                                timed_out: false,
+                                correlation_id: None,
+                                encoding: Encoding::Utf8,
+                                applied_config: ExecutionConfig::default(),
+                                warnings: Vec::new(),
+                                cache_hit: false,
+                                output_truncated: false,
+                                outputs: Default::default(),
+                            },
+                        }));
+                        let json = serde_json::to_string(&response).unwrap();
+                        framed.send(json).await.unwrap();
+                    }
+                });
+            }
+        });
+
+        let request = ExecuteRequest {
+            binary_id: Uuid::nil(),
+            input: "hi".to_string(),
+            config: None,
+            stream: false,
+            request_id: None,
+            env: EnvVars::new(),
+            correlation_id: None,
+            namespace: None,
+            encoding: Encoding::Utf8,
+            deadline_unix_ms: None,
+            priority: 0,
+        };
+        let report = run(&path, request, 20, 4).await.unwrap();
+
+        assert_eq!(report.executions, 20);
+        assert_eq!(report.failures, 0);
+        assert_eq!((report.p50_ms, report.p95_ms, report.p99_ms), (3, 3, 3));
+        assert!(report.executions_per_sec() > 0.0);
+    }
+}
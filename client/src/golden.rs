@@ -0,0 +1,63 @@
+/// Compare a plugin's output against the contents of an expected-output file.
+/// Returns `None` when they match, otherwise a line diff of the two.
+///
+/// A single trailing newline on either side is ignored, since editors add
+/// one to files that plugin output usually doesn't end with.
+pub fn diff(expected: &str, actual: &str) -> Option<String> {
+    let expected = expected.strip_suffix('\n').unwrap_or(expected);
+    let actual = actual.strip_suffix('\n').unwrap_or(actual);
+    if expected == actual {
+        return None;
+    }
+
+    let old: Vec<&str> = expected.split('\n').collect();
+    let new: Vec<&str> = actual.split('\n').collect();
+
+    // lcs[i][j] is the longest common subsequence of old[i..] and new[j..]
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut out = String::from("--- expected\n+++ actual\n");
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            out.push_str(&format!(" {}\n", old[i]));
+            i += 1;
+            j += 1;
+        } else if i < old.len() && (j == new.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            out.push_str(&format!("-{}\n", old[i]));
+            i += 1;
+        } else {
+            out.push_str(&format!("+{}\n", new[j]));
+            j += 1;
+        }
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matching_output() {
+        assert_eq!(diff("HELLO\nWORLD", "HELLO\nWORLD"), None);
+        // Trailing newlines don't count
+        assert_eq!(diff("HELLO WORLD\n", "HELLO WORLD"), None);
+        assert_eq!(diff("HELLO WORLD", "HELLO WORLD\n"), None);
+    }
+
+    #[test]
+    fn test_mismatching_output() {
+        let diff = diff("one\ntwo\nthree\n", "one\n2\nthree").unwrap();
+        assert_eq!(diff, "--- expected\n+++ actual\n one\n-two\n+2\n three\n");
+    }
+}
@@ -1,10 +1,20 @@
+mod bench;
+mod golden;
+mod repl;
+mod scaffold;
 mod socket_client;
 
 use anyhow::Result;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine as _;
 use clap::{Parser, Subcommand};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 use uuid::Uuid;
-use wasm_shared::ExecutionConfig;
+use wasm_shared::{
+    Encoding, EnvVars, ErrorCode, ErrorResponse, ExecuteRequest, ExecutionConfig, IntegrityStatus,
+    LogLevel, ReloadStatus, TraceEventType, TraceFormat, TraceQuery, SOCKET_PATH,
+};
 
 use socket_client::*;
 
@@ -12,6 +22,14 @@ use socket_client::*;
 #[command(name = "wasm-client")]
 #[command(about = "Client for WASM Core Server", long_about = None)]
 struct Cli {
+    /// Retry the initial connection this many times (500ms apart) before giving up
+    #[arg(long, global = true, default_value = "0")]
+    connect_retries: u32,
+
+    /// Namespace to load, list, execute and unload binaries in
+    #[arg(long, global = true)]
+    namespace: Option<String>,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -21,6 +39,31 @@ enum Commands {
     Load {
         #[arg(short, long)]
         path: PathBuf,
+
+        /// File holding a detached Ed25519 signature over the wasm bytes
+        #[arg(short, long)]
+        signature: Option<PathBuf>,
+
+        /// Default timeout in ms for executions of this binary
+        #[arg(long)]
+        default_timeout: Option<u64>,
+
+        /// Default memory limit in MB for executions of this binary
+        #[arg(long)]
+        default_memory: Option<u64>,
+    },
+
+    /// Set the config used when executing a binary without one.
+    /// With neither limit given, the binary falls back to the server default.
+    SetDefault {
+        #[arg(short, long)]
+        binary_id: Uuid,
+
+        #[arg(short, long)]
+        timeout: Option<u64>,
+
+        #[arg(short, long)]
+        memory: Option<u64>,
     },
 
     Execute {
@@ -30,11 +73,81 @@ enum Commands {
         #[arg(short, long)]
         input: String,
 
-        #[arg(short, long, default_value = "5000")]
-        timeout: u64,
+        /// Timeout in ms (server default when omitted)
+        #[arg(short, long)]
+        timeout: Option<u64>,
+
+        /// Memory limit in MB (server default when omitted)
+        #[arg(short, long)]
+        memory: Option<u64>,
+
+        /// Print output chunks as the plugin emits them
+        #[arg(long)]
+        stream: bool,
 
-        #[arg(short, long, default_value = "64")]
-        memory: u64,
+        /// Extra env entry passed to the plugin (repeatable)
+        #[arg(short, long, value_name = "KEY=VALUE", value_parser = parse_env_pair)]
+        env: Vec<(String, String)>,
+
+        /// ID recorded on the server-side trace, for finding it with `traces`
+        #[arg(long)]
+        correlation_id: Option<String>,
+
+        /// How the input is encoded and the output should be: utf8, base64 or hex
+        #[arg(long, default_value = "utf8", value_parser = parse_encoding)]
+        encoding: Encoding,
+
+        /// Queue priority when the server is at its concurrency limit;
+        /// higher starts sooner
+        #[arg(long, default_value_t = 0)]
+        priority: u8,
+    },
+
+    /// Execute a binary and compare its output against an expected-output file
+    Test {
+        #[arg(short, long)]
+        binary_id: Uuid,
+
+        /// File whose contents are sent as input
+        #[arg(short, long)]
+        input: PathBuf,
+
+        /// File holding the output the binary should produce
+        #[arg(long)]
+        expect: PathBuf,
+
+        /// Timeout in ms (server default when omitted)
+        #[arg(short, long)]
+        timeout: Option<u64>,
+
+        /// Memory limit in MB (server default when omitted)
+        #[arg(short, long)]
+        memory: Option<u64>,
+
+        /// Extra env entry passed to the plugin (repeatable)
+        #[arg(short, long, value_name = "KEY=VALUE", value_parser = parse_env_pair)]
+        env: Vec<(String, String)>,
+    },
+
+    /// Load (unless already loaded) and execute a binary in one step
+    Run {
+        #[arg(short, long)]
+        path: PathBuf,
+
+        #[arg(short, long)]
+        input: String,
+
+        /// Timeout in ms (server default when omitted)
+        #[arg(short, long)]
+        timeout: Option<u64>,
+
+        /// Memory limit in MB (server default when omitted)
+        #[arg(short, long)]
+        memory: Option<u64>,
+
+        /// Extra env entry passed to the plugin (repeatable)
+        #[arg(short, long, value_name = "KEY=VALUE", value_parser = parse_env_pair)]
+        env: Vec<(String, String)>,
     },
 
     Chain {
@@ -44,11 +157,26 @@ enum Commands {
         #[arg(short, long)]
         input: String,
 
-        #[arg(short, long, default_value = "5000")]
-        timeout: u64,
+        /// Timeout in ms (server default when omitted)
+        #[arg(short, long)]
+        timeout: Option<u64>,
 
-        #[arg(short, long, default_value = "64")]
-        memory: u64,
+        /// Memory limit in MB (server default when omitted)
+        #[arg(short, long)]
+        memory: Option<u64>,
+
+        /// Env entry passed to every step (repeatable)
+        #[arg(short, long, value_name = "KEY=VALUE", value_parser = parse_env_pair)]
+        env: Vec<(String, String)>,
+
+        /// Env entry for a single step, numbered from 1 (repeatable)
+        #[arg(long, value_name = "STEP:KEY=VALUE", value_parser = parse_step_env)]
+        step_env: Vec<(usize, String, String)>,
+
+        /// Let a binary that appears more than once keep its scratch buffer
+        /// between steps
+        #[arg(long)]
+        persist_host_state: bool,
     },
 
     List,
@@ -57,23 +185,251 @@ enum Commands {
         #[arg(short, long)]
         binary_id: Uuid,
     },
+
+    /// Recompile every loaded binary whose file changed, keeping its ID
+    ReloadAll,
+
+    /// Write every loaded binary to a bundle file on the server, for `import`
+    /// on another server
+    Export {
+        /// Bundle path, as the server sees it
+        path: PathBuf,
+    },
+
+    /// Load every binary in a bundle written by `export`, keeping their IDs
+    Import {
+        /// Bundle path, as the server sees it
+        path: PathBuf,
+    },
+
+    /// Save a loaded binary's wasm bytes to a local file (the server must
+    /// set allow_binary_download)
+    Fetch {
+        binary_id: Uuid,
+        /// Where to write the wasm file
+        #[arg(short, long)]
+        output: PathBuf,
+    },
+
+    /// Check loaded binaries against their files on disk; exits 1 on any mismatch
+    Verify {
+        /// Only this binary; every loaded binary when omitted
+        #[arg(short, long)]
+        binary_id: Option<Uuid>,
+    },
+
+    /// Show the server's versions, engine features and default config
+    Info,
+
+    /// Measure fuel and time per input without keeping logs or output
+    Meter {
+        #[arg(short, long)]
+        binary_id: Uuid,
+
+        /// Representative input (repeatable)
+        #[arg(short, long, required = true)]
+        input: Vec<String>,
+
+        /// Timeout in ms (the built-in default when omitted)
+        #[arg(short, long)]
+        timeout: Option<u64>,
+
+        /// Memory limit in MB (the built-in default when omitted)
+        #[arg(short, long)]
+        memory: Option<u64>,
+    },
+
+    /// Measure latency percentiles and throughput of repeated executions
+    Bench {
+        #[arg(short, long)]
+        binary_id: Uuid,
+
+        #[arg(short, long, default_value = "")]
+        input: String,
+
+        /// Total executions to run
+        #[arg(short = 'n', long, default_value = "100")]
+        iterations: usize,
+
+        /// Connections executing in parallel
+        #[arg(short, long, default_value = "1")]
+        concurrency: usize,
+    },
+
+    /// Run a binary once, discarding the result, so later calls start warm
+    Warmup {
+        #[arg(short, long)]
+        binary_id: Uuid,
+
+        #[arg(short, long, default_value = "")]
+        input: String,
+    },
+
+    /// Show a binary's kind, memory, exports and required imports
+    Describe {
+        #[arg(short, long)]
+        binary_id: Uuid,
+    },
+
+    /// Abort a running execution by the request ID `execute` printed
+    Cancel {
+        #[arg(short, long)]
+        request_id: Uuid,
+    },
+
+    /// Show the server's execution audit log
+    Audit {
+        /// Only entries after this sequence number
+        #[arg(short, long)]
+        since: Option<u64>,
+    },
+
+    /// Show the server's recent execution traces
+    Traces {
+        /// Only traces of failed executions
+        #[arg(long)]
+        failures: bool,
+        /// Only traces containing an event of this type (repeatable), e.g. ExecutionError
+        #[arg(short, long = "event-type", value_name = "TYPE")]
+        event_types: Vec<TraceEventType>,
+        #[arg(short, long)]
+        binary_id: Option<Uuid>,
+        /// Only the trace of the execution sent with this correlation ID
+        #[arg(long)]
+        correlation_id: Option<String>,
+    },
+
+    /// Show a binary's most recent results, oldest first
+    History {
+        binary_id: Uuid,
+        /// Only this many of the most recent results
+        #[arg(short = 'n', long)]
+        limit: Option<usize>,
+    },
+
+    /// Show live execution stats until interrupted
+    Top {
+        /// Stop after this many snapshots
+        #[arg(short = 'n', long)]
+        count: Option<usize>,
+    },
+
+    /// Print the server's log lines as they are logged, until interrupted
+    Logs {
+        /// Follow new lines; the server keeps no log history to show instead
+        #[arg(short, long, required = true)]
+        follow: bool,
+        /// error, warn, info, debug or trace; more severe lines are shown too
+        #[arg(short, long, default_value = "info")]
+        level: LogLevel,
+    },
+
+    /// Print a binary's most recent trace, e.g. `--format folded | inferno-flamegraph`
+    ExportTrace {
+        #[arg(short, long)]
+        binary_id: Uuid,
+        /// json or folded
+        #[arg(short, long, default_value = "json")]
+        format: TraceFormat,
+    },
+
+    /// Run a traced execution again with its recorded input, env and config
+    Replay {
+        /// Trace ID as shown by `traces`
+        #[arg(short, long)]
+        trace_id: Uuid,
+    },
+
+    /// Read commands from stdin and run them over one connection until EOF
+    Repl,
+
+    /// Create a new no_std plugin crate from the template. Needs no server.
+    NewPlugin {
+        /// Crate name, e.g. plugin-word-count
+        name: String,
+
+        /// Directory to create the crate in
+        #[arg(long, default_value = ".")]
+        dir: PathBuf,
+    },
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
     let cli = Cli::parse();
-    let mut client = SocketClient::connect().await?;
+    if let Commands::NewPlugin { name, dir } = &cli.command {
+        match scaffold::new_plugin(dir, name) {
+            Ok(path) => {
+                println!("? Created plugin crate: {}", path.display());
+                println!("Add it to the workspace `members` to build it with the others:");
+                println!(
+                    "  cargo build --target wasm32-unknown-unknown --release -p {}",
+                    name
+                );
+            }
+            Err(e) => {
+                eprintln!("? Failed to create plugin: {}", e);
+                std::process::exit(1);
+            }
+        }
+        return Ok(());
+    }
+    let mut client = if cli.connect_retries > 0 {
+        SocketClient::connect_with_retry(cli.connect_retries + 1, Duration::from_millis(500))
+            .await?
+    } else {
+        SocketClient::connect().await?
+    }
+    .with_namespace(cli.namespace);
 
     match cli.command {
-        Commands::Load { path } => {
+        Commands::Load {
+            path,
+            signature,
+            default_timeout,
+            default_memory,
+        } => {
             println!("?? Loading binary: {}", path.display());
             println!();
 
-            match client.load_binary(path.to_string_lossy().to_string()).await {
+            let signature = match signature.map(std::fs::read).transpose() {
+                Ok(signature) => signature,
+                Err(e) => {
+                    eprintln!("? Failed to read signature file: {}", e);
+                    std::process::exit(1);
+                }
+            };
+
+            let default_config = request_config(default_timeout, default_memory);
+            match client
+                .load_binary(
+                    path.to_string_lossy().to_string(),
+                    signature,
+                    default_config,
+                )
+                .await
+            {
                 Ok(response) => {
                     println!("? Binary loaded successfully!");
                     println!("Binary ID: {}", response.binary_id);
                     println!("Size: {} bytes", response.size);
+                    println!("Kind: {}", response.kind);
+                    if let Some(min) = response.memory_min_pages {
+                        let max = response
+                            .memory_max_pages
+                            .map(|max| max.to_string())
+                            .unwrap_or_else(|| "unbounded".to_string());
+                        println!("Memory pages: min {}, max {}", min, max);
+                    }
+                    if !response.exports.is_empty() {
+                        println!("Exports: {}", response.exports.join(", "));
+                    }
+                    if let Some(signer) = &response.signer {
+                        println!("Signed by: {}", signer);
+                    }
+                    if response.needs_memory_export {
+                        println!("??  No 'memory' export: this binary cannot be executed");
+                    }
                     println!();
                     println!("Use this ID to execute the binary:");
                     println!(
@@ -83,7 +439,7 @@ async fn main() -> Result<()> {
                 }
                 Err(e) => {
                     eprintln!("? Failed to load binary: {}", e);
-                    std::process::exit(1);
+                    std::process::exit(exit_code(&e));
                 }
             }
         }
@@ -93,32 +449,174 @@ async fn main() -> Result<()> {
             input,
             timeout,
             memory,
+            stream,
+            env,
+            correlation_id,
+            encoding,
+            priority,
         } => {
             println!("?? Executing binary: {}", binary_id);
             println!("Input: \"{}\"", input);
-            println!("Timeout: {}ms", timeout);
-            println!("Memory: {}MB", memory);
+            let config = request_config(timeout, memory);
+            match &config {
+                Some(config) => {
+                    println!("Timeout: {}ms", config.timeout_ms);
+                    println!("Memory: {}MB", config.memory_limit_mb);
+                }
+                None => println!("Limits: server defaults"),
+            }
+            let request_id = Uuid::new_v4();
+            println!(
+                "Request ID: {} (cancel with `cancel --request-id`)",
+                request_id
+            );
+            if let Some(correlation_id) = &correlation_id {
+                println!("Correlation ID: {}", correlation_id);
+            }
             println!();
 
-            let config = Some(ExecutionConfig {
-                timeout_ms: timeout,
-                memory_limit_mb: memory,
-            });
+            let request = ExecuteRequest {
+                binary_id,
+                input,
+                config,
+                stream,
+                request_id: Some(request_id),
+                env: env.into_iter().collect(),
+                correlation_id,
+                namespace: None,
+                encoding,
+                deadline_unix_ms: None,
+                priority,
+            };
+            let response = if stream {
+                client
+                    .execute_streaming(request, |chunk| {
+                        println!("[chunk {}] {}", chunk.index, chunk.data);
+                    })
+                    .await
+            } else {
+                client.execute_request(request).await
+            };
 
-            match client.execute(binary_id, input, config).await {
+            match response {
                 Ok(response) => {
-                    println!("? Execution completed!");
-                    println!("Return code: {}", response.result.return_code);
+                    if response.result.timed_out {
+                        println!("??  Execution timed out; showing partial output");
+                    } else {
+                        println!("? Execution completed!");
+                        println!("Return code: {}", response.result.return_code);
+                    }
                     if !response.result.output.is_empty() {
                         println!("Output:");
                         println!("{}", response.result.output);
                     }
                     println!("Execution time: {}ms", response.result.execution_time_ms);
                     println!("Fuel consumed: {}", response.result.fuel_consumed);
+                    if response.result.cache_hit {
+                        println!("Served from the server's result cache");
+                    }
+                    print_warnings(&response.result.warnings, "");
+                    let applied = &response.result.applied_config;
+                    println!(
+                        "Applied config: timeout {}ms, memory {}MB, fuel {}",
+                        applied.timeout_ms,
+                        applied.memory_limit_mb,
+                        applied
+                            .fuel
+                            .map_or_else(|| "from timeout".to_string(), |fuel| fuel.to_string())
+                    );
                 }
                 Err(e) => {
                     eprintln!("? Execution failed: {}", e);
+                    std::process::exit(exit_code(&e));
+                }
+            }
+        }
+
+        Commands::Test {
+            binary_id,
+            input,
+            expect,
+            timeout,
+            memory,
+            env,
+        } => {
+            let read = |path: &PathBuf| {
+                std::fs::read_to_string(path).unwrap_or_else(|e| {
+                    eprintln!("? Failed to read {}: {}", path.display(), e);
                     std::process::exit(1);
+                })
+            };
+            let (input_data, expected) = (read(&input), read(&expect));
+
+            let request = ExecuteRequest {
+                binary_id,
+                input: input_data,
+                config: request_config(timeout, memory),
+                stream: false,
+                request_id: None,
+                env: env.into_iter().collect(),
+                correlation_id: None,
+                namespace: None,
+                encoding: Encoding::Utf8,
+                deadline_unix_ms: None,
+                priority: 0,
+            };
+            match client.execute_request(request).await {
+                Ok(response) => {
+                    if response.result.timed_out {
+                        println!("??  Execution timed out");
+                    } else if response.result.return_code != 0 {
+                        println!("Return code: {}", response.result.return_code);
+                    }
+                    match golden::diff(&expected, &response.result.output) {
+                        None => println!("? {} matches {}", input.display(), expect.display()),
+                        Some(diff) => {
+                            println!("? Output differs from {}", expect.display());
+                            print!("{}", diff);
+                            std::process::exit(1);
+                        }
+                    }
+                }
+                Err(e) => {
+                    eprintln!("? Execution failed: {}", e);
+                    std::process::exit(exit_code(&e));
+                }
+            }
+        }
+
+        Commands::Run {
+            path,
+            input,
+            timeout,
+            memory,
+            env,
+        } => {
+            let config = request_config(timeout, memory);
+            let env: EnvVars = env.into_iter().collect();
+            match client
+                .execute_by_path(path.to_string_lossy().to_string(), input, config, env)
+                .await
+            {
+                Ok(response) => {
+                    println!("Binary ID: {}", response.binary_id);
+                    if response.result.timed_out {
+                        println!("??  Execution timed out; showing partial output");
+                    } else {
+                        println!("Return code: {}", response.result.return_code);
+                    }
+                    if !response.result.output.is_empty() {
+                        println!("Output:");
+                        println!("{}", response.result.output);
+                    }
+                    print_warnings(&response.result.warnings, "");
+                    if response.result.return_code != 0 {
+                        std::process::exit(1);
+                    }
+                }
+                Err(e) => {
+                    eprintln!("? Run failed: {}", e);
+                    std::process::exit(exit_code(&e));
                 }
             }
         }
@@ -128,6 +626,9 @@ async fn main() -> Result<()> {
             input,
             timeout,
             memory,
+            env,
+            step_env,
+            persist_host_state,
         } => {
             println!("??  Executing chain: {} binaries", binary_ids.len());
             println!("Binary IDs:");
@@ -136,32 +637,58 @@ async fn main() -> Result<()> {
             }
             println!();
             println!("Initial input: \"{}\"", input);
-            println!("Timeout: {}ms", timeout);
-            println!("Memory: {}MB", memory);
+            let config = request_config(timeout, memory);
+            match &config {
+                Some(config) => {
+                    println!("Timeout: {}ms", config.timeout_ms);
+                    println!("Memory: {}MB", config.memory_limit_mb);
+                }
+                None => println!("Limits: server defaults"),
+            }
             println!();
 
-            let config = Some(ExecutionConfig {
-                timeout_ms: timeout,
-                memory_limit_mb: memory,
-            });
+            let env: EnvVars = env.into_iter().collect();
+            let mut steps = vec![EnvVars::new(); binary_ids.len()];
+            for (step, key, value) in step_env {
+                match steps.get_mut(step.wrapping_sub(1)) {
+                    Some(entries) => {
+                        entries.insert(key, value);
+                    }
+                    None => {
+                        eprintln!("? --step-env step {} is not in the chain", step);
+                        std::process::exit(2);
+                    }
+                }
+            }
 
-            match client.execute_chain(binary_ids, input, config).await {
+            match client
+                .execute_chain(binary_ids, input, config, env, steps, persist_host_state)
+                .await
+            {
                 Ok(response) => {
                     println!("? Chain execution completed!");
                     println!();
                     for (i, result) in response.results.iter().enumerate() {
                         println!("Step {}: {}", i + 1, result.binary_id);
-                        println!("  Return code: {}", result.return_code);
+                        if result.timed_out {
+                            println!("  Timed out (chain stopped here)");
+                        } else {
+                            println!("  Return code: {}", result.return_code);
+                        }
                         if !result.output.is_empty() {
                             println!("  Output: {}", result.output);
                         }
                         println!("  Execution time: {}ms", result.execution_time_ms);
+                        print_warnings(&result.warnings, "  ");
                         println!();
                     }
+                    if let Some(reason) = &response.halted {
+                        println!("Chain halted early: {}", reason);
+                    }
                 }
                 Err(e) => {
                     eprintln!("? Chain execution failed: {}", e);
-                    std::process::exit(1);
+                    std::process::exit(exit_code(&e));
                 }
             }
         }
@@ -185,6 +712,13 @@ async fn main() -> Result<()> {
                             println!("ID: {}", binary.id);
                             println!("  Path: {}", binary.path);
                             println!("  Size: {} bytes", binary.size);
+                            println!(
+                                "  Compiled: {} bytes of code in {}ms",
+                                binary.code_size, binary.compile_time_ms
+                            );
+                            if binary.needs_memory_export {
+                                println!("  ??  Missing 'memory' export");
+                            }
                             let datetime =
                                 chrono::DateTime::from_timestamp(binary.loaded_at as i64, 0)
                                     .map(|dt| dt.format("%Y-%m-%d %H:%M:%S").to_string())
@@ -196,11 +730,19 @@ async fn main() -> Result<()> {
                 }
                 Err(e) => {
                     eprintln!("? Failed to list binaries: {}", e);
-                    std::process::exit(1);
+                    std::process::exit(exit_code(&e));
                 }
             }
         }
 
+        Commands::Repl => {
+            let stdin = tokio::io::BufReader::new(tokio::io::stdin());
+            if let Err(e) = repl::run_repl(&mut client, stdin, std::io::stdout()).await {
+                eprintln!("? REPL failed: {}", e);
+                std::process::exit(1);
+            }
+        }
+
         Commands::Unload { binary_id } => {
             println!("???  Unloading binary: {}", binary_id);
             println!();
@@ -210,10 +752,608 @@ async fn main() -> Result<()> {
                 }
                 Err(e) => {
                     eprintln!("? Failed to unload binary: {}", e);
+                    std::process::exit(exit_code(&e));
+                }
+            }
+        }
+
+        Commands::ReloadAll => match client.reload_all().await {
+            Ok(response) => {
+                let mut failed = false;
+                for result in &response.results {
+                    let status = match result.status {
+                        ReloadStatus::Reloaded => "reloaded",
+                        ReloadStatus::Unchanged => "unchanged",
+                        ReloadStatus::Error => {
+                            failed = true;
+                            "error"
+                        }
+                    };
+                    println!("{:<10} {} {}", status, result.binary_id, result.path);
+                    if let Some(error) = &result.error {
+                        println!("           {}", error);
+                    }
+                }
+                if failed {
                     std::process::exit(1);
                 }
             }
+            Err(e) => {
+                eprintln!("? Reload failed: {}", e);
+                std::process::exit(exit_code(&e));
+            }
+        },
+
+        Commands::Export { path } => {
+            match client
+                .export_bundle(path.to_string_lossy().to_string())
+                .await
+            {
+                Ok(response) => {
+                    println!(
+                        "? Exported {} binaries to {} ({} bytes)",
+                        response.binary_count, response.path, response.size
+                    );
+                }
+                Err(e) => {
+                    eprintln!("? Export failed: {}", e);
+                    std::process::exit(exit_code(&e));
+                }
+            }
+        }
+
+        Commands::Import { path } => {
+            match client
+                .import_bundle(path.to_string_lossy().to_string())
+                .await
+            {
+                Ok(response) => {
+                    println!(
+                        "? Imported {} binaries into {}",
+                        response.binary_ids.len(),
+                        response.dir
+                    );
+                    for binary_id in &response.binary_ids {
+                        println!("  {}", binary_id);
+                    }
+                }
+                Err(e) => {
+                    eprintln!("? Import failed: {}", e);
+                    std::process::exit(exit_code(&e));
+                }
+            }
+        }
+
+        Commands::Fetch { binary_id, output } => {
+            let fetched = client
+                .get_binary_bytes(binary_id)
+                .await
+                .and_then(|response| {
+                    let wasm_bytes = STANDARD.decode(&response.wasm_base64)?;
+                    std::fs::write(&output, &wasm_bytes)?;
+                    Ok(wasm_bytes.len())
+                });
+            match fetched {
+                Ok(size) => {
+                    println!("? Saved {} bytes to {}", size, output.display());
+                }
+                Err(e) => {
+                    eprintln!("? Fetch failed: {}", e);
+                    std::process::exit(exit_code(&e));
+                }
+            }
+        }
+
+        Commands::Verify { binary_id } => match client.verify_integrity(binary_id).await {
+            Ok(response) => {
+                let mut failed = false;
+                for result in &response.results {
+                    let status = match result.status {
+                        IntegrityStatus::Ok => "ok",
+                        IntegrityStatus::Mismatch => "mismatch",
+                        IntegrityStatus::Missing => "missing",
+                    };
+                    failed |= result.status != IntegrityStatus::Ok;
+                    println!("{:<10} {} {}", status, result.binary_id, result.path);
+                    if result.status == IntegrityStatus::Mismatch {
+                        println!(
+                            "           expected {}, found {}",
+                            result
+                                .expected_sha256
+                                .as_deref()
+                                .unwrap_or("no recorded hash"),
+                            result.actual_sha256.as_deref().unwrap_or("-")
+                        );
+                    }
+                }
+                if failed {
+                    std::process::exit(1);
+                }
+            }
+            Err(e) => {
+                eprintln!("? Verification failed: {}", e);
+                std::process::exit(exit_code(&e));
+            }
+        },
+
+        Commands::Info => match client.info().await {
+            Ok(info) => {
+                let yes_no = |enabled: bool| if enabled { "yes" } else { "no" };
+                let defaults = &info.default_config;
+                println!(
+                    "wasm-core {} (Wasmtime {})",
+                    info.version, info.wasmtime_version
+                );
+                println!("Socket: {}", info.socket_path);
+                println!("Loaded binaries: {}", info.binary_count);
+                println!(
+                    "Engine: fuel {}, async {}, SIMD {}",
+                    yes_no(info.engine_features.fuel),
+                    yes_no(info.engine_features.async_support),
+                    yes_no(info.engine_features.simd)
+                );
+                println!(
+                    "Default config: timeout {}ms, memory {}MB, fuel {}",
+                    defaults.timeout_ms,
+                    defaults.memory_limit_mb,
+                    defaults
+                        .fuel
+                        .map_or_else(|| "from timeout".to_string(), |fuel| fuel.to_string())
+                );
+            }
+            Err(e) => {
+                eprintln!("? Info failed: {}", e);
+                std::process::exit(exit_code(&e));
+            }
+        },
+
+        Commands::Bench {
+            binary_id,
+            input,
+            iterations,
+            concurrency,
+        } => {
+            println!(
+                "?? Benchmarking binary: {} ({} executions, {} connections)",
+                binary_id, iterations, concurrency
+            );
+            let request = ExecuteRequest {
+                binary_id,
+                input,
+                config: None,
+                stream: false,
+                request_id: None,
+                env: EnvVars::new(),
+                correlation_id: None,
+                namespace: client.namespace().map(str::to_string),
+                encoding: Encoding::Utf8,
+                deadline_unix_ms: None,
+                priority: 0,
+            };
+            match bench::run(Path::new(SOCKET_PATH), request, iterations, concurrency).await {
+                Ok(report) => {
+                    println!();
+                    println!("Executions: {}", report.executions);
+                    if report.failures > 0 {
+                        println!("Failures:   {}", report.failures);
+                    }
+                    println!("Elapsed:    {:.2}s", report.elapsed.as_secs_f64());
+                    println!("Throughput: {:.1} exec/s", report.executions_per_sec());
+                    println!(
+                        "Latency:    p50 {}ms  p95 {}ms  p99 {}ms",
+                        report.p50_ms, report.p95_ms, report.p99_ms
+                    );
+                }
+                Err(e) => {
+                    eprintln!("? Benchmark failed: {}", e);
+                    std::process::exit(exit_code(&e));
+                }
+            }
+        }
+
+        Commands::Meter {
+            binary_id,
+            input,
+            timeout,
+            memory,
+        } => {
+            let defaults = ExecutionConfig::default();
+            let config = match ExecutionConfig::builder()
+                .timeout(timeout.unwrap_or(defaults.timeout_ms))
+                .memory_mb(memory.unwrap_or(defaults.memory_limit_mb))
+                .meter_only(true)
+                .build()
+            {
+                Ok(config) => config,
+                Err(e) => {
+                    eprintln!("? Invalid execution config: {}", e);
+                    std::process::exit(1);
+                }
+            };
+
+            println!("?? Metering binary: {}", binary_id);
+            println!();
+            println!(
+                "{:<32} {:>14} {:>10} {:>6}",
+                "INPUT", "FUEL", "TIME (ms)", "CODE"
+            );
+            for input in input {
+                let response = client
+                    .execute(
+                        binary_id,
+                        input.clone(),
+                        Some(config.clone()),
+                        None,
+                        EnvVars::new(),
+                    )
+                    .await;
+                let label: String = input.chars().take(32).collect();
+                match response {
+                    Ok(response) => {
+                        let code = if response.result.timed_out {
+                            "T/O".to_string()
+                        } else {
+                            response.result.return_code.to_string()
+                        };
+                        println!(
+                            "{:<32} {:>14} {:>10} {:>6}",
+                            label,
+                            response.result.fuel_consumed,
+                            response.result.execution_time_ms,
+                            code
+                        );
+                    }
+                    Err(e) => {
+                        eprintln!("? Metering failed for \"{}\": {}", label, e);
+                        std::process::exit(exit_code(&e));
+                    }
+                }
+            }
+        }
+
+        Commands::Warmup { binary_id, input } => match client.warmup(binary_id, input).await {
+            Ok(response) => {
+                println!("? Warmed up {}", response.binary_id);
+                println!("Return code: {}", response.return_code);
+                println!("Execution time: {}ms", response.execution_time_ms);
+                println!("Fuel consumed: {}", response.fuel_consumed);
+            }
+            Err(e) => {
+                eprintln!("? Warmup failed: {}", e);
+                std::process::exit(exit_code(&e));
+            }
+        },
+
+        Commands::Describe { binary_id } => match client.describe_binary(binary_id).await {
+            Ok(response) => {
+                println!("?? Binary: {}", response.binary_id);
+                println!("Path: {}", response.path);
+                println!("Size: {} bytes", response.size);
+                println!("Kind: {}", response.kind);
+                println!("Code size: {} bytes", response.code_size);
+                println!("Compile time: {}ms", response.compile_time_ms);
+                if let Some(min) = response.memory_min_pages {
+                    let max = response
+                        .memory_max_pages
+                        .map(|max| max.to_string())
+                        .unwrap_or_else(|| "unbounded".to_string());
+                    println!("Memory pages: min {}, max {}", min, max);
+                }
+                if let Some(signer) = &response.signer {
+                    println!("Signed by: {}", signer);
+                }
+                if let Some(manifest) = &response.manifest {
+                    let version = manifest.version.as_deref().unwrap_or("unversioned");
+                    println!("Plugin: {} {}", manifest.name, version);
+                    if let Some(author) = &manifest.author {
+                        println!("Author: {}", author);
+                    }
+                    if !manifest.capabilities.is_empty() {
+                        println!("Capabilities: {}", manifest.capabilities.join(", "));
+                    }
+                }
+                if let Some(config) = &response.default_config {
+                    println!(
+                        "Default config: {}ms, {}MB",
+                        config.timeout_ms, config.memory_limit_mb
+                    );
+                }
+                if response.needs_memory_export {
+                    println!("??  No 'memory' export: this binary cannot be executed");
+                }
+                println!("Exports: {}", response.exports.join(", "));
+                println!("Imports:");
+                if response.imports.is_empty() {
+                    println!("  (none)");
+                }
+                for import in &response.imports {
+                    if import.module.is_empty() {
+                        println!("  {}: {}", import.name, import.ty);
+                    } else {
+                        println!("  {}::{}: {}", import.module, import.name, import.ty);
+                    }
+                }
+            }
+            Err(e) => {
+                eprintln!("? Failed to describe binary: {}", e);
+                std::process::exit(exit_code(&e));
+            }
+        },
+
+        Commands::SetDefault {
+            binary_id,
+            timeout,
+            memory,
+        } => {
+            let config = request_config(timeout, memory);
+            match client.set_default_config(binary_id, config).await {
+                Ok(response) => match response.config {
+                    Some(config) => println!(
+                        "? Default config for {}: {}ms, {}MB",
+                        response.binary_id, config.timeout_ms, config.memory_limit_mb
+                    ),
+                    None => println!(
+                        "? Default config cleared for {}; server defaults apply",
+                        response.binary_id
+                    ),
+                },
+                Err(e) => {
+                    eprintln!("? Failed to set default config: {}", e);
+                    std::process::exit(exit_code(&e));
+                }
+            }
+        }
+
+        Commands::Cancel { request_id } => match client.cancel(request_id).await {
+            Ok(response) => {
+                println!("? Cancelled request {}", response.request_id);
+            }
+            Err(e) => {
+                eprintln!("? Failed to cancel: {}", e);
+                std::process::exit(exit_code(&e));
+            }
+        },
+
+        Commands::Audit { since } => match client.get_audit_log(since).await {
+            Ok(response) => {
+                println!("?? Audit entries: {}", response.entries.len());
+                for entry in response.entries {
+                    println!();
+                    println!("#{} at {}ms", entry.seq, entry.timestamp_ms);
+                    println!("  Binary: {}", entry.binary_id);
+                    println!("  Input:  {}", entry.input_hash);
+                    println!("  Env:    {}", entry.env_hash);
+                    println!("  Output: {}", entry.output_hash);
+                    println!(
+                        "  Return code: {}, fuel: {}",
+                        entry.return_code, entry.fuel_consumed
+                    );
+                    println!("  Hash: {}", entry.hash);
+                }
+            }
+            Err(e) => {
+                eprintln!("? Failed to read audit log: {}", e);
+                std::process::exit(exit_code(&e));
+            }
+        },
+        Commands::Traces {
+            failures,
+            event_types,
+            binary_id,
+            correlation_id,
+        } => {
+            let query = TraceQuery {
+                only_failures: failures,
+                event_types,
+                binary_id,
+                correlation_id,
+                namespace: client.namespace().map(str::to_string),
+            };
+            match client.get_traces(query).await {
+                Ok(response) => {
+                    println!("?? Traces: {}", response.traces.len());
+                    for trace in response.traces {
+                        println!();
+                        println!(
+                            "{} ({}ms, {})",
+                            trace.binary_id,
+                            trace.duration_ms,
+                            if trace.success { "ok" } else { "failed" }
+                        );
+                        println!("  Trace ID: {}", trace.trace_id);
+                        if let Some(correlation_id) = &trace.correlation_id {
+                            println!("  Correlation ID: {}", correlation_id);
+                        }
+                        if let Some(error) = &trace.error_message {
+                            println!("  Error: {}", error);
+                        }
+                        for event in &trace.events {
+                            println!(
+                                "  [{:>10}μs +{:>8}μs] {:?}: {}",
+                                event.timestamp, event.delta_us, event.event_type, event.message
+                            );
+                        }
+                    }
+                }
+                Err(e) => {
+                    eprintln!("? Failed to read traces: {}", e);
+                    std::process::exit(exit_code(&e));
+                }
+            }
+        }
+        Commands::History { binary_id, limit } => {
+            match client.get_history(binary_id, limit).await {
+                Ok(response) => {
+                    println!("?? Recent results: {}", response.results.len());
+                    for result in response.results {
+                        println!();
+                        if result.timed_out {
+                            println!("Timed out");
+                        } else {
+                            println!("Return code: {}", result.return_code);
+                        }
+                        if !result.output.is_empty() {
+                            println!("  Output: {}", result.output);
+                        }
+                        for (name, output) in &result.outputs {
+                            println!("  Output [{}]: {}", name, output);
+                        }
+                        println!(
+                            "  Execution time: {}ms, fuel: {}{}",
+                            result.execution_time_ms,
+                            result.fuel_consumed,
+                            if result.cache_hit { " (cached)" } else { "" }
+                        );
+                    }
+                }
+                Err(e) => {
+                    eprintln!("? Failed to read history: {}", e);
+                    std::process::exit(exit_code(&e));
+                }
+            }
+        }
+        Commands::Top { count } => {
+            let mut seen = 0;
+            let result = client
+                .subscribe_stats(|snapshot| {
+                    println!(
+                        "active: {:<4} total: {:<8} {:.1} exec/s",
+                        snapshot.active, snapshot.total, snapshot.executions_per_sec
+                    );
+                    for (binary_id, executions) in &snapshot.per_binary {
+                        println!("  {} {}", binary_id, executions);
+                    }
+                    seen += 1;
+                    count.is_none_or(|count| seen < count)
+                })
+                .await;
+            if let Err(e) = result {
+                eprintln!("? Stats subscription failed: {}", e);
+                std::process::exit(exit_code(&e));
+            }
+        }
+        Commands::Logs { follow: _, level } => {
+            let result = client
+                .tail_logs(level, |line| {
+                    println!(
+                        "{} {:<5} {}: {}",
+                        line.timestamp_ms,
+                        format!("{:?}", line.level).to_uppercase(),
+                        line.target,
+                        line.message
+                    );
+                    true
+                })
+                .await;
+            if let Err(e) = result {
+                eprintln!("? Log tail failed: {}", e);
+                std::process::exit(exit_code(&e));
+            }
+        }
+        Commands::Replay { trace_id } => match client.replay(trace_id).await {
+            Ok(response) => {
+                println!(
+                    "?? Replayed trace {} of {}",
+                    response.trace_id, response.binary_id
+                );
+                match response.original_return_code {
+                    Some(code) => println!("Original return code: {}", code),
+                    None => println!("Original execution failed"),
+                }
+                println!("Return code: {}", response.result.return_code);
+                if !response.result.output.is_empty() {
+                    println!("Output:");
+                    println!("{}", response.result.output);
+                }
+                println!("Execution time: {}ms", response.result.execution_time_ms);
+                println!("Fuel consumed: {}", response.result.fuel_consumed);
+            }
+            Err(e) => {
+                eprintln!("? Replay failed: {}", e);
+                std::process::exit(exit_code(&e));
+            }
+        },
+        Commands::NewPlugin { .. } => unreachable!("handled before connecting"),
+        Commands::ExportTrace { binary_id, format } => {
+            match client.export_trace(binary_id, format).await {
+                // Raw data only, so it can be piped straight into other tools
+                Ok(response) => print!("{}", response.data),
+                Err(e) => {
+                    eprintln!("? Failed to export trace: {}", e);
+                    std::process::exit(exit_code(&e));
+                }
+            }
         }
     }
     Ok(())
 }
+
+fn parse_env_pair(s: &str) -> Result<(String, String), String> {
+    match s.split_once('=') {
+        Some((key, value)) if !key.is_empty() => Ok((key.to_string(), value.to_string())),
+        _ => Err(format!("expected KEY=VALUE, got `{}`", s)),
+    }
+}
+
+fn parse_encoding(s: &str) -> Result<Encoding, String> {
+    match s {
+        "utf8" => Ok(Encoding::Utf8),
+        "base64" => Ok(Encoding::Base64),
+        "hex" => Ok(Encoding::Hex),
+        _ => Err(format!("expected utf8, base64 or hex, got `{}`", s)),
+    }
+}
+
+fn parse_step_env(s: &str) -> Result<(usize, String, String), String> {
+    let (step, pair) = s
+        .split_once(':')
+        .ok_or_else(|| format!("expected STEP:KEY=VALUE, got `{}`", s))?;
+    let step = step
+        .parse()
+        .map_err(|_| format!("invalid step number `{}`", step))?;
+    let (key, value) = parse_env_pair(pair)?;
+    Ok((step, key, value))
+}
+
+/// Build the per-request config from the command-line limits. When neither is
+/// given the request carries no config and the server applies its defaults.
+fn request_config(timeout: Option<u64>, memory: Option<u64>) -> Option<ExecutionConfig> {
+    if timeout.is_none() && memory.is_none() {
+        return None;
+    }
+    let defaults = ExecutionConfig::default();
+    match ExecutionConfig::builder()
+        .timeout(timeout.unwrap_or(defaults.timeout_ms))
+        .memory_mb(memory.unwrap_or(defaults.memory_limit_mb))
+        .build()
+    {
+        Ok(config) => Some(config),
+        Err(e) => {
+            eprintln!("? Invalid execution config: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Print a result's warnings, each on its own line after `indent`
+fn print_warnings(warnings: &[String], indent: &str) {
+    for warning in warnings {
+        println!("{}??  Warning: {}", indent, warning);
+    }
+}
+
+/// Process exit status for a failed request, so scripts can tell failures
+/// apart without parsing messages
+fn exit_code(e: &anyhow::Error) -> i32 {
+    match e.downcast_ref::<ErrorResponse>().map(|e| e.code) {
+        Some(ErrorCode::InvalidRequest) => 2,
+        Some(ErrorCode::NotFound) => 3,
+        Some(ErrorCode::InvalidBinary) | Some(ErrorCode::VerificationFailed) => 4,
+        Some(ErrorCode::Timeout) => 5,
+        Some(ErrorCode::OutOfFuel) | Some(ErrorCode::MemoryLimit) => 6,
+        Some(ErrorCode::Cancelled) => 7,
+        Some(ErrorCode::Unauthorized) => 8,
+        Some(ErrorCode::UnsupportedProtocol) => 9,
+        Some(ErrorCode::Internal) | None => 1,
+    }
+}
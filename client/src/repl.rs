@@ -0,0 +1,227 @@
+use anyhow::{anyhow, Result};
+use std::io::Write;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt};
+use uuid::Uuid;
+use wasm_shared::*;
+
+use crate::socket_client::SocketClient;
+
+/// Read commands line by line from `reader` and send them over the single
+/// connection held by `client`, writing each response as one JSON line.
+/// Lines that fail to parse produce an `error: ...` line instead.
+///
+/// Supported lines:
+///   load <path>
+///   execute <binary_id> <input...>
+///   chain <id1,id2,...> <input...>
+///   list
+///   unload <binary_id>
+///   quit | exit
+///
+/// Blank lines and lines starting with `#` are ignored. Stops at EOF.
+pub async fn run_repl<R, W>(client: &mut SocketClient, reader: R, mut writer: W) -> Result<()>
+where
+    R: AsyncBufRead + Unpin,
+    W: Write,
+{
+    let mut lines = reader.lines();
+    while let Some(line) = lines.next_line().await? {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if line == "quit" || line == "exit" {
+            break;
+        }
+        match parse_command(line, client.namespace()) {
+            Ok(command) => {
+                let response = client.send_command(command).await?;
+                writeln!(writer, "{}", serde_json::to_string(&response)?)?;
+            }
+            Err(e) => writeln!(writer, "error: {}", e)?,
+        }
+        writer.flush()?;
+    }
+    Ok(())
+}
+
+/// Parse one repl line into a command scoped to `namespace`
+fn parse_command(line: &str, namespace: Option<&str>) -> Result<Command> {
+    let namespace = namespace.map(str::to_string);
+    let (verb, rest) = match line.split_once(char::is_whitespace) {
+        Some((verb, rest)) => (verb, rest.trim()),
+        None => (line, ""),
+    };
+    match verb {
+        "load" if !rest.is_empty() => Ok(Command::LoadBinary(LoadBinaryRequest {
+            path: rest.to_string(),
+            signature: None,
+            default_config: None,
+            namespace,
+        })),
+        "execute" => {
+            let (id, input) = split_first(rest);
+            Ok(Command::Execute(ExecuteRequest {
+                binary_id: parse_id(id)?,
+                input: input.to_string(),
+                config: None,
+                stream: false,
+                request_id: None,
+                env: Default::default(),
+                correlation_id: None,
+                namespace,
+                encoding: Encoding::Utf8,
+                deadline_unix_ms: None,
+                priority: 0,
+            }))
+        }
+        "chain" => {
+            let (ids, input) = split_first(rest);
+            let binary_ids = ids.split(',').map(parse_id).collect::<Result<Vec<_>>>()?;
+            Ok(Command::ExecuteChain(ExecuteChainRequest {
+                binary_ids,
+                input: input.to_string(),
+                config: None,
+                env: Default::default(),
+                step_env: Vec::new(),
+                namespace,
+                persist_host_state: false,
+            }))
+        }
+        "list" => Ok(Command::ListBinaries(ListBinariesRequest { namespace })),
+        "unload" => Ok(Command::UnloadBinary(UnloadBinaryRequest {
+            binary_id: parse_id(rest)?,
+            namespace,
+        })),
+        "describe" => Ok(Command::DescribeBinary(DescribeBinaryRequest {
+            binary_id: parse_id(rest)?,
+            namespace,
+        })),
+        "audit" => {
+            let since = match rest {
+                "" => None,
+                since => Some(
+                    since
+                        .parse()
+                        .map_err(|e| anyhow!("Invalid sequence number '{}': {}", since, e))?,
+                ),
+            };
+            Ok(Command::GetAuditLog { since, namespace })
+        }
+        _ => Err(anyhow!("Unknown or incomplete command: {}", line)),
+    }
+}
+
+fn split_first(s: &str) -> (&str, &str) {
+    match s.split_once(char::is_whitespace) {
+        Some((first, rest)) => (first, rest),
+        None => (s, ""),
+    }
+}
+
+fn parse_id(s: &str) -> Result<Uuid> {
+    Uuid::parse_str(s.trim()).map_err(|e| anyhow!("Invalid binary id '{}': {}", s, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::{SinkExt, StreamExt};
+    use tokio::net::UnixListener;
+    use tokio_util::codec::{Framed, LinesCodec};
+
+    use crate::socket_client::hello_response;
+
+    #[test]
+    fn test_parse_command() {
+        let id = Uuid::from_u128(42);
+        match parse_command(&format!("execute {} hello world", id), None).unwrap() {
+            Command::Execute(req) => {
+                assert_eq!(req.binary_id, id);
+                assert_eq!(req.input, "hello world");
+            }
+            other => panic!("unexpected command: {:?}", other),
+        }
+        assert!(matches!(
+            parse_command("list", Some("team-a")),
+            Ok(Command::ListBinaries(ListBinariesRequest { namespace: Some(ns) })) if ns == "team-a"
+        ));
+        assert!(parse_command("load", None).is_err());
+        assert!(parse_command("bogus", None).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_repl_reuses_connection_in_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("wasm-core.sock");
+        let listener = UnixListener::bind(&path).unwrap();
+
+        // Fake server: a single connection answering every command in turn
+        let server = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut framed = Framed::new(stream, LinesCodec::new());
+            let binary_id = Uuid::nil();
+            let mut handled = 0;
+            while let Some(Ok(line)) = framed.next().await {
+                let response = match serde_json::from_str::<Command>(&line).unwrap() {
+                    Command::Hello { .. } => {
+                        let hello = hello_response();
+                        framed
+                            .send(serde_json::to_string(&hello).unwrap())
+                            .await
+                            .unwrap();
+                        continue;
+                    }
+                    Command::LoadBinary(_) => Response::LoadBinary(Ok(LoadBinaryResponse {
+                        binary_id,
+                        size: 42,
+                        kind: "core".to_string(),
+                        memory_min_pages: None,
+                        memory_max_pages: None,
+                        exports: Vec::new(),
+                        signer: None,
+                        needs_memory_export: false,
+                    })),
+                    Command::ListBinaries(_) => {
+                        Response::ListBinaries(Ok(ListBinariesResponse { binaries: vec![] }))
+                    }
+                    Command::UnloadBinary(req) => {
+                        Response::UnloadBinary(Ok(UnloadBinaryResponse {
+                            message: format!("Binary {} unloaded successfully", req.binary_id),
+                        }))
+                    }
+                    other => panic!("unexpected command: {:?}", other),
+                };
+                framed
+                    .send(serde_json::to_string(&response).unwrap())
+                    .await
+                    .unwrap();
+                handled += 1;
+            }
+            handled
+        });
+
+        let mut client = SocketClient::connect_to(&path).await.unwrap();
+        let script = format!(
+            "# comment\nload ./plugins/reverser.wasm\n\nlist\nnot-a-command\nunload {}\n",
+            Uuid::nil()
+        );
+        let mut output = Vec::new();
+        run_repl(&mut client, script.as_bytes(), &mut output)
+            .await
+            .unwrap();
+        drop(client);
+
+        let output = String::from_utf8(output).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines.len(), 4);
+        let parse = |line: &str| serde_json::from_str::<Response>(line).unwrap();
+        assert!(matches!(parse(lines[0]), Response::LoadBinary(Ok(_))));
+        assert!(matches!(parse(lines[1]), Response::ListBinaries(Ok(_))));
+        assert!(lines[2].starts_with("error: Unknown or incomplete command"));
+        assert!(matches!(parse(lines[3]), Response::UnloadBinary(Ok(_))));
+
+        // Every real command went over the one connection
+        assert_eq!(server.await.unwrap(), 3);
+    }
+}
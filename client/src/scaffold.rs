@@ -0,0 +1,175 @@
+use anyhow::{bail, Context, Result};
+use std::path::{Path, PathBuf};
+
+const CARGO_TOML: &str = r#"[package]
+name = "{name}"
+version = "0.1.0"
+edition = "2021"
+
+[lib]
+crate-type = ["cdylib"]
+test = false
+
+[dependencies]
+"#;
+
+const LIB_RS: &str = r#"//! {title} Plugin
+//!
+//! Writes its input back unchanged. Replace the body of `process` with the
+//! plugin's own logic.
+
+#![no_std]
+
+use core::panic::PanicInfo;
+use core::slice;
+
+#[link(wasm_import_module = "host")]
+extern "C" {
+    fn log(ptr: *const u8, len: usize);
+    fn write_output(ptr: *const u8, len: usize);
+}
+
+fn log_message(message: &str) {
+    unsafe {
+        log(message.as_ptr(), message.len());
+    }
+}
+
+const IO_BUFFER_SIZE: usize = 4096;
+
+/// Scratch region the host writes input and env into, kept clear of the stack
+static mut IO_BUFFER: [u8; IO_BUFFER_SIZE] = [0; IO_BUFFER_SIZE];
+
+/// Reserve `len` bytes for the host's input and env writes
+#[no_mangle]
+pub extern "C" fn alloc(len: usize) -> *mut u8 {
+    if len > IO_BUFFER_SIZE {
+        return core::ptr::null_mut();
+    }
+    core::ptr::addr_of_mut!(IO_BUFFER) as *mut u8
+}
+
+#[no_mangle]
+pub extern "C" fn process(
+    input_ptr: *const u8,
+    input_len: usize,
+    _env_ptr: *const u8,
+    _env_len: usize,
+) -> i32 {
+    log_message("[{title}] Starting");
+
+    let input = unsafe { slice::from_raw_parts(input_ptr, input_len) };
+    unsafe {
+        write_output(input.as_ptr(), input.len());
+    }
+
+    log_message("[{title}] Done");
+    0
+}
+
+#[panic_handler]
+fn panic(_info: &PanicInfo) -> ! {
+    log_message("[{title}] PANIC occurred!");
+    loop {}
+}
+
+#[global_allocator]
+static ALLOCATOR: DummyAllocator = DummyAllocator;
+
+struct DummyAllocator;
+
+unsafe impl core::alloc::GlobalAlloc for DummyAllocator {
+    unsafe fn alloc(&self, _layout: core::alloc::Layout) -> *mut u8 {
+        core::ptr::null_mut()
+    }
+    unsafe fn dealloc(&self, _ptr: *mut u8, _layout: core::alloc::Layout) {}
+}
+"#;
+
+/// Write a new `no_std` plugin crate named `name` into `parent/name`: a
+/// `Cargo.toml` and a `src/lib.rs` with the host imports, allocator, panic
+/// handler and a `process` that echoes its input. Returns the crate directory.
+pub fn new_plugin(parent: &Path, name: &str) -> Result<PathBuf> {
+    validate_name(name)?;
+    let dir = parent.join(name);
+    if dir.exists() {
+        bail!("{} already exists", dir.display());
+    }
+    std::fs::create_dir_all(dir.join("src"))
+        .with_context(|| format!("Failed to create {}", dir.display()))?;
+
+    let title = title(name);
+    std::fs::write(dir.join("Cargo.toml"), CARGO_TOML.replace("{name}", name))?;
+    std::fs::write(dir.join("src/lib.rs"), LIB_RS.replace("{title}", &title))?;
+    Ok(dir)
+}
+
+/// Crate names are ASCII letters, digits, `-` and `_`, starting with a letter
+fn validate_name(name: &str) -> Result<()> {
+    let valid = name.starts_with(|c: char| c.is_ascii_alphabetic())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !valid {
+        bail!(
+            "Invalid plugin name `{}`: use ASCII letters, digits, `-` and `_`, starting with a letter",
+            name
+        );
+    }
+    Ok(())
+}
+
+/// `plugin-word-count` becomes `Word-Count`, for doc comments and log tags
+fn title(name: &str) -> String {
+    let name = name.strip_prefix("plugin-").unwrap_or(name);
+    name.split('-')
+        .map(|part| {
+            let mut chars = part.chars();
+            match chars.next() {
+                Some(first) => first.to_ascii_uppercase().to_string() + chars.as_str(),
+                None => String::new(),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process::Command;
+
+    #[test]
+    fn test_invalid_names_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["", "1plugin", "my plugin", "../escape"] {
+            assert!(new_plugin(dir.path(), name).is_err(), "{:?}", name);
+        }
+        assert_eq!(title("plugin-word-count"), "Word-Count");
+    }
+
+    #[test]
+    fn test_generated_plugin_compiles_to_wasm() {
+        let dir = tempfile::tempdir().unwrap();
+        let crate_dir = new_plugin(dir.path(), "plugin-scaffolded").unwrap();
+        assert!(new_plugin(dir.path(), "plugin-scaffolded").is_err());
+
+        let cargo = std::env::var("CARGO").unwrap_or_else(|_| "cargo".to_string());
+        let output = Command::new(cargo)
+            .args(["build", "--release", "--target", "wasm32-unknown-unknown"])
+            .arg("--manifest-path")
+            .arg(crate_dir.join("Cargo.toml"))
+            .env("CARGO_TARGET_DIR", dir.path().join("target"))
+            .output()
+            .unwrap();
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        assert!(dir
+            .path()
+            .join("target/wasm32-unknown-unknown/release/plugin_scaffolded.wasm")
+            .exists());
+    }
+}
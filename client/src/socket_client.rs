@@ -1,5 +1,7 @@
 use anyhow::{Context, Result};
 use futures::{SinkExt, StreamExt};
+use std::path::Path;
+use std::time::Duration;
 use tokio::net::UnixStream;
 use tokio_util::codec::{Framed, LinesCodec};
 use uuid::Uuid;
@@ -8,32 +10,148 @@ use wasm_shared::*;
 
 pub struct SocketClient {
     framed: Framed<UnixStream, LinesCodec>,
+    namespace: Option<String>,
 }
 
 impl SocketClient {
     pub async fn connect() -> Result<Self> {
-        let stream = UnixStream::connect(SOCKET_PATH)
+        Self::connect_to(SOCKET_PATH).await
+    }
+
+    /// Connect to the socket at `path`, authenticating with `$WASM_CORE_TOKEN`
+    /// when it is set
+    pub(crate) async fn connect_to(path: impl AsRef<Path>) -> Result<Self> {
+        Self::connect_with_token(path, std::env::var(TOKEN_ENV).ok()).await
+    }
+
+    async fn connect_with_token(path: impl AsRef<Path>, token: Option<String>) -> Result<Self> {
+        let stream = UnixStream::connect(path)
             .await
             .context("Failed to connect to server. Is wasm-core running?")?;
-        let framed = Framed::new(stream, LinesCodec::new());
-        Ok(Self { framed })
+        let mut client = Self {
+            framed: Framed::new(stream, LinesCodec::new()),
+            namespace: None,
+        };
+        if let Some(token) = token {
+            client.authenticate(token).await?;
+        }
+        client.hello().await?;
+        Ok(client)
+    }
+
+    /// Make sure the server speaks this client's protocol version before
+    /// anything else is sent
+    async fn hello(&mut self) -> Result<()> {
+        let command = Command::Hello {
+            client_version: env!("CARGO_PKG_VERSION").to_string(),
+            protocol_version: PROTOCOL_VERSION,
+        };
+        match self.send_command(command).await? {
+            Response::Hello {
+                server_version,
+                supported_protocols,
+            } => {
+                if supported_protocols.contains(&PROTOCOL_VERSION) {
+                    return Ok(());
+                }
+                Err(ErrorResponse::new(
+                    ErrorCode::UnsupportedProtocol,
+                    format!(
+                        "wasm-core {} supports protocol versions {:?}, but this client speaks {}; \
+                         use a client and server from matching releases",
+                        server_version, supported_protocols, PROTOCOL_VERSION
+                    ),
+                )
+                .into())
+            }
+            // Servers from before the handshake don't know the command
+            Response::Error(e) if e.code == ErrorCode::InvalidRequest => Err(ErrorResponse::new(
+                ErrorCode::UnsupportedProtocol,
+                format!(
+                    "The server predates protocol negotiation ({}); upgrade wasm-core",
+                    e.message
+                ),
+            )
+            .into()),
+            Response::Error(e) => Err(e.into()),
+            _ => Err(anyhow::anyhow!("Unexpected response type")),
+        }
+    }
+
+    async fn authenticate(&mut self, token: String) -> Result<()> {
+        match self.send_command(Command::Auth { token }).await? {
+            Response::Authenticated => Ok(()),
+            Response::Error(e) => Err(e.into()),
+            _ => Err(anyhow::anyhow!("Unexpected response type")),
+        }
     }
 
-    async fn send_command(&mut self, command: Command) -> Result<Response> {
+    /// Connect, retrying up to `attempts` times with a fixed `delay` between
+    /// tries. Returns the last connection error once attempts are exhausted.
+    pub async fn connect_with_retry(attempts: u32, delay: Duration) -> Result<Self> {
+        Self::connect_to_with_retry(SOCKET_PATH, attempts, delay).await
+    }
+
+    async fn connect_to_with_retry(
+        path: impl AsRef<Path>,
+        attempts: u32,
+        delay: Duration,
+    ) -> Result<Self> {
+        let path = path.as_ref();
+        let mut last_error = None;
+        for attempt in 1..=attempts.max(1) {
+            match Self::connect_to(path).await {
+                Ok(client) => return Ok(client),
+                Err(e) => {
+                    last_error = Some(e);
+                    if attempt < attempts {
+                        tokio::time::sleep(delay).await;
+                    }
+                }
+            }
+        }
+        Err(last_error.expect("at least one connection attempt is made"))
+    }
+
+    /// Scope binary commands sent from now on to `namespace`
+    pub fn with_namespace(mut self, namespace: Option<String>) -> Self {
+        self.namespace = namespace;
+        self
+    }
+
+    pub fn namespace(&self) -> Option<&str> {
+        self.namespace.as_deref()
+    }
+
+    pub(crate) async fn send_command(&mut self, command: Command) -> Result<Response> {
         let json = serde_json::to_string(&command)?;
         self.framed.send(json).await?;
+        self.read_response().await
+    }
+
+    async fn read_response(&mut self) -> Result<Response> {
         let line = self.framed.next().await.context("Connection closed")??;
         let response: Response = serde_json::from_str(&line)?;
         Ok(response)
     }
 
-    pub async fn load_binary(&mut self, path: String) -> Result<LoadBinaryResponse> {
-        let command = Command::LoadBinary(LoadBinaryRequest { path });
+    pub async fn load_binary(
+        &mut self,
+        path: String,
+        signature: Option<Vec<u8>>,
+        default_config: Option<ExecutionConfig>,
+    ) -> Result<LoadBinaryResponse> {
+        let command = Command::LoadBinary(LoadBinaryRequest {
+            path,
+            signature,
+            default_config,
+            namespace: self.namespace.clone(),
+        });
         let response = self.send_command(command).await?;
         match response {
             Response::LoadBinary(Ok(resp)) => Ok(resp),
-            Response::LoadBinary(Err(e)) => Err(anyhow::anyhow!(e)),
-            Response::Error(e) => Err(anyhow::anyhow!(e)),
+            Response::LoadBinary(Err(e)) => Err(e.into()),
+            Response::Error(e) => Err(e.into()),
             _ => Err(anyhow::anyhow!("Unexpected response type")),
         }
     }
@@ -43,17 +161,86 @@ impl SocketClient {
         binary_id: Uuid,
         input: String,
         config: Option<ExecutionConfig>,
+        request_id: Option<Uuid>,
+        env: EnvVars,
     ) -> Result<ExecuteResponse> {
-        let command = Command::Execute(ExecuteRequest {
+        self.execute_request(ExecuteRequest {
             binary_id,
             input,
             config,
+            stream: false,
+            request_id,
+            env,
+            correlation_id: None,
+            namespace: None,
+            encoding: Encoding::Utf8,
+            deadline_unix_ms: None,
+            priority: 0,
+        })
+        .await
+    }
+
+    /// Like `execute`, with every request field available; `stream` is
+    /// ignored, and the client's namespace applies unless `req` names one
+    pub async fn execute_request(&mut self, req: ExecuteRequest) -> Result<ExecuteResponse> {
+        let command = Command::Execute(ExecuteRequest {
+            stream: false,
+            namespace: req.namespace.or_else(|| self.namespace.clone()),
+            ..req
         });
         let response = self.send_command(command).await?;
         match response {
             Response::Execute(Ok(resp)) => Ok(resp),
-            Response::Execute(Err(e)) => Err(anyhow::anyhow!(e)),
-            Response::Error(e) => Err(anyhow::anyhow!(e)),
+            Response::Execute(Err(e)) => Err(e.into()),
+            Response::Error(e) => Err(e.into()),
+            _ => Err(anyhow::anyhow!("Unexpected response type")),
+        }
+    }
+
+    /// Execute in streaming mode, handing each chunk to `on_chunk` as it
+    /// arrives, then return the final response
+    pub async fn execute_streaming(
+        &mut self,
+        req: ExecuteRequest,
+        mut on_chunk: impl FnMut(OutputChunk),
+    ) -> Result<ExecuteResponse> {
+        let command = Command::Execute(ExecuteRequest {
+            stream: true,
+            namespace: req.namespace.or_else(|| self.namespace.clone()),
+            ..req
+        });
+        let mut response = self.send_command(command).await?;
+        loop {
+            match response {
+                Response::OutputChunk(chunk) => on_chunk(chunk),
+                Response::Execute(Ok(resp)) => return Ok(resp),
+                Response::Execute(Err(e)) => return Err(e.into()),
+                Response::Error(e) => return Err(e.into()),
+                _ => return Err(anyhow::anyhow!("Unexpected response type")),
+            }
+            response = self.read_response().await?;
+        }
+    }
+
+    pub async fn execute_by_path(
+        &mut self,
+        path: String,
+        input: String,
+        config: Option<ExecutionConfig>,
+        env: EnvVars,
+    ) -> Result<ExecuteByPathResponse> {
+        let command = Command::ExecuteByPath(ExecuteByPathRequest {
+            path,
+            input,
+            config,
+            env,
+            namespace: self.namespace.clone(),
+        });
+        let response = self.send_command(command).await?;
+        match response {
+            Response::ExecuteByPath(Ok(resp)) => Ok(resp),
+            Response::ExecuteByPath(Err(e)) => Err(e.into()),
+            Response::Error(e) => Err(e.into()),
             _ => Err(anyhow::anyhow!("Unexpected response type")),
         }
     }
@@ -63,40 +250,502 @@ impl SocketClient {
         binary_ids: Vec<Uuid>,
         input: String,
         config: Option<ExecutionConfig>,
+        env: EnvVars,
+        step_env: Vec<EnvVars>,
+        persist_host_state: bool,
     ) -> Result<ExecuteChainResponse> {
         let command = Command::ExecuteChain(ExecuteChainRequest {
             binary_ids,
             input,
             config,
+            env,
+            step_env,
+            namespace: self.namespace.clone(),
+            persist_host_state,
         });
         let response = self.send_command(command).await?;
         match response {
             Response::ExecuteChain(Ok(resp)) => Ok(resp),
-            Response::ExecuteChain(Err(e)) => Err(anyhow::anyhow!(e)),
-            Response::Error(e) => Err(anyhow::anyhow!(e)),
+            Response::ExecuteChain(Err(e)) => Err(e.into()),
+            Response::Error(e) => Err(e.into()),
             _ => Err(anyhow::anyhow!("Unexpected response type")),
         }
     }
 
     pub async fn list_binaries(&mut self) -> Result<ListBinariesResponse> {
-        let command = Command::ListBinaries;
+        let command = Command::ListBinaries(ListBinariesRequest {
+            namespace: self.namespace.clone(),
+        });
         let response = self.send_command(command).await?;
         match response {
             Response::ListBinaries(Ok(resp)) => Ok(resp),
-            Response::ListBinaries(Err(e)) => Err(anyhow::anyhow!(e)),
-            Response::Error(e) => Err(anyhow::anyhow!(e)),
+            Response::ListBinaries(Err(e)) => Err(e.into()),
+            Response::Error(e) => Err(e.into()),
             _ => Err(anyhow::anyhow!("Unexpected response type")),
         }
     }
 
     pub async fn unload_binary(&mut self, binary_id: Uuid) -> Result<UnloadBinaryResponse> {
-        let command = Command::UnloadBinary(UnloadBinaryRequest { binary_id });
+        let command = Command::UnloadBinary(UnloadBinaryRequest {
+            binary_id,
+            namespace: self.namespace.clone(),
+        });
         let response = self.send_command(command).await?;
         match response {
             Response::UnloadBinary(Ok(resp)) => Ok(resp),
-            Response::UnloadBinary(Err(e)) => Err(anyhow::anyhow!(e)),
-            Response::Error(e) => Err(anyhow::anyhow!(e)),
+            Response::UnloadBinary(Err(e)) => Err(e.into()),
+            Response::Error(e) => Err(e.into()),
+            _ => Err(anyhow::anyhow!("Unexpected response type")),
+        }
+    }
+
+    pub async fn info(&mut self) -> Result<InfoResponse> {
+        let response = self.send_command(Command::Info).await?;
+        match response {
+            Response::Info(Ok(resp)) => Ok(resp),
+            Response::Info(Err(e)) => Err(e.into()),
+            Response::Error(e) => Err(e.into()),
+            _ => Err(anyhow::anyhow!("Unexpected response type")),
+        }
+    }
+
+    pub async fn verify_integrity(
+        &mut self,
+        binary_id: Option<Uuid>,
+    ) -> Result<VerifyIntegrityResponse> {
+        let command = Command::VerifyIntegrity {
+            binary_id,
+            namespace: self.namespace.clone(),
+        };
+        let response = self.send_command(command).await?;
+        match response {
+            Response::VerifyIntegrity(Ok(resp)) => Ok(resp),
+            Response::VerifyIntegrity(Err(e)) => Err(e.into()),
+            Response::Error(e) => Err(e.into()),
+            _ => Err(anyhow::anyhow!("Unexpected response type")),
+        }
+    }
+
+    pub async fn reload_all(&mut self) -> Result<ReloadAllResponse> {
+        let response = self
+            .send_command(Command::ReloadAll {
+                namespace: self.namespace.clone(),
+            })
+            .await?;
+        match response {
+            Response::ReloadAll(Ok(resp)) => Ok(resp),
+            Response::ReloadAll(Err(e)) => Err(e.into()),
+            Response::Error(e) => Err(e.into()),
+            _ => Err(anyhow::anyhow!("Unexpected response type")),
+        }
+    }
+
+    pub async fn export_bundle(&mut self, path: String) -> Result<ExportBundleResponse> {
+        let command = Command::ExportBundle {
+            path,
+            namespace: self.namespace.clone(),
+        };
+        let response = self.send_command(command).await?;
+        match response {
+            Response::ExportBundle(Ok(resp)) => Ok(resp),
+            Response::ExportBundle(Err(e)) => Err(e.into()),
+            Response::Error(e) => Err(e.into()),
+            _ => Err(anyhow::anyhow!("Unexpected response type")),
+        }
+    }
+
+    pub async fn import_bundle(&mut self, path: String) -> Result<ImportBundleResponse> {
+        let command = Command::ImportBundle {
+            path,
+            namespace: self.namespace.clone(),
+        };
+        let response = self.send_command(command).await?;
+        match response {
+            Response::ImportBundle(Ok(resp)) => Ok(resp),
+            Response::ImportBundle(Err(e)) => Err(e.into()),
+            Response::Error(e) => Err(e.into()),
+            _ => Err(anyhow::anyhow!("Unexpected response type")),
+        }
+    }
+
+    pub async fn describe_binary(&mut self, binary_id: Uuid) -> Result<DescribeBinaryResponse> {
+        let command = Command::DescribeBinary(DescribeBinaryRequest {
+            binary_id,
+            namespace: self.namespace.clone(),
+        });
+        let response = self.send_command(command).await?;
+        match response {
+            Response::DescribeBinary(Ok(resp)) => Ok(resp),
+            Response::DescribeBinary(Err(e)) => Err(e.into()),
+            Response::Error(e) => Err(e.into()),
+            _ => Err(anyhow::anyhow!("Unexpected response type")),
+        }
+    }
+
+    pub async fn set_default_config(
+        &mut self,
+        binary_id: Uuid,
+        config: Option<ExecutionConfig>,
+    ) -> Result<SetDefaultConfigResponse> {
+        let command = Command::SetDefaultConfig(SetDefaultConfigRequest {
+            binary_id,
+            config,
+            namespace: self.namespace.clone(),
+        });
+        let response = self.send_command(command).await?;
+        match response {
+            Response::SetDefaultConfig(Ok(resp)) => Ok(resp),
+            Response::SetDefaultConfig(Err(e)) => Err(e.into()),
+            Response::Error(e) => Err(e.into()),
+            _ => Err(anyhow::anyhow!("Unexpected response type")),
+        }
+    }
+
+    pub async fn warmup(
+        &mut self,
+        binary_id: Uuid,
+        sample_input: String,
+    ) -> Result<WarmupResponse> {
+        let command = Command::Warmup {
+            binary_id,
+            sample_input,
+            namespace: self.namespace.clone(),
+        };
+        let response = self.send_command(command).await?;
+        match response {
+            Response::Warmup(Ok(resp)) => Ok(resp),
+            Response::Warmup(Err(e)) => Err(e.into()),
+            Response::Error(e) => Err(e.into()),
+            _ => Err(anyhow::anyhow!("Unexpected response type")),
+        }
+    }
+
+    pub async fn cancel(&mut self, request_id: Uuid) -> Result<CancelResponse> {
+        let command = Command::Cancel {
+            request_id,
+            namespace: self.namespace.clone(),
+        };
+        let response = self.send_command(command).await?;
+        match response {
+            Response::Cancelled(Ok(resp)) => Ok(resp),
+            Response::Cancelled(Err(e)) => Err(e.into()),
+            Response::Error(e) => Err(e.into()),
+            _ => Err(anyhow::anyhow!("Unexpected response type")),
+        }
+    }
+
+    pub async fn get_traces(&mut self, query: TraceQuery) -> Result<GetTracesResponse> {
+        let response = self.send_command(Command::GetTraces(query)).await?;
+        match response {
+            Response::GetTraces(Ok(resp)) => Ok(resp),
+            Response::GetTraces(Err(e)) => Err(e.into()),
+            Response::Error(e) => Err(e.into()),
+            _ => Err(anyhow::anyhow!("Unexpected response type")),
+        }
+    }
+
+    pub async fn get_history(
+        &mut self,
+        binary_id: Uuid,
+        limit: Option<usize>,
+    ) -> Result<GetHistoryResponse> {
+        let response = self
+            .send_command(Command::GetHistory {
+                binary_id,
+                limit,
+                namespace: self.namespace.clone(),
+            })
+            .await?;
+        match response {
+            Response::GetHistory(Ok(resp)) => Ok(resp),
+            Response::GetHistory(Err(e)) => Err(e.into()),
+            Response::Error(e) => Err(e.into()),
+            _ => Err(anyhow::anyhow!("Unexpected response type")),
+        }
+    }
+
+    pub async fn get_binary_bytes(&mut self, binary_id: Uuid) -> Result<GetBinaryBytesResponse> {
+        let response = self
+            .send_command(Command::GetBinaryBytes {
+                binary_id,
+                namespace: self.namespace.clone(),
+            })
+            .await?;
+        match response {
+            Response::GetBinaryBytes(Ok(resp)) => Ok(resp),
+            Response::GetBinaryBytes(Err(e)) => Err(e.into()),
+            Response::Error(e) => Err(e.into()),
+            _ => Err(anyhow::anyhow!("Unexpected response type")),
+        }
+    }
+
+    /// Subscribe to stats snapshots, handing each to `on_snapshot` until it
+    /// returns false or the server closes the connection
+    pub async fn subscribe_stats(
+        &mut self,
+        mut on_snapshot: impl FnMut(StatsSnapshot) -> bool,
+    ) -> Result<()> {
+        let command = Command::Subscribe {
+            kind: SubscriptionKind::Stats,
+            namespace: self.namespace.clone(),
+        };
+        self.framed.send(serde_json::to_string(&command)?).await?;
+        while let Some(line) = self.framed.next().await {
+            match serde_json::from_str(&line?)? {
+                Response::StatsSnapshot(snapshot) => {
+                    if !on_snapshot(snapshot) {
+                        break;
+                    }
+                }
+                Response::Error(e) => return Err(e.into()),
+                _ => return Err(anyhow::anyhow!("Unexpected response type")),
+            }
+        }
+        Ok(())
+    }
+
+    /// Follow the server's log at `level` or more severe, handing each line
+    /// to `on_line` until it returns false or the server closes the connection
+    pub async fn tail_logs(
+        &mut self,
+        level: LogLevel,
+        mut on_line: impl FnMut(LogLine) -> bool,
+    ) -> Result<()> {
+        let command = Command::TailLogs { level };
+        self.framed.send(serde_json::to_string(&command)?).await?;
+        while let Some(line) = self.framed.next().await {
+            match serde_json::from_str(&line?)? {
+                Response::LogLine(line) => {
+                    if !on_line(line) {
+                        break;
+                    }
+                }
+                Response::Error(e) => return Err(e.into()),
+                _ => return Err(anyhow::anyhow!("Unexpected response type")),
+            }
+        }
+        Ok(())
+    }
+
+    pub async fn export_trace(
+        &mut self,
+        binary_id: Uuid,
+        format: TraceFormat,
+    ) -> Result<ExportTraceResponse> {
+        let command = Command::ExportTrace {
+            binary_id,
+            format,
+            namespace: self.namespace.clone(),
+        };
+        let response = self.send_command(command).await?;
+        match response {
+            Response::ExportTrace(Ok(resp)) => Ok(resp),
+            Response::ExportTrace(Err(e)) => Err(e.into()),
+            Response::Error(e) => Err(e.into()),
+            _ => Err(anyhow::anyhow!("Unexpected response type")),
+        }
+    }
+
+    pub async fn replay(&mut self, trace_id: Uuid) -> Result<ReplayResponse> {
+        let command = Command::Replay {
+            trace_id,
+            namespace: self.namespace.clone(),
+        };
+        let response = self.send_command(command).await?;
+        match response {
+            Response::Replay(Ok(resp)) => Ok(resp),
+            Response::Replay(Err(e)) => Err(e.into()),
+            Response::Error(e) => Err(e.into()),
+            _ => Err(anyhow::anyhow!("Unexpected response type")),
+        }
+    }
+
+    pub async fn get_audit_log(&mut self, since: Option<u64>) -> Result<GetAuditLogResponse> {
+        let command = Command::GetAuditLog {
+            since,
+            namespace: self.namespace.clone(),
+        };
+        let response = self.send_command(command).await?;
+        match response {
+            Response::GetAuditLog(Ok(resp)) => Ok(resp),
+            Response::GetAuditLog(Err(e)) => Err(e.into()),
+            Response::Error(e) => Err(e.into()),
             _ => Err(anyhow::anyhow!("Unexpected response type")),
         }
     }
 }
+
+/// What a current server answers to `Hello`, for fake servers in tests
+#[cfg(test)]
+pub(crate) fn hello_response() -> Response {
+    Response::Hello {
+        server_version: "0.1.0".to_string(),
+        supported_protocols: vec![PROTOCOL_VERSION],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::UnixListener;
+
+    /// Accept one connection and answer its first line with `response`
+    fn answer_first_line(listener: UnixListener, response: Response) {
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut framed = Framed::new(stream, LinesCodec::new());
+            framed.next().await.unwrap().unwrap();
+            framed
+                .send(serde_json::to_string(&response).unwrap())
+                .await
+                .unwrap();
+            // Hold the connection open until the client is done with it
+            framed.next().await;
+        });
+    }
+
+    #[tokio::test]
+    async fn test_hello_with_matching_protocol() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("wasm-core.sock");
+        answer_first_line(UnixListener::bind(&path).unwrap(), hello_response());
+
+        assert!(SocketClient::connect_with_token(&path, None).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_hello_with_mismatched_protocol_is_refused() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("wasm-core.sock");
+        let response = Response::Hello {
+            server_version: "9.0.0".to_string(),
+            supported_protocols: vec![PROTOCOL_VERSION + 1],
+        };
+        answer_first_line(UnixListener::bind(&path).unwrap(), response);
+
+        let err = SocketClient::connect_with_token(&path, None)
+            .await
+            .err()
+            .unwrap();
+        let err = err.downcast_ref::<ErrorResponse>().unwrap();
+        assert_eq!(err.code, ErrorCode::UnsupportedProtocol);
+        assert!(err
+            .message
+            .contains("wasm-core 9.0.0 supports protocol versions"));
+    }
+
+    #[tokio::test]
+    async fn test_error_code_reaches_caller() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("wasm-core.sock");
+        let listener = UnixListener::bind(&path).unwrap();
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut framed = Framed::new(stream, LinesCodec::new());
+            let responses = [
+                hello_response(),
+                Response::Execute(Err(ErrorResponse::new(
+                    ErrorCode::OutOfFuel,
+                    "Out of fuel after 1000 units",
+                ))),
+            ];
+            for response in responses {
+                framed.next().await.unwrap().unwrap();
+                framed
+                    .send(serde_json::to_string(&response).unwrap())
+                    .await
+                    .unwrap();
+            }
+        });
+
+        let mut client = SocketClient::connect_with_token(&path, None).await.unwrap();
+        let err = client
+            .execute(Uuid::nil(), String::new(), None, None, EnvVars::new())
+            .await
+            .err()
+            .unwrap();
+        let err = err.downcast_ref::<ErrorResponse>().unwrap();
+        assert_eq!(err.code, ErrorCode::OutOfFuel);
+        assert_eq!(err.message, "Out of fuel after 1000 units");
+    }
+
+    #[tokio::test]
+    async fn test_server_without_hello_is_refused() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("wasm-core.sock");
+        let response = Response::Error(ErrorResponse::new(
+            ErrorCode::InvalidRequest,
+            "Invalid command: unknown variant `Hello`",
+        ));
+        answer_first_line(UnixListener::bind(&path).unwrap(), response);
+
+        let err = SocketClient::connect_with_token(&path, None)
+            .await
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("predates protocol negotiation"));
+    }
+
+    #[tokio::test]
+    async fn test_connect_with_retry_waits_for_server() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("wasm-core.sock");
+
+        // Bring the "server" up only after the client has started retrying
+        let server_path = path.clone();
+        let server = tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(150)).await;
+            let listener = UnixListener::bind(server_path).unwrap();
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut framed = Framed::new(stream, LinesCodec::new());
+            framed.next().await.unwrap().unwrap();
+            framed
+                .send(serde_json::to_string(&hello_response()).unwrap())
+                .await
+                .unwrap();
+        });
+
+        let client =
+            SocketClient::connect_to_with_retry(&path, 20, Duration::from_millis(50)).await;
+        assert!(client.is_ok());
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_connect_with_retry_returns_last_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("missing.sock");
+
+        let result = SocketClient::connect_to_with_retry(&path, 3, Duration::from_millis(1)).await;
+        let err = result.err().unwrap();
+        assert!(err.to_string().contains("Failed to connect to server"));
+    }
+
+    #[tokio::test]
+    async fn test_token_is_sent_before_anything_else() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("wasm-core.sock");
+        let listener = UnixListener::bind(&path).unwrap();
+        let server = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut framed = Framed::new(stream, LinesCodec::new());
+            let line = framed.next().await.unwrap().unwrap();
+            framed
+                .send(serde_json::to_string(&Response::Authenticated).unwrap())
+                .await
+                .unwrap();
+            framed.next().await.unwrap().unwrap();
+            framed
+                .send(serde_json::to_string(&hello_response()).unwrap())
+                .await
+                .unwrap();
+            line
+        });
+
+        SocketClient::connect_with_token(&path, Some("s3cret".to_string()))
+            .await
+            .unwrap();
+        let first_line = server.await.unwrap();
+        assert_eq!(first_line, r#"{"type":"Auth","token":"s3cret"}"#);
+    }
+}
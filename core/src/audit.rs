@@ -0,0 +1,179 @@
+use anyhow::{anyhow, Context, Result};
+use sha2::{Digest, Sha256};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use uuid::Uuid;
+use wasm_shared::AuditEntry;
+
+/// `prev_hash` of the first entry in a log
+const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// What a single execution consumed and produced, before hashing
+pub struct AuditRecord<'a> {
+    pub binary_id: Uuid,
+    pub input: &'a [u8],
+    pub env: &'a [u8],
+    pub output: &'a [u8],
+    pub return_code: i32,
+    pub fuel_consumed: u64,
+}
+
+/// Append-only, hash-chained log of executions, one JSON entry per line.
+///
+/// Unlike traces this keeps only digests, and every entry commits to the one
+/// before it, so editing or dropping a line breaks the chain.
+pub struct AuditLog {
+    path: PathBuf,
+    /// Sequence number and hash of the last entry written
+    head: Mutex<(u64, String)>,
+}
+
+impl AuditLog {
+    /// Open (or create) the log at `path`, verifying the existing chain
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let entries = Self::read_entries(&path)?;
+        let head = verify_chain(&entries)?;
+        Ok(Self {
+            path,
+            head: Mutex::new(head),
+        })
+    }
+
+    pub fn append(&self, record: AuditRecord<'_>) -> Result<AuditEntry> {
+        let mut head = self
+            .head
+            .lock()
+            .map_err(|_| anyhow!("Audit log lock poisoned"))?;
+        let timestamp_ms = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64;
+
+        let mut entry = AuditEntry {
+            seq: head.0 + 1,
+            timestamp_ms,
+            binary_id: record.binary_id,
+            input_hash: sha256_hex(record.input),
+            env_hash: sha256_hex(record.env),
+            output_hash: sha256_hex(record.output),
+            return_code: record.return_code,
+            fuel_consumed: record.fuel_consumed,
+            prev_hash: head.1.clone(),
+            hash: String::new(),
+        };
+        entry.hash = entry_hash(&entry);
+
+        let mut line = serde_json::to_string(&entry).context("Failed to serialize audit entry")?;
+        line.push('\n');
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .with_context(|| format!("Failed to open audit log: {}", self.path.display()))?;
+        file.write_all(line.as_bytes())
+            .context("Failed to write audit entry")?;
+
+        *head = (entry.seq, entry.hash.clone());
+        Ok(entry)
+    }
+
+    /// Entries with a sequence number greater than `since`
+    pub fn entries_since(&self, since: Option<u64>) -> Result<Vec<AuditEntry>> {
+        let _head = self
+            .head
+            .lock()
+            .map_err(|_| anyhow!("Audit log lock poisoned"))?;
+        let since = since.unwrap_or(0);
+        Ok(Self::read_entries(&self.path)?
+            .into_iter()
+            .filter(|entry| entry.seq > since)
+            .collect())
+    }
+
+    fn read_entries(path: &Path) -> Result<Vec<AuditEntry>> {
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+        let data = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read audit log: {}", path.display()))?;
+        data.lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| serde_json::from_str(line).context("Failed to parse audit entry"))
+            .collect()
+    }
+}
+
+/// Walk the chain, returning the head, or an error at the first broken link
+fn verify_chain(entries: &[AuditEntry]) -> Result<(u64, String)> {
+    let mut head = (0, GENESIS_HASH.to_string());
+    for entry in entries {
+        if entry.seq != head.0 + 1 || entry.prev_hash != head.1 || entry.hash != entry_hash(entry) {
+            return Err(anyhow!("Audit log chain broken at entry {}", entry.seq));
+        }
+        head = (entry.seq, entry.hash.clone());
+    }
+    Ok(head)
+}
+
+fn entry_hash(entry: &AuditEntry) -> String {
+    let fields = format!(
+        "{}|{}|{}|{}|{}|{}|{}|{}|{}",
+        entry.seq,
+        entry.timestamp_ms,
+        entry.binary_id,
+        entry.input_hash,
+        entry.env_hash,
+        entry.output_hash,
+        entry.return_code,
+        entry.fuel_consumed,
+        entry.prev_hash
+    );
+    sha256_hex(fields.as_bytes())
+}
+
+fn sha256_hex(bytes: &[u8]) -> String {
+    hex::encode(Sha256::digest(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(input: &[u8]) -> AuditRecord<'_> {
+        AuditRecord {
+            binary_id: Uuid::nil(),
+            input,
+            env: b"{}",
+            output: b"ok",
+            return_code: 0,
+            fuel_consumed: 10,
+        }
+    }
+
+    #[test]
+    fn test_chain_survives_reopen_and_detects_tampering() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("audit.log");
+
+        let log = AuditLog::open(&path).unwrap();
+        let first = log.append(record(b"one")).unwrap();
+        assert_eq!(first.prev_hash, GENESIS_HASH);
+        log.append(record(b"two")).unwrap();
+
+        let reopened = AuditLog::open(&path).unwrap();
+        let third = reopened.append(record(b"three")).unwrap();
+        assert_eq!(third.seq, 3);
+        assert_eq!(reopened.entries_since(Some(1)).unwrap().len(), 2);
+
+        let data = std::fs::read_to_string(&path).unwrap();
+        std::fs::write(
+            &path,
+            data.replacen("\"return_code\":0", "\"return_code\":1", 1),
+        )
+        .unwrap();
+        let err = AuditLog::open(&path).err().unwrap();
+        assert_eq!(err.to_string(), "Audit log chain broken at entry 1");
+    }
+}
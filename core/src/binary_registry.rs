@@ -1,10 +1,22 @@
 use anyhow::{anyhow, Context, Result};
 use dashmap::DashMap;
+use ed25519_dalek::{Signature, Verifier, VerifyingKey};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::path::{Path, PathBuf};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex, OnceLock};
 use uuid::Uuid;
-use wasmtime::{Engine, Module};
+use wasm_shared::{
+    DataKind, ErrorCode, ErrorResponse, ExecutionConfig, ImportInfo, PluginManifest,
+};
+use wasmparser::BinaryReaderError;
+use wasmtime::component::Component;
+use wasmtime::{Engine, ExternType, InstancePre, Module};
+
+use crate::bundle;
+use crate::executor::HostState;
+use crate::module_cache::ModuleCache;
+use crate::result_cache::ResultCache;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BinaryMetadata {
@@ -12,60 +24,402 @@ pub struct BinaryMetadata {
     pub path: PathBuf,
     pub size: usize,
     pub loaded_at: std::time::SystemTime,
+    /// Fingerprint of the key that signed the binary, if it was verified
+    #[serde(default)]
+    pub signer: Option<String>,
+    /// Detached signature, kept so the binary can be re-verified on restart
+    #[serde(default)]
+    pub signature: Option<Vec<u8>>,
+    /// Everything the binary needs the host to provide
+    #[serde(default)]
+    pub imports: Vec<ImportInfo>,
+    /// Used when an execute request carries no config of its own
+    #[serde(default)]
+    pub default_config: Option<ExecutionConfig>,
+    /// Only requests naming this namespace can see the binary; `None` is the
+    /// default namespace
+    #[serde(default)]
+    pub namespace: Option<String>,
+    /// Hex SHA-256 of the wasm bytes, used to skip unchanged files on reload
+    #[serde(default)]
+    pub content_hash: Option<String>,
+    /// Wall time of the last compilation (or module cache load), rounded up
+    #[serde(default)]
+    pub compile_time_ms: u64,
+    /// Bytes of compiled code and data, i.e. the serialized artifact size
+    #[serde(default)]
+    pub code_size: usize,
+    /// Read from the binary's manifest file when it was loaded
+    #[serde(default)]
+    pub manifest: Option<PluginManifest>,
+}
+
+/// A compiled binary: either a core module or a component-model component
+#[derive(Clone)]
+pub enum BinaryKind {
+    Core(Module),
+    Component(Component),
+}
+
+impl BinaryKind {
+    /// Component binaries share the `\0asm` magic but carry layer 1 in the
+    /// last two bytes of the version field, core modules carry layer 0
+    pub fn is_component(wasm_bytes: &[u8]) -> bool {
+        wasm_bytes.len() >= 8 && wasm_bytes.starts_with(b"\0asm") && wasm_bytes[6..8] == [1, 0]
+    }
+
+    /// Size of the compiled image wasmtime keeps in memory for this binary
+    pub fn code_size(&self) -> usize {
+        let range = match self {
+            BinaryKind::Core(module) => module.image_range(),
+            BinaryKind::Component(component) => component.image_range(),
+        };
+        range.end as usize - range.start as usize
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            BinaryKind::Core(_) => "core",
+            BinaryKind::Component(_) => "component",
+        }
+    }
+
+    pub fn imports(&self, engine: &Engine) -> Vec<ImportInfo> {
+        match self {
+            BinaryKind::Core(module) => module
+                .imports()
+                .map(|import| ImportInfo {
+                    module: import.module().to_string(),
+                    name: import.name().to_string(),
+                    ty: describe_extern(&import.ty()),
+                })
+                .collect(),
+            BinaryKind::Component(component) => component
+                .component_type()
+                .imports(engine)
+                .map(|(name, _)| ImportInfo {
+                    module: String::new(),
+                    name: name.to_string(),
+                    ty: "component item".to_string(),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl BinaryMetadata {
+    /// Input kind declared by the manifest, if any
+    pub fn input_kind(&self) -> Option<DataKind> {
+        self.manifest.as_ref()?.input_kind
+    }
+
+    /// Output kind declared by the manifest, if any
+    pub fn output_kind(&self) -> Option<DataKind> {
+        self.manifest.as_ref()?.output_kind
+    }
+
+    /// Whether the manifest declares the binary pure
+    pub fn is_pure(&self) -> bool {
+        self.manifest.as_ref().is_some_and(|manifest| manifest.pure)
+    }
+
+    /// JSON Schema declared by the manifest for the binary's input, if any
+    pub fn input_schema(&self) -> Option<&serde_json::Value> {
+        self.manifest.as_ref()?.input_schema.as_ref()
+    }
 }
 
 #[derive(Clone)]
 pub struct LoadedBinary {
     pub metadata: BinaryMetadata,
-    pub module: Module,
+    pub kind: BinaryKind,
+    /// The module linked against the host functions, set by its first
+    /// execution or a warmup and dropped with this entry on reload or unload
+    pub(crate) linked: Arc<OnceLock<InstancePre<HostState>>>,
+}
+
+impl LoadedBinary {
+    pub fn new(metadata: BinaryMetadata, kind: BinaryKind) -> Self {
+        Self {
+            metadata,
+            kind,
+            linked: Arc::default(),
+        }
+    }
+
+    /// Whether an execution has linked the module yet, so the next one can
+    /// skip straight to instantiating it
+    #[cfg(test)]
+    pub fn is_linked(&self) -> bool {
+        self.linked.get().is_some()
+    }
+
+    /// Declared `(min, max)` pages of the module's first exported memory.
+    /// Components encapsulate their memories, so they report `None`.
+    pub fn memory_pages(&self) -> Option<(u64, Option<u64>)> {
+        match &self.kind {
+            BinaryKind::Core(module) => module.exports().find_map(|export| match export.ty() {
+                ExternType::Memory(memory) => Some((memory.minimum(), memory.maximum())),
+                _ => None,
+            }),
+            BinaryKind::Component(_) => None,
+        }
+    }
+
+    /// Core modules receive their input through an exported `memory`; one
+    /// without it loads fine but can never execute
+    pub fn needs_memory_export(&self) -> bool {
+        match &self.kind {
+            BinaryKind::Core(module) => {
+                !matches!(module.get_export("memory"), Some(ExternType::Memory(_)))
+            }
+            BinaryKind::Component(_) => false,
+        }
+    }
+
+    pub fn export_names(&self, engine: &Engine) -> Vec<String> {
+        match &self.kind {
+            BinaryKind::Core(module) => module
+                .exports()
+                .map(|export| export.name().to_string())
+                .collect(),
+            BinaryKind::Component(component) => component
+                .component_type()
+                .exports(engine)
+                .map(|(name, _)| name.to_string())
+                .collect(),
+        }
+    }
 }
 
+const DEFAULT_METADATA_PATH: &str = "metadata.json";
+
+/// Largest metadata file `load` reads. Each entry is well under a kilobyte,
+/// so anything bigger is not a file this server wrote.
+const MAX_METADATA_BYTES: u64 = 64 * 1024 * 1024;
+
 #[derive(Clone)]
 pub struct BinaryRegistry {
     binaries: Arc<DashMap<Uuid, LoadedBinary>>,
     engine: Engine,
+    metadata_path: PathBuf,
+    trusted_key: Option<VerifyingKey>,
+    module_cache: Option<ModuleCache>,
+    result_cache: Option<ResultCache>,
+    /// Canonical directories binaries may be loaded from; any path when empty
+    allowed_dirs: Vec<PathBuf>,
+    /// Canonical directory bundles are exported to and imported from; the
+    /// allowed directories when unset
+    bundle_dir: Option<PathBuf>,
+    /// Held while the metadata file is written, shared by every clone
+    save_lock: Arc<Mutex<()>>,
+    /// Held for the whole run by executions with `serialize_per_binary` set
+    binary_locks: Arc<DashMap<Uuid, Arc<tokio::sync::Mutex<()>>>>,
 }
 
 impl BinaryRegistry {
     pub fn new(engine: Engine) -> Self {
+        Self::with_metadata_path(engine, DEFAULT_METADATA_PATH)
+    }
+
+    /// Create a registry that persists its metadata to a custom location
+    pub fn with_metadata_path(engine: Engine, metadata_path: impl Into<PathBuf>) -> Self {
         Self {
             binaries: Arc::new(DashMap::new()),
             engine,
+            metadata_path: metadata_path.into(),
+            trusted_key: None,
+            module_cache: None,
+            result_cache: None,
+            allowed_dirs: Vec::new(),
+            bundle_dir: None,
+            save_lock: Arc::new(Mutex::new(())),
+            binary_locks: Arc::new(DashMap::new()),
+        }
+    }
+
+    /// Require every binary to carry a valid Ed25519 signature from `key`
+    pub fn with_trusted_key(mut self, key: VerifyingKey) -> Self {
+        self.trusted_key = Some(key);
+        self
+    }
+
+    /// Reuse compiled binaries from `cache`, and add newly compiled ones to it
+    pub fn with_module_cache(mut self, cache: ModuleCache) -> Self {
+        self.module_cache = Some(cache);
+        self
+    }
+
+    /// Keep results of pure binaries in `cache`; a binary's entries are
+    /// dropped when it is unloaded or reloaded
+    pub fn with_result_cache(mut self, cache: ResultCache) -> Self {
+        self.result_cache = Some(cache);
+        self
+    }
+
+    /// Only load binaries whose canonical path is inside one of `dirs`.
+    /// Fails if a directory doesn't exist.
+    pub fn with_allowed_dirs(
+        mut self,
+        dirs: impl IntoIterator<Item = impl AsRef<Path>>,
+    ) -> Result<Self> {
+        for dir in dirs {
+            let dir = dir.as_ref();
+            let canonical = dir
+                .canonicalize()
+                .with_context(|| format!("Invalid allowed plugin directory: {}", dir.display()))?;
+            self.allowed_dirs.push(canonical);
+        }
+        Ok(self)
+    }
+
+    /// Keep bundle paths inside `dir`, resolving relative ones against it.
+    /// Fails if the directory doesn't exist.
+    pub fn with_bundle_dir(mut self, dir: impl AsRef<Path>) -> Result<Self> {
+        let dir = dir.as_ref();
+        let canonical = dir
+            .canonicalize()
+            .with_context(|| format!("Invalid bundle directory: {}", dir.display()))?;
+        self.bundle_dir = Some(canonical);
+        Ok(self)
+    }
+
+    pub fn module_cache(&self) -> Option<&ModuleCache> {
+        self.module_cache.as_ref()
+    }
+
+    pub fn result_cache(&self) -> Option<&ResultCache> {
+        self.result_cache.as_ref()
+    }
+
+    /// The lock executions of `id` with `serialize_per_binary` set take turns on
+    pub fn binary_lock(&self, id: Uuid) -> Arc<tokio::sync::Mutex<()>> {
+        self.binary_locks.entry(id).or_default().clone()
+    }
+
+    /// Forget cached results of `id`, whose code just changed or went away,
+    /// and its execution lock unless a run still holds it
+    fn invalidate_results(&self, id: &Uuid) {
+        if let Some(cache) = &self.result_cache {
+            cache.invalidate(id);
+        }
+        self.binary_locks
+            .remove_if(id, |_, lock| Arc::strong_count(lock) == 1);
+    }
+
+    /// Compile every `.wasm` file directly inside `dir` into the module
+    /// cache, returning the artifact paths
+    pub fn precompile_dir(&self, dir: impl AsRef<Path>) -> Result<Vec<PathBuf>> {
+        let dir = dir.as_ref();
+        let cache = self
+            .module_cache
+            .as_ref()
+            .ok_or_else(|| anyhow!("Precompiling requires a module cache directory"))?;
+
+        let mut paths: Vec<_> = std::fs::read_dir(dir)
+            .with_context(|| format!("Failed to read directory: {}", dir.display()))?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<std::io::Result<_>>()?;
+        paths.retain(|path| path.extension().is_some_and(|ext| ext == "wasm"));
+        paths.sort();
+
+        let mut artifacts = Vec::new();
+        for path in paths {
+            let wasm_bytes = std::fs::read(&path)
+                .with_context(|| format!("Failed to read WASM file: {}", path.display()))?;
+            self.compile(&wasm_bytes)
+                .with_context(|| format!("Failed to precompile {}", path.display()))?;
+            let artifact = cache.artifact_path(&self.engine, &wasm_bytes);
+            tracing::info!("Precompiled {} -> {}", path.display(), artifact.display());
+            artifacts.push(artifact);
         }
+        Ok(artifacts)
+    }
+
+    /// Load every `.wasm` file directly inside `dir`, in name order. Files
+    /// already registered under the same path are updated in place. A file
+    /// that fails to load is logged and skipped; the IDs of the rest are
+    /// returned.
+    pub async fn load_directory(&self, dir: impl AsRef<Path>) -> Result<Vec<Uuid>> {
+        let dir = dir.as_ref();
+        let mut paths: Vec<_> = std::fs::read_dir(dir)
+            .with_context(|| format!("Failed to read directory: {}", dir.display()))?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<std::io::Result<_>>()?;
+        paths.retain(|path| path.extension().is_some_and(|ext| ext == "wasm"));
+        paths.sort();
+
+        let mut ids = Vec::new();
+        for path in paths {
+            match self.load_binary(&path, None).await {
+                Ok(id) => ids.push(id),
+                Err(e) => tracing::warn!("Skipping {}: {:#}", path.display(), e),
+            }
+        }
+        Ok(ids)
+    }
+
+    pub async fn load_binary(
+        &self,
+        path: impl AsRef<Path>,
+        signature: Option<&[u8]>,
+    ) -> Result<Uuid> {
+        self.load_binary_in(path, signature, None).await
     }
 
-    pub async fn load_binary(&self, path: impl AsRef<Path>) -> Result<Uuid> {
+    /// Load a binary into `namespace`. Paths are deduplicated per namespace,
+    /// so two namespaces loading the same file get separate entries.
+    pub async fn load_binary_in(
+        &self,
+        path: impl AsRef<Path>,
+        signature: Option<&[u8]>,
+        namespace: Option<&str>,
+    ) -> Result<Uuid> {
         let path = path.as_ref();
-        
+        self.check_allowed(path)?;
+
         // Check if a binary with the same path already exists
-        if let Some(existing_id) = self.find_binary_by_path(path) {
+        if let Some(existing_id) = self.find_binary_by_path(path, namespace) {
             tracing::info!(
                 "Binary with path {} already exists (id: {}), updating...",
                 path.display(),
                 existing_id
             );
-            
+
             // Read and compile the new WASM file
             let wasm_bytes = tokio::fs::read(path)
                 .await
                 .with_context(|| format!("Failed to read WASM file: {}", path.display()))?;
             let size = wasm_bytes.len();
-            let module = Module::from_binary(&self.engine, &wasm_bytes)
-                .context("Failed to compile WASM module")?;
-            
-            // Update the existing entry with the same UUID
+            let signer = self.verify(&wasm_bytes, signature)?;
+            let manifest = read_manifest(path)?;
+            let (kind, compile_time_ms) = self.compile_timed(&wasm_bytes)?;
+
+            // Update the existing entry with the same UUID, keeping its default config
+            let default_config = self
+                .get_binary(&existing_id)?
+                .metadata
+                .default_config
+                .or_else(|| manifest.as_ref()?.default_config.clone());
             let metadata = BinaryMetadata {
                 id: existing_id,
                 path: path.to_path_buf(),
                 size,
                 loaded_at: std::time::SystemTime::now(),
+                signature: signer.as_ref().and(signature.map(<[u8]>::to_vec)),
+                signer,
+                imports: kind.imports(&self.engine),
+                default_config,
+                namespace: namespace.map(str::to_string),
+                content_hash: Some(content_hash(&wasm_bytes)),
+                compile_time_ms,
+                code_size: kind.code_size(),
+                manifest,
             };
-            let loaded = LoadedBinary {
-                metadata: metadata.clone(),
-                module,
-            };
+            let loaded = LoadedBinary::new(metadata.clone(), kind);
             self.binaries.insert(existing_id, loaded);
-            
+            self.invalidate_results(&existing_id);
+
             tracing::info!(
                 "Binary updated successfully: {} (size: {} bytes, id: {})",
                 path.display(),
@@ -75,26 +429,33 @@ impl BinaryRegistry {
             self.save()?;
             return Ok(existing_id);
         }
-        
+
         // No existing binary found, create a new one
         tracing::info!("Loading new binary from: {}", path.display());
         let wasm_bytes = tokio::fs::read(path)
             .await
             .with_context(|| format!("Failed to read WASM file: {}", path.display()))?;
         let size = wasm_bytes.len();
-        let module = Module::from_binary(&self.engine, &wasm_bytes)
-            .context("Failed to compile WASM module")?;
+        let signer = self.verify(&wasm_bytes, signature)?;
+        let manifest = read_manifest(path)?;
+        let (kind, compile_time_ms) = self.compile_timed(&wasm_bytes)?;
         let id = Uuid::new_v4();
         let metadata = BinaryMetadata {
             id,
             path: path.to_path_buf(),
             size,
             loaded_at: std::time::SystemTime::now(),
+            signature: signer.as_ref().and(signature.map(<[u8]>::to_vec)),
+            signer,
+            imports: kind.imports(&self.engine),
+            default_config: manifest.as_ref().and_then(|m| m.default_config.clone()),
+            namespace: namespace.map(str::to_string),
+            content_hash: Some(content_hash(&wasm_bytes)),
+            compile_time_ms,
+            code_size: kind.code_size(),
+            manifest,
         };
-        let loaded = LoadedBinary {
-            metadata: metadata.clone(),
-            module,
-        };
+        let loaded = LoadedBinary::new(metadata.clone(), kind);
         self.binaries.insert(id, loaded);
         tracing::info!(
             "Binary loaded successfully: {} (size: {} bytes, id: {})",
@@ -106,25 +467,358 @@ impl BinaryRegistry {
         Ok(id)
     }
 
+    /// Recompile a binary from its stored path in place, keeping its ID,
+    /// config and namespace. Returns false without recompiling when the file's
+    /// content hash matches the loaded one. On failure the old binary stays.
+    pub async fn reload_binary(&self, id: &Uuid) -> Result<bool> {
+        let metadata = self.get_binary(id)?.metadata;
+        self.check_allowed(&metadata.path)?;
+        let wasm_bytes = tokio::fs::read(&metadata.path)
+            .await
+            .with_context(|| format!("Failed to read WASM file: {}", metadata.path.display()))?;
+        let hash = content_hash(&wasm_bytes);
+        if metadata.content_hash.as_deref() == Some(hash.as_str()) {
+            return Ok(false);
+        }
+
+        self.verify(&wasm_bytes, metadata.signature.as_deref())?;
+        let (kind, compile_time_ms) = self.compile_timed(&wasm_bytes)?;
+        let metadata = BinaryMetadata {
+            size: wasm_bytes.len(),
+            loaded_at: std::time::SystemTime::now(),
+            imports: kind.imports(&self.engine),
+            content_hash: Some(hash),
+            compile_time_ms,
+            code_size: kind.code_size(),
+            ..metadata
+        };
+        self.binaries.insert(*id, LoadedBinary::new(metadata, kind));
+        self.invalidate_results(id);
+        tracing::info!("Binary reloaded: {}", id);
+        self.save()?;
+        Ok(true)
+    }
+
+    /// `reload_binary` for every binary in `namespace`, in path order
+    pub async fn reload_all(&self, namespace: Option<&str>) -> Vec<(BinaryMetadata, Result<bool>)> {
+        let mut binaries = self.list_binaries_in(namespace);
+        binaries.sort_by(|a, b| a.path.cmp(&b.path));
+        let mut results = Vec::with_capacity(binaries.len());
+        for metadata in binaries {
+            let result = self.reload_binary(&metadata.id).await;
+            results.push((metadata, result));
+        }
+        results
+    }
+
+    /// Hash the file behind `id`, or behind every binary in `namespace` in
+    /// path order, pairing each binary with its file's current hex SHA-256.
+    /// The hash is `None` when the file can't be read. Nothing is reloaded.
+    pub async fn hash_sources(
+        &self,
+        id: Option<&Uuid>,
+        namespace: Option<&str>,
+    ) -> Result<Vec<(BinaryMetadata, Option<String>)>> {
+        let binaries = match id {
+            Some(id) => vec![self.get_binary_in(id, namespace)?.metadata],
+            None => {
+                let mut binaries = self.list_binaries_in(namespace);
+                binaries.sort_by(|a, b| a.path.cmp(&b.path));
+                binaries
+            }
+        };
+        let mut results = Vec::with_capacity(binaries.len());
+        for metadata in binaries {
+            let hash = tokio::fs::read(&metadata.path)
+                .await
+                .ok()
+                .map(|wasm_bytes| content_hash(&wasm_bytes));
+            results.push((metadata, hash));
+        }
+        Ok(results)
+    }
+
+    /// Write every binary in `namespace`, bytes and metadata, to a bundle at
+    /// `path` that `import_bundle` can restore on another server. Fails if a
+    /// binary's file no longer matches what was loaded from it, since the
+    /// bundle would then carry code other than what is running. Returns the
+    /// resolved path, how many binaries were written and the bundle's size
+    /// in bytes.
+    pub fn export_bundle(
+        &self,
+        path: impl AsRef<Path>,
+        namespace: Option<&str>,
+    ) -> Result<(PathBuf, usize, u64)> {
+        let path = self.bundle_path(path.as_ref())?;
+        let mut binaries = Vec::new();
+        let mut listed = self.list_binaries_in(namespace);
+        listed.sort_by(|a, b| a.path.cmp(&b.path));
+        for metadata in listed {
+            let wasm_bytes = read_unchanged(&metadata)?;
+            binaries.push((metadata, wasm_bytes));
+        }
+        let size = bundle::write(&path, &binaries)?;
+        Ok((path, binaries.len(), size))
+    }
+
+    /// The bytes of the file binary `id` was loaded from. Fails if the file
+    /// changed since, as they would no longer be what the server runs.
+    pub fn binary_bytes(&self, id: &Uuid) -> Result<Vec<u8>> {
+        read_unchanged(&self.get_binary(id)?.metadata)
+    }
+
+    /// Register every binary in the bundle at `path` in `namespace`, under
+    /// the ID it had when exported. Their bytes are written to `<id>.wasm` in
+    /// a `<bundle name>-binaries` directory beside the bundle, which becomes
+    /// their path; it is returned with the IDs. Nothing is written or
+    /// registered unless every binary verifies and compiles and none of
+    /// their IDs is already loaded.
+    pub fn import_bundle(
+        &self,
+        path: impl AsRef<Path>,
+        namespace: Option<&str>,
+    ) -> Result<(Vec<Uuid>, PathBuf)> {
+        let path = self.bundle_path(path.as_ref())?;
+        let mut dir_name = path.file_stem().unwrap_or_default().to_os_string();
+        dir_name.push("-binaries");
+        let dir = path.with_file_name(dir_name);
+        // The directory may not exist yet; its parent is the bundle's
+        match dir.exists() {
+            true => self.check_allowed(&dir)?,
+            false => self.check_allowed(dir.parent().unwrap_or(&dir))?,
+        }
+
+        let binaries = bundle::read(&path)?;
+        let mut imported = Vec::with_capacity(binaries.len());
+        for (meta, wasm_bytes) in binaries {
+            if self.binaries.contains_key(&meta.id) {
+                return Err(ErrorResponse::new(
+                    ErrorCode::InvalidRequest,
+                    format!("Binary {} is already loaded", meta.id),
+                )
+                .into());
+            }
+            self.verify(&wasm_bytes, meta.signature.as_deref())?;
+            let (kind, compile_time_ms) = self.compile_timed(&wasm_bytes)?;
+            let metadata = BinaryMetadata {
+                path: dir.join(format!("{}.wasm", meta.id)),
+                size: wasm_bytes.len(),
+                loaded_at: std::time::SystemTime::now(),
+                imports: kind.imports(&self.engine),
+                content_hash: Some(content_hash(&wasm_bytes)),
+                compile_time_ms,
+                code_size: kind.code_size(),
+                namespace: namespace.map(str::to_string),
+                ..meta
+            };
+            imported.push((LoadedBinary::new(metadata, kind), wasm_bytes));
+        }
+
+        std::fs::create_dir_all(&dir)
+            .with_context(|| format!("Failed to create directory: {}", dir.display()))?;
+        for (binary, wasm_bytes) in &imported {
+            let wasm_path = &binary.metadata.path;
+            std::fs::write(wasm_path, wasm_bytes)
+                .with_context(|| format!("Failed to write WASM file: {}", wasm_path.display()))?;
+        }
+        let ids: Vec<_> = imported
+            .iter()
+            .map(|(binary, _)| binary.metadata.id)
+            .collect();
+        for (binary, _) in imported {
+            let id = binary.metadata.id;
+            self.binaries.insert(id, binary);
+            self.invalidate_results(&id);
+        }
+        tracing::info!("Imported {} binaries into {}", ids.len(), dir.display());
+        self.save()?;
+        Ok((ids, dir))
+    }
+
+    /// Where a bundle at `path` lives: relative paths are taken from the
+    /// bundle directory, and the result, symlinks followed, must be inside
+    /// it, or inside an allowed directory when none is set. Bundles are
+    /// refused outright when neither is configured, since the server would
+    /// otherwise read and write any file its user can.
+    fn bundle_path(&self, path: &Path) -> Result<PathBuf> {
+        let roots = match &self.bundle_dir {
+            Some(dir) => std::slice::from_ref(dir),
+            None => self.allowed_dirs.as_slice(),
+        };
+        if roots.is_empty() {
+            return Err(ErrorResponse::new(
+                ErrorCode::Unauthorized,
+                "Bundles are disabled; set bundle_dir or allowed_plugin_dirs to enable them",
+            )
+            .into());
+        }
+        let joined = match &self.bundle_dir {
+            Some(dir) => dir.join(path),
+            None => path.to_path_buf(),
+        };
+        // The bundle may not exist yet, so its directory is resolved instead;
+        // anything already at its name, even a dangling symlink, must resolve
+        let resolved = joined
+            .parent()
+            .zip(joined.file_name())
+            .and_then(|(parent, name)| {
+                let path = parent.canonicalize().ok()?.join(name);
+                match path.symlink_metadata() {
+                    Ok(_) => path.canonicalize().ok(),
+                    Err(_) => Some(path),
+                }
+            });
+        match resolved {
+            Some(path) if roots.iter().any(|root| path.starts_with(root)) => Ok(path),
+            _ => {
+                let inside = match self.bundle_dir {
+                    Some(_) => "the bundle directory",
+                    None => "an allowed plugin directory",
+                };
+                Err(ErrorResponse::new(
+                    ErrorCode::Unauthorized,
+                    format!("{} is not inside {}", path.display(), inside),
+                )
+                .into())
+            }
+        }
+    }
+
+    /// Reject `path` unless it resolves, after following `..` and symlinks,
+    /// to somewhere inside an allowed directory. Paths that don't resolve are
+    /// rejected the same way, so the error says nothing about what exists.
+    fn check_allowed(&self, path: &Path) -> Result<()> {
+        if self.allowed_dirs.is_empty() {
+            return Ok(());
+        }
+        let allowed = path
+            .canonicalize()
+            .is_ok_and(|path| self.allowed_dirs.iter().any(|dir| path.starts_with(dir)));
+        if !allowed {
+            return Err(ErrorResponse::new(
+                ErrorCode::Unauthorized,
+                format!(
+                    "{} is not inside an allowed plugin directory",
+                    path.display()
+                ),
+            )
+            .into());
+        }
+        Ok(())
+    }
+
+    /// Check `signature` against the trusted key, returning the signer's
+    /// fingerprint. With no trusted key configured every binary is accepted.
+    fn verify(&self, wasm_bytes: &[u8], signature: Option<&[u8]>) -> Result<Option<String>> {
+        let Some(key) = &self.trusted_key else {
+            return Ok(None);
+        };
+        let signature = signature.ok_or_else(|| {
+            ErrorResponse::new(ErrorCode::VerificationFailed, "Binary is not signed")
+        })?;
+        let signature = Signature::from_slice(signature).map_err(|e| {
+            ErrorResponse::new(
+                ErrorCode::VerificationFailed,
+                format!("Invalid signature: {}", e),
+            )
+        })?;
+        key.verify(wasm_bytes, &signature).map_err(|_| {
+            ErrorResponse::new(
+                ErrorCode::VerificationFailed,
+                "Signature verification failed",
+            )
+        })?;
+        Ok(Some(key_fingerprint(key)))
+    }
+
+    /// Use the cached compilation when there is one. Otherwise validate the
+    /// bytes up front so malformed modules get a specific error, compile them
+    /// and cache the result.
+    fn compile(&self, wasm_bytes: &[u8]) -> Result<BinaryKind> {
+        let Some(cache) = &self.module_cache else {
+            return self.compile_uncached(wasm_bytes);
+        };
+        if let Some(kind) = cache.get(&self.engine, wasm_bytes) {
+            return Ok(kind);
+        }
+        let kind = self.compile_uncached(wasm_bytes)?;
+        if let Err(e) = cache.put(&self.engine, wasm_bytes, &kind) {
+            tracing::warn!("Failed to cache compiled binary: {:#}", e);
+        }
+        Ok(kind)
+    }
+
+    /// `compile`, also returning how long it took in milliseconds, rounded up
+    /// so that any compilation reports at least 1
+    fn compile_timed(&self, wasm_bytes: &[u8]) -> Result<(BinaryKind, u64)> {
+        let started = std::time::Instant::now();
+        let kind = self.compile(wasm_bytes)?;
+        let compile_time_ms = started.elapsed().as_nanos().div_ceil(1_000_000) as u64;
+        Ok((kind, compile_time_ms))
+    }
+
+    fn compile_uncached(&self, wasm_bytes: &[u8]) -> Result<BinaryKind> {
+        if BinaryKind::is_component(wasm_bytes) {
+            // Component compilation validates as part of parsing
+            let component = Component::from_binary(&self.engine, wasm_bytes)
+                .map_err(|e| validation_error(e, wasm_bytes))?;
+            return Ok(BinaryKind::Component(component));
+        }
+        Module::validate(&self.engine, wasm_bytes).map_err(|e| validation_error(e, wasm_bytes))?;
+        // Valid modules can still fail to compile, e.g. for a feature the
+        // engine has turned off; keep wasmtime's reason in the message
+        let module = Module::from_binary(&self.engine, wasm_bytes).map_err(|e| {
+            ErrorResponse::new(
+                ErrorCode::InvalidBinary,
+                format!("Failed to compile WASM module: {:#}", e),
+            )
+        })?;
+        Ok(BinaryKind::Core(module))
+    }
+
     pub fn get_binary(&self, id: &Uuid) -> Result<LoadedBinary> {
         self.binaries
             .get(id)
             .map(|entry| entry.value().clone())
-            .ok_or_else(|| anyhow!("Binary not found: {}", id))
+            .ok_or_else(|| not_found(id))
+    }
+
+    /// The binary in `namespace`, reported as not found when it belongs to
+    /// another one
+    pub fn get_binary_in(&self, id: &Uuid, namespace: Option<&str>) -> Result<LoadedBinary> {
+        self.get_binary(id)
+            .ok()
+            .filter(|binary| binary.metadata.namespace.as_deref() == namespace)
+            .ok_or_else(|| not_found(id))
     }
 
-    pub fn find_binary_by_path(&self, path: impl AsRef<Path>) -> Option<Uuid> {
+    pub fn find_binary_by_path(
+        &self,
+        path: impl AsRef<Path>,
+        namespace: Option<&str>,
+    ) -> Option<Uuid> {
         let path = path.as_ref();
         self.binaries
             .iter()
-            .find(|entry| entry.value().metadata.path == path)
+            .find(|entry| {
+                let metadata = &entry.value().metadata;
+                metadata.path == path && metadata.namespace.as_deref() == namespace
+            })
             .map(|entry| entry.value().metadata.id)
     }
 
-    pub fn unload_binary(&self, id: &Uuid) -> Result<()> {
+    pub fn set_default_config(&self, id: &Uuid, config: Option<ExecutionConfig>) -> Result<()> {
         self.binaries
-            .remove(id)
-            .ok_or_else(|| anyhow!("Binary not found: {}", id))?;
+            .get_mut(id)
+            .ok_or_else(|| not_found(id))?
+            .metadata
+            .default_config = config;
+        self.save()
+    }
+
+    pub fn unload_binary(&self, id: &Uuid) -> Result<()> {
+        self.binaries.remove(id).ok_or_else(|| not_found(id))?;
+        self.invalidate_results(id);
         tracing::info!("Binary unloaded: {}", id);
         self.save()?;
         Ok(())
@@ -137,6 +831,22 @@ impl BinaryRegistry {
             .collect()
     }
 
+    /// The binaries loaded in `namespace`; see `get_binary_in`
+    pub fn list_binaries_in(&self, namespace: Option<&str>) -> Vec<BinaryMetadata> {
+        self.binaries
+            .iter()
+            .map(|entry| entry.value().metadata.clone())
+            .filter(|metadata| metadata.namespace.as_deref() == namespace)
+            .collect()
+    }
+
+    pub fn list_loaded(&self) -> Vec<LoadedBinary> {
+        self.binaries
+            .iter()
+            .map(|entry| entry.value().clone())
+            .collect()
+    }
+
     pub fn print_binaries(&self) -> Result<()> {
         tracing::info!("Loaded Binaries:");
         self.list_binaries().iter().for_each(|meta| {
@@ -159,37 +869,284 @@ impl BinaryRegistry {
         &self.engine
     }
 
+    /// Write every binary's metadata to the metadata file. Loads and unloads
+    /// on different connections save concurrently, so one save runs at a
+    /// time, each writing a snapshot taken once it holds the lock. The file
+    /// is written beside the old one and renamed over it, so it is never
+    /// seen half-written.
     pub fn save(&self) -> Result<()> {
+        let _guard = self
+            .save_lock
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
         let metadata: Vec<_> = self
             .binaries
             .iter()
             .map(|entry| entry.metadata.clone())
             .collect();
         let json = serde_json::to_string(&metadata).context("Failed to serialize metadata")?;
-        std::fs::write("metadata.json", json).context("Failed to write metadata file")?;
+        let mut temp_path = self.metadata_path.clone().into_os_string();
+        temp_path.push(".tmp");
+        std::fs::write(&temp_path, json).context("Failed to write metadata file")?;
+        std::fs::rename(&temp_path, &self.metadata_path)
+            .context("Failed to replace metadata file")?;
         tracing::info!("Binary registry metadata saved");
         Ok(())
     }
 
+    /// Load the binaries listed in the metadata file. An entry that can no
+    /// longer be loaded (its file deleted, unreadable, outside the allowed
+    /// directories, or failing verification or compilation) is skipped with a
+    /// warning and pruned from the file, so one bad plugin doesn't keep the
+    /// rest from loading.
     pub fn load(&self) -> Result<()> {
+        let size = std::fs::metadata(&self.metadata_path)
+            .context("Failed to read metadata file")?
+            .len();
+        if size > MAX_METADATA_BYTES {
+            return Err(anyhow!(
+                "Metadata file {} is {} bytes, more than the {} byte limit",
+                self.metadata_path.display(),
+                size,
+                MAX_METADATA_BYTES
+            ));
+        }
         let data =
-            std::fs::read_to_string("metadata.json").context("Failed to read metadata file")?;
+            std::fs::read_to_string(&self.metadata_path).context("Failed to read metadata file")?;
         let metadata: Vec<BinaryMetadata> =
             serde_json::from_str(&data).context("Failed to deserialize metadata")?;
+        let mut pruned = 0;
         for meta in metadata {
-            let wasm_bytes = std::fs::read(&meta.path)
-                .with_context(|| format!("Failed to read WASM file: {}", meta.path.display()))?;
-            let module = Module::from_binary(&self.engine, &wasm_bytes)
-                .context("Failed to compile WASM module")?;
-            let loaded = LoadedBinary {
-                metadata: meta.clone(),
-                module,
-            };
-            self.binaries.insert(meta.id, loaded);
+            let (id, path) = (meta.id, meta.path.clone());
+            match self.load_saved(meta) {
+                Ok(loaded) => {
+                    self.binaries.insert(id, loaded);
+                }
+                Err(e) => {
+                    tracing::warn!("Dropping binary {} ({}): {:#}", id, path.display(), e);
+                    pruned += 1;
+                }
+            }
+        }
+        if pruned > 0 {
+            self.save()?;
+            tracing::warn!("Pruned {} binaries from the metadata", pruned);
         }
         tracing::info!("Binary registry metadata loaded");
         Ok(())
     }
+
+    /// Recompile a binary recorded in the metadata file from its path. A
+    /// file that no longer matches its recorded hash was changed while the
+    /// server was down and is refused rather than trusted.
+    fn load_saved(&self, meta: BinaryMetadata) -> Result<LoadedBinary> {
+        self.check_allowed(&meta.path)?;
+        let wasm_bytes = std::fs::read(&meta.path)
+            .with_context(|| format!("Failed to read WASM file: {}", meta.path.display()))?;
+        let actual_hash = content_hash(&wasm_bytes);
+        if let Some(expected) = meta
+            .content_hash
+            .as_ref()
+            .filter(|hash| **hash != actual_hash)
+        {
+            return Err(ErrorResponse::new(
+                ErrorCode::VerificationFailed,
+                format!(
+                    "{} changed since it was loaded: SHA-256 {} recorded, {} found",
+                    meta.path.display(),
+                    expected,
+                    actual_hash
+                ),
+            )
+            .into());
+        }
+        self.verify(&wasm_bytes, meta.signature.as_deref())?;
+        let (kind, compile_time_ms) = self.compile_timed(&wasm_bytes)?;
+        // Metadata from older versions lacks imports and hashes; fill them in
+        let metadata = BinaryMetadata {
+            imports: kind.imports(&self.engine),
+            content_hash: Some(actual_hash),
+            compile_time_ms,
+            code_size: kind.code_size(),
+            ..meta
+        };
+        Ok(LoadedBinary::new(metadata, kind))
+    }
+}
+
+/// The manifest shipped with the binary at `wasm_path`: `<name>.toml` beside
+/// `<name>.wasm`, or else a `plugin.toml` in the same directory when that is
+/// the directory's only `.wasm` file. `None` when there is neither.
+fn read_manifest(wasm_path: &Path) -> Result<Option<PluginManifest>> {
+    let own = wasm_path.with_extension("toml");
+    let shared = wasm_path.with_file_name("plugin.toml");
+    let path = if own.is_file() {
+        own
+    } else if shared.is_file() && only_wasm_in_dir(wasm_path) {
+        shared
+    } else {
+        return Ok(None);
+    };
+    let invalid = |message: String| {
+        ErrorResponse::new(
+            ErrorCode::InvalidBinary,
+            format!("Invalid manifest {}: {}", path.display(), message),
+        )
+    };
+    let data = std::fs::read_to_string(&path).map_err(|e| invalid(e.to_string()))?;
+    let manifest: PluginManifest = toml::from_str(&data).map_err(|e| invalid(e.to_string()))?;
+    if let Some(config) = &manifest.default_config {
+        config.validate().map_err(|e| invalid(e.to_string()))?;
+    }
+    if let Some(schema) = &manifest.input_schema {
+        jsonschema::validator_for(schema)
+            .map_err(|e| invalid(format!("input_schema is not a valid JSON Schema: {}", e)))?;
+    }
+    Ok(Some(manifest))
+}
+
+/// Whether `wasm_path` is the one `.wasm` file in its directory, so a
+/// directory-wide manifest can only be describing it
+fn only_wasm_in_dir(wasm_path: &Path) -> bool {
+    let Some(Ok(entries)) = wasm_path.parent().map(std::fs::read_dir) else {
+        return false;
+    };
+    entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "wasm"))
+        .take(2)
+        .count()
+        == 1
+}
+
+fn content_hash(wasm_bytes: &[u8]) -> String {
+    hex::encode(Sha256::digest(wasm_bytes))
+}
+
+/// Read the file `metadata` was loaded from, failing if its content hash no
+/// longer matches the one recorded at load time
+fn read_unchanged(metadata: &BinaryMetadata) -> Result<Vec<u8>> {
+    let wasm_bytes = std::fs::read(&metadata.path)
+        .with_context(|| format!("Failed to read WASM file: {}", metadata.path.display()))?;
+    if metadata.content_hash.as_deref() != Some(content_hash(&wasm_bytes).as_str()) {
+        return Err(ErrorResponse::new(
+            ErrorCode::InvalidRequest,
+            format!(
+                "{} changed since binary {} was loaded; reload it first",
+                metadata.path.display(),
+                metadata.id
+            ),
+        )
+        .into());
+    }
+    Ok(wasm_bytes)
+}
+
+fn not_found(id: &Uuid) -> anyhow::Error {
+    ErrorResponse::new(ErrorCode::NotFound, format!("Binary not found: {}", id)).into()
+}
+
+/// Wasmtime's parse or validation error, with the offset it gives and, for
+/// core modules, the section that offset falls in
+fn validation_error(e: anyhow::Error, wasm_bytes: &[u8]) -> ErrorResponse {
+    let detail = match e.downcast_ref::<BinaryReaderError>() {
+        Some(parse) => {
+            let section = match BinaryKind::is_component(wasm_bytes) {
+                true => None,
+                false => section_at(wasm_bytes, parse.offset()),
+            };
+            format!(
+                "{} at offset {:#x}{}",
+                parse.message(),
+                parse.offset(),
+                section.map_or(String::new(), |name| format!(" in the {} section", name))
+            )
+        }
+        None => format!("{:#}", e),
+    };
+    ErrorResponse::new(
+        ErrorCode::InvalidBinary,
+        format!("WASM validation failed: {}", detail),
+    )
+}
+
+/// Core module section names, by section ID
+const SECTION_NAMES: &[&str] = &[
+    "custom",
+    "type",
+    "import",
+    "function",
+    "table",
+    "memory",
+    "global",
+    "export",
+    "start",
+    "element",
+    "code",
+    "data",
+    "data count",
+];
+
+/// Name of the core module section holding byte `offset`, found by walking
+/// the section headers. `None` for the preamble, unknown section IDs, or
+/// headers too broken to read.
+fn section_at(wasm_bytes: &[u8], offset: usize) -> Option<&'static str> {
+    // Past the magic number and version
+    let mut start = 8;
+    while start < wasm_bytes.len() && start <= offset {
+        let id = wasm_bytes[start];
+        let (size, size_len) = read_leb128_u32(&wasm_bytes[start + 1..])?;
+        let end = start + 1 + size_len + size as usize;
+        if offset < end {
+            return SECTION_NAMES.get(id as usize).copied();
+        }
+        start = end;
+    }
+    None
+}
+
+/// An unsigned LEB128 `u32` and how many bytes it took
+fn read_leb128_u32(bytes: &[u8]) -> Option<(u32, usize)> {
+    let mut value = 0u32;
+    for (i, &byte) in bytes.iter().take(5).enumerate() {
+        value |= u32::from(byte & 0x7f) << (7 * i);
+        if byte & 0x80 == 0 {
+            return Some((value, i + 1));
+        }
+    }
+    None
+}
+
+/// Render an import's type the way it reads in the text format
+fn describe_extern(ty: &ExternType) -> String {
+    match ty {
+        ExternType::Func(func) => {
+            let params: Vec<_> = func.params().map(|p| p.to_string()).collect();
+            let results: Vec<_> = func.results().map(|r| r.to_string()).collect();
+            if results.is_empty() {
+                format!("func({})", params.join(", "))
+            } else {
+                format!("func({}) -> ({})", params.join(", "), results.join(", "))
+            }
+        }
+        ExternType::Memory(_) => "memory".to_string(),
+        ExternType::Table(_) => "table".to_string(),
+        ExternType::Global(global) => format!("global {}", global.content()),
+    }
+}
+
+/// Short hex fingerprint of a public key: the first 8 bytes of its SHA-256
+pub fn key_fingerprint(key: &VerifyingKey) -> String {
+    hex::encode(&Sha256::digest(key.as_bytes())[..8])
+}
+
+/// Parse a hex-encoded 32-byte Ed25519 public key
+pub fn parse_verifying_key(hex_key: &str) -> Result<VerifyingKey> {
+    let bytes: [u8; 32] = hex::decode(hex_key.trim())
+        .context("Trusted key is not valid hex")?
+        .try_into()
+        .map_err(|_| anyhow!("Trusted key must be 32 bytes"))?;
+    VerifyingKey::from_bytes(&bytes).map_err(|e| anyhow!("Invalid trusted key: {}", e))
 }
 
 #[cfg(test)]
@@ -220,4 +1177,666 @@ mod tests {
         // registry.unload_binary(&id).unwrap();
         // assert_eq!(registry.count(), 0);
     }
+
+    #[tokio::test]
+    async fn test_truncated_binary_reports_validation_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let registry =
+            BinaryRegistry::with_metadata_path(Engine::default(), dir.path().join("metadata.json"));
+
+        let bytes =
+            wat::parse_str(r#"(module (memory (export "memory") 1 4) (func (export "process")))"#)
+                .unwrap();
+        let path = dir.path().join("truncated.wasm");
+        std::fs::write(&path, &bytes[..bytes.len() - 4]).unwrap();
+
+        let err = registry.load_binary(&path, None).await.unwrap_err();
+        assert!(
+            err.to_string().starts_with("WASM validation failed"),
+            "unexpected error: {}",
+            err
+        );
+        assert_eq!(registry.count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_corrupted_binary_reports_offset_and_section() {
+        let dir = tempfile::tempdir().unwrap();
+        let registry =
+            BinaryRegistry::with_metadata_path(Engine::default(), dir.path().join("metadata.json"));
+
+        let mut bytes =
+            wat::parse_str(r#"(module (memory (export "memory") 1 4) (func (export "process")))"#)
+                .unwrap();
+        // The memory count, first byte of the memory section's contents
+        assert_eq!(&bytes[18..21], &[5, 4, 1]);
+        bytes[20] = 0xff;
+        let path = dir.path().join("corrupted.wasm");
+        std::fs::write(&path, &bytes).unwrap();
+
+        let err = registry.load_binary(&path, None).await.unwrap_err();
+        let message = err.to_string();
+        assert!(
+            message.starts_with("WASM validation failed"),
+            "unexpected error: {}",
+            message
+        );
+        assert!(message.contains("at offset 0x14"), "{}", message);
+        assert!(message.contains("in the memory section"), "{}", message);
+    }
+
+    #[test]
+    fn test_section_at() {
+        let bytes = wat::parse_str(r#"(module (memory 1) (func))"#).unwrap();
+        assert_eq!(section_at(&bytes, 3), None);
+        assert_eq!(section_at(&bytes, 9), Some("type"));
+        assert_eq!(section_at(&bytes, bytes.len() - 1), Some("code"));
+        assert_eq!(section_at(&bytes, bytes.len()), None);
+    }
+
+    #[tokio::test]
+    async fn test_memory_pages_and_exports() {
+        let dir = tempfile::tempdir().unwrap();
+        let registry =
+            BinaryRegistry::with_metadata_path(Engine::default(), dir.path().join("metadata.json"));
+
+        let path = dir.path().join("plugin.wasm");
+        let bytes =
+            wat::parse_str(r#"(module (memory (export "memory") 2 8) (func (export "process")))"#)
+                .unwrap();
+        std::fs::write(&path, bytes).unwrap();
+
+        let id = registry.load_binary(&path, None).await.unwrap();
+        let binary = registry.get_binary(&id).unwrap();
+        assert_eq!(binary.memory_pages(), Some((2, Some(8))));
+        assert!(binary.metadata.compile_time_ms > 0);
+        assert!(binary.metadata.code_size > 0);
+        assert_eq!(
+            binary.export_names(registry.engine()),
+            vec!["memory", "process"]
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_concurrent_loads_all_saved() {
+        let dir = tempfile::tempdir().unwrap();
+        let metadata_path = dir.path().join("metadata.json");
+        let registry = BinaryRegistry::with_metadata_path(Engine::default(), &metadata_path);
+        let bytes = wat::parse_str(r#"(module (memory (export "memory") 1))"#).unwrap();
+
+        let loads: Vec<_> = (0..8)
+            .map(|i| {
+                let path = dir.path().join(format!("plugin-{}.wasm", i));
+                std::fs::write(&path, &bytes).unwrap();
+                let registry = registry.clone();
+                tokio::spawn(async move { registry.load_binary(&path, None).await.unwrap() })
+            })
+            .collect();
+        let mut ids = Vec::new();
+        for load in loads {
+            ids.push(load.await.unwrap());
+        }
+
+        let saved: Vec<BinaryMetadata> =
+            serde_json::from_str(&std::fs::read_to_string(&metadata_path).unwrap()).unwrap();
+        let mut saved_ids: Vec<_> = saved.iter().map(|meta| meta.id).collect();
+        saved_ids.sort();
+        ids.sort();
+        assert_eq!(saved_ids, ids);
+        assert!(!dir.path().join("metadata.json.tmp").exists());
+    }
+
+    #[tokio::test]
+    async fn test_idle_binary_locks_dropped_on_reload_and_unload() {
+        let dir = tempfile::tempdir().unwrap();
+        let registry =
+            BinaryRegistry::with_metadata_path(Engine::default(), dir.path().join("metadata.json"));
+        let path = dir.path().join("plugin.wasm");
+        let bytes = wat::parse_str(r#"(module (memory (export "memory") 1))"#).unwrap();
+        std::fs::write(&path, &bytes).unwrap();
+        let id = registry.load_binary(&path, None).await.unwrap();
+
+        // A run still holding the lock keeps it across a reload
+        let held = registry.binary_lock(id);
+        let guard = held.clone().lock_owned().await;
+        registry.load_binary(&path, None).await.unwrap();
+        assert!(Arc::ptr_eq(&registry.binary_lock(id), &held));
+        drop(guard);
+        drop(held);
+
+        registry.load_binary(&path, None).await.unwrap();
+        assert!(registry.binary_locks.is_empty());
+        registry.binary_lock(id);
+        registry.unload_binary(&id).unwrap();
+        assert!(registry.binary_locks.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_load_prunes_binaries_whose_file_is_gone() {
+        let dir = tempfile::tempdir().unwrap();
+        let metadata_path = dir.path().join("metadata.json");
+        let registry = BinaryRegistry::with_metadata_path(Engine::default(), &metadata_path);
+        let bytes = wat::parse_str(r#"(module (memory (export "memory") 1))"#).unwrap();
+        let kept = dir.path().join("kept.wasm");
+        let deleted = dir.path().join("deleted.wasm");
+        std::fs::write(&kept, &bytes).unwrap();
+        std::fs::write(&deleted, &bytes).unwrap();
+        let kept_id = registry.load_binary(&kept, None).await.unwrap();
+        registry.load_binary(&deleted, None).await.unwrap();
+        std::fs::remove_file(&deleted).unwrap();
+
+        let restarted = BinaryRegistry::with_metadata_path(Engine::default(), &metadata_path);
+        restarted.load().unwrap();
+        assert_eq!(restarted.count(), 1);
+        assert!(restarted.get_binary(&kept_id).is_ok());
+
+        // The dead entry is gone from the file too
+        let saved: Vec<BinaryMetadata> =
+            serde_json::from_str(&std::fs::read_to_string(&metadata_path).unwrap()).unwrap();
+        let saved_ids: Vec<_> = saved.iter().map(|meta| meta.id).collect();
+        assert_eq!(saved_ids, vec![kept_id]);
+    }
+
+    #[tokio::test]
+    async fn test_load_prunes_bad_binaries_under_allowed_dirs() {
+        let dir = tempfile::tempdir().unwrap();
+        let plugins = dir.path().join("plugins");
+        std::fs::create_dir(&plugins).unwrap();
+        let metadata_path = dir.path().join("metadata.json");
+        let registry = || {
+            BinaryRegistry::with_metadata_path(Engine::default(), &metadata_path)
+                .with_allowed_dirs([&plugins])
+                .unwrap()
+        };
+        let bytes = wat::parse_str(r#"(module (memory (export "memory") 1))"#).unwrap();
+        let loaded = registry();
+        let mut ids = Vec::new();
+        for name in ["kept", "deleted", "corrupted"] {
+            let path = plugins.join(format!("{name}.wasm"));
+            std::fs::write(&path, &bytes).unwrap();
+            ids.push(loaded.load_binary(&path, None).await.unwrap());
+        }
+        // The allow-list check can't resolve a deleted file either
+        std::fs::remove_file(plugins.join("deleted.wasm")).unwrap();
+        std::fs::write(plugins.join("corrupted.wasm"), b"\0asm garbage").unwrap();
+
+        let restarted = registry();
+        restarted.load().unwrap();
+        assert_eq!(restarted.count(), 1);
+        assert!(restarted.get_binary(&ids[0]).is_ok());
+        let saved: Vec<BinaryMetadata> =
+            serde_json::from_str(&std::fs::read_to_string(&metadata_path).unwrap()).unwrap();
+        let saved_ids: Vec<_> = saved.iter().map(|meta| meta.id).collect();
+        assert_eq!(saved_ids, vec![ids[0]]);
+    }
+
+    #[tokio::test]
+    async fn test_load_refuses_files_changed_while_down() {
+        let dir = tempfile::tempdir().unwrap();
+        let metadata_path = dir.path().join("metadata.json");
+        let registry = BinaryRegistry::with_metadata_path(Engine::default(), &metadata_path);
+        let path = dir.path().join("plugin.wasm");
+        let original = wat::parse_str(r#"(module (func (export "process")))"#).unwrap();
+        std::fs::write(&path, &original).unwrap();
+        let id = registry.load_binary(&path, None).await.unwrap();
+        registry.save().unwrap();
+
+        // Still valid wasm, just not what was loaded
+        let swapped =
+            wat::parse_str(r#"(module (func (export "process") (result i32) (i32.const 1)))"#)
+                .unwrap();
+        std::fs::write(&path, &swapped).unwrap();
+
+        let restarted = BinaryRegistry::with_metadata_path(Engine::default(), &metadata_path);
+        restarted.load().unwrap();
+        assert!(restarted.get_binary(&id).is_err());
+        let saved: Vec<BinaryMetadata> =
+            serde_json::from_str(&std::fs::read_to_string(&metadata_path).unwrap()).unwrap();
+        assert!(saved.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_load_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let plugins = dir.path().join("plugins");
+        std::fs::create_dir(&plugins).unwrap();
+        let registry =
+            BinaryRegistry::with_metadata_path(Engine::default(), dir.path().join("metadata.json"));
+
+        let bytes =
+            wat::parse_str(r#"(module (memory (export "memory") 1) (func (export "process")))"#)
+                .unwrap();
+        std::fs::write(plugins.join("a.wasm"), &bytes).unwrap();
+        std::fs::write(plugins.join("b.wasm"), &bytes).unwrap();
+        std::fs::write(plugins.join("notes.txt"), "not a plugin").unwrap();
+
+        let ids = registry.load_directory(&plugins).await.unwrap();
+        assert_eq!(ids.len(), 2);
+        assert_eq!(registry.count(), 2);
+        assert_eq!(
+            registry.find_binary_by_path(plugins.join("a.wasm"), None),
+            Some(ids[0])
+        );
+        assert_eq!(
+            registry.find_binary_by_path(plugins.join("b.wasm"), None),
+            Some(ids[1])
+        );
+
+        // Loading again updates the same entries
+        assert_eq!(registry.load_directory(&plugins).await.unwrap(), ids);
+        assert_eq!(registry.count(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_bundle_round_trip_keeps_ids() {
+        let dir = tempfile::tempdir().unwrap();
+        let plugins = dir.path().join("plugins");
+        std::fs::create_dir(&plugins).unwrap();
+        let registry =
+            BinaryRegistry::with_metadata_path(Engine::default(), dir.path().join("metadata.json"))
+                .with_bundle_dir(dir.path())
+                .unwrap();
+
+        let small =
+            wat::parse_str(r#"(module (memory (export "memory") 1) (func (export "process")))"#)
+                .unwrap();
+        let large = wat::parse_str(
+            r#"(module
+                (memory (export "memory") 1)
+                (func (export "process") (result i32) (i32.const 7))
+                (func (export "other") (result i32) (i32.const 8)))"#,
+        )
+        .unwrap();
+        std::fs::write(plugins.join("small.wasm"), &small).unwrap();
+        std::fs::write(plugins.join("large.wasm"), &large).unwrap();
+        let small_id = registry
+            .load_binary(plugins.join("small.wasm"), None)
+            .await
+            .unwrap();
+        let large_id = registry
+            .load_binary(plugins.join("large.wasm"), None)
+            .await
+            .unwrap();
+        let config = ExecutionConfig::builder().timeout(1234).build().unwrap();
+        registry
+            .set_default_config(&large_id, Some(config.clone()))
+            .unwrap();
+
+        let bundle = dir.path().join("registry.tar");
+        let (path, count, size) = registry.export_bundle("registry.tar", None).unwrap();
+        assert_eq!(path, bundle.canonicalize().unwrap());
+        assert_eq!(count, 2);
+        assert_eq!(size, std::fs::metadata(&bundle).unwrap().len());
+
+        registry.unload_binary(&small_id).unwrap();
+        registry.unload_binary(&large_id).unwrap();
+        assert_eq!(registry.count(), 0);
+
+        let (mut ids, restored) = registry.import_bundle(&bundle, None).unwrap();
+        assert!(restored.ends_with("registry-binaries"));
+        ids.sort();
+        let mut expected = vec![small_id, large_id];
+        expected.sort();
+        assert_eq!(ids, expected);
+        for (id, bytes) in [(small_id, &small), (large_id, &large)] {
+            let metadata = registry.get_binary(&id).unwrap().metadata;
+            assert_eq!(metadata.size, bytes.len());
+            assert_eq!(metadata.path, restored.join(format!("{}.wasm", id)));
+            assert_eq!(std::fs::read(&metadata.path).unwrap(), *bytes);
+        }
+        let metadata = registry.get_binary(&large_id).unwrap().metadata;
+        assert_eq!(metadata.default_config.unwrap().timeout_ms, 1234);
+
+        // The restored binaries are what a restart finds too
+        let restarted =
+            BinaryRegistry::with_metadata_path(Engine::default(), dir.path().join("metadata.json"));
+        restarted.load().unwrap();
+        assert_eq!(restarted.count(), 2);
+
+        // A file edited since it was loaded isn't exported
+        std::fs::write(restored.join(format!("{}.wasm", small_id)), &large).unwrap();
+        let err = registry.export_bundle(&bundle, None).unwrap_err();
+        let err = err.downcast_ref::<ErrorResponse>().unwrap();
+        assert_eq!(err.code, ErrorCode::InvalidRequest);
+    }
+
+    #[tokio::test]
+    async fn test_allowed_dirs() {
+        let dir = tempfile::tempdir().unwrap();
+        let plugins = dir.path().join("plugins");
+        std::fs::create_dir(&plugins).unwrap();
+        let registry =
+            BinaryRegistry::with_metadata_path(Engine::default(), dir.path().join("metadata.json"))
+                .with_allowed_dirs([&plugins])
+                .unwrap();
+
+        let bytes = wat::parse_str(r#"(module (func (export "process")))"#).unwrap();
+        std::fs::write(plugins.join("inside.wasm"), &bytes).unwrap();
+        std::fs::write(dir.path().join("outside.wasm"), &bytes).unwrap();
+        std::os::unix::fs::symlink(dir.path().join("outside.wasm"), plugins.join("link.wasm"))
+            .unwrap();
+
+        assert!(registry
+            .load_binary(plugins.join("inside.wasm"), None)
+            .await
+            .is_ok());
+
+        for path in [
+            plugins.join("../../etc/x.wasm"),
+            plugins.join("../outside.wasm"),
+            dir.path().join("outside.wasm"),
+            plugins.join("link.wasm"),
+        ] {
+            let err = registry.load_binary(&path, None).await.unwrap_err();
+            let err = err.downcast_ref::<ErrorResponse>().unwrap();
+            assert_eq!(err.code, ErrorCode::Unauthorized, "{}", path.display());
+        }
+        assert_eq!(registry.count(), 1);
+
+        // The allowed directories themselves must exist
+        let missing =
+            BinaryRegistry::new(Engine::default()).with_allowed_dirs([dir.path().join("nope")]);
+        assert!(missing.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_bundle_paths_stay_inside_bundle_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let bundles = dir.path().join("bundles");
+        let plugins = dir.path().join("plugins");
+        std::fs::create_dir(&bundles).unwrap();
+        std::fs::create_dir(&plugins).unwrap();
+        std::os::unix::fs::symlink(dir.path().join("outside.tar"), bundles.join("link.tar"))
+            .unwrap();
+        let metadata_path = dir.path().join("metadata.json");
+        let assert_refused = |result: Result<(PathBuf, usize, u64)>| {
+            let err = result.unwrap_err();
+            let err = err.downcast_ref::<ErrorResponse>().unwrap();
+            assert_eq!(err.code, ErrorCode::Unauthorized);
+        };
+
+        // Without a bundle directory or allowed directories, no path is safe
+        let open = BinaryRegistry::with_metadata_path(Engine::default(), &metadata_path);
+        assert_refused(open.export_bundle(bundles.join("a.tar"), None));
+
+        let registry = BinaryRegistry::with_metadata_path(Engine::default(), &metadata_path)
+            .with_bundle_dir(&bundles)
+            .unwrap();
+        for path in [
+            PathBuf::from("../escape.tar"),
+            dir.path().join("outside.tar"),
+            bundles.join("link.tar"),
+            PathBuf::from(".."),
+        ] {
+            assert_refused(registry.export_bundle(&path, None));
+        }
+        assert!(!dir.path().join("escape.tar").exists());
+        assert!(!dir.path().join("outside.tar").exists());
+        let (path, _, _) = registry.export_bundle("a.tar", None).unwrap();
+        assert_eq!(path, bundles.canonicalize().unwrap().join("a.tar"));
+
+        // The allowed directories stand in for an unset bundle directory
+        let restricted = BinaryRegistry::with_metadata_path(Engine::default(), &metadata_path)
+            .with_allowed_dirs([&plugins])
+            .unwrap();
+        assert_refused(restricted.export_bundle(bundles.join("b.tar"), None));
+        assert!(restricted
+            .export_bundle(plugins.join("b.tar"), None)
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_import_checks_everything_before_writing() {
+        let dir = tempfile::tempdir().unwrap();
+        let plugins = dir.path().join("plugins");
+        std::fs::create_dir(&plugins).unwrap();
+        let registry =
+            BinaryRegistry::with_metadata_path(Engine::default(), dir.path().join("metadata.json"))
+                .with_bundle_dir(dir.path())
+                .unwrap();
+        let wasm = wat::parse_str(r#"(module (func (export "process")))"#).unwrap();
+        std::fs::write(plugins.join("plugin.wasm"), &wasm).unwrap();
+        let id = registry
+            .load_binary(plugins.join("plugin.wasm"), None)
+            .await
+            .unwrap();
+        registry.export_bundle("good.tar", None).unwrap();
+
+        // IDs already loaded are refused rather than replaced
+        let err = registry.import_bundle("good.tar", None).unwrap_err();
+        let err = err.downcast_ref::<ErrorResponse>().unwrap();
+        assert_eq!(err.code, ErrorCode::InvalidRequest);
+        assert!(!dir.path().join("good-binaries").exists());
+
+        // A binary that doesn't compile stops the import before any write
+        let metadata = BinaryMetadata {
+            id: Uuid::new_v4(),
+            ..registry.get_binary(&id).unwrap().metadata
+        };
+        bundle::write(
+            &dir.path().join("bad.tar"),
+            &[(metadata.clone(), b"not wasm".to_vec())],
+        )
+        .unwrap();
+        assert!(registry.import_bundle("bad.tar", None).is_err());
+        assert!(!dir.path().join("bad-binaries").exists());
+        assert!(registry.get_binary(&metadata.id).is_err());
+
+        // Imported files must land where a restart may load them from
+        let restricted =
+            BinaryRegistry::with_metadata_path(Engine::default(), dir.path().join("other.json"))
+                .with_bundle_dir(dir.path())
+                .unwrap()
+                .with_allowed_dirs([&plugins])
+                .unwrap();
+        let err = restricted.import_bundle("good.tar", None).unwrap_err();
+        let err = err.downcast_ref::<ErrorResponse>().unwrap();
+        assert_eq!(err.code, ErrorCode::Unauthorized);
+        assert!(!dir.path().join("good-binaries").exists());
+
+        // Binaries join the importer's namespace, not the exporter's
+        registry.unload_binary(&id).unwrap();
+        let (ids, _) = registry.import_bundle("good.tar", Some("beta")).unwrap();
+        assert_eq!(ids, vec![id]);
+        assert!(registry.get_binary_in(&id, Some("beta")).is_ok());
+        assert!(registry.get_binary_in(&id, None).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_manifest_read_from_sibling_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let registry =
+            BinaryRegistry::with_metadata_path(Engine::default(), dir.path().join("metadata.json"));
+        let bytes = wat::parse_str(r#"(module (func (export "process")))"#).unwrap();
+        let path = dir.path().join("hasher.wasm");
+        std::fs::write(&path, &bytes).unwrap();
+        std::fs::write(
+            dir.path().join("hasher.toml"),
+            r#"
+name = "hasher"
+version = "1.2.0"
+author = "Plugin Team"
+capabilities = ["write_output"]
+
+[default_config]
+timeout_ms = 2000
+memory_limit_mb = 16
+"#,
+        )
+        .unwrap();
+
+        let id = registry.load_binary(&path, None).await.unwrap();
+        let metadata = registry.get_binary(&id).unwrap().metadata;
+        let manifest = metadata.manifest.unwrap();
+        assert_eq!(manifest.name, "hasher");
+        assert_eq!(manifest.version.as_deref(), Some("1.2.0"));
+        assert_eq!(manifest.author.as_deref(), Some("Plugin Team"));
+        assert_eq!(manifest.capabilities, vec!["write_output"]);
+        assert_eq!(metadata.default_config.unwrap().timeout_ms, 2000);
+
+        // Without a manifest there is nothing to report
+        let bare = dir.path().join("bare");
+        std::fs::create_dir(&bare).unwrap();
+        std::fs::write(bare.join("plain.wasm"), &bytes).unwrap();
+        let id = registry
+            .load_binary(bare.join("plain.wasm"), None)
+            .await
+            .unwrap();
+        assert!(registry
+            .get_binary(&id)
+            .unwrap()
+            .metadata
+            .manifest
+            .is_none());
+
+        // A malformed manifest fails the load
+        std::fs::write(bare.join("plugin.toml"), "nmae = \"typo\"\n").unwrap();
+        let err = registry
+            .load_binary(bare.join("plain.wasm"), None)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().starts_with("Invalid manifest"), "{}", err);
+
+        // ...but only while the directory holds that one binary; with two,
+        // plugin.toml could belong to either and is ignored
+        std::fs::write(bare.join("other.wasm"), &bytes).unwrap();
+        let id = registry
+            .load_binary(bare.join("plain.wasm"), None)
+            .await
+            .unwrap();
+        assert!(registry
+            .get_binary(&id)
+            .unwrap()
+            .metadata
+            .manifest
+            .is_none());
+    }
+
+    fn signed_fixture(dir: &Path) -> (PathBuf, Vec<u8>, ed25519_dalek::SigningKey) {
+        use ed25519_dalek::Signer;
+
+        let signing_key = ed25519_dalek::SigningKey::from_bytes(&[7u8; 32]);
+        let bytes = wat::parse_str(r#"(module (func (export "process")))"#).unwrap();
+        let signature = signing_key.sign(&bytes).to_bytes().to_vec();
+        let path = dir.join("signed.wasm");
+        std::fs::write(&path, bytes).unwrap();
+        (path, signature, signing_key)
+    }
+
+    #[tokio::test]
+    async fn test_valid_signature_records_signer() {
+        let dir = tempfile::tempdir().unwrap();
+        let (path, signature, signing_key) = signed_fixture(dir.path());
+        let registry =
+            BinaryRegistry::with_metadata_path(Engine::default(), dir.path().join("metadata.json"))
+                .with_trusted_key(signing_key.verifying_key());
+
+        assert!(registry.load_binary(&path, None).await.is_err());
+
+        let id = registry.load_binary(&path, Some(&signature)).await.unwrap();
+        let metadata = registry.get_binary(&id).unwrap().metadata;
+        assert_eq!(
+            metadata.signer,
+            Some(key_fingerprint(&signing_key.verifying_key()))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_tampered_binary_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let (path, signature, signing_key) = signed_fixture(dir.path());
+        let registry =
+            BinaryRegistry::with_metadata_path(Engine::default(), dir.path().join("metadata.json"))
+                .with_trusted_key(signing_key.verifying_key());
+
+        let tampered = wat::parse_str(r#"(module (func (export "process") nop))"#).unwrap();
+        std::fs::write(&path, tampered).unwrap();
+
+        let err = registry
+            .load_binary(&path, Some(&signature))
+            .await
+            .unwrap_err();
+        assert_eq!(err.to_string(), "Signature verification failed");
+        assert_eq!(registry.count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_verification_disabled_accepts_unsigned() {
+        let dir = tempfile::tempdir().unwrap();
+        let (path, _, _) = signed_fixture(dir.path());
+        let registry =
+            BinaryRegistry::with_metadata_path(Engine::default(), dir.path().join("metadata.json"));
+
+        let id = registry.load_binary(&path, None).await.unwrap();
+        assert_eq!(registry.get_binary(&id).unwrap().metadata.signer, None);
+    }
+
+    #[tokio::test]
+    async fn test_imports_recorded() {
+        let dir = tempfile::tempdir().unwrap();
+        let registry =
+            BinaryRegistry::with_metadata_path(Engine::default(), dir.path().join("metadata.json"));
+
+        let path = dir.path().join("logger.wasm");
+        let bytes = wat::parse_str(
+            r#"(module
+                (import "host" "log" (func (param i32 i32)))
+                (memory (export "memory") 1)
+                (func (export "process")))"#,
+        )
+        .unwrap();
+        std::fs::write(&path, bytes).unwrap();
+
+        let id = registry.load_binary(&path, None).await.unwrap();
+        assert_eq!(
+            registry.get_binary(&id).unwrap().metadata.imports,
+            vec![ImportInfo {
+                module: "host".to_string(),
+                name: "log".to_string(),
+                ty: "func(i32, i32)".to_string(),
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_missing_memory_export_flagged() {
+        let dir = tempfile::tempdir().unwrap();
+        let registry =
+            BinaryRegistry::with_metadata_path(Engine::default(), dir.path().join("metadata.json"));
+
+        let path = dir.path().join("no-memory.wasm");
+        let bytes = wat::parse_str(r#"(module (func (export "process")))"#).unwrap();
+        std::fs::write(&path, bytes).unwrap();
+
+        let id = registry.load_binary(&path, None).await.unwrap();
+        assert!(registry.get_binary(&id).unwrap().needs_memory_export());
+    }
+
+    #[tokio::test]
+    async fn test_precompiled_artifacts_used_on_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let plugins = dir.path().join("plugins");
+        std::fs::create_dir(&plugins).unwrap();
+        let path = plugins.join("noop.wasm");
+        let bytes = wat::parse_str(r#"(module (memory (export "memory") 1))"#).unwrap();
+        std::fs::write(&path, &bytes).unwrap();
+        std::fs::write(plugins.join("README.txt"), "not a plugin").unwrap();
+
+        let cache = ModuleCache::new(dir.path().join("cache")).unwrap();
+        let precompiler =
+            BinaryRegistry::with_metadata_path(Engine::default(), dir.path().join("metadata.json"))
+                .with_module_cache(cache.clone());
+        let artifacts = precompiler.precompile_dir(&plugins).unwrap();
+        assert_eq!(artifacts.len(), 1);
+        assert!(artifacts[0].exists());
+        assert_eq!(cache.hits(), 0);
+
+        // A fresh server process: same cache directory, new engine
+        let cache = ModuleCache::new(dir.path().join("cache")).unwrap();
+        let registry =
+            BinaryRegistry::with_metadata_path(Engine::default(), dir.path().join("metadata.json"))
+                .with_module_cache(cache.clone());
+        registry.load_binary(&path, None).await.unwrap();
+        assert_eq!(cache.hits(), 1);
+    }
 }
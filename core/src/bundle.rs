@@ -0,0 +1,96 @@
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::Path;
+use uuid::Uuid;
+use wasm_shared::{ErrorCode, ErrorResponse};
+
+use crate::binary_registry::BinaryMetadata;
+
+/// Name of the bundle entry listing every binary's metadata
+const METADATA_ENTRY: &str = "metadata.json";
+
+/// Directory of the bundle holding the wasm bytes, one `<id>.wasm` per binary
+const BINARIES_DIR: &str = "binaries";
+
+/// Write `binaries` to a tar archive at `path`: their metadata as
+/// `metadata.json` and their bytes as `binaries/<id>.wasm`. The archive is
+/// written beside `path` and renamed over it. Returns its size in bytes.
+pub fn write(path: &Path, binaries: &[(BinaryMetadata, Vec<u8>)]) -> Result<u64> {
+    let metadata: Vec<_> = binaries.iter().map(|(metadata, _)| metadata).collect();
+    let json = serde_json::to_vec_pretty(&metadata).context("Failed to serialize metadata")?;
+
+    let mut temp_path = path.as_os_str().to_owned();
+    temp_path.push(".tmp");
+    let file = std::fs::File::create(&temp_path)
+        .with_context(|| format!("Failed to create bundle: {}", path.display()))?;
+    let mut archive = tar::Builder::new(file);
+    append(&mut archive, METADATA_ENTRY, &json)?;
+    for (metadata, wasm_bytes) in binaries {
+        let name = format!("{}/{}.wasm", BINARIES_DIR, metadata.id);
+        append(&mut archive, &name, wasm_bytes)?;
+    }
+    let file = archive.into_inner().context("Failed to write bundle")?;
+    let size = file.metadata()?.len();
+    std::fs::rename(&temp_path, path)
+        .with_context(|| format!("Failed to write bundle: {}", path.display()))?;
+    Ok(size)
+}
+
+fn append(archive: &mut tar::Builder<std::fs::File>, name: &str, data: &[u8]) -> Result<()> {
+    let mut header = tar::Header::new_gnu();
+    header.set_size(data.len() as u64);
+    header.set_mode(0o644);
+    header.set_cksum();
+    archive
+        .append_data(&mut header, name, data)
+        .with_context(|| format!("Failed to add {} to bundle", name))
+}
+
+/// Read a bundle written by `write`, pairing each binary's metadata with its
+/// bytes. Entries other than the metadata and the listed binaries are
+/// ignored; a listed binary without bytes makes the bundle invalid.
+pub fn read(path: &Path) -> Result<Vec<(BinaryMetadata, Vec<u8>)>> {
+    let file = std::fs::File::open(path)
+        .with_context(|| format!("Failed to open bundle: {}", path.display()))?;
+    let mut metadata: Option<Vec<BinaryMetadata>> = None;
+    let mut wasm: HashMap<Uuid, Vec<u8>> = HashMap::new();
+    let mut archive = tar::Archive::new(file);
+    for entry in archive.entries().map_err(|e| invalid(path, e))? {
+        let mut entry = entry.map_err(|e| invalid(path, e))?;
+        let name = entry.path().map_err(|e| invalid(path, e))?.into_owned();
+        let mut data = Vec::new();
+        entry.read_to_end(&mut data).map_err(|e| invalid(path, e))?;
+        if name == Path::new(METADATA_ENTRY) {
+            metadata = Some(serde_json::from_slice(&data).map_err(|e| invalid(path, e))?);
+            continue;
+        }
+        let id = name
+            .strip_prefix(BINARIES_DIR)
+            .ok()
+            .filter(|file| file.extension().is_some_and(|ext| ext == "wasm"))
+            .and_then(|file| file.file_stem()?.to_str()?.parse::<Uuid>().ok());
+        if let Some(id) = id {
+            wasm.insert(id, data);
+        }
+    }
+
+    let metadata = metadata.ok_or_else(|| invalid(path, "no metadata.json"))?;
+    metadata
+        .into_iter()
+        .map(|metadata| {
+            let wasm_bytes = wasm
+                .remove(&metadata.id)
+                .ok_or_else(|| invalid(path, format!("no bytes for binary {}", metadata.id)))?;
+            Ok((metadata, wasm_bytes))
+        })
+        .collect()
+}
+
+fn invalid(path: &Path, reason: impl std::fmt::Display) -> anyhow::Error {
+    ErrorResponse::new(
+        ErrorCode::InvalidRequest,
+        format!("Invalid bundle {}: {}", path.display(), reason),
+    )
+    .into()
+}
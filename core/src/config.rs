@@ -0,0 +1,552 @@
+use anyhow::{anyhow, Context, Result};
+use ed25519_dalek::VerifyingKey;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use wasm_shared::{ExecutionConfig, SOCKET_PATH, TOKEN_ENV};
+use wasmtime::OptLevel;
+
+use crate::binary_registry::parse_verifying_key;
+
+/// Default location of the server config file, relative to the working directory
+pub const DEFAULT_CONFIG_PATH: &str = "wasm-core.toml";
+
+/// Permission bits of the listening socket unless configured otherwise:
+/// only the user running the server can connect
+pub const DEFAULT_SOCKET_MODE: u32 = 0o600;
+
+/// Open connections allowed at once unless configured otherwise
+pub const DEFAULT_MAX_CONNECTIONS: usize = 256;
+
+/// Longest command line a client may send unless configured otherwise
+pub const DEFAULT_MAX_FRAME_BYTES: usize = 16 * 1024 * 1024;
+
+/// Seconds a connection may go without sending a command unless configured
+/// otherwise
+pub const DEFAULT_IDLE_TIMEOUT_SECS: u64 = 300;
+
+/// Most binaries a chain may run unless configured otherwise
+pub const DEFAULT_MAX_CHAIN_LENGTH: usize = 10;
+
+/// Fraction of its timeout an execution may take before it gets a warning
+/// unless configured otherwise
+pub const DEFAULT_SLOW_WARNING_FRACTION: f64 = 0.8;
+
+/// Output size beyond which an execution gets a warning unless configured
+/// otherwise
+pub const DEFAULT_LARGE_OUTPUT_WARNING_BYTES: usize = 1024 * 1024;
+
+/// Results of pure binaries kept for reuse unless configured otherwise
+pub const DEFAULT_RESULT_CACHE_SIZE: usize = 256;
+
+/// Recent results kept per binary for `GetHistory` unless configured otherwise
+pub const DEFAULT_HISTORY_SIZE: usize = 20;
+
+/// Fuel a running plugin burns between yields to the async runtime unless
+/// configured otherwise
+pub const DEFAULT_FUEL_YIELD_INTERVAL: u64 = 100_000;
+
+/// Server-wide settings. Loaded from `wasm-core.toml`, then overridden by
+/// `WASM_CORE_*` environment variables, then by command-line flags.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CoreConfig {
+    pub socket_path: PathBuf,
+    /// Permission bits set on the socket after binding, e.g. `0o660` to let
+    /// the owning group connect too
+    pub socket_mode: u32,
+    /// Applied when an execute request omits `config`
+    pub default_timeout_ms: u64,
+    pub default_memory_limit_mb: u64,
+    pub default_fuel: Option<u64>,
+    /// Maximum number of executions running at once
+    pub max_concurrency: usize,
+    /// Maximum number of open socket connections; further ones are closed
+    pub max_connections: usize,
+    /// Longest command line accepted, in bytes; longer ones get an error
+    pub max_frame_bytes: usize,
+    /// Close connections that send no command for this many seconds, which
+    /// also cuts off clients that vanished without closing; 0 never does
+    pub idle_timeout_secs: u64,
+    /// Longest chain accepted; longer ones are rejected before anything runs
+    pub max_chain_length: usize,
+    /// Fuel a whole chain may burn; once its steps have used more, the rest
+    /// are skipped. Unlimited when unset.
+    pub chain_fuel_budget: Option<u64>,
+    /// Executions taking more than this fraction of their timeout succeed
+    /// with a warning attached
+    pub slow_warning_fraction: f64,
+    /// Executions producing more output than this, in bytes, succeed with a
+    /// warning attached
+    pub large_output_warning_bytes: usize,
+    /// How many results of binaries whose manifest declares them `pure` are
+    /// kept to answer repeated requests; 0 turns the cache off
+    pub result_cache_size: usize,
+    /// How many recent results of each binary `GetHistory` can return;
+    /// 0 keeps none
+    pub history_size: usize,
+    /// Fuel a plugin burns before handing its worker thread back to the
+    /// runtime; lower keeps other connections more responsive at some cost
+    /// to the plugin's own speed
+    pub fuel_yield_interval: u64,
+    /// Hex-encoded Ed25519 public key; when set, unsigned binaries are rejected
+    pub trusted_key: Option<String>,
+    /// Append-only execution audit log; auditing is off when unset
+    pub audit_log: Option<PathBuf>,
+    /// Directory of precompiled `.cwasm` artifacts; compiled binaries are
+    /// not cached when unset
+    pub module_cache: Option<PathBuf>,
+    /// Every `.wasm` file in this directory is loaded at startup, alongside
+    /// the binaries recorded in the metadata file
+    pub plugin_dir: Option<PathBuf>,
+    /// Binaries can only be loaded from inside these directories; any path
+    /// the server can read is allowed when empty
+    pub allowed_plugin_dirs: Vec<PathBuf>,
+    /// `ExportBundle` and `ImportBundle` paths must be inside this directory,
+    /// and relative ones are taken from it; they must be inside
+    /// `allowed_plugin_dirs` when unset, and bundles are refused when both are
+    pub bundle_dir: Option<PathBuf>,
+    /// Let clients fetch the wasm bytes of loaded binaries with
+    /// `GetBinaryBytes`; off by default since it hands out plugin code
+    pub allow_binary_download: bool,
+    /// Traces and stats are saved here on shutdown and restored at startup;
+    /// they only live as long as the process when unset
+    pub state_dir: Option<PathBuf>,
+    /// Shared secret clients must send in an `Auth` command before anything
+    /// else; the socket is open to any local user when unset
+    pub auth_token: Option<String>,
+    /// How often `Subscribe` connections receive a stats snapshot
+    pub stats_interval_ms: u64,
+    /// Fraction of executions recorded by the tracer, from 0.0 to 1.0
+    pub trace_sample_rate: f64,
+    /// Wasmtime engine options, the `[engine]` table
+    pub engine: EngineOptions,
+}
+
+/// Wasm proposals and codegen settings passed to `wasmtime::Config`. Anything
+/// left unset keeps Wasmtime's own default.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EngineOptions {
+    /// Also toggles relaxed SIMD
+    pub simd: Option<bool>,
+    /// Wasmtime refuses to disable this while reference types are enabled
+    pub bulk_memory: Option<bool>,
+    pub reference_types: Option<bool>,
+    pub multi_memory: Option<bool>,
+    pub cranelift_opt_level: Option<CraneliftOptLevel>,
+    /// Bytes of native stack each running plugin gets; must leave room for
+    /// Wasmtime's wasm stack limit (512 KiB by default)
+    pub async_stack_size: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CraneliftOptLevel {
+    /// Fastest to compile; useful for short-lived or frequently reloaded plugins
+    None,
+    Speed,
+    SpeedAndSize,
+}
+
+impl std::str::FromStr for CraneliftOptLevel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(Self::None),
+            "speed" => Ok(Self::Speed),
+            "speed_and_size" => Ok(Self::SpeedAndSize),
+            _ => Err("expected none, speed or speed_and_size".to_string()),
+        }
+    }
+}
+
+impl From<CraneliftOptLevel> for OptLevel {
+    fn from(level: CraneliftOptLevel) -> Self {
+        match level {
+            CraneliftOptLevel::None => OptLevel::None,
+            CraneliftOptLevel::Speed => OptLevel::Speed,
+            CraneliftOptLevel::SpeedAndSize => OptLevel::SpeedAndSize,
+        }
+    }
+}
+
+impl Default for CoreConfig {
+    fn default() -> Self {
+        let execution = ExecutionConfig::default();
+        Self {
+            socket_path: PathBuf::from(SOCKET_PATH),
+            socket_mode: DEFAULT_SOCKET_MODE,
+            default_timeout_ms: execution.timeout_ms,
+            default_memory_limit_mb: execution.memory_limit_mb,
+            default_fuel: execution.fuel,
+            max_concurrency: 64,
+            max_connections: DEFAULT_MAX_CONNECTIONS,
+            max_frame_bytes: DEFAULT_MAX_FRAME_BYTES,
+            idle_timeout_secs: DEFAULT_IDLE_TIMEOUT_SECS,
+            max_chain_length: DEFAULT_MAX_CHAIN_LENGTH,
+            chain_fuel_budget: None,
+            slow_warning_fraction: DEFAULT_SLOW_WARNING_FRACTION,
+            large_output_warning_bytes: DEFAULT_LARGE_OUTPUT_WARNING_BYTES,
+            result_cache_size: DEFAULT_RESULT_CACHE_SIZE,
+            history_size: DEFAULT_HISTORY_SIZE,
+            fuel_yield_interval: DEFAULT_FUEL_YIELD_INTERVAL,
+            trusted_key: None,
+            audit_log: None,
+            module_cache: None,
+            plugin_dir: None,
+            allowed_plugin_dirs: Vec::new(),
+            bundle_dir: None,
+            allow_binary_download: false,
+            state_dir: None,
+            auth_token: None,
+            stats_interval_ms: 1000,
+            trace_sample_rate: 1.0,
+            engine: EngineOptions::default(),
+        }
+    }
+}
+
+impl CoreConfig {
+    /// Load the config file at `path`. A missing file yields the defaults
+    /// unless `required` is set (i.e. the path was given explicitly).
+    pub fn from_file(path: impl AsRef<Path>, required: bool) -> Result<Self> {
+        let path = path.as_ref();
+        if !path.exists() && !required {
+            return Ok(Self::default());
+        }
+        let data = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config file: {}", path.display()))?;
+        toml::from_str(&data)
+            .with_context(|| format!("Failed to parse config file: {}", path.display()))
+    }
+
+    /// Apply `WASM_CORE_*` overrides from the process environment
+    pub fn apply_env(&mut self) -> Result<()> {
+        self.apply_vars(|key| std::env::var(key).ok())
+    }
+
+    fn apply_vars(&mut self, var: impl Fn(&str) -> Option<String>) -> Result<()> {
+        if let Some(path) = var("WASM_CORE_SOCKET_PATH") {
+            self.socket_path = PathBuf::from(path);
+        }
+        if let Some(value) = var("WASM_CORE_SOCKET_MODE") {
+            self.socket_mode = parse_mode("WASM_CORE_SOCKET_MODE", &value)?;
+        }
+        if let Some(value) = var("WASM_CORE_TIMEOUT_MS") {
+            self.default_timeout_ms = parse_var("WASM_CORE_TIMEOUT_MS", &value)?;
+        }
+        if let Some(value) = var("WASM_CORE_MEMORY_LIMIT_MB") {
+            self.default_memory_limit_mb = parse_var("WASM_CORE_MEMORY_LIMIT_MB", &value)?;
+        }
+        if let Some(value) = var("WASM_CORE_FUEL") {
+            self.default_fuel = Some(parse_var("WASM_CORE_FUEL", &value)?);
+        }
+        if let Some(value) = var("WASM_CORE_MAX_CONCURRENCY") {
+            self.max_concurrency = parse_var("WASM_CORE_MAX_CONCURRENCY", &value)?;
+        }
+        if let Some(value) = var("WASM_CORE_MAX_CONNECTIONS") {
+            self.max_connections = parse_var("WASM_CORE_MAX_CONNECTIONS", &value)?;
+        }
+        if let Some(value) = var("WASM_CORE_MAX_FRAME_BYTES") {
+            self.max_frame_bytes = parse_var("WASM_CORE_MAX_FRAME_BYTES", &value)?;
+        }
+        if let Some(value) = var("WASM_CORE_IDLE_TIMEOUT_SECS") {
+            self.idle_timeout_secs = parse_var("WASM_CORE_IDLE_TIMEOUT_SECS", &value)?;
+        }
+        if let Some(value) = var("WASM_CORE_MAX_CHAIN_LENGTH") {
+            self.max_chain_length = parse_var("WASM_CORE_MAX_CHAIN_LENGTH", &value)?;
+        }
+        if let Some(value) = var("WASM_CORE_CHAIN_FUEL_BUDGET") {
+            self.chain_fuel_budget = Some(parse_var("WASM_CORE_CHAIN_FUEL_BUDGET", &value)?);
+        }
+        if let Some(value) = var("WASM_CORE_SLOW_WARNING_FRACTION") {
+            self.slow_warning_fraction = parse_var("WASM_CORE_SLOW_WARNING_FRACTION", &value)?;
+        }
+        if let Some(value) = var("WASM_CORE_LARGE_OUTPUT_WARNING_BYTES") {
+            self.large_output_warning_bytes =
+                parse_var("WASM_CORE_LARGE_OUTPUT_WARNING_BYTES", &value)?;
+        }
+        if let Some(value) = var("WASM_CORE_RESULT_CACHE_SIZE") {
+            self.result_cache_size = parse_var("WASM_CORE_RESULT_CACHE_SIZE", &value)?;
+        }
+        if let Some(value) = var("WASM_CORE_HISTORY_SIZE") {
+            self.history_size = parse_var("WASM_CORE_HISTORY_SIZE", &value)?;
+        }
+        if let Some(value) = var("WASM_CORE_FUEL_YIELD_INTERVAL") {
+            self.fuel_yield_interval = parse_var("WASM_CORE_FUEL_YIELD_INTERVAL", &value)?;
+        }
+        if let Some(key) = var("WASM_CORE_TRUSTED_KEY") {
+            self.trusted_key = Some(key);
+        }
+        if let Some(path) = var("WASM_CORE_AUDIT_LOG") {
+            self.audit_log = Some(PathBuf::from(path));
+        }
+        if let Some(path) = var("WASM_CORE_MODULE_CACHE") {
+            self.module_cache = Some(PathBuf::from(path));
+        }
+        if let Some(path) = var("WASM_CORE_PLUGIN_DIR") {
+            self.plugin_dir = Some(PathBuf::from(path));
+        }
+        if let Some(paths) = var("WASM_CORE_ALLOWED_PLUGIN_DIRS") {
+            self.allowed_plugin_dirs = std::env::split_paths(&paths).collect();
+        }
+        if let Some(path) = var("WASM_CORE_BUNDLE_DIR") {
+            self.bundle_dir = Some(PathBuf::from(path));
+        }
+        if let Some(value) = var("WASM_CORE_ALLOW_BINARY_DOWNLOAD") {
+            self.allow_binary_download = parse_var("WASM_CORE_ALLOW_BINARY_DOWNLOAD", &value)?;
+        }
+        if let Some(path) = var("WASM_CORE_STATE_DIR") {
+            self.state_dir = Some(PathBuf::from(path));
+        }
+        if let Some(value) = var("WASM_CORE_STATS_INTERVAL_MS") {
+            self.stats_interval_ms = parse_var("WASM_CORE_STATS_INTERVAL_MS", &value)?;
+        }
+        if let Some(value) = var("WASM_CORE_TRACE_SAMPLE_RATE") {
+            self.trace_sample_rate = parse_var("WASM_CORE_TRACE_SAMPLE_RATE", &value)?;
+        }
+        if let Some(token) = var(TOKEN_ENV) {
+            self.auth_token = Some(token);
+        }
+        if let Some(value) = var("WASM_CORE_SIMD") {
+            self.engine.simd = Some(parse_var("WASM_CORE_SIMD", &value)?);
+        }
+        if let Some(value) = var("WASM_CORE_BULK_MEMORY") {
+            self.engine.bulk_memory = Some(parse_var("WASM_CORE_BULK_MEMORY", &value)?);
+        }
+        if let Some(value) = var("WASM_CORE_REFERENCE_TYPES") {
+            self.engine.reference_types = Some(parse_var("WASM_CORE_REFERENCE_TYPES", &value)?);
+        }
+        if let Some(value) = var("WASM_CORE_MULTI_MEMORY") {
+            self.engine.multi_memory = Some(parse_var("WASM_CORE_MULTI_MEMORY", &value)?);
+        }
+        if let Some(value) = var("WASM_CORE_OPT_LEVEL") {
+            self.engine.cranelift_opt_level = Some(parse_var("WASM_CORE_OPT_LEVEL", &value)?);
+        }
+        if let Some(value) = var("WASM_CORE_ASYNC_STACK_SIZE") {
+            self.engine.async_stack_size = Some(parse_var("WASM_CORE_ASYNC_STACK_SIZE", &value)?);
+        }
+        Ok(())
+    }
+
+    /// The Wasmtime config for the server's engine. Async support and fuel
+    /// metering are always on; the executor depends on both.
+    pub fn engine_config(&self) -> wasmtime::Config {
+        let mut config = wasmtime::Config::new();
+        config.async_support(true);
+        config.consume_fuel(true);
+
+        let engine = &self.engine;
+        if let Some(enable) = engine.simd {
+            // Relaxed SIMD builds on SIMD and Wasmtime won't keep one without the other
+            config.wasm_simd(enable).wasm_relaxed_simd(enable);
+        }
+        if let Some(enable) = engine.bulk_memory {
+            config.wasm_bulk_memory(enable);
+        }
+        if let Some(enable) = engine.reference_types {
+            config.wasm_reference_types(enable);
+        }
+        if let Some(enable) = engine.multi_memory {
+            config.wasm_multi_memory(enable);
+        }
+        if let Some(level) = engine.cranelift_opt_level {
+            config.cranelift_opt_level(level.into());
+        }
+        if let Some(size) = engine.async_stack_size {
+            config.async_stack_size(size);
+        }
+        config
+    }
+
+    /// The execution config used when a request doesn't carry one
+    pub fn default_execution_config(&self) -> ExecutionConfig {
+        ExecutionConfig {
+            timeout_ms: self.default_timeout_ms,
+            memory_limit_mb: self.default_memory_limit_mb,
+            fuel: self.default_fuel,
+            ..ExecutionConfig::default()
+        }
+    }
+
+    /// `idle_timeout_secs` as a duration, `None` when idle connections are kept
+    pub fn idle_timeout(&self) -> Option<std::time::Duration> {
+        (self.idle_timeout_secs > 0).then(|| std::time::Duration::from_secs(self.idle_timeout_secs))
+    }
+
+    /// The parsed `trusted_key`, if signature verification is enabled
+    pub fn verifying_key(&self) -> Result<Option<VerifyingKey>> {
+        self.trusted_key
+            .as_deref()
+            .map(parse_verifying_key)
+            .transpose()
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        self.default_execution_config()
+            .validate()
+            .map_err(|e| anyhow!("Invalid default execution config: {}", e))?;
+        if self.max_concurrency == 0 {
+            return Err(anyhow!("max_concurrency must be greater than 0"));
+        }
+        if self.max_connections == 0 {
+            return Err(anyhow!("max_connections must be greater than 0"));
+        }
+        if self.max_frame_bytes == 0 {
+            return Err(anyhow!("max_frame_bytes must be greater than 0"));
+        }
+        if self.max_chain_length == 0 {
+            return Err(anyhow!("max_chain_length must be greater than 0"));
+        }
+        if self.chain_fuel_budget == Some(0) {
+            return Err(anyhow!("chain_fuel_budget must be greater than 0"));
+        }
+        if !(self.slow_warning_fraction > 0.0 && self.slow_warning_fraction <= 1.0) {
+            return Err(anyhow!(
+                "slow_warning_fraction must be greater than 0.0 and at most 1.0, got {}",
+                self.slow_warning_fraction
+            ));
+        }
+        if self.fuel_yield_interval == 0 {
+            return Err(anyhow!("fuel_yield_interval must be greater than 0"));
+        }
+        if self.stats_interval_ms == 0 {
+            return Err(anyhow!("stats_interval_ms must be greater than 0"));
+        }
+        if self.socket_mode > 0o777 {
+            return Err(anyhow!(
+                "socket_mode must be at most 0o777, got {:#o}",
+                self.socket_mode
+            ));
+        }
+        if !(0.0..=1.0).contains(&self.trace_sample_rate) {
+            return Err(anyhow!(
+                "trace_sample_rate must be between 0.0 and 1.0, got {}",
+                self.trace_sample_rate
+            ));
+        }
+        if self.auth_token.as_deref() == Some("") {
+            return Err(anyhow!("auth_token must not be empty"));
+        }
+        self.verifying_key()?;
+        wasmtime::Engine::new(&self.engine_config())
+            .map_err(|e| anyhow!("Invalid engine options: {}", e))?;
+        Ok(())
+    }
+}
+
+fn parse_var<T: std::str::FromStr>(key: &str, value: &str) -> Result<T>
+where
+    T::Err: std::fmt::Display,
+{
+    value
+        .parse()
+        .map_err(|e| anyhow!("Invalid value for {}: '{}' ({})", key, value, e))
+}
+
+/// Octal permission bits, with or without a leading `0o`
+fn parse_mode(key: &str, value: &str) -> Result<u32> {
+    let digits = value.strip_prefix("0o").unwrap_or(value);
+    u32::from_str_radix(digits, 8)
+        .map_err(|e| anyhow!("Invalid value for {}: '{}' ({})", key, value, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_missing_optional_file_uses_defaults() {
+        let config = CoreConfig::from_file("/nonexistent/wasm-core.toml", false).unwrap();
+        assert_eq!(config.default_timeout_ms, 5000);
+        assert!(CoreConfig::from_file("/nonexistent/wasm-core.toml", true).is_err());
+    }
+
+    #[test]
+    fn test_file_then_env_overrides() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("wasm-core.toml");
+        std::fs::write(
+            &path,
+            "default_timeout_ms = 1500\ndefault_memory_limit_mb = 16\nmax_concurrency = 4\n",
+        )
+        .unwrap();
+
+        let mut config = CoreConfig::from_file(&path, true).unwrap();
+        assert_eq!(config.default_timeout_ms, 1500);
+        assert_eq!(config.default_memory_limit_mb, 16);
+        assert_eq!(config.max_concurrency, 4);
+
+        config
+            .apply_vars(|key| match key {
+                "WASM_CORE_TIMEOUT_MS" => Some("2500".to_string()),
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(config.default_timeout_ms, 2500);
+        assert_eq!(config.default_memory_limit_mb, 16);
+
+        assert!(config
+            .apply_vars(|key| (key == "WASM_CORE_FUEL").then(|| "lots".to_string()))
+            .is_err());
+    }
+
+    #[test]
+    fn test_engine_options_gate_simd() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("wasm-core.toml");
+        std::fs::write(
+            &path,
+            "[engine]\nsimd = true\ncranelift_opt_level = \"none\"\n",
+        )
+        .unwrap();
+        let mut config = CoreConfig::from_file(&path, true).unwrap();
+        assert_eq!(
+            config.engine.cranelift_opt_level,
+            Some(CraneliftOptLevel::None)
+        );
+
+        let simd_module = wat::parse_str(
+            r#"(module
+                (func (export "splat") (param i32) (result v128)
+                    local.get 0
+                    i32x4.splat))"#,
+        )
+        .unwrap();
+        let engine = wasmtime::Engine::new(&config.engine_config()).unwrap();
+        wasmtime::Module::new(&engine, &simd_module).unwrap();
+
+        config
+            .apply_vars(|key| (key == "WASM_CORE_SIMD").then(|| "false".to_string()))
+            .unwrap();
+        let engine = wasmtime::Engine::new(&config.engine_config()).unwrap();
+        assert!(wasmtime::Module::new(&engine, &simd_module).is_err());
+    }
+
+    #[test]
+    fn test_unknown_keys_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("wasm-core.toml");
+        std::fs::write(&path, "default_timeout = 1\n").unwrap();
+        assert!(CoreConfig::from_file(&path, true).is_err());
+    }
+
+    #[test]
+    fn test_socket_mode_is_octal() {
+        let mut config = CoreConfig::default();
+        assert_eq!(config.socket_mode, 0o600);
+
+        config
+            .apply_vars(|key| (key == "WASM_CORE_SOCKET_MODE").then(|| "0o660".to_string()))
+            .unwrap();
+        assert_eq!(config.socket_mode, 0o660);
+        config
+            .apply_vars(|key| (key == "WASM_CORE_SOCKET_MODE").then(|| "640".to_string()))
+            .unwrap();
+        assert_eq!(config.socket_mode, 0o640);
+
+        config.socket_mode = 0o1777;
+        assert!(config.validate().is_err());
+    }
+}
@@ -0,0 +1,50 @@
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine as _;
+use wasm_shared::{Encoding, ErrorCode, ErrorResponse};
+
+/// Turn a request's `input` string into the bytes the plugin receives
+pub fn decode(encoding: Encoding, input: &str) -> Result<Vec<u8>, ErrorResponse> {
+    let invalid = |e: &dyn std::fmt::Display| {
+        ErrorResponse::new(
+            ErrorCode::InvalidRequest,
+            format!("Input is not valid {:?}: {}", encoding, e),
+        )
+    };
+    match encoding {
+        Encoding::Utf8 => Ok(input.as_bytes().to_vec()),
+        Encoding::Base64 => STANDARD.decode(input).map_err(|e| invalid(&e)),
+        Encoding::Hex => hex::decode(input).map_err(|e| invalid(&e)),
+    }
+}
+
+/// Turn the bytes a plugin produced into a result's `output` string
+pub fn encode(encoding: Encoding, output: &[u8]) -> String {
+    match encoding {
+        Encoding::Utf8 => String::from_utf8_lossy(output).into_owned(),
+        Encoding::Base64 => STANDARD.encode(output),
+        Encoding::Hex => hex::encode(output),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let bytes = [0xff, 0x00, b'o', b'k', 0xfe];
+        for encoding in [Encoding::Base64, Encoding::Hex] {
+            let encoded = encode(encoding, &bytes);
+            assert_eq!(decode(encoding, &encoded).unwrap(), bytes);
+        }
+        assert_eq!(encode(Encoding::Hex, &bytes), "ff006f6bfe");
+        assert_eq!(encode(Encoding::Base64, &bytes), "/wBva/4=");
+    }
+
+    #[test]
+    fn test_invalid_input() {
+        let err = decode(Encoding::Hex, "xyz").unwrap_err();
+        assert_eq!(err.code, ErrorCode::InvalidRequest);
+        assert!(decode(Encoding::Base64, "not base64!").is_err());
+    }
+}
@@ -1,33 +1,245 @@
 // Note: Tracer was added by AI
 use anyhow::{anyhow, Context, Result};
+use dashmap::DashMap;
 use rand::random;
-use std::time::Duration;
-use tokio::time::timeout;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+use tokio::sync::mpsc::UnboundedSender;
+use tokio::time::{timeout_at, Instant};
+use tokio_util::sync::CancellationToken;
 use uuid::Uuid;
-use wasm_shared::{ExecutionConfig, ExecutionResult};
+use wasm_shared::plugin_helpers::{ERROR_ENV_PARSING, ERROR_INVALID_UTF8, ERROR_UNKNOWN};
+use wasm_shared::{
+    Encoding, EnvVars, ErrorCode, ErrorResponse, ExecuteChainResponse, ExecutionConfig,
+    ExecutionResult, OutputChunk, RETURN_CODE_TIMED_OUT,
+};
+use wasmtime::component::{self, Component};
 use wasmtime::*;
 
-use crate::binary_registry::{BinaryRegistry, LoadedBinary};
-use crate::tracer::{ExecutionTrace, TraceEventType, Tracer};
+use crate::audit::{AuditLog, AuditRecord};
+use crate::binary_registry::{BinaryKind, BinaryMetadata, BinaryRegistry, LoadedBinary};
+use crate::config::DEFAULT_FUEL_YIELD_INTERVAL;
+use crate::encoding;
+use crate::hooks::{ExecutionHook, HookContext};
+use crate::preprocess;
+use crate::stats::ExecutionStats;
+use crate::tracer::{ExecutionTrace, RecordedRequest, TraceEventType, Tracer};
+
+/// How long past its deadline an execution may take to hand back partial
+/// output before it is abandoned outright
+const TIMEOUT_GRACE: Duration = Duration::from_millis(250);
+
+/// Plugins exporting this global opt into returning their output by length:
+/// a `process` return value with the high bit set means "the output is the
+/// first `value & !OUTPUT_LEN_FLAG` bytes at the input base", unless it is in
+/// `RETURNED_ERROR_CODES`
+const OUTPUT_LEN_EXPORT: &str = "__returns_output_len";
+/// Plugins export this i32 global to declare the most output they can
+/// build; a longer input probably means they cut their result short
+const OUTPUT_CAP_EXPORT: &str = "__output_cap";
+const OUTPUT_LEN_FLAG: u32 = 0x8000_0000;
+/// Return values an opted-in plugin can still use as error codes, from
+/// `ERROR_INVALID_UTF8` down to `ERROR_UNKNOWN`; they set the high bit too
+const RETURNED_ERROR_CODES: std::ops::RangeInclusive<i32> = ERROR_UNKNOWN..=ERROR_INVALID_UTF8;
+/// Plugins importing this host function pull their input through it instead
+/// of having it written at the input base, so it can be larger than `alloc`
+/// or the memory would allow
+const READ_INPUT_CHUNK_IMPORT: &str = "read_input_chunk";
+
+/// Largest env, as JSON, handed to a plugin. Plugins parse it into
+/// fixed-size buffers, so a bigger env is refused before the plugin runs
+/// rather than left to fail inside it.
+const MAX_ENV_BYTES: usize = 64 * 1024;
+/// Longest env key accepted; values are only bounded by `MAX_ENV_BYTES`
+const MAX_ENV_KEY_BYTES: usize = 256;
+/// Longest name `host::counter_inc` accepts
+const MAX_COUNTER_KEY_BYTES: usize = 256;
+/// Most counters one namespace can hold; they live until the server stops
+const MAX_COUNTERS_PER_NAMESPACE: usize = 10_000;
+/// `host::counter_inc` values by namespace, then by name
+type Counters = DashMap<Option<String>, DashMap<String, i64>>;
+
+/// Most bytes `host::scratch_write` keeps
+const MAX_SCRATCH_BYTES: usize = 64 * 1024;
+
+/// What a plugin kept with `host::scratch_write`, shared between the chain
+/// steps of one binary when the chain persists host state
+pub type Scratch = Arc<Mutex<Vec<u8>>>;
+/// Manifest capability a plugin must declare to import the digest functions
+const CRYPTO_CAPABILITY: &str = "crypto";
+/// Hashes `data` for one of the digest host functions
+type DigestFn = fn(&[u8]) -> [u8; 32];
+/// Host functions `name(ptr, len, out_ptr)` writing the 32-byte digest of
+/// `len` bytes at `ptr` to `out_ptr`, registered for `crypto` plugins only
+const DIGESTS: &[(&str, DigestFn)] = &[
+    ("sha256", |data| Sha256::digest(data).into()),
+    ("blake3", |data| *blake3::hash(data).as_bytes()),
+];
+
+/// Per-request hooks into a running execution
+#[derive(Default)]
+pub struct ExecutionContext {
+    /// Lets `Executor::cancel` abort the execution while it runs
+    pub request_id: Option<Uuid>,
+    /// Receives every `host::emit_chunk` call as it happens
+    pub chunks: Option<UnboundedSender<OutputChunk>>,
+    /// Extra entries for the plugin's env JSON
+    pub env: EnvVars,
+    /// Caller's ID for the request, kept on the trace and echoed in the result
+    pub correlation_id: Option<String>,
+    /// How the result's `output` is encoded
+    pub encoding: Encoding,
+    /// Scratch buffer carried over from an earlier run, instead of an empty
+    /// one; the run leaves what it wrote there for the next
+    pub scratch: Option<Scratch>,
+    /// Wall-clock time the execution must finish by, on top of its timeout
+    pub deadline: Option<SystemTime>,
+    /// A `Warmup` run: kept out of the stats, the history and the traces
+    pub warmup: bool,
+}
 
 pub struct Executor {
     registry: BinaryRegistry,
     tracer: Tracer,
+    audit: Option<AuditLog>,
+    /// In-flight executions that can be cancelled, by request ID
+    /// Running cancellable executions: request ID to binary ID and token
+    cancellations: DashMap<Uuid, (Uuid, CancellationToken)>,
+    stats: Arc<ExecutionStats>,
+    /// Longest chain `execute_chain` accepts
+    max_chain_length: Option<usize>,
+    /// Fuel a whole chain may consume before its remaining steps are skipped
+    chain_fuel_budget: Option<u64>,
+    /// Fraction of its timeout an execution may take before it gets a warning
+    slow_warning_fraction: Option<f64>,
+    /// Output size, in bytes, beyond which an execution gets a warning
+    large_output_warning_bytes: Option<usize>,
+    /// Values behind `host::counter_inc`, kept across executions. Each
+    /// namespace has its own, so tenants can't read or bump each other's.
+    counters: Arc<Counters>,
+    /// The most recent results of each binary, oldest first
+    history: DashMap<Uuid, VecDeque<ExecutionResult>>,
+    /// Results kept per binary in `history`; 0 keeps none
+    history_size: usize,
+    /// Fuel burned between points where a running guest yields to the async
+    /// runtime, so timeouts, cancellation and other connections get a turn
+    /// even while a plugin spins in a tight loop
+    fuel_yield_interval: u64,
+    hooks: Vec<Box<dyn ExecutionHook>>,
 }
 
 impl Executor {
+    #[allow(dead_code)]
     pub fn new(registry: BinaryRegistry) -> Self {
+        Self::with_tracer(registry, Tracer::default())
+    }
+
+    /// Create an executor with a custom tracer configuration
+    /// This is useful for advanced use cases where you want to control tracing behavior
+    pub fn with_tracer(registry: BinaryRegistry, tracer: Tracer) -> Self {
         Self {
             registry,
-            tracer: Tracer::default(),
+            tracer,
+            audit: None,
+            cancellations: DashMap::new(),
+            stats: ExecutionStats::new(),
+            max_chain_length: None,
+            chain_fuel_budget: None,
+            slow_warning_fraction: None,
+            large_output_warning_bytes: None,
+            counters: Arc::new(DashMap::new()),
+            history: DashMap::new(),
+            history_size: 0,
+            fuel_yield_interval: DEFAULT_FUEL_YIELD_INTERVAL,
+            hooks: Vec::new(),
         }
     }
 
-    /// Create an executor with a custom tracer configuration
-    /// This is useful for advanced use cases where you want to control tracing behavior
+    /// Run `hook` around every execution, after the hooks already added
     #[allow(dead_code)]
-    pub fn with_tracer(registry: BinaryRegistry, tracer: Tracer) -> Self {
-        Self { registry, tracer }
+    pub fn with_hook(mut self, hook: impl ExecutionHook + 'static) -> Self {
+        self.hooks.push(Box::new(hook));
+        self
+    }
+
+    /// Reject chains of more than `max` binaries
+    pub fn with_max_chain_length(mut self, max: usize) -> Self {
+        self.max_chain_length = Some(max);
+        self
+    }
+
+    /// Stop a chain once its steps together have consumed more than `budget`
+    /// fuel, returning the results so far
+    pub fn with_chain_fuel_budget(mut self, budget: u64) -> Self {
+        self.chain_fuel_budget = Some(budget);
+        self
+    }
+
+    /// Warn about executions that take more than `fraction` of their timeout
+    pub fn with_slow_warning_fraction(mut self, fraction: f64) -> Self {
+        self.slow_warning_fraction = Some(fraction);
+        self
+    }
+
+    /// Warn about executions whose output is larger than `bytes`
+    pub fn with_large_output_warning_bytes(mut self, bytes: usize) -> Self {
+        self.large_output_warning_bytes = Some(bytes);
+        self
+    }
+
+    /// Keep the last `size` results of each binary for `history`
+    pub fn with_history_size(mut self, size: usize) -> Self {
+        self.history_size = size;
+        self
+    }
+
+    /// Yield to the async runtime every `fuel` units a plugin burns
+    pub fn with_fuel_yield_interval(mut self, fuel: u64) -> Self {
+        self.fuel_yield_interval = fuel;
+        self
+    }
+
+    /// Record every successful execution in `audit`
+    pub fn with_audit_log(mut self, audit: AuditLog) -> Self {
+        self.audit = Some(audit);
+        self
+    }
+
+    pub fn audit_log(&self) -> Option<&AuditLog> {
+        self.audit.as_ref()
+    }
+
+    /// Live counters of the executions run by this executor
+    pub fn stats(&self) -> &Arc<ExecutionStats> {
+        &self.stats
+    }
+
+    /// Up to `limit` of the most recent results of `binary_id`, oldest first;
+    /// all that are kept when `limit` is unset
+    pub fn history(&self, binary_id: &Uuid, limit: Option<usize>) -> Vec<ExecutionResult> {
+        let Some(results) = self.history.get(binary_id) else {
+            return Vec::new();
+        };
+        let skip = limit.map_or(0, |limit| results.len().saturating_sub(limit));
+        results.iter().skip(skip).cloned().collect()
+    }
+
+    /// Forget the results kept for `binary_id`, e.g. once it is unloaded
+    pub fn clear_history(&self, binary_id: &Uuid) {
+        self.history.remove(binary_id);
+    }
+
+    fn record_history(&self, result: &ExecutionResult, warmup: bool) {
+        if warmup || self.history_size == 0 {
+            return;
+        }
+        let mut results = self.history.entry(result.binary_id).or_default();
+        if results.len() == self.history_size {
+            results.pop_front();
+        }
+        results.push_back(result.clone());
     }
 
     /// Get a reference to the tracer for accessing execution traces
@@ -36,14 +248,87 @@ impl Executor {
         &self.tracer
     }
 
+    #[allow(dead_code)]
     pub async fn execute(
         &self,
         binary_id: Uuid,
         input: String,
         config: ExecutionConfig,
     ) -> Result<ExecutionResult> {
+        self.execute_with(binary_id, input, config, ExecutionContext::default())
+            .await
+    }
+
+    /// Abort the in-flight execution registered under `request_id`.
+    /// Returns false if no such execution is running on a binary in
+    /// `namespace`.
+    pub fn cancel(&self, request_id: &Uuid, namespace: Option<&str>) -> bool {
+        match self.cancellations.get(request_id) {
+            Some(entry) if self.registry.get_binary_in(&entry.0, namespace).is_ok() => {
+                entry.1.cancel();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Like `execute`, with the streaming and cancellation hooks in `context`.
+    /// When streaming, the final result still carries the full output.
+    pub async fn execute_with(
+        &self,
+        binary_id: Uuid,
+        input: impl Into<Vec<u8>>,
+        config: ExecutionConfig,
+        context: ExecutionContext,
+    ) -> Result<ExecutionResult> {
+        if context
+            .deadline
+            .is_some_and(|deadline| time_left(deadline).is_zero())
+        {
+            return Err(ErrorResponse::new(ErrorCode::Timeout, "Deadline already passed").into());
+        }
+        let input = input.into();
+        // Kept on the trace, so replays start from what the caller sent
+        let recorded_input = self.tracer.is_enabled().then(|| input.clone());
+        let input = preprocess::apply(&config.preprocess, input)?;
+        let token = CancellationToken::new();
+        let _registration = match context.request_id {
+            Some(request_id) => {
+                Some(self.register_cancellation(request_id, binary_id, token.clone())?)
+            }
+            None => None,
+        };
+
+        // Wait before the clock starts: queueing behind another run of the
+        // same binary doesn't count toward this one's timeout
+        let _binary_guard = match config.serialize_per_binary {
+            true => {
+                let lock = self.registry.binary_lock(binary_id);
+                Some(lock.lock_owned().await)
+            }
+            false => None,
+        };
+
+        let warmup = context.warmup;
+        let _active = (!warmup).then(|| self.stats.start(binary_id));
+
         // Start tracing if enabled
-        let mut trace = self.tracer.start_trace(binary_id).await;
+        let mut trace = match warmup {
+            true => None,
+            false => self.tracer.start_trace(binary_id).await,
+        };
+        let correlation_id = context.correlation_id.clone();
+        let context_encoding = context.encoding;
+        let applied_config = config.clone();
+        if let Some(ref mut t) = trace {
+            t.correlation_id = correlation_id.clone();
+            t.request = recorded_input.map(|input| RecordedRequest {
+                input,
+                env: context.env.clone(),
+                config: config.clone(),
+                encoding: context.encoding,
+            });
+        }
 
         let start = std::time::Instant::now();
         tracing::info!("Executing binary: {}", binary_id);
@@ -83,12 +368,100 @@ impl Executor {
             }
         };
 
-        let result = match timeout(
-            Duration::from_millis(config.timeout_ms),
-            self.execute_binary(binary, input, config, trace.as_mut()),
-        )
-        .await
-        {
+        let mut hook_context = HookContext {
+            binary_id,
+            input,
+            config: config.clone(),
+        };
+        for hook in &self.hooks {
+            hook.before(&mut hook_context).await;
+        }
+        let input = match self.hooks.is_empty() {
+            true => std::mem::take(&mut hook_context.input),
+            false => hook_context.input.clone(),
+        };
+
+        // Streamed chunks and scratch carried in from a chain aren't part of
+        // the key, so those runs always execute
+        let cacheable = self.registry.result_cache().is_some()
+            && binary.metadata.is_pure()
+            && !config.meter_only
+            && context.chunks.is_none()
+            && context.scratch.is_none();
+        let cache_key =
+            cacheable.then(|| result_cache_key(&binary.metadata, &input, &config, &context));
+        if let Some(cached) = cache_key.as_ref().and_then(|key| self.cached_result(key)) {
+            tracing::info!("Execution served from cache: {}", binary_id);
+            if let Some(mut t) = trace {
+                t.add_event(
+                    TraceEventType::ExecutionComplete,
+                    "Result served from cache".to_string(),
+                    None,
+                );
+                t.return_code = Some(cached.return_code);
+                t.complete(true, None);
+                self.tracer.complete_trace(t).await;
+            }
+            let mut result = ExecutionResult {
+                execution_time_ms: start.elapsed().as_millis() as u64,
+                fuel_consumed: 0,
+                correlation_id,
+                applied_config,
+                warnings: Vec::new(),
+                cache_hit: true,
+                ..cached
+            };
+            for hook in &self.hooks {
+                hook.after(&hook_context, &mut result).await;
+            }
+            self.record_history(&result, warmup);
+            return Ok(result);
+        }
+
+        // The plugin call itself stops at `deadline` and returns what it has
+        // so far; the grace period only covers wrapping that up
+        let mut budget = Duration::from_millis(config.timeout_ms);
+        if let Some(deadline) = context.deadline {
+            budget = budget.min(time_left(deadline));
+        }
+        let deadline = Instant::now() + budget;
+        let execution = timeout_at(
+            deadline + TIMEOUT_GRACE,
+            self.execute_binary(binary, input, config, context, deadline, trace.as_mut()),
+        );
+        let outcome = tokio::select! {
+            outcome = execution => Some(outcome),
+            _ = token.cancelled() => None,
+        };
+        let Some(outcome) = outcome else {
+            let error_msg = "Execution cancelled";
+            tracing::info!("{}: {}", error_msg, binary_id);
+            if let Some(mut t) = trace {
+                t.add_event(TraceEventType::ExecutionError, error_msg.to_string(), None);
+                t.complete(false, Some(error_msg.to_string()));
+                self.tracer.complete_trace(t).await;
+            }
+            return Err(ErrorResponse::new(ErrorCode::Cancelled, error_msg).into());
+        };
+
+        let result = match outcome {
+            Ok(Ok(result)) if result.timed_out => {
+                tracing::warn!(
+                    "Execution timed out, returning partial output: {}",
+                    binary_id
+                );
+                if let Some(ref mut t) = trace {
+                    t.add_event(
+                        TraceEventType::ExecutionError,
+                        "Execution timeout, partial output returned".to_string(),
+                        Some(serde_json::json!({
+                            "fuel_consumed": result.fuel_consumed,
+                            "logs": result.logs.len(),
+                        })),
+                    );
+                }
+                result
+            }
             Ok(Ok(result)) => {
                 if let Some(ref mut t) = trace {
                     t.add_event(
@@ -120,7 +493,7 @@ impl Executor {
                     t.complete(false, Some(error_msg.to_string()));
                     self.tracer.complete_trace(t).await;
                 }
-                return Err(anyhow!("Execution timeout"));
+                return Err(ErrorResponse::new(ErrorCode::Timeout, error_msg).into());
             }
         };
 
@@ -133,41 +506,192 @@ impl Executor {
         );
 
         if let Some(mut t) = trace {
+            t.return_code = Some(result.return_code);
             t.complete(true, None);
             self.tracer.complete_trace(t).await;
         }
 
-        Ok(ExecutionResult {
-            binary_id,
-            return_code: result.return_code,
-            output: result.output,
+        let mut warnings = result.warnings.clone();
+        warnings.extend(self.soft_limit_warnings(&result, &applied_config, execution_time_ms));
+        if result.return_code == ERROR_ENV_PARSING {
+            warnings.push(format!(
+                "Plugin returned ERROR_ENV_PARSING ({}): it could not parse its env",
+                ERROR_ENV_PARSING
+            ));
+        }
+        let mut result = ExecutionResult {
             execution_time_ms,
-            fuel_consumed: result.fuel_consumed,
-        })
+            correlation_id,
+            encoding: context_encoding,
+            applied_config,
+            warnings,
+            ..result
+        };
+        if let Some(key) = cache_key {
+            if result.return_code == 0 && !result.timed_out {
+                self.cache_result(key, &result);
+            }
+        }
+        for hook in &self.hooks {
+            hook.after(&hook_context, &mut result).await;
+        }
+        self.record_history(&result, warmup);
+        Ok(result)
+    }
+
+    /// Results of pure binaries (those whose manifest sets `pure = true`)
+    /// live in the registry's result cache, by `result_cache_key`
+    fn cached_result(&self, key: &[u8; 32]) -> Option<ExecutionResult> {
+        self.registry.result_cache()?.get(key)
+    }
+
+    fn cache_result(&self, key: [u8; 32], result: &ExecutionResult) {
+        if let Some(cache) = self.registry.result_cache() {
+            cache.put(key, result.clone());
+        }
     }
 
+    /// Notes on a successful execution that came close to its timeout or
+    /// produced an unusually large output. Timed-out runs already say so.
+    fn soft_limit_warnings(
+        &self,
+        result: &ExecutionResult,
+        config: &ExecutionConfig,
+        execution_time_ms: u64,
+    ) -> Vec<String> {
+        let mut warnings = Vec::new();
+        if let Some(fraction) = self.slow_warning_fraction {
+            if !result.timed_out && execution_time_ms as f64 > config.timeout_ms as f64 * fraction {
+                warnings.push(format!(
+                    "Execution took {}ms, more than {:.0}% of its {}ms timeout",
+                    execution_time_ms,
+                    fraction * 100.0,
+                    config.timeout_ms
+                ));
+            }
+        }
+        if let Some(limit) = self.large_output_warning_bytes {
+            if result.output.len() > limit {
+                warnings.push(format!(
+                    "Output is {} bytes, more than the {} byte soft limit",
+                    result.output.len(),
+                    limit
+                ));
+            }
+        }
+        warnings
+    }
+
+    fn register_cancellation(
+        &self,
+        request_id: Uuid,
+        binary_id: Uuid,
+        token: CancellationToken,
+    ) -> Result<CancellationRegistration<'_>> {
+        match self.cancellations.entry(request_id) {
+            dashmap::mapref::entry::Entry::Occupied(_) => Err(ErrorResponse::new(
+                ErrorCode::InvalidRequest,
+                format!("Request ID already in use: {}", request_id),
+            )
+            .into()),
+            dashmap::mapref::entry::Entry::Vacant(entry) => {
+                entry.insert((binary_id, token));
+                Ok(CancellationRegistration {
+                    cancellations: &self.cancellations,
+                    request_id,
+                })
+            }
+        }
+    }
+
+    /// Run `binary_ids` in order, each step's output feeding the next. Step
+    /// `i` runs with `configs[i]` and sees `env` overlaid with `step_env[i]`,
+    /// if present. With `persist_host_state`, a binary's later steps start
+    /// with the scratch buffer its earlier steps left behind.
+    ///
+    /// A step that times out, or that takes the chain's fuel use past its
+    /// budget, ends the chain early; the response says why.
     pub async fn execute_chain(
         &self,
         binary_ids: Vec<Uuid>,
         initial_input: String,
-        config: ExecutionConfig,
-    ) -> Result<Vec<ExecutionResult>> {
+        configs: Vec<ExecutionConfig>,
+        env: EnvVars,
+        step_env: Vec<EnvVars>,
+        persist_host_state: bool,
+    ) -> Result<ExecuteChainResponse> {
         tracing::info!("Executing binary chain: {} binaries", binary_ids.len());
+        if let Some(max) = self.max_chain_length {
+            if binary_ids.len() > max {
+                return Err(ErrorResponse::new(
+                    ErrorCode::InvalidRequest,
+                    format!(
+                        "Chain has {} steps; at most {} are allowed",
+                        binary_ids.len(),
+                        max
+                    ),
+                )
+                .into());
+            }
+        }
+        self.check_chain_kinds(&binary_ids)?;
         let mut results = Vec::new();
+        let mut halted = None;
+        let mut fuel_used: u64 = 0;
         let mut current_input = initial_input;
-        for (index, binary_id) in binary_ids.iter().enumerate() {
+        let mut scratches: HashMap<Uuid, Scratch> = HashMap::new();
+        for (index, (binary_id, config)) in binary_ids.iter().zip(configs).enumerate() {
             tracing::info!(
                 "Chain step {}/{}: {}",
                 index + 1,
                 binary_ids.len(),
                 binary_id
             );
+            let mut context = ExecutionContext {
+                env: env.clone(),
+                ..Default::default()
+            };
+            if let Some(overrides) = step_env.get(index) {
+                context.env.extend(overrides.clone());
+            }
+            if persist_host_state {
+                context.scratch = Some(scratches.entry(*binary_id).or_default().clone());
+            }
             let result = self
-                .execute(*binary_id, current_input.clone(), config.clone())
+                .execute_with(*binary_id, current_input.clone(), config, context)
                 .await?;
+            fuel_used = fuel_used.saturating_add(result.fuel_consumed);
+
+            // Partial output is for inspection, not for feeding the next step
+            if result.timed_out {
+                let reason = format!("Step {} timed out", index + 1);
+                tracing::warn!("Chain stopped: {}", reason);
+                results.push(result);
+                halted = Some(reason);
+                break;
+            }
+            if let Some(budget) = self.chain_fuel_budget {
+                if fuel_used > budget && index + 1 < binary_ids.len() {
+                    let reason = format!(
+                        "Fuel budget exhausted after step {}: used {} of {}",
+                        index + 1,
+                        fuel_used,
+                        budget
+                    );
+                    tracing::warn!("Chain stopped: {}", reason);
+                    results.push(result);
+                    halted = Some(reason);
+                    break;
+                }
+            }
 
-            // Extract the actual result for the next plugin in the chain
-            current_input = Self::extract_result(&result.output);
+            // Extract the actual result for the next plugin in the chain,
+            // looking at the logs line by line whatever separator joined them
+            current_input = if result.from_output_buffer {
+                result.output.clone()
+            } else {
+                Self::extract_result(&result.logs.join("\n"))
+            };
             tracing::debug!(
                 "Chain step {} extracted output: {}",
                 index + 1,
@@ -177,65 +701,169 @@ impl Executor {
             results.push(result);
         }
         tracing::info!("Chain execution completed: {} steps", results.len());
-        Ok(results)
+        Ok(ExecuteChainResponse { results, halted })
+    }
+
+    /// Reject a chain where a step declares an output kind the next step
+    /// declares it can't take. Steps without a declared kind match anything.
+    fn check_chain_kinds(&self, binary_ids: &[Uuid]) -> Result<()> {
+        let metadata = binary_ids
+            .iter()
+            .map(|id| Ok(self.registry.get_binary(id)?.metadata))
+            .collect::<Result<Vec<_>>>()?;
+        for (index, pair) in metadata.windows(2).enumerate() {
+            let (from, to) = (&pair[0], &pair[1]);
+            if let (Some(output), Some(input)) = (from.output_kind(), to.input_kind()) {
+                if output != input {
+                    return Err(ErrorResponse::new(
+                        ErrorCode::InvalidRequest,
+                        format!(
+                            "Chain step {} ({}) outputs {} but step {} ({}) expects {} input",
+                            index + 1,
+                            from.id,
+                            output.name(),
+                            index + 2,
+                            to.id,
+                            input.name()
+                        ),
+                    )
+                    .into());
+                }
+            }
+        }
+        Ok(())
     }
 
     async fn execute_binary(
         &self,
         binary: LoadedBinary,
-        input: String,
+        input: Vec<u8>,
         config: ExecutionConfig,
+        context: ExecutionContext,
+        deadline: Instant,
         mut trace: Option<&mut ExecutionTrace>,
     ) -> Result<ExecutionResult> {
-        let mut store = Store::new(self.registry.engine(), HostState::new());
-        let fuel_limit = config.timeout_ms * 1_000_000;
-        store.set_fuel(fuel_limit)?;
+        let module = match &binary.kind {
+            BinaryKind::Core(_) if binary.needs_memory_export() => {
+                return Err(ErrorResponse::new(
+                    ErrorCode::InvalidBinary,
+                    format!(
+                        "Binary {} does not export 'memory' (flagged when it was loaded); \
+                         plugins must export their memory to receive input",
+                        binary.metadata.id
+                    ),
+                )
+                .into());
+            }
+            BinaryKind::Core(module) => module.clone(),
+            BinaryKind::Component(component) => {
+                let component = component.clone();
+                return self
+                    .execute_component(
+                        binary.metadata.id,
+                        &component,
+                        input,
+                        config,
+                        &context,
+                        deadline,
+                        trace,
+                    )
+                    .await;
+            }
+        };
 
-        if let Some(ref mut t) = trace {
-            t.add_event(
-                TraceEventType::FuelCheckpoint,
-                format!("Fuel limit set: {}", fuel_limit),
-                Some(serde_json::json!({"fuel_limit": fuel_limit})),
-            );
+        let streams_input = module
+            .imports()
+            .any(|import| import.module() == "host" && import.name() == READ_INPUT_CHUNK_IMPORT);
+        let crypto = binary.metadata.manifest.as_ref().is_some_and(|manifest| {
+            manifest
+                .capabilities
+                .iter()
+                .any(|capability| capability == CRYPTO_CAPABILITY)
+        });
+        let digest_import = module.imports().find(|import| {
+            import.module() == "host" && DIGESTS.iter().any(|(name, _)| *name == import.name())
+        });
+        if let (false, Some(import)) = (crypto, digest_import) {
+            return Err(ErrorResponse::new(
+                ErrorCode::Unauthorized,
+                format!(
+                    "Binary {} imports host::{} but its manifest doesn't declare the '{}' capability",
+                    binary.metadata.id,
+                    import.name(),
+                    CRYPTO_CAPABILITY
+                ),
+            )
+            .into());
         }
+        let input = Arc::new(input);
+        let state = HostState {
+            scratch: context.scratch.unwrap_or_default(),
+            input: input.clone(),
+            chunks: context.chunks,
+            encoding: context.encoding,
+            meter_only: config.meter_only,
+            counters: self.counters.clone(),
+            namespace: binary.metadata.namespace.clone(),
+            max_output: config.max_output_bytes,
+            ..HostState::new()
+        };
+        let mut store = Store::new(self.registry.engine(), state);
+        store.limiter(|state| state);
+        let fuel_limit = config.fuel.unwrap_or(config.timeout_ms * 1_000_000);
+        let metered = start_metering(&mut store, fuel_limit, self.fuel_yield_interval)?;
 
-        let mut linker = Linker::new(self.registry.engine());
-        linker.func_wrap_async(
-            "host",
-            "log",
-            |mut caller: Caller<'_, HostState>, (ptr, len): (i32, i32)| {
-                Box::new(async move {
-                    let mem = caller
-                        .get_export("memory")
-                        .and_then(|e| e.into_memory())
-                        .ok_or_else(|| anyhow!("No memory export"))?;
-                    let mut buf = vec![0u8; len as usize];
-                    mem.read(&caller, ptr as usize, &mut buf)?;
-                    let message = std::str::from_utf8(&buf).context("Invalid UTF-8")?;
-                    caller.data_mut().logs.push(message.to_string());
-                    tracing::debug!("[Plugin Log]: {}", message);
-                    Ok(())
-                })
-            },
-        )?;
+        if let Some(ref mut t) = trace {
+            if metered {
+                t.add_event(
+                    TraceEventType::FuelCheckpoint,
+                    format!("Fuel limit set: {}", fuel_limit),
+                    Some(serde_json::json!({"fuel_limit": fuel_limit})),
+                );
+            } else {
+                t.add_event(
+                    TraceEventType::FuelCheckpoint,
+                    "Engine has no fuel metering; fuel limit not enforced".to_string(),
+                    None,
+                );
+            }
+        }
 
         if let Some(ref mut t) = trace {
             t.add_event(
                 TraceEventType::HostFunctionCall,
-                "Host function 'log' registered".to_string(),
+                "Host functions 'log', 'write_output', 'write_named_output', 'emit_chunk', 'fuel_remaining', 'counter_inc', 'read_input_chunk', 'scratch_read', 'scratch_write' registered"
+                    .to_string(),
                 None,
             );
+            if crypto {
+                t.add_event(
+                    TraceEventType::HostFunctionCall,
+                    "Digest host functions 'sha256', 'blake3' registered".to_string(),
+                    None,
+                );
+            }
         }
 
-        linker.allow_shadowing(true);
-
-        let instance = linker
-            .instantiate_async(&mut store, &binary.module)
-            .await
-            .map_err(|e| {
-                tracing::error!("Instantiation error: {:?}", e);
-                anyhow!("Failed to instantiate module: {}. Check that all required imports are satisfied.", e)
-            })?;
+        let instance = match binary.linked.get() {
+            Some(linked) => linked.clone(),
+            None => {
+                let linked = self.link(&module, binary.metadata.id, crypto)?;
+                // A concurrent first run may have linked it too; either will do
+                let _ = binary.linked.set(linked.clone());
+                linked
+            }
+        }
+        .instantiate_async(&mut store)
+        .await
+        .map_err(|e| {
+            tracing::error!("Instantiation error: {:?}", e);
+            anyhow!(
+                "Failed to instantiate module: {}. Check that all required imports are satisfied.",
+                e
+            )
+        })?;
+        store.data_mut().limit_memory(&config);
 
         if let Some(ref mut t) = trace {
             t.add_event(
@@ -262,10 +890,12 @@ impl Executor {
         }
 
         if memory_size_mb > config.memory_limit_mb {
-            let error = anyhow!(
-                "Memory limit exceeded: {} MB > {} MB",
-                memory_size_mb,
-                config.memory_limit_mb
+            let error = ErrorResponse::new(
+                ErrorCode::MemoryLimit,
+                format!(
+                    "Memory limit exceeded: {} MB > {} MB",
+                    memory_size_mb, config.memory_limit_mb
+                ),
             );
             if let Some(ref mut t) = trace {
                 t.add_event(
@@ -277,26 +907,55 @@ impl Executor {
                     None,
                 );
             }
-            return Err(error);
+            return Err(error.into());
         }
 
-        let input_bytes = input.as_bytes();
+        let env_json = Self::env_json(&context.env, config.deterministic)?;
+        let env_bytes = env_json.as_bytes();
+        let input_bytes = input.as_slice();
+        // A streaming plugin reads its input through `read_input_chunk`, so
+        // only the env is written; `process` still gets the full input length
+        let written_input: &[u8] = if streams_input { &[] } else { input_bytes };
+
+        // Empty input still takes a byte, so `env_ptr` never equals
+        // `input_ptr` and a plugin can't mistake the env for its input
+        let input_span = written_input.len().max(1);
+        let input_ptr =
+            Self::resolve_input_base(&mut store, &instance, input_span + env_bytes.len(), &config)
+                .await;
+        // An `alloc` refused more memory fails with a less useful error
+        store.data().check_memory_growth()?;
+        let input_ptr = input_ptr?;
+        let env_ptr = input_ptr + input_span;
+
         memory
-            .write(&mut store, 0, input_bytes)
+            .write(&mut store, input_ptr, written_input)
             .context("Failed to write input to memory")?;
 
         if let Some(ref mut t) = trace {
+            let message = match streams_input {
+                true => format!(
+                    "Input left for 'read_input_chunk': {} bytes",
+                    input_bytes.len()
+                ),
+                false => format!(
+                    "Input written to memory: {} bytes at offset {}",
+                    input_bytes.len(),
+                    input_ptr
+                ),
+            };
             t.add_event(
                 TraceEventType::MemoryOp,
-                format!("Input written to memory: {} bytes", input_bytes.len()),
-                Some(serde_json::json!({"input_bytes": input_bytes.len()})),
+                message,
+                Some(serde_json::json!({
+                    "input_bytes": input_bytes.len(),
+                    "input_ptr": input_ptr,
+                })),
             );
         }
 
-        let env_json = Self::env_json().context("Failed to generate environment JSON")?;
-        let env_bytes = env_json.as_bytes();
         memory
-            .write(&mut store, input_bytes.len(), env_bytes)
+            .write(&mut store, env_ptr, env_bytes)
             .context("Failed to write env JSON to memory")?;
 
         if let Some(ref mut t) = trace {
@@ -316,28 +975,61 @@ impl Executor {
                 TraceEventType::FunctionCall,
                 "Calling 'process' function".to_string(),
                 Some(serde_json::json!({
-                    "input_ptr": 0,
+                    "input_ptr": input_ptr,
                     "input_len": input_bytes.len(),
-                    "env_ptr": input_bytes.len(),
+                    "env_ptr": env_ptr,
                     "env_len": env_bytes.len(),
                 })),
             );
         }
 
-        let return_code = process_func
-            .call_async(
-                &mut store,
-                (
-                    0,
-                    input_bytes.len() as i32,
-                    input_bytes.len() as i32,
-                    env_bytes.len() as i32,
-                ),
-            )
-            .await
-            .context("Plugin execution failed")?;
+        let call = process_func.call_async(
+            &mut store,
+            (
+                input_ptr as i32,
+                input_bytes.len() as i32,
+                env_ptr as i32,
+                env_bytes.len() as i32,
+            ),
+        );
+        // Dropping the call at the deadline leaves the store, and everything
+        // the plugin logged or wrote so far, intact
+        let outcome = timeout_at(deadline, call).await;
+        // A refused `memory.grow` usually ends in a trap; report the cause
+        store.data().check_memory_growth()?;
+        let (mut return_code, timed_out) = match outcome {
+            Ok(return_code) => (return_code.map_err(|e| call_error(e, fuel_limit))?, false),
+            Err(_) => (RETURN_CODE_TIMED_OUT, true),
+        };
+
+        let returns_output_len = instance.get_global(&mut store, OUTPUT_LEN_EXPORT).is_some();
+        if returns_output_len
+            && !timed_out
+            && return_code as u32 & OUTPUT_LEN_FLAG != 0
+            && !RETURNED_ERROR_CODES.contains(&return_code)
+        {
+            let len = (return_code as u32 & !OUTPUT_LEN_FLAG) as usize;
+            // Borrowed first, so a bogus length can't make the host allocate
+            let output = memory
+                .data(&store)
+                .get(input_ptr..)
+                .and_then(|rest| rest.get(..len))
+                .ok_or_else(|| {
+                    ErrorResponse::new(
+                        ErrorCode::InvalidBinary,
+                        format!(
+                            "Plugin returned an output length of {} bytes at offset {}, past the end of memory",
+                            len, input_ptr
+                        ),
+                    )
+                })?
+                .to_vec();
+            // Takes precedence over anything passed to `write_output`
+            store.data_mut().output = Some(output);
+            return_code = 0;
+        }
 
-        let fuel_consumed = fuel_limit - store.get_fuel().unwrap_or(0);
+        let fuel_consumed = fuel_consumed(&store, metered, fuel_limit);
 
         if let Some(ref mut t) = trace {
             t.add_event(
@@ -351,7 +1043,66 @@ impl Executor {
             );
         }
 
-        let output = store.data().logs.join("\n");
+        if config.meter_only {
+            return Ok(metered_result(
+                binary.metadata.id,
+                return_code,
+                fuel_consumed,
+                timed_out,
+            ));
+        }
+
+        let output_cap = instance
+            .get_global(&mut store, OUTPUT_CAP_EXPORT)
+            .and_then(|global| global.get(&mut store).i32());
+
+        // Prefer the dedicated output buffer; fall back to the joined logs for
+        // plugins that only report their result through `log`
+        let from_output_buffer = store.data().output.is_some();
+        let joined_logs;
+        let output_bytes = match store.data().output.as_deref() {
+            Some(bytes) => bytes,
+            None => {
+                joined_logs = store.data().logs.join(config.output_separator());
+                joined_logs.as_bytes()
+            }
+        };
+        let (output_bytes, cut) = cap_output(output_bytes, config.max_output_bytes);
+        let mut warnings = Vec::new();
+        let mut output_truncated = false;
+        if cut || store.data().output_truncated {
+            warnings.push(max_output_warning(&config));
+            output_truncated = true;
+        }
+        if let Some(cap) = output_cap.map(|cap| cap as u32 as usize) {
+            if input_bytes.len() > cap {
+                warnings.push(format!(
+                    "Input is {} bytes, more than the {} byte output capacity the plugin declares; its output is likely truncated",
+                    input_bytes.len(),
+                    cap
+                ));
+                output_truncated = true;
+            }
+        }
+        let output = encoding::encode(context.encoding, output_bytes);
+        let outputs = store
+            .data()
+            .named_outputs
+            .iter()
+            .map(|(name, bytes)| (name.clone(), encoding::encode(context.encoding, bytes)))
+            .collect();
+
+        if let Some(audit) = &self.audit {
+            // Hash the raw buffer so lossy decoding can't hide what was written
+            audit.append(AuditRecord {
+                binary_id: binary.metadata.id,
+                input: input_bytes,
+                env: env_bytes,
+                output: output_bytes,
+                return_code,
+                fuel_consumed,
+            })?;
+        }
 
         // Log all plugin messages to trace
         if let Some(ref mut t) = trace {
@@ -364,36 +1115,492 @@ impl Executor {
             binary_id: binary.metadata.id,
             return_code,
             output,
+            logs: store.data().logs.clone(),
+            from_output_buffer,
             execution_time_ms: 0, // Will be set by caller
             fuel_consumed,
+            timed_out,
+            correlation_id: None,                       // Will be set by caller
+            encoding: Encoding::Utf8,                   // Will be set by caller
+            applied_config: ExecutionConfig::default(), // Will be set by caller
+            warnings,
+            cache_hit: false,
+            output_truncated,
+            outputs,
         })
     }
 
-    /// Extract the actual result from plugin output
-    /// Plugins may log multiple lines, but the result is typically after "Result = "
-    /// If no "Result = " marker is found, return the last non-empty line
-    fn extract_result(output: &str) -> String {
-        let lines: Vec<&str> = output.lines().collect();
-
-        // Look for "Result = " marker
-        for (i, line) in lines.iter().enumerate() {
-            if line.contains("Result = ") {
-                // The result is typically on the next line
-                if i + 1 < lines.len() {
-                    let result = lines[i + 1].trim();
-                    if !result.is_empty() {
-                        return result.to_string();
-                    }
-                }
-                // Or it might be on the same line after the marker
-                if let Some(pos) = line.find("Result = ") {
-                    let result = line[pos + 9..].trim();
-                    if !result.is_empty() {
-                        return result.to_string();
+    /// Link `module` against the host functions, once per loaded binary;
+    /// the digest functions only for `crypto` plugins
+    fn link(
+        &self,
+        module: &Module,
+        binary_id: Uuid,
+        crypto: bool,
+    ) -> Result<InstancePre<HostState>> {
+        let mut linker = Linker::new(self.registry.engine());
+        linker.func_wrap_async(
+            "host",
+            "log",
+            |mut caller: Caller<'_, HostState>, (ptr, len): (i32, i32)| {
+                Box::new(async move {
+                    if caller.data().meter_only {
+                        return Ok(());
                     }
-                }
-            }
-        }
+                    let mem = caller
+                        .get_export("memory")
+                        .and_then(|e| e.into_memory())
+                        .ok_or_else(|| anyhow!("No memory export"))?;
+                    let buf = guest_bytes(mem.data(&caller), ptr, len)?;
+                    // Never fail the execution over a badly encoded log line
+                    let message = String::from_utf8_lossy(buf).into_owned();
+                    tracing::debug!("[Plugin Log]: {}", message);
+                    caller.data_mut().logs.push(message);
+                    Ok(())
+                })
+            },
+        )?;
+
+        linker.func_wrap_async(
+            "host",
+            "write_output",
+            |mut caller: Caller<'_, HostState>, (ptr, len): (i32, i32)| {
+                Box::new(async move {
+                    if caller.data().meter_only {
+                        return Ok(());
+                    }
+                    let mem = caller
+                        .get_export("memory")
+                        .and_then(|e| e.into_memory())
+                        .ok_or_else(|| anyhow!("No memory export"))?;
+                    let (data, state) = mem.data_and_store_mut(&mut caller);
+                    state.append_output(guest_bytes(data, ptr, len)?);
+                    Ok(())
+                })
+            },
+        )?;
+
+        linker.func_wrap_async(
+            "host",
+            "write_named_output",
+            |mut caller: Caller<'_, HostState>,
+             (name_ptr, name_len, ptr, len): (i32, i32, i32, i32)| {
+                Box::new(async move {
+                    if caller.data().meter_only {
+                        return Ok(());
+                    }
+                    let mem = caller
+                        .get_export("memory")
+                        .and_then(|e| e.into_memory())
+                        .ok_or_else(|| anyhow!("No memory export"))?;
+                    let (data, state) = mem.data_and_store_mut(&mut caller);
+                    let name = guest_bytes(data, name_ptr, name_len)?;
+                    let name = String::from_utf8_lossy(name).into_owned();
+                    state.append_named_output(name, guest_bytes(data, ptr, len)?);
+                    Ok(())
+                })
+            },
+        )?;
+
+        linker.func_wrap_async(
+            "host",
+            "emit_chunk",
+            move |mut caller: Caller<'_, HostState>, (ptr, len): (i32, i32)| {
+                Box::new(async move {
+                    if caller.data().meter_only {
+                        return Ok(());
+                    }
+                    let mem = caller
+                        .get_export("memory")
+                        .and_then(|e| e.into_memory())
+                        .ok_or_else(|| anyhow!("No memory export"))?;
+                    let (data, state) = mem.data_and_store_mut(&mut caller);
+                    let kept = state.append_output(guest_bytes(data, ptr, len)?);
+                    // Nothing is streamed once the output is full
+                    if kept.is_empty() && state.output_truncated {
+                        return Ok(());
+                    }
+                    if let Some(chunks) = &state.chunks {
+                        // The receiver goes away if the client disconnects;
+                        // keep running so the result still lands in the buffer
+                        let _ = chunks.send(OutputChunk {
+                            binary_id,
+                            index: state.chunk_count,
+                            data: encoding::encode(state.encoding, kept),
+                        });
+                    }
+                    state.chunk_count += 1;
+                    Ok(())
+                })
+            },
+        )?;
+
+        // Lets long-running plugins stop cleanly before they run out of fuel
+        linker.func_wrap(
+            "host",
+            "fuel_remaining",
+            |caller: Caller<'_, HostState>| -> i64 {
+                // Without metering the fuel never runs out
+                caller.get_fuel().unwrap_or(u64::MAX).min(i64::MAX as u64) as i64
+            },
+        )?;
+
+        // Counters outlive the execution, so plugins can count across runs
+        linker.func_wrap(
+            "host",
+            "counter_inc",
+            |mut caller: Caller<'_, HostState>, ptr: i32, len: i32| -> Result<i64> {
+                let mem = caller
+                    .get_export("memory")
+                    .and_then(|e| e.into_memory())
+                    .ok_or_else(|| anyhow!("No memory export"))?;
+                let key = guest_bytes(mem.data(&caller), ptr, len)?;
+                if key.len() > MAX_COUNTER_KEY_BYTES {
+                    return Err(anyhow!(
+                        "Counter name is {} bytes, more than the {} byte limit",
+                        key.len(),
+                        MAX_COUNTER_KEY_BYTES
+                    ));
+                }
+                let key = String::from_utf8_lossy(key).into_owned();
+                let state = caller.data();
+                // A dry run sees the value it would get but changes nothing
+                if state.meter_only {
+                    let value = state
+                        .counters
+                        .get(&state.namespace)
+                        .and_then(|counters| counters.get(&key).map(|value| *value));
+                    return Ok(value.unwrap_or(0) + 1);
+                }
+                let counters = state.counters.entry(state.namespace.clone()).or_default();
+                if !counters.contains_key(&key) && counters.len() >= MAX_COUNTERS_PER_NAMESPACE {
+                    return Err(anyhow!(
+                        "Namespace already holds the maximum of {} counters",
+                        MAX_COUNTERS_PER_NAMESPACE
+                    ));
+                }
+                let mut value = counters.entry(key).or_insert(0);
+                *value += 1;
+                Ok(*value)
+            },
+        )?;
+
+        // Scratch space a binary can read back in its later steps of a chain
+        // that persists host state; otherwise it starts empty every run
+        linker.func_wrap(
+            "host",
+            "scratch_write",
+            |mut caller: Caller<'_, HostState>, ptr: i32, len: i32| -> Result<()> {
+                let mem = caller
+                    .get_export("memory")
+                    .and_then(|e| e.into_memory())
+                    .ok_or_else(|| anyhow!("No memory export"))?;
+                let data = guest_bytes(mem.data(&caller), ptr, len)?;
+                if data.len() > MAX_SCRATCH_BYTES {
+                    return Err(anyhow!(
+                        "Scratch write is {} bytes, more than the {} byte limit",
+                        data.len(),
+                        MAX_SCRATCH_BYTES
+                    ));
+                }
+                let state = caller.data();
+                if !state.meter_only {
+                    let mut scratch = state.scratch.lock().unwrap();
+                    scratch.clear();
+                    scratch.extend_from_slice(data);
+                }
+                Ok(())
+            },
+        )?;
+
+        // Copies at most `len` bytes of the scratch buffer and returns its
+        // full length, so a plugin can tell when its buffer was too small
+        linker.func_wrap(
+            "host",
+            "scratch_read",
+            |mut caller: Caller<'_, HostState>, ptr: i32, len: i32| -> Result<i32> {
+                let mem = caller
+                    .get_export("memory")
+                    .and_then(|e| e.into_memory())
+                    .ok_or_else(|| anyhow!("No memory export"))?;
+                let scratch = caller.data().scratch.lock().unwrap().clone();
+                let copied = scratch.len().min(len.max(0) as usize);
+                mem.write(&mut caller, ptr as u32 as usize, &scratch[..copied])?;
+                Ok(scratch.len() as i32)
+            },
+        )?;
+
+        // Hands out the input a buffer at a time; 0 once all of it was read
+        linker.func_wrap(
+            "host",
+            READ_INPUT_CHUNK_IMPORT,
+            |mut caller: Caller<'_, HostState>, ptr: i32, len: i32| -> Result<i32> {
+                let mem = caller
+                    .get_export("memory")
+                    .and_then(|e| e.into_memory())
+                    .ok_or_else(|| anyhow!("No memory export"))?;
+                let state = caller.data();
+                let input = state.input.clone();
+                let start = state.input_read;
+                let end = input.len().min(start + len.max(0) as usize);
+                mem.write(&mut caller, ptr as u32 as usize, &input[start..end])?;
+                caller.data_mut().input_read = end;
+                Ok((end - start) as i32)
+            },
+        )?;
+
+        if crypto {
+            for &(name, digest) in DIGESTS {
+                linker.func_wrap(
+                    "host",
+                    name,
+                    move |mut caller: Caller<'_, HostState>,
+                          ptr: i32,
+                          len: i32,
+                          out_ptr: i32|
+                          -> Result<()> {
+                        let mem = caller
+                            .get_export("memory")
+                            .and_then(|e| e.into_memory())
+                            .ok_or_else(|| anyhow!("No memory export"))?;
+                        let hash = digest(guest_bytes(mem.data(&caller), ptr, len)?);
+                        mem.write(&mut caller, out_ptr as u32 as usize, &hash)?;
+                        Ok(())
+                    },
+                )?;
+            }
+        }
+
+        linker.allow_shadowing(true);
+        linker.instantiate_pre(module).map_err(|e| {
+            anyhow!(
+                "Failed to instantiate module: {}. Check that all required imports are satisfied.",
+                e
+            )
+        })
+    }
+
+    /// Component-model path: the component imports `log: func(message: string)`
+    /// and exports `process: func(input: string, env: string) -> tuple<s32, string>`.
+    /// The canonical ABI handles memory, so there is no input offset or memory check.
+    #[allow(clippy::too_many_arguments)]
+    async fn execute_component(
+        &self,
+        binary_id: Uuid,
+        component: &Component,
+        input: Vec<u8>,
+        config: ExecutionConfig,
+        context: &ExecutionContext,
+        deadline: Instant,
+        mut trace: Option<&mut ExecutionTrace>,
+    ) -> Result<ExecutionResult> {
+        let state = HostState {
+            meter_only: config.meter_only,
+            ..HostState::new()
+        };
+        let mut store = Store::new(self.registry.engine(), state);
+        store.limiter(|state| state);
+        let fuel_limit = config.fuel.unwrap_or(config.timeout_ms * 1_000_000);
+        let metered = start_metering(&mut store, fuel_limit, self.fuel_yield_interval)?;
+
+        if let Some(ref mut t) = trace {
+            if metered {
+                t.add_event(
+                    TraceEventType::FuelCheckpoint,
+                    format!("Fuel limit set: {}", fuel_limit),
+                    Some(serde_json::json!({"fuel_limit": fuel_limit})),
+                );
+            } else {
+                t.add_event(
+                    TraceEventType::FuelCheckpoint,
+                    "Engine has no fuel metering; fuel limit not enforced".to_string(),
+                    None,
+                );
+            }
+        }
+
+        let mut linker = component::Linker::<HostState>::new(self.registry.engine());
+        linker.root().func_wrap_async(
+            "log",
+            |mut store: StoreContextMut<'_, HostState>, (message,): (String,)| {
+                Box::new(async move {
+                    if store.data().meter_only {
+                        return Ok(());
+                    }
+                    tracing::debug!("[Plugin Log]: {}", message);
+                    store.data_mut().logs.push(message);
+                    Ok(())
+                })
+            },
+        )?;
+
+        let instance = linker
+            .instantiate_async(&mut store, component)
+            .await
+            .map_err(|e| {
+                tracing::error!("Component instantiation error: {:?}", e);
+                anyhow!("Failed to instantiate component: {}. Check that all required imports are satisfied.", e)
+            })?;
+        store.data_mut().limit_memory(&config);
+
+        if let Some(ref mut t) = trace {
+            t.add_event(
+                TraceEventType::FunctionCall,
+                "Component instantiated successfully".to_string(),
+                None,
+            );
+        }
+
+        let process_func = instance
+            .get_typed_func::<(&str, &str), ((i32, String),)>(&mut store, "process")
+            .context(
+                "Component must export 'process: func(input: string, env: string) -> tuple<s32, string>'",
+            )?;
+
+        // The canonical ABI only carries strings
+        let input = String::from_utf8(input).map_err(|_| {
+            ErrorResponse::new(
+                ErrorCode::InvalidRequest,
+                "Components take UTF-8 input; the decoded input is not valid UTF-8",
+            )
+        })?;
+        let env_json = Self::env_json(&context.env, config.deterministic)?;
+        let call = process_func.call_async(&mut store, (&input, &env_json));
+        let outcome = timeout_at(deadline, call).await;
+        store.data().check_memory_growth()?;
+        let (return_code, output, timed_out) = match outcome {
+            Ok(returned) => {
+                let ((return_code, output),) = returned.map_err(|e| call_error(e, fuel_limit))?;
+                process_func.post_return_async(&mut store).await?;
+                (return_code, output, false)
+            }
+            // Components return their output in one piece, so only logs survive
+            Err(_) => (
+                RETURN_CODE_TIMED_OUT,
+                store.data().logs.join(config.output_separator()),
+                true,
+            ),
+        };
+
+        let fuel_consumed = fuel_consumed(&store, metered, fuel_limit);
+
+        if config.meter_only {
+            return Ok(metered_result(
+                binary_id,
+                return_code,
+                fuel_consumed,
+                timed_out,
+            ));
+        }
+
+        let (output_bytes, output_truncated) =
+            cap_output(output.as_bytes(), config.max_output_bytes);
+        let warnings = match output_truncated {
+            true => vec![max_output_warning(&config)],
+            false => Vec::new(),
+        };
+
+        if let Some(audit) = &self.audit {
+            audit.append(AuditRecord {
+                binary_id,
+                input: input.as_bytes(),
+                env: env_json.as_bytes(),
+                output: output_bytes,
+                return_code,
+                fuel_consumed,
+            })?;
+        }
+
+        if let Some(ref mut t) = trace {
+            t.add_event(
+                TraceEventType::FuelCheckpoint,
+                format!("Execution completed with return code: {}", return_code),
+                Some(serde_json::json!({
+                    "return_code": return_code,
+                    "fuel_consumed": fuel_consumed,
+                })),
+            );
+            for log in &store.data().logs {
+                t.add_event(TraceEventType::PluginLog, log.clone(), None);
+            }
+        }
+
+        Ok(ExecutionResult {
+            binary_id,
+            return_code,
+            output: encoding::encode(context.encoding, output_bytes),
+            logs: store.data().logs.clone(),
+            from_output_buffer: !timed_out,
+            execution_time_ms: 0, // Will be set by caller
+            fuel_consumed,
+            timed_out,
+            correlation_id: None,                       // Will be set by caller
+            encoding: Encoding::Utf8,                   // Will be set by caller
+            applied_config: ExecutionConfig::default(), // Will be set by caller
+            warnings,
+            cache_hit: false,
+            output_truncated,
+            outputs: BTreeMap::new(),
+        })
+    }
+
+    /// Decide where in guest memory the input and env are written.
+    ///
+    /// In order of preference: the plugin's exported `alloc(len) -> ptr`, its
+    /// exported `__input_base` global, then `config.input_base` (default 0).
+    /// The input goes at the base and the env right after it, or one byte
+    /// after the base when the input is empty; `len` covers both.
+    async fn resolve_input_base(
+        store: &mut Store<HostState>,
+        instance: &Instance,
+        len: usize,
+        config: &ExecutionConfig,
+    ) -> Result<usize> {
+        if let Ok(alloc) = instance.get_typed_func::<i32, i32>(&mut *store, "alloc") {
+            let ptr = alloc
+                .call_async(&mut *store, len as i32)
+                .await
+                .context("Plugin 'alloc' call failed")?;
+            if ptr <= 0 {
+                return Err(anyhow!("Plugin 'alloc' could not reserve {} bytes", len));
+            }
+            return Ok(ptr as usize);
+        }
+
+        if let Some(global) = instance.get_global(&mut *store, "__input_base") {
+            if let Some(base) = global.get(&mut *store).i32() {
+                return Ok(base as u32 as usize);
+            }
+        }
+
+        Ok(config.input_base.unwrap_or(0) as usize)
+    }
+
+    /// Extract the actual result from plugin output
+    /// Plugins may log multiple lines, but the result is typically after "Result = "
+    /// If no "Result = " marker is found, return the last non-empty line
+    fn extract_result(output: &str) -> String {
+        let lines: Vec<&str> = output.lines().collect();
+
+        // Look for "Result = " marker
+        for (i, line) in lines.iter().enumerate() {
+            if line.contains("Result = ") {
+                // The result is typically on the next line
+                if i + 1 < lines.len() {
+                    let result = lines[i + 1].trim();
+                    if !result.is_empty() {
+                        return result.to_string();
+                    }
+                }
+                // Or it might be on the same line after the marker
+                if let Some(pos) = line.find("Result = ") {
+                    let result = line[pos + 9..].trim();
+                    if !result.is_empty() {
+                        return result.to_string();
+                    }
+                }
+            }
+        }
 
         // Fallback: return the last non-empty line
         lines
@@ -404,30 +1611,1914 @@ impl Executor {
             .unwrap_or_default()
     }
 
-    fn env_json() -> Result<String> {
-        // Placeholder for environment JSON generation logic
-        let now = std::time::SystemTime::now();
-        let timestamp = now
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_nanos() as i64;
-
-        let random_seed = random::<i64>();
-        let mut env = serde_json::Map::new();
+    /// The env handed to a plugin: the request's env vars plus `timestamp`
+    /// and `random_seed`. An empty key, one over `MAX_ENV_KEY_BYTES`, or an
+    /// env over `MAX_ENV_BYTES` is an invalid request.
+    fn env_json(extra: &EnvVars, deterministic: bool) -> Result<String> {
+        if let Some(key) = extra
+            .keys()
+            .find(|key| key.is_empty() || key.len() > MAX_ENV_KEY_BYTES)
+        {
+            return Err(ErrorResponse::new(
+                ErrorCode::InvalidRequest,
+                format!(
+                    "Env key of {} bytes; keys must be 1 to {} bytes",
+                    key.len(),
+                    MAX_ENV_KEY_BYTES
+                ),
+            )
+            .into());
+        }
+        let (timestamp, random_seed) = if deterministic {
+            (0, 0)
+        } else {
+            let timestamp = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_nanos() as i64;
+            (timestamp, random::<i64>())
+        };
+        let mut env: serde_json::Map<_, _> = extra
+            .iter()
+            .map(|(key, value)| (key.clone(), serde_json::json!(value)))
+            .collect();
         env.insert("timestamp".to_string(), serde_json::json!(timestamp));
         env.insert("random_seed".to_string(), serde_json::json!(random_seed));
         let json = serde_json::to_string(&env).context("Failed to serialize env to JSON")?;
+        if json.len() > MAX_ENV_BYTES {
+            return Err(ErrorResponse::new(
+                ErrorCode::InvalidRequest,
+                format!(
+                    "Env is {} bytes as JSON, more than the {} byte limit",
+                    json.len(),
+                    MAX_ENV_BYTES
+                ),
+            )
+            .into());
+        }
         Ok(json)
     }
 }
 
+/// The `len` bytes at `ptr` in guest memory, borrowed rather than copied. A
+/// negative length or a range past the end of memory traps instead of
+/// allocating whatever the guest asked for.
+fn guest_bytes(memory: &[u8], ptr: i32, len: i32) -> Result<&[u8]> {
+    let start = ptr as u32 as usize;
+    let len =
+        usize::try_from(len).map_err(|_| anyhow!("Negative length {} passed to host", len))?;
+    start
+        .checked_add(len)
+        .and_then(|end| memory.get(start..end))
+        .ok_or_else(|| anyhow!("{} bytes at {:#x} are outside guest memory", len, start))
+}
+
+/// Give `store` its fuel limit, if the engine meters fuel at all, yielding
+/// every `yield_interval` units. Engines built without `consume_fuel` run
+/// unmetered, leaving the deadline as the only limit; returns whether
+/// metering is on.
+fn start_metering<T>(store: &mut Store<T>, fuel_limit: u64, yield_interval: u64) -> Result<bool> {
+    // Reading the fuel only fails when the engine doesn't meter it
+    if store.get_fuel().is_err() {
+        return Ok(false);
+    }
+    store.set_fuel(fuel_limit)?;
+    store.fuel_async_yield_interval(Some(yield_interval))?;
+    Ok(true)
+}
+
+/// Classify a failed `process` call. Running out of fuel gets its own error
+/// carrying the limit; any other trap is reported as a plain failure.
+fn call_error(e: anyhow::Error, fuel_limit: u64) -> anyhow::Error {
+    if let Some(Trap::OutOfFuel) = e.downcast_ref::<Trap>() {
+        return ErrorResponse::new(
+            ErrorCode::OutOfFuel,
+            format!(
+                "Plugin ran out of fuel: used its whole limit of {} without returning",
+                fuel_limit
+            ),
+        )
+        .into();
+    }
+    e.context("Plugin execution failed")
+}
+
+/// Fuel burned so far, 0 when the store isn't metered
+fn fuel_consumed<T>(store: &Store<T>, metered: bool, fuel_limit: u64) -> u64 {
+    if !metered {
+        return 0;
+    }
+    fuel_limit - store.get_fuel().unwrap_or(0)
+}
+
+/// How long until `deadline`, zero once it has passed
+fn time_left(deadline: SystemTime) -> Duration {
+    deadline
+        .duration_since(SystemTime::now())
+        .unwrap_or(Duration::ZERO)
+}
+
+/// Identifies a run of a pure binary: runs with the same key give the same
+/// result. The content hash keeps a reloaded binary, which keeps its ID, from
+/// being answered with its old code's results.
+fn result_cache_key(
+    metadata: &BinaryMetadata,
+    input: &[u8],
+    config: &ExecutionConfig,
+    context: &ExecutionContext,
+) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(metadata.id.as_bytes());
+    hasher.update(metadata.content_hash.as_deref().unwrap_or_default());
+    hasher.update((input.len() as u64).to_le_bytes());
+    hasher.update(input);
+    let settings = serde_json::to_vec(&(config, &context.env, context.encoding))
+        .expect("config and env serialize to JSON");
+    hasher.update(settings);
+    hasher.finalize().into()
+}
+
+/// A meter-only run's result: fuel and timing, nothing the plugin produced
+fn metered_result(
+    binary_id: Uuid,
+    return_code: i32,
+    fuel_consumed: u64,
+    timed_out: bool,
+) -> ExecutionResult {
+    ExecutionResult {
+        binary_id,
+        return_code,
+        output: String::new(),
+        logs: Vec::new(),
+        from_output_buffer: false,
+        execution_time_ms: 0, // Will be set by caller
+        fuel_consumed,
+        timed_out,
+        correlation_id: None,                       // Will be set by caller
+        encoding: Encoding::Utf8,                   // Will be set by caller
+        applied_config: ExecutionConfig::default(), // Will be set by caller
+        warnings: Vec::new(),                       // Will be set by caller
+        cache_hit: false,
+        output_truncated: false,
+        outputs: BTreeMap::new(),
+    }
+}
+
+/// `output` cut to at most `max` bytes, and whether anything was cut
+fn cap_output(output: &[u8], max: Option<usize>) -> (&[u8], bool) {
+    match max {
+        Some(max) if output.len() > max => (&output[..max], true),
+        _ => (output, false),
+    }
+}
+
+fn max_output_warning(config: &ExecutionConfig) -> String {
+    format!(
+        "Output was cut at the {} byte max_output_bytes limit",
+        config.max_output_bytes.unwrap_or_default()
+    )
+}
+
+/// Unregisters a cancellable execution when it finishes, however it finishes
+struct CancellationRegistration<'a> {
+    cancellations: &'a DashMap<Uuid, (Uuid, CancellationToken)>,
+    request_id: Uuid,
+}
+
+impl Drop for CancellationRegistration<'_> {
+    fn drop(&mut self) {
+        self.cancellations.remove(&self.request_id);
+    }
+}
+
 #[derive(Default)]
-struct HostState {
+pub(crate) struct HostState {
     logs: Vec<String>,
+    /// The execution's input, for `host::read_input_chunk`
+    input: Arc<Vec<u8>>,
+    /// How much of `input` `host::read_input_chunk` has handed out
+    input_read: usize,
+    /// Bytes written through `host::write_output` or `host::emit_chunk`, if
+    /// the plugin used either
+    output: Option<Vec<u8>>,
+    /// Where `host::emit_chunk` forwards chunks when the caller is streaming
+    chunks: Option<UnboundedSender<OutputChunk>>,
+    /// How chunks sent to `chunks` are encoded
+    encoding: Encoding,
+    chunk_count: u32,
+    /// Host functions drop everything the plugin sends them
+    meter_only: bool,
+    /// The executor's `host::counter_inc` values
+    counters: Arc<Counters>,
+    /// Namespace of the running binary, whose counters it sees
+    namespace: Option<String>,
+    /// `host::scratch_write`'s buffer, empty unless a chain carried one in
+    scratch: Scratch,
+    /// `ExecutionConfig::max_output_bytes`
+    max_output: Option<usize>,
+    /// Bytes written through `host::write_named_output`, by channel
+    named_outputs: BTreeMap<String, Vec<u8>>,
+    /// Some output was dropped for going past `max_output`
+    output_truncated: bool,
+    /// Refuse every `memory.grow`; set from `!allow_memory_growth` once the
+    /// instance exists
+    forbid_memory_growth: bool,
+    /// The plugin tried to grow its memory while `forbid_memory_growth` was set
+    memory_growth_blocked: bool,
+    /// `memory_limit_mb` in bytes; `memory.grow` past it is refused. Set,
+    /// like `forbid_memory_growth`, once the instance exists.
+    memory_limit: Option<usize>,
+    /// The plugin tried to grow a memory past `memory_limit`
+    memory_limit_hit: bool,
 }
 
 impl HostState {
     fn new() -> Self {
         Self::default()
     }
+
+    /// Append as much of `data` to the output buffer as `max_output` allows,
+    /// returning the part that was kept
+    fn append_output<'a>(&mut self, data: &'a [u8]) -> &'a [u8] {
+        let output = self.output.get_or_insert_with(Vec::new);
+        let kept = append_capped(output, data, self.max_output);
+        self.output_truncated |= kept.len() < data.len();
+        kept
+    }
+
+    /// Fail the execution if the plugin tried to grow its memory against
+    /// `allow_memory_growth` or past `memory_limit_mb`
+    fn check_memory_growth(&self) -> Result<(), ErrorResponse> {
+        if self.memory_growth_blocked {
+            return Err(ErrorResponse::new(
+                ErrorCode::MemoryLimit,
+                "Plugin tried to grow its memory, which allow_memory_growth = false forbids",
+            ));
+        }
+        if self.memory_limit_hit {
+            return Err(ErrorResponse::new(
+                ErrorCode::MemoryLimit,
+                format!(
+                    "Plugin tried to grow its memory past its memory_limit_mb of {} MB",
+                    self.memory_limit.unwrap_or_default() / (1024 * 1024)
+                ),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Start enforcing `config`'s memory settings; creating the instance's
+    /// memories counts as growing them, so this waits until they exist
+    fn limit_memory(&mut self, config: &ExecutionConfig) {
+        self.forbid_memory_growth = !config.allow_memory_growth;
+        self.memory_limit = Some((config.memory_limit_mb as usize).saturating_mul(1024 * 1024));
+    }
+
+    /// Append `data` to the named output channel; `max_output` caps each
+    /// channel on its own
+    fn append_named_output(&mut self, name: String, data: &[u8]) {
+        let output = self.named_outputs.entry(name).or_default();
+        let kept = append_capped(output, data, self.max_output);
+        self.output_truncated |= kept.len() < data.len();
+    }
+}
+
+impl ResourceLimiter for HostState {
+    fn memory_growing(
+        &mut self,
+        _current: usize,
+        desired: usize,
+        _maximum: Option<usize>,
+    ) -> Result<bool> {
+        if self.forbid_memory_growth {
+            self.memory_growth_blocked = true;
+            return Ok(false);
+        }
+        if self.memory_limit.is_some_and(|limit| desired > limit) {
+            self.memory_limit_hit = true;
+            return Ok(false);
+        }
+        Ok(true)
+    }
+
+    fn table_growing(
+        &mut self,
+        _current: usize,
+        _desired: usize,
+        _maximum: Option<usize>,
+    ) -> Result<bool> {
+        Ok(true)
+    }
+}
+
+/// Append as much of `data` to `buffer` as keeps it within `max` bytes,
+/// returning the part that was kept
+fn append_capped<'a>(buffer: &mut Vec<u8>, data: &'a [u8], max: Option<usize>) -> &'a [u8] {
+    let room = max.map_or(usize::MAX, |max| max.saturating_sub(buffer.len()));
+    let kept = &data[..data.len().min(room)];
+    buffer.extend_from_slice(kept);
+    kept
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::result_cache::ResultCache;
+    use std::num::NonZeroUsize;
+    use wasmtime::Config;
+
+    fn test_engine() -> Engine {
+        let mut config = Config::new();
+        config.async_support(true);
+        config.consume_fuel(true);
+        Engine::new(&config).unwrap()
+    }
+
+    fn test_registry(dir: &tempfile::TempDir) -> BinaryRegistry {
+        BinaryRegistry::with_metadata_path(test_engine(), dir.path().join("metadata.json"))
+    }
+
+    async fn load_wat(registry: &BinaryRegistry, dir: &tempfile::TempDir, wat: &str) -> Uuid {
+        let path = dir.path().join(format!("{}.wasm", Uuid::new_v4()));
+        std::fs::write(&path, wat::parse_str(wat).unwrap()).unwrap();
+        registry.load_binary(&path, None).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_counters_persist_across_executions() {
+        let dir = tempfile::tempdir().unwrap();
+        let registry = test_registry(&dir);
+        let id = load_wat(
+            &registry,
+            &dir,
+            r#"(module
+                (import "host" "counter_inc" (func $inc (param i32 i32) (result i64)))
+                (memory (export "memory") 1)
+                (data (i32.const 1024) "hits")
+                (func (export "process") (param i32 i32 i32 i32) (result i32)
+                    (i32.wrap_i64 (call $inc (i32.const 1024) (i32.const 4)))))"#,
+        )
+        .await;
+
+        let executor = Executor::new(registry);
+        for expected in 1..=3 {
+            let result = executor
+                .execute(id, String::new(), ExecutionConfig::default())
+                .await
+                .unwrap();
+            assert_eq!(result.return_code, expected);
+        }
+        assert_eq!(
+            *executor.counters.get(&None).unwrap().get("hits").unwrap(),
+            3
+        );
+
+        // A dry run doesn't count
+        let config = ExecutionConfig {
+            meter_only: true,
+            ..Default::default()
+        };
+        let result = executor.execute(id, String::new(), config).await.unwrap();
+        assert_eq!(result.return_code, 4);
+        assert_eq!(
+            *executor.counters.get(&None).unwrap().get("hits").unwrap(),
+            3
+        );
+    }
+
+    #[tokio::test]
+    async fn test_counters_are_per_namespace_and_bounded() {
+        let dir = tempfile::tempdir().unwrap();
+        let registry = test_registry(&dir);
+        // Counts the counter named by its input
+        let path = dir.path().join("count.wasm");
+        let wasm = wat::parse_str(
+            r#"(module
+                (import "host" "counter_inc" (func $inc (param i32 i32) (result i64)))
+                (memory (export "memory") 1)
+                (func (export "alloc") (param i32) (result i32) (i32.const 1024))
+                (func (export "process") (param i32 i32 i32 i32) (result i32)
+                    (i32.wrap_i64 (call $inc (local.get 0) (local.get 1)))))"#,
+        )
+        .unwrap();
+        std::fs::write(&path, wasm).unwrap();
+        let alpha = registry
+            .load_binary_in(&path, None, Some("alpha"))
+            .await
+            .unwrap();
+        let beta = registry
+            .load_binary_in(&path, None, Some("beta"))
+            .await
+            .unwrap();
+        let executor = Executor::new(registry);
+        let count =
+            |id, key: &str| executor.execute(id, key.to_string(), ExecutionConfig::default());
+
+        assert_eq!(count(alpha, "hits").await.unwrap().return_code, 1);
+        assert_eq!(count(alpha, "hits").await.unwrap().return_code, 2);
+        // Same name, other tenant: a counter of its own
+        assert_eq!(count(beta, "hits").await.unwrap().return_code, 1);
+
+        let long_key = "k".repeat(MAX_COUNTER_KEY_BYTES + 1);
+        assert!(count(alpha, &long_key).await.is_err());
+
+        let full: DashMap<String, i64> = (0..MAX_COUNTERS_PER_NAMESPACE)
+            .map(|i| (i.to_string(), 1))
+            .collect();
+        executor.counters.insert(Some("alpha".to_string()), full);
+        assert!(count(alpha, "new").await.is_err());
+        assert_eq!(count(alpha, "0").await.unwrap().return_code, 2);
+        assert_eq!(count(beta, "new").await.unwrap().return_code, 1);
+    }
+
+    #[tokio::test]
+    async fn test_non_utf8_output_is_lossy() {
+        let dir = tempfile::tempdir().unwrap();
+        let registry = test_registry(&dir);
+        let id = load_wat(
+            &registry,
+            &dir,
+            r#"(module
+                (import "host" "log" (func $log (param i32 i32)))
+                (import "host" "write_output" (func $out (param i32 i32)))
+                (memory (export "memory") 1)
+                (data (i32.const 1024) "\ff\fe ok")
+                (func (export "process") (param i32 i32 i32 i32) (result i32)
+                    (call $log (i32.const 1024) (i32.const 5))
+                    (call $out (i32.const 1024) (i32.const 5))
+                    (i32.const 0)))"#,
+        )
+        .await;
+
+        let executor = Executor::new(registry);
+        let result = executor
+            .execute(id, "input".to_string(), ExecutionConfig::default())
+            .await
+            .unwrap();
+
+        assert_eq!(result.return_code, 0);
+        assert!(result.from_output_buffer);
+        assert_eq!(result.output, "\u{fffd}\u{fffd} ok");
+        assert_eq!(result.logs, vec!["\u{fffd}\u{fffd} ok".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_input_does_not_clobber_static_data() {
+        let dir = tempfile::tempdir().unwrap();
+        let registry = test_registry(&dir);
+        // Static data lives at offset 16; `alloc` hands out a region at 4096
+        let id = load_wat(
+            &registry,
+            &dir,
+            r#"(module
+                (import "host" "write_output" (func $out (param i32 i32)))
+                (memory (export "memory") 1)
+                (data (i32.const 16) "SECRET")
+                (func (export "alloc") (param i32) (result i32)
+                    (i32.const 4096))
+                (func (export "process") (param i32 i32 i32 i32) (result i32)
+                    (call $out (i32.const 16) (i32.const 6))
+                    (local.get 0)))"#,
+        )
+        .await;
+
+        let executor = Executor::new(registry);
+        let result = executor
+            .execute(id, "x".repeat(64), ExecutionConfig::default())
+            .await
+            .unwrap();
+
+        assert_eq!(result.output, "SECRET");
+        assert_eq!(result.return_code, 4096);
+    }
+
+    #[tokio::test]
+    async fn test_input_larger_than_memory_is_read_in_chunks() {
+        let dir = tempfile::tempdir().unwrap();
+        let registry = test_registry(&dir);
+        // `alloc` only has room for the env; the input goes through a
+        // 100-byte buffer, and the memory is a single 64 KiB page
+        let id = load_wat(
+            &registry,
+            &dir,
+            r#"(module
+                (import "host" "read_input_chunk" (func $read (param i32 i32) (result i32)))
+                (import "host" "write_output" (func $out (param i32 i32)))
+                (memory (export "memory") 1)
+                (func (export "alloc") (param i32) (result i32)
+                    (select (i32.const 0) (i32.const 1024) (i32.gt_u (local.get 0) (i32.const 256))))
+                (func (export "process") (param i32) (param $len i32) (param i32 i32) (result i32)
+                    (local $n i32) (local $total i32)
+                    (block $done
+                        (loop $next
+                            (local.set $n (call $read (i32.const 2048) (i32.const 100)))
+                            (br_if $done (i32.eqz (local.get $n)))
+                            (call $out (i32.const 2048) (local.get $n))
+                            (local.set $total (i32.add (local.get $total) (local.get $n)))
+                            (br $next)))
+                    (i32.sub (local.get $total) (local.get $len))))"#,
+        )
+        .await;
+
+        let input: String = (0..200_000u32)
+            .map(|i| (b'a' + (i % 26) as u8) as char)
+            .collect();
+        let result = Executor::new(registry)
+            .execute(id, input.clone(), ExecutionConfig::default())
+            .await
+            .unwrap();
+
+        // `process` was told the full length and read exactly that much
+        assert_eq!(result.return_code, 0);
+        assert_eq!(result.output, input);
+    }
+
+    #[tokio::test]
+    async fn test_input_base_global() {
+        let dir = tempfile::tempdir().unwrap();
+        let registry = test_registry(&dir);
+        let id = load_wat(
+            &registry,
+            &dir,
+            r#"(module
+                (import "host" "write_output" (func $out (param i32 i32)))
+                (memory (export "memory") 1)
+                (global (export "__input_base") i32 (i32.const 2048))
+                (func (export "process") (param i32 i32 i32 i32) (result i32)
+                    (call $out (local.get 0) (local.get 1))
+                    (local.get 0)))"#,
+        )
+        .await;
+
+        let executor = Executor::new(registry);
+        let result = executor
+            .execute(id, "hello".to_string(), ExecutionConfig::default())
+            .await
+            .unwrap();
+
+        assert_eq!(result.output, "hello");
+        assert_eq!(result.return_code, 2048);
+    }
+
+    #[tokio::test]
+    async fn test_named_outputs_are_kept_apart() {
+        let dir = tempfile::tempdir().unwrap();
+        let registry = test_registry(&dir);
+        let id = load_wat(
+            &registry,
+            &dir,
+            r#"(module
+                (import "host" "write_named_output" (func $named (param i32 i32 i32 i32)))
+                (memory (export "memory") 1)
+                (data (i32.const 1024) "resultdebugdatatook 3 steps")
+                (func (export "process") (param i32 i32 i32 i32) (result i32)
+                    (call $named (i32.const 1024) (i32.const 6) (i32.const 1035) (i32.const 4))
+                    (call $named (i32.const 1030) (i32.const 5) (i32.const 1039) (i32.const 12))
+                    (call $named (i32.const 1024) (i32.const 6) (i32.const 1035) (i32.const 4))
+                    (i32.const 0)))"#,
+        )
+        .await;
+
+        let result = Executor::new(registry)
+            .execute(id, String::new(), ExecutionConfig::default())
+            .await
+            .unwrap();
+        // Named outputs don't count as the primary output
+        assert!(!result.from_output_buffer);
+        assert_eq!(result.outputs.len(), 2);
+        assert_eq!(result.outputs["result"], "datadata");
+        assert_eq!(result.outputs["debug"], "took 3 steps");
+    }
+
+    #[tokio::test]
+    async fn test_output_truncation_is_reported() {
+        let dir = tempfile::tempdir().unwrap();
+        let registry = test_registry(&dir);
+        // Echoes at most 8 bytes of its input, and says so
+        let id = load_wat(
+            &registry,
+            &dir,
+            r#"(module
+                (import "host" "write_output" (func $out (param i32 i32)))
+                (memory (export "memory") 1)
+                (global (export "__output_cap") i32 (i32.const 8))
+                (func (export "process") (param i32 i32 i32 i32) (result i32)
+                    (if (i32.gt_u (local.get 1) (i32.const 8))
+                        (then (local.set 1 (i32.const 8))))
+                    (call $out (local.get 0) (local.get 1))
+                    (i32.const 0)))"#,
+        )
+        .await;
+
+        let executor = Executor::new(registry);
+        let result = executor
+            .execute(id, "short".to_string(), ExecutionConfig::default())
+            .await
+            .unwrap();
+        assert_eq!(result.output, "short");
+        assert!(!result.output_truncated);
+        assert!(result.warnings.is_empty());
+
+        // Over the plugin's declared capacity
+        let result = executor
+            .execute(id, "a longer input".to_string(), ExecutionConfig::default())
+            .await
+            .unwrap();
+        assert_eq!(result.output, "a longer");
+        assert!(result.output_truncated);
+        assert_eq!(result.warnings.len(), 1);
+        assert!(result.warnings[0].contains("output capacity"));
+
+        // Over the host's limit
+        let config = ExecutionConfig::builder()
+            .max_output_bytes(3)
+            .build()
+            .unwrap();
+        let result = executor
+            .execute(id, "short".to_string(), config)
+            .await
+            .unwrap();
+        assert_eq!(result.output, "sho");
+        assert!(result.output_truncated);
+        assert_eq!(
+            result.warnings,
+            vec!["Output was cut at the 3 byte max_output_bytes limit".to_string()]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_empty_input_keeps_env_apart() {
+        let dir = tempfile::tempdir().unwrap();
+        let registry = test_registry(&dir);
+        // Echoes the env and returns how far past the input it starts
+        let id = load_wat(
+            &registry,
+            &dir,
+            r#"(module
+                (import "host" "write_output" (func $out (param i32 i32)))
+                (memory (export "memory") 1)
+                (func (export "process") (param i32 i32 i32 i32) (result i32)
+                    (call $out (local.get 2) (local.get 3))
+                    (i32.sub (local.get 2) (local.get 0))))"#,
+        )
+        .await;
+
+        let executor = Executor::new(registry);
+        let result = executor
+            .execute(id, String::new(), ExecutionConfig::default())
+            .await
+            .unwrap();
+
+        assert_eq!(result.return_code, 1);
+        let env: serde_json::Value = serde_json::from_str(&result.output).unwrap();
+        assert!(env["timestamp"].is_number());
+
+        // Non-empty input is still followed directly by the env
+        let result = executor
+            .execute(id, "abc".to_string(), ExecutionConfig::default())
+            .await
+            .unwrap();
+        assert_eq!(result.return_code, 3);
+    }
+
+    #[tokio::test]
+    async fn test_oversized_env_is_refused_before_the_plugin_runs() {
+        let dir = tempfile::tempdir().unwrap();
+        let registry = test_registry(&dir);
+        // Gives up on any env as a plugin with a small parse buffer would
+        let id = load_wat(
+            &registry,
+            &dir,
+            r#"(module
+                (memory (export "memory") 1)
+                (func (export "process") (param i32 i32 i32 i32) (result i32)
+                    (i32.const -6)))"#,
+        )
+        .await;
+        let executor = Executor::new(registry);
+        let run = |value: String| {
+            let context = ExecutionContext {
+                env: [("BLOB".to_string(), value)].into_iter().collect(),
+                ..Default::default()
+            };
+            executor.execute_with(id, "abc".to_string(), ExecutionConfig::default(), context)
+        };
+
+        let err = run("é".repeat(MAX_ENV_BYTES)).await.unwrap_err();
+        let err = err.downcast_ref::<ErrorResponse>().unwrap();
+        assert_eq!(err.code, ErrorCode::InvalidRequest);
+        assert!(err.message.starts_with("Env is "), "{}", err.message);
+
+        // An env the plugin could not parse is called out as such
+        let result = run("é".to_string()).await.unwrap();
+        assert_eq!(result.return_code, ERROR_ENV_PARSING);
+        assert!(
+            result
+                .warnings
+                .iter()
+                .any(|w| w.contains("ERROR_ENV_PARSING")),
+            "{:?}",
+            result.warnings
+        );
+    }
+
+    #[tokio::test]
+    async fn test_component_plugin_alongside_core_module() {
+        let dir = tempfile::tempdir().unwrap();
+        let registry = test_registry(&dir);
+        let component_id = load_wat(
+            &registry,
+            &dir,
+            include_str!("../../plugin-component-echo/echo.wat"),
+        )
+        .await;
+        let core_id = load_wat(
+            &registry,
+            &dir,
+            r#"(module
+                (import "host" "write_output" (func $out (param i32 i32)))
+                (memory (export "memory") 1)
+                (func (export "process") (param i32 i32 i32 i32) (result i32)
+                    (call $out (local.get 0) (local.get 1))
+                    (i32.const 0)))"#,
+        )
+        .await;
+
+        assert_eq!(
+            registry.get_binary(&component_id).unwrap().kind.name(),
+            "component"
+        );
+        assert_eq!(registry.get_binary(&core_id).unwrap().kind.name(), "core");
+
+        let executor = Executor::new(registry);
+        let component_result = executor
+            .execute(
+                component_id,
+                "hello".to_string(),
+                ExecutionConfig::default(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(component_result.return_code, 0);
+        assert_eq!(component_result.output, "hello");
+        assert_eq!(component_result.logs, vec!["[Echo] component ready"]);
+
+        let core_result = executor
+            .execute(core_id, "world".to_string(), ExecutionConfig::default())
+            .await
+            .unwrap();
+        assert_eq!(core_result.output, "world");
+    }
+
+    #[tokio::test]
+    async fn test_history_keeps_the_most_recent_results_in_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let registry = test_registry(&dir);
+        let id = load_wat(
+            &registry,
+            &dir,
+            r#"(module
+                (import "host" "write_output" (func $out (param i32 i32)))
+                (memory (export "memory") 1)
+                (func (export "process") (param i32 i32 i32 i32) (result i32)
+                    (call $out (local.get 0) (local.get 1))
+                    (i32.const 0)))"#,
+        )
+        .await;
+
+        let executor = Executor::new(registry).with_history_size(3);
+        for i in 0..5 {
+            executor
+                .execute(id, format!("run {}", i), ExecutionConfig::default())
+                .await
+                .unwrap();
+        }
+
+        let outputs = |results: Vec<ExecutionResult>| -> Vec<String> {
+            results.into_iter().map(|result| result.output).collect()
+        };
+        assert_eq!(
+            outputs(executor.history(&id, None)),
+            ["run 2", "run 3", "run 4"]
+        );
+        assert_eq!(outputs(executor.history(&id, Some(2))), ["run 3", "run 4"]);
+        assert!(executor.history(&Uuid::new_v4(), None).is_empty());
+
+        executor.clear_history(&id);
+        assert!(executor.history(&id, None).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_identical_executions_have_identical_audit_hashes() {
+        let dir = tempfile::tempdir().unwrap();
+        let registry = test_registry(&dir);
+        let id = load_wat(
+            &registry,
+            &dir,
+            r#"(module
+                (import "host" "write_output" (func $out (param i32 i32)))
+                (memory (export "memory") 1)
+                (func (export "process") (param i32 i32 i32 i32) (result i32)
+                    (call $out (local.get 0) (local.get 1))
+                    (i32.const 0)))"#,
+        )
+        .await;
+
+        let audit = AuditLog::open(dir.path().join("audit.log")).unwrap();
+        let executor = Executor::new(registry).with_audit_log(audit);
+        for _ in 0..2 {
+            executor
+                .execute(id, "same input".to_string(), ExecutionConfig::default())
+                .await
+                .unwrap();
+        }
+
+        let entries = executor.audit_log().unwrap().entries_since(None).unwrap();
+        assert_eq!(entries.len(), 2);
+        let (first, second) = (&entries[0], &entries[1]);
+        assert_eq!(first.input_hash, second.input_hash);
+        assert_eq!(first.output_hash, second.output_hash);
+        assert_eq!(first.return_code, second.return_code);
+        assert_eq!(first.fuel_consumed, second.fuel_consumed);
+        // The env carries a timestamp and random seed, so it differs per run
+        assert_ne!(first.env_hash, second.env_hash);
+        assert_eq!(second.prev_hash, first.hash);
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_spinning_plugin_does_not_starve_others() {
+        let dir = tempfile::tempdir().unwrap();
+        let registry = test_registry(&dir);
+        let spin = load_wat(
+            &registry,
+            &dir,
+            r#"(module
+                (memory (export "memory") 1)
+                (func (export "process") (param i32 i32 i32 i32) (result i32)
+                    (loop $forever (br $forever))
+                    (i32.const 0)))"#,
+        )
+        .await;
+        let echo = load_wat(
+            &registry,
+            &dir,
+            r#"(module
+                (import "host" "write_output" (func $out (param i32 i32)))
+                (memory (export "memory") 1)
+                (func (export "process") (param i32 i32 i32 i32) (result i32)
+                    (call $out (local.get 0) (local.get 1))
+                    (i32.const 0)))"#,
+        )
+        .await;
+
+        // One runtime thread: the light execution can only make progress
+        // while the spinning one is parked at a fuel yield
+        let executor = Arc::new(Executor::new(registry).with_fuel_yield_interval(10_000));
+        let request_id = Uuid::from_u128(11);
+        let config = ExecutionConfig::builder().timeout(60_000).build().unwrap();
+        let spinning = {
+            let executor = executor.clone();
+            let config = config.clone();
+            tokio::spawn(async move {
+                let context = ExecutionContext {
+                    request_id: Some(request_id),
+                    ..Default::default()
+                };
+                executor
+                    .execute_with(spin, String::new(), config, context)
+                    .await
+            })
+        };
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        let light = tokio::time::timeout(
+            Duration::from_secs(5),
+            executor.execute(echo, "still here".to_string(), config),
+        )
+        .await
+        .expect("light execution should not wait for the spinning one")
+        .unwrap();
+        assert_eq!(light.output, "still here");
+        assert!(!spinning.is_finished());
+
+        assert!(executor.cancel(&request_id, None));
+        assert!(spinning.await.unwrap().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_cancel_running_execution() {
+        let dir = tempfile::tempdir().unwrap();
+        let registry = test_registry(&dir);
+        let id = load_wat(
+            &registry,
+            &dir,
+            r#"(module
+                (memory (export "memory") 1)
+                (func (export "process") (param i32 i32 i32 i32) (result i32)
+                    (loop $forever (br $forever))
+                    (i32.const 0)))"#,
+        )
+        .await;
+
+        let executor = std::sync::Arc::new(Executor::new(registry));
+        let request_id = Uuid::from_u128(7);
+        let config = ExecutionConfig::builder().timeout(60_000).build().unwrap();
+        let running = {
+            let executor = executor.clone();
+            tokio::spawn(async move {
+                let context = ExecutionContext {
+                    request_id: Some(request_id),
+                    ..Default::default()
+                };
+                executor
+                    .execute_with(id, String::new(), config, context)
+                    .await
+            })
+        };
+
+        while !executor.cancel(&request_id, None) {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        let err = tokio::time::timeout(Duration::from_secs(5), running)
+            .await
+            .expect("cancelled execution should stop promptly")
+            .unwrap()
+            .unwrap_err();
+        assert_eq!(err.to_string(), "Execution cancelled");
+        assert!(!executor.cancel(&request_id, None));
+    }
+
+    #[tokio::test]
+    async fn test_cancel_is_namespaced() {
+        let dir = tempfile::tempdir().unwrap();
+        let registry = test_registry(&dir);
+        let path = dir.path().join("spin.wasm");
+        let wasm = wat::parse_str(
+            r#"(module
+                (memory (export "memory") 1)
+                (func (export "process") (param i32 i32 i32 i32) (result i32)
+                    (loop $forever (br $forever))
+                    (i32.const 0)))"#,
+        )
+        .unwrap();
+        std::fs::write(&path, wasm).unwrap();
+        let id = registry
+            .load_binary_in(&path, None, Some("alpha"))
+            .await
+            .unwrap();
+
+        let executor = std::sync::Arc::new(Executor::new(registry));
+        let request_id = Uuid::from_u128(9);
+        let config = ExecutionConfig::builder().timeout(60_000).build().unwrap();
+        let running = {
+            let executor = executor.clone();
+            tokio::spawn(async move {
+                let context = ExecutionContext {
+                    request_id: Some(request_id),
+                    ..Default::default()
+                };
+                executor
+                    .execute_with(id, String::new(), config, context)
+                    .await
+            })
+        };
+        while !executor.cancellations.contains_key(&request_id) {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+
+        assert!(!executor.cancel(&request_id, Some("beta")));
+        assert!(!executor.cancel(&request_id, None));
+        assert!(!running.is_finished());
+        assert!(executor.cancel(&request_id, Some("alpha")));
+        let result = tokio::time::timeout(Duration::from_secs(5), running)
+            .await
+            .expect("cancelled execution should stop promptly")
+            .unwrap();
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_missing_memory_export_fails_before_instantiation() {
+        let dir = tempfile::tempdir().unwrap();
+        let registry = test_registry(&dir);
+        // The unsatisfiable import shows the check runs before instantiation
+        let id = load_wat(
+            &registry,
+            &dir,
+            r#"(module
+                (import "host" "not_provided" (func))
+                (func (export "process") (param i32 i32 i32 i32) (result i32)
+                    (i32.const 0)))"#,
+        )
+        .await;
+
+        let executor = Executor::new(registry);
+        let err = executor
+            .execute(id, String::new(), ExecutionConfig::default())
+            .await
+            .unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("does not export 'memory' (flagged when it was loaded)"),
+            "unexpected error: {}",
+            err
+        );
+    }
+
+    #[tokio::test]
+    async fn test_timeout_returns_logs_written_before_hang() {
+        let dir = tempfile::tempdir().unwrap();
+        let registry = test_registry(&dir);
+        let id = load_wat(
+            &registry,
+            &dir,
+            r#"(module
+                (import "host" "log" (func $log (param i32 i32)))
+                (memory (export "memory") 1)
+                (data (i32.const 1024) "before hang")
+                (func (export "process") (param i32 i32 i32 i32) (result i32)
+                    (call $log (i32.const 1024) (i32.const 11))
+                    (loop $forever (br $forever))
+                    (i32.const 0)))"#,
+        )
+        .await;
+
+        let executor = Executor::new(registry);
+        // Plenty of fuel, so the deadline is what stops it
+        let config = ExecutionConfig::builder()
+            .timeout(200)
+            .fuel(u64::MAX / 2)
+            .build()
+            .unwrap();
+        let result = executor.execute(id, String::new(), config).await.unwrap();
+
+        assert!(result.timed_out);
+        assert_eq!(result.return_code, RETURN_CODE_TIMED_OUT);
+        assert_eq!(result.logs, vec!["before hang".to_string()]);
+        assert!(result.fuel_consumed > 0);
+    }
+
+    #[test]
+    fn test_env_json_keeps_host_keys() {
+        let mut extra = EnvVars::new();
+        extra.insert("mode".to_string(), "decode".to_string());
+        extra.insert("timestamp".to_string(), "forged".to_string());
+
+        let env: serde_json::Value =
+            serde_json::from_str(&Executor::env_json(&extra, false).unwrap()).unwrap();
+        assert_eq!(env["mode"], "decode");
+        assert!(env["timestamp"].is_i64());
+        assert!(env["random_seed"].is_i64());
+    }
+
+    #[test]
+    fn test_env_keys_are_bounded() {
+        for key in [String::new(), "k".repeat(MAX_ENV_KEY_BYTES + 1)] {
+            let extra: EnvVars = [(key, "value".to_string())].into_iter().collect();
+            let err = Executor::env_json(&extra, false).unwrap_err();
+            let err = err.downcast_ref::<ErrorResponse>().unwrap();
+            assert_eq!(err.code, ErrorCode::InvalidRequest);
+            assert!(err.message.starts_with("Env key of "), "{}", err.message);
+        }
+
+        let extra: EnvVars = [("k".repeat(MAX_ENV_KEY_BYTES), String::new())]
+            .into_iter()
+            .collect();
+        assert!(Executor::env_json(&extra, false).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_deterministic_env_is_fixed() {
+        let dir = tempfile::tempdir().unwrap();
+        let registry = test_registry(&dir);
+        // Echoes the env JSON it was given
+        let id = load_wat(
+            &registry,
+            &dir,
+            r#"(module
+                (import "host" "write_output" (func $out (param i32 i32)))
+                (memory (export "memory") 1)
+                (func (export "process") (param i32 i32 i32 i32) (result i32)
+                    (call $out (local.get 2) (local.get 3))
+                    (i32.const 0)))"#,
+        )
+        .await;
+        let executor = Executor::new(registry);
+
+        let config = ExecutionConfig::builder()
+            .deterministic(true)
+            .build()
+            .unwrap();
+        let first = executor
+            .execute(id, String::new(), config.clone())
+            .await
+            .unwrap();
+        let second = executor.execute(id, String::new(), config).await.unwrap();
+        assert_eq!(first.output, second.output);
+        assert_eq!(first.output, r#"{"random_seed":0,"timestamp":0}"#);
+    }
+
+    #[tokio::test]
+    async fn test_meter_only_reports_fuel_without_output() {
+        let dir = tempfile::tempdir().unwrap();
+        let registry = test_registry(&dir);
+        let audit = AuditLog::open(dir.path().join("audit.log")).unwrap();
+        let id = load_wat(
+            &registry,
+            &dir,
+            r#"(module
+                (import "host" "log" (func $log (param i32 i32)))
+                (import "host" "write_output" (func $out (param i32 i32)))
+                (memory (export "memory") 1)
+                (data (i32.const 1024) "metered")
+                (func (export "process") (param i32 i32 i32 i32) (result i32)
+                    (call $log (i32.const 1024) (i32.const 7))
+                    (call $out (i32.const 1024) (i32.const 7))
+                    (i32.const 0)))"#,
+        )
+        .await;
+        let executor = Executor::new(registry).with_audit_log(audit);
+
+        let config = ExecutionConfig::builder().meter_only(true).build().unwrap();
+        let metered = executor.execute(id, "x".to_string(), config).await.unwrap();
+        assert_eq!(metered.return_code, 0);
+        assert!(metered.fuel_consumed > 0);
+        assert!(metered.output.is_empty());
+        assert!(metered.logs.is_empty());
+        assert!(executor
+            .audit_log()
+            .unwrap()
+            .entries_since(None)
+            .unwrap()
+            .is_empty());
+
+        let normal = executor
+            .execute(id, "x".to_string(), ExecutionConfig::default())
+            .await
+            .unwrap();
+        assert_eq!(normal.output, "metered");
+        assert_eq!(normal.fuel_consumed, metered.fuel_consumed);
+    }
+
+    #[tokio::test]
+    async fn test_output_by_returned_length() {
+        let dir = tempfile::tempdir().unwrap();
+        let registry = test_registry(&dir);
+        // Returns 0x80000003: three bytes of output at the input base
+        let opted_in = load_wat(
+            &registry,
+            &dir,
+            r#"(module
+                (memory (export "memory") 1)
+                (global (export "__returns_output_len") i32 (i32.const 1))
+                (func (export "process") (param i32 i32 i32 i32) (result i32)
+                    (i32.store8 (local.get 0) (i32.const 0x6f))
+                    (i32.const 0x80000003)))"#,
+        )
+        .await;
+        let opted_out = load_wat(
+            &registry,
+            &dir,
+            r#"(module
+                (memory (export "memory") 1)
+                (func (export "process") (param i32 i32 i32 i32) (result i32)
+                    (i32.const -1)))"#,
+        )
+        .await;
+        let executor = Executor::new(registry.clone());
+
+        let result = executor
+            .execute(opted_in, "xyz".to_string(), ExecutionConfig::default())
+            .await
+            .unwrap();
+        assert_eq!(result.return_code, 0);
+        assert_eq!(result.output, "oyz");
+        assert!(result.from_output_buffer);
+
+        let failing = load_wat(
+            &registry,
+            &dir,
+            r#"(module
+                (memory (export "memory") 1)
+                (global (export "__returns_output_len") i32 (i32.const 1))
+                (func (export "process") (param i32 i32 i32 i32) (result i32)
+                    (i32.const -1)))"#,
+        )
+        .await;
+        let result = executor
+            .execute(failing, "xyz".to_string(), ExecutionConfig::default())
+            .await
+            .unwrap();
+        assert_eq!(result.return_code, ERROR_INVALID_UTF8);
+        assert!(!result.from_output_buffer);
+
+        // Past the single page: refused without reading or allocating it
+        let overlong = load_wat(
+            &registry,
+            &dir,
+            r#"(module
+                (memory (export "memory") 1)
+                (global (export "__returns_output_len") i32 (i32.const 1))
+                (func (export "process") (param i32 i32 i32 i32) (result i32)
+                    (i32.const 0xFFFFFF00)))"#,
+        )
+        .await;
+        let err = executor
+            .execute(overlong, "xyz".to_string(), ExecutionConfig::default())
+            .await
+            .unwrap_err();
+        let err = err.downcast_ref::<ErrorResponse>().unwrap();
+        assert_eq!(err.code, ErrorCode::InvalidBinary);
+
+        let result = executor
+            .execute(opted_out, "xyz".to_string(), ExecutionConfig::default())
+            .await
+            .unwrap();
+        assert_eq!(result.return_code, -1);
+        assert!(!result.from_output_buffer);
+    }
+
+    #[tokio::test]
+    async fn test_serialized_runs_of_one_binary_do_not_overlap() {
+        let dir = tempfile::tempdir().unwrap();
+        let registry = test_registry(&dir);
+        let id = load_wat(
+            &registry,
+            &dir,
+            r#"(module
+                (memory (export "memory") 1)
+                (func (export "process") (param i32 i32 i32 i32) (result i32)
+                    (local $i i32)
+                    (loop $again
+                        (local.set $i (i32.add (local.get $i) (i32.const 1)))
+                        (br_if $again (i32.lt_u (local.get $i) (i32.const 20000000))))
+                    (i32.const 0)))"#,
+        )
+        .await;
+        let executor = Arc::new(Executor::new(registry));
+        let config = ExecutionConfig::builder()
+            .serialize_per_binary(true)
+            .build()
+            .unwrap();
+
+        // Fuel yields let unserialized runs interleave even on one thread,
+        // which would make each run's time close to the total
+        let start = std::time::Instant::now();
+        let runs: Vec<_> = (0..3)
+            .map(|_| {
+                let executor = executor.clone();
+                let config = config.clone();
+                tokio::spawn(async move { executor.execute(id, String::new(), config).await })
+            })
+            .collect();
+        let mut busy_ms = 0;
+        for run in runs {
+            busy_ms += run.await.unwrap().unwrap().execution_time_ms;
+        }
+        assert!(busy_ms <= start.elapsed().as_millis() as u64);
+    }
+
+    #[tokio::test]
+    async fn test_fuel_remaining_starts_at_limit() {
+        let dir = tempfile::tempdir().unwrap();
+        let registry = test_registry(&dir);
+        let id = load_wat(
+            &registry,
+            &dir,
+            r#"(module
+                (import "host" "fuel_remaining" (func $fuel (result i64)))
+                (memory (export "memory") 1)
+                (func (export "process") (param i32 i32 i32 i32) (result i32)
+                    (i32.wrap_i64 (call $fuel))))"#,
+        )
+        .await;
+        let executor = Executor::new(registry);
+
+        let config = ExecutionConfig::builder().fuel(50_000).build().unwrap();
+        let result = executor.execute(id, String::new(), config).await.unwrap();
+        // The whole limit is available; the only fuel spent before the
+        // query is what wasmtime charges to get to the call
+        let reported = result.return_code as u64;
+        assert!(reported <= 50_000);
+        assert!(reported >= 50_000 - result.fuel_consumed);
+        assert!(result.fuel_consumed < 10);
+    }
+
+    #[tokio::test]
+    async fn test_engine_without_fuel_runs_unmetered() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = Config::new();
+        config.async_support(true);
+        let engine = Engine::new(&config).unwrap();
+        let registry = BinaryRegistry::with_metadata_path(engine, dir.path().join("metadata.json"));
+        let id = load_wat(
+            &registry,
+            &dir,
+            r#"(module
+                (import "host" "write_output" (func $out (param i32 i32)))
+                (memory (export "memory") 1)
+                (func (export "process") (param i32 i32 i32 i32) (result i32)
+                    (call $out (local.get 0) (local.get 1))
+                    (i32.const 0)))"#,
+        )
+        .await;
+        let executor = Executor::new(registry);
+
+        let result = executor
+            .execute(id, "unmetered".to_string(), ExecutionConfig::default())
+            .await
+            .unwrap();
+        assert_eq!(result.return_code, 0);
+        assert_eq!(result.output, "unmetered");
+        assert_eq!(result.fuel_consumed, 0);
+    }
+
+    #[tokio::test]
+    async fn test_out_of_fuel_is_classified() {
+        let dir = tempfile::tempdir().unwrap();
+        let registry = test_registry(&dir);
+        let id = load_wat(
+            &registry,
+            &dir,
+            r#"(module
+                (memory (export "memory") 1)
+                (func (export "process") (param i32 i32 i32 i32) (result i32)
+                    (loop $spin (br $spin))
+                    (i32.const 0)))"#,
+        )
+        .await;
+        let executor = Executor::new(registry);
+
+        // Far too little fuel to reach the deadline
+        let config = ExecutionConfig::builder().fuel(10_000).build().unwrap();
+        let err = executor
+            .execute(id, String::new(), config)
+            .await
+            .unwrap_err();
+        let err = err.downcast_ref::<ErrorResponse>().unwrap();
+        assert_eq!(err.code, ErrorCode::OutOfFuel);
+        assert!(err.message.contains("10000"), "{}", err.message);
+    }
+
+    #[tokio::test]
+    async fn test_memory_growth_can_be_forbidden() {
+        let dir = tempfile::tempdir().unwrap();
+        let registry = test_registry(&dir);
+        // Grows by a page when given any input, then returns its page count
+        let id = load_wat(
+            &registry,
+            &dir,
+            r#"(module
+                (memory (export "memory") 1)
+                (func (export "process") (param i32 i32 i32 i32) (result i32)
+                    (if (local.get 1)
+                        (then (if (i32.lt_s (memory.grow (i32.const 1)) (i32.const 0))
+                            (then unreachable))))
+                    (memory.size)))"#,
+        )
+        .await;
+        let executor = Executor::new(registry);
+        let forbidden = || {
+            ExecutionConfig::builder()
+                .allow_memory_growth(false)
+                .build()
+                .unwrap()
+        };
+
+        let result = executor
+            .execute(id, "grow".to_string(), ExecutionConfig::default())
+            .await
+            .unwrap();
+        assert_eq!(result.return_code, 2);
+
+        // The initial memory is still created
+        let result = executor
+            .execute(id, String::new(), forbidden())
+            .await
+            .unwrap();
+        assert_eq!(result.return_code, 1);
+
+        let err = executor
+            .execute(id, "grow".to_string(), forbidden())
+            .await
+            .unwrap_err();
+        let err = err.downcast_ref::<ErrorResponse>().unwrap();
+        assert_eq!(err.code, ErrorCode::MemoryLimit);
+        assert!(
+            err.message.contains("allow_memory_growth"),
+            "{}",
+            err.message
+        );
+    }
+
+    #[tokio::test]
+    async fn test_memory_growth_stops_at_memory_limit() {
+        let dir = tempfile::tempdir().unwrap();
+        let registry = test_registry(&dir);
+        // Grows by 30 pages plus the input length, then returns its page count
+        let id = load_wat(
+            &registry,
+            &dir,
+            r#"(module
+                (memory (export "memory") 1)
+                (func (export "process") (param i32 i32 i32 i32) (result i32)
+                    (if (i32.lt_s (memory.grow (i32.add (i32.const 30) (local.get 1)))
+                                  (i32.const 0))
+                        (then unreachable))
+                    (memory.size)))"#,
+        )
+        .await;
+        let executor = Executor::new(registry);
+        let config = || ExecutionConfig::builder().memory_mb(2).build().unwrap();
+
+        // 32 pages are exactly 2 MB
+        let result = executor
+            .execute(id, "a".to_string(), config())
+            .await
+            .unwrap();
+        assert_eq!(result.return_code, 32);
+
+        let err = executor
+            .execute(id, "ab".to_string(), config())
+            .await
+            .unwrap_err();
+        let err = err.downcast_ref::<ErrorResponse>().unwrap();
+        assert_eq!(err.code, ErrorCode::MemoryLimit);
+        assert!(
+            err.message.contains("memory_limit_mb of 2 MB"),
+            "{}",
+            err.message
+        );
+    }
+
+    #[tokio::test]
+    async fn test_chain_rejects_incompatible_data_kinds() {
+        let dir = tempfile::tempdir().unwrap();
+        let registry = test_registry(&dir);
+        // Traps if it ever runs, so a plain rejection proves nothing executed
+        let trapping = wat::parse_str(
+            r#"(module
+                (memory (export "memory") 1)
+                (func (export "process") (param i32 i32 i32 i32) (result i32)
+                    unreachable))"#,
+        )
+        .unwrap();
+        let mut ids = Vec::new();
+        for (name, manifest) in [
+            ("emitter", "name = \"emitter\"\noutput_kind = \"json\"\n"),
+            ("reader", "name = \"reader\"\ninput_kind = \"text\"\n"),
+        ] {
+            std::fs::write(dir.path().join(format!("{name}.wasm")), &trapping).unwrap();
+            std::fs::write(dir.path().join(format!("{name}.toml")), manifest).unwrap();
+            let path = dir.path().join(format!("{name}.wasm"));
+            ids.push(registry.load_binary(&path, None).await.unwrap());
+        }
+        let executor = Executor::new(registry);
+
+        let err = executor
+            .execute_chain(
+                ids,
+                "{}".to_string(),
+                Vec::new(),
+                EnvVars::new(),
+                Vec::new(),
+                false,
+            )
+            .await
+            .unwrap_err();
+        let err = err.downcast_ref::<ErrorResponse>().unwrap();
+        assert_eq!(err.code, ErrorCode::InvalidRequest);
+        assert!(
+            err.message.contains("outputs json") && err.message.contains("expects text"),
+            "{}",
+            err.message
+        );
+    }
+
+    #[tokio::test]
+    async fn test_logs_joined_with_configured_separator() {
+        let dir = tempfile::tempdir().unwrap();
+        let registry = test_registry(&dir);
+        // Logs "a\nb" and then "c" without ever writing output
+        let id = load_wat(
+            &registry,
+            &dir,
+            r#"(module
+                (import "host" "log" (func $log (param i32 i32)))
+                (memory (export "memory") 1)
+                (data (i32.const 1024) "a\0ab" "c")
+                (func (export "process") (param i32 i32 i32 i32) (result i32)
+                    (call $log (i32.const 1024) (i32.const 3))
+                    (call $log (i32.const 1027) (i32.const 1))
+                    (i32.const 0)))"#,
+        )
+        .await;
+        let executor = Executor::new(registry);
+
+        let result = executor
+            .execute(id, String::new(), ExecutionConfig::default())
+            .await
+            .unwrap();
+        assert_eq!(result.output, "a\nb\nc");
+
+        let config = ExecutionConfig::builder()
+            .output_separator("\u{1e}")
+            .build()
+            .unwrap();
+        let result = executor.execute(id, String::new(), config).await.unwrap();
+        assert_eq!(result.output, "a\nb\u{1e}c");
+        assert_eq!(result.logs, vec!["a\nb", "c"]);
+    }
+
+    /// Records every execution it sees and tags the results
+    struct RecordingHook {
+        seen: Arc<std::sync::Mutex<Vec<Uuid>>>,
+    }
+
+    #[async_trait::async_trait]
+    impl ExecutionHook for RecordingHook {
+        async fn before(&self, context: &mut HookContext) {
+            self.seen.lock().unwrap().push(context.binary_id);
+        }
+
+        async fn after(&self, _context: &HookContext, result: &mut ExecutionResult) {
+            result.logs.push("seen by hook".to_string());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_hooks_run_around_every_execution() {
+        let dir = tempfile::tempdir().unwrap();
+        let registry = test_registry(&dir);
+        let noop = r#"(module
+            (memory (export "memory") 1)
+            (func (export "process") (param i32 i32 i32 i32) (result i32)
+                (i32.const 0)))"#;
+        let first = load_wat(&registry, &dir, noop).await;
+        let second = load_wat(&registry, &dir, noop).await;
+        let seen = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let executor = Executor::new(registry).with_hook(RecordingHook { seen: seen.clone() });
+
+        for id in [first, second, first] {
+            let result = executor
+                .execute(id, String::new(), ExecutionConfig::default())
+                .await
+                .unwrap();
+            assert_eq!(result.logs, vec!["seen by hook"]);
+        }
+        assert_eq!(*seen.lock().unwrap(), vec![first, second, first]);
+    }
+
+    /// Spins through 1000 iterations, so every run burns the same fuel
+    const FUEL_BURNER: &str = r#"(module
+        (memory (export "memory") 1)
+        (func (export "process") (param i32 i32 i32 i32) (result i32)
+            (local $i i32)
+            (loop $spin
+                (local.set $i (i32.add (local.get $i) (i32.const 1)))
+                (br_if $spin (i32.lt_u (local.get $i) (i32.const 1000))))
+            (i32.const 0)))"#;
+
+    #[tokio::test]
+    async fn test_chain_longer_than_limit_is_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let registry = test_registry(&dir);
+        let id = load_wat(&registry, &dir, FUEL_BURNER).await;
+        let executor = Executor::new(registry).with_max_chain_length(2);
+        let configs = vec![ExecutionConfig::default(); 3];
+
+        let err = executor
+            .execute_chain(
+                vec![id; 3],
+                String::new(),
+                configs.clone(),
+                EnvVars::new(),
+                Vec::new(),
+                false,
+            )
+            .await
+            .unwrap_err();
+        let err = err.downcast_ref::<ErrorResponse>().unwrap();
+        assert_eq!(err.code, ErrorCode::InvalidRequest);
+        assert!(err.message.contains("at most 2"), "{}", err.message);
+
+        let response = executor
+            .execute_chain(
+                vec![id; 2],
+                String::new(),
+                configs,
+                EnvVars::new(),
+                Vec::new(),
+                false,
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.results.len(), 2);
+        assert_eq!(response.halted, None);
+    }
+
+    #[tokio::test]
+    async fn test_chain_halts_when_fuel_budget_is_spent() {
+        let dir = tempfile::tempdir().unwrap();
+        let registry = test_registry(&dir);
+        let id = load_wat(&registry, &dir, FUEL_BURNER).await;
+        let configs = vec![ExecutionConfig::default(); 4];
+
+        // Measure one step, then allow a step and a half
+        let step_fuel = Executor::new(registry.clone())
+            .execute(id, String::new(), ExecutionConfig::default())
+            .await
+            .unwrap()
+            .fuel_consumed;
+        let executor = Executor::new(registry).with_chain_fuel_budget(step_fuel * 3 / 2);
+
+        let response = executor
+            .execute_chain(
+                vec![id; 4],
+                String::new(),
+                configs,
+                EnvVars::new(),
+                Vec::new(),
+                false,
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.results.len(), 2);
+        let reason = response.halted.unwrap();
+        assert!(reason.contains("after step 2"), "{}", reason);
+    }
+
+    /// Adds one to the i32 in its scratch buffer, keeps it and returns it
+    const SCRATCH_COUNTER: &str = r#"(module
+        (import "host" "scratch_read" (func $read (param i32 i32) (result i32)))
+        (import "host" "scratch_write" (func $write (param i32 i32)))
+        (memory (export "memory") 1)
+        (func (export "process") (param i32 i32 i32 i32) (result i32)
+            (drop (call $read (i32.const 60000) (i32.const 4)))
+            (i32.store (i32.const 60000) (i32.add (i32.load (i32.const 60000)) (i32.const 1)))
+            (call $write (i32.const 60000) (i32.const 4))
+            (i32.load (i32.const 60000))))"#;
+
+    #[tokio::test]
+    async fn test_chain_persists_scratch_per_binary() {
+        let dir = tempfile::tempdir().unwrap();
+        let registry = test_registry(&dir);
+        let first = load_wat(&registry, &dir, SCRATCH_COUNTER).await;
+        let second = load_wat(&registry, &dir, SCRATCH_COUNTER).await;
+        let executor = Executor::new(registry);
+        let chain = |persist_host_state| {
+            executor.execute_chain(
+                vec![first, second, first, first],
+                String::new(),
+                vec![ExecutionConfig::default(); 4],
+                EnvVars::new(),
+                Vec::new(),
+                persist_host_state,
+            )
+        };
+        let return_codes = |response: ExecuteChainResponse| {
+            response
+                .results
+                .iter()
+                .map(|result| result.return_code)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(return_codes(chain(false).await.unwrap()), vec![1, 1, 1, 1]);
+        // Each binary picks up its own count, not the other one's
+        assert_eq!(return_codes(chain(true).await.unwrap()), vec![1, 1, 2, 3]);
+        // Nothing outlives the chain
+        assert_eq!(return_codes(chain(true).await.unwrap()), vec![1, 1, 2, 3]);
+    }
+
+    #[tokio::test]
+    async fn test_slow_execution_gets_warning() {
+        let dir = tempfile::tempdir().unwrap();
+        let registry = test_registry(&dir);
+        let spinner = FUEL_BURNER.replace("1000", "100000000");
+        let slow_id = load_wat(&registry, &dir, &spinner).await;
+        let fast_id = load_wat(&registry, &dir, FUEL_BURNER).await;
+
+        // Time the spinner, then give it a timeout it uses about half of
+        let unhurried = ExecutionConfig::builder()
+            .timeout(60_000)
+            .fuel(u64::MAX / 2)
+            .build()
+            .unwrap();
+        let took = Executor::new(registry.clone())
+            .execute(slow_id, String::new(), unhurried)
+            .await
+            .unwrap()
+            .execution_time_ms;
+        let config = ExecutionConfig::builder()
+            .timeout(took.max(50) * 2)
+            .fuel(u64::MAX / 2)
+            .build()
+            .unwrap();
+        let executor = Executor::new(registry).with_slow_warning_fraction(0.25);
+
+        let result = executor
+            .execute(slow_id, String::new(), config.clone())
+            .await
+            .unwrap();
+        assert!(!result.timed_out);
+        assert_eq!(result.warnings.len(), 1, "{:?}", result.warnings);
+        assert!(result.warnings[0].contains("more than 25% of its"));
+
+        let result = executor
+            .execute(fast_id, String::new(), config)
+            .await
+            .unwrap();
+        assert!(result.warnings.is_empty(), "{:?}", result.warnings);
+    }
+
+    #[tokio::test]
+    async fn test_pure_binary_results_are_cached() {
+        let dir = tempfile::tempdir().unwrap();
+        let registry =
+            test_registry(&dir).with_result_cache(ResultCache::new(NonZeroUsize::new(8).unwrap()));
+        let echo = wat::parse_str(
+            r#"(module
+                (import "host" "write_output" (func $out (param i32 i32)))
+                (memory (export "memory") 1)
+                (func (export "process") (param i32 i32 i32 i32) (result i32)
+                    (call $out (local.get 0) (local.get 1))
+                    (i32.const 0)))"#,
+        )
+        .unwrap();
+        let mut ids = Vec::new();
+        for (name, manifest) in [
+            ("pure", "name = \"pure\"\npure = true\n"),
+            ("impure", "name = \"impure\"\n"),
+        ] {
+            let path = dir.path().join(format!("{name}.wasm"));
+            std::fs::write(&path, &echo).unwrap();
+            std::fs::write(dir.path().join(format!("{name}.toml")), manifest).unwrap();
+            ids.push(registry.load_binary(&path, None).await.unwrap());
+        }
+        let executor = Executor::new(registry);
+        let run = |id: Uuid, input: &str| {
+            executor.execute(id, input.to_string(), ExecutionConfig::default())
+        };
+
+        let first = run(ids[0], "hello").await.unwrap();
+        assert!(!first.cache_hit);
+        assert!(first.fuel_consumed > 0);
+
+        let second = run(ids[0], "hello").await.unwrap();
+        assert!(second.cache_hit);
+        assert_eq!(second.fuel_consumed, 0);
+        assert_eq!(second.output, "hello");
+
+        // A different input, or a binary that isn't declared pure, runs
+        assert!(!run(ids[0], "other").await.unwrap().cache_hit);
+        run(ids[1], "hello").await.unwrap();
+        assert!(!run(ids[1], "hello").await.unwrap().cache_hit);
+    }
+
+    #[tokio::test]
+    async fn test_digest_host_functions_need_crypto_capability() {
+        let dir = tempfile::tempdir().unwrap();
+        let registry = test_registry(&dir);
+        // Writes sha256(input) then blake3(input) as the output
+        let digests = wat::parse_str(
+            r#"(module
+                (import "host" "sha256" (func $sha256 (param i32 i32 i32)))
+                (import "host" "blake3" (func $blake3 (param i32 i32 i32)))
+                (import "host" "write_output" (func $out (param i32 i32)))
+                (memory (export "memory") 1)
+                (func (export "alloc") (param i32) (result i32)
+                    (i32.const 1024))
+                (func (export "process") (param i32 i32 i32 i32) (result i32)
+                    (call $sha256 (local.get 0) (local.get 1) (i32.const 256))
+                    (call $blake3 (local.get 0) (local.get 1) (i32.const 288))
+                    (call $out (i32.const 256) (i32.const 64))
+                    (i32.const 0)))"#,
+        )
+        .unwrap();
+        let mut ids = Vec::new();
+        for (name, manifest) in [
+            ("crypto", "name = \"crypto\"\ncapabilities = [\"crypto\"]\n"),
+            ("plain", "name = \"plain\"\n"),
+        ] {
+            let path = dir.path().join(format!("{name}.wasm"));
+            std::fs::write(&path, &digests).unwrap();
+            std::fs::write(dir.path().join(format!("{name}.toml")), manifest).unwrap();
+            ids.push(registry.load_binary(&path, None).await.unwrap());
+        }
+        let executor = Executor::new(registry);
+        let run = |id: Uuid| {
+            let context = ExecutionContext {
+                encoding: Encoding::Hex,
+                ..Default::default()
+            };
+            executor.execute_with(id, "abc".to_string(), ExecutionConfig::default(), context)
+        };
+
+        let result = run(ids[0]).await.unwrap();
+        assert_eq!(
+            result.output,
+            concat!(
+                "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+                "6437b3ac38465133ffb63b75273a8db548c558465d79db03fd359c6cd5bd9d85",
+            )
+        );
+
+        let err = run(ids[1]).await.unwrap_err();
+        let err = err.downcast_ref::<ErrorResponse>().unwrap();
+        assert_eq!(err.code, ErrorCode::Unauthorized);
+        assert!(err.message.contains("crypto"), "{}", err.message);
+    }
+
+    #[tokio::test]
+    async fn test_out_of_range_host_call_traps_instead_of_allocating() {
+        let dir = tempfile::tempdir().unwrap();
+        let registry = test_registry(&dir);
+        let path = dir.path().join("digest.wasm");
+        // Calls the host with whatever pointer and length the input names
+        let wasm = wat::parse_str(
+            r#"(module
+                (import "host" "log" (func $log (param i32 i32)))
+                (import "host" "sha256" (func $sha256 (param i32 i32 i32)))
+                (memory (export "memory") 1)
+                (func (export "process") (param i32 i32 i32 i32) (result i32)
+                    (if (i32.eq (i32.load8_u (local.get 0)) (i32.const 0x6c))
+                        (then (call $log (i32.const 0) (i32.const -1)))
+                        (else (call $sha256 (i32.const 0) (i32.const 0x7fffffff) (i32.const 0))))
+                    (i32.const 0)))"#,
+        )
+        .unwrap();
+        std::fs::write(&path, wasm).unwrap();
+        std::fs::write(
+            dir.path().join("digest.toml"),
+            "name = \"digest\"\ncapabilities = [\"crypto\"]\n",
+        )
+        .unwrap();
+        let id = registry.load_binary(&path, None).await.unwrap();
+        let executor = Executor::new(registry);
+
+        for (input, expected) in [("l", "Negative length -1"), ("s", "outside guest memory")] {
+            let err = executor
+                .execute(id, input.to_string(), ExecutionConfig::default())
+                .await
+                .unwrap_err();
+            assert!(format!("{:#}", err).contains(expected), "{:#}", err);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reload_drops_cached_results() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = ResultCache::new(NonZeroUsize::new(8).unwrap());
+        let registry = test_registry(&dir).with_result_cache(cache.clone());
+        let path = dir.path().join("pure.wasm");
+        std::fs::write(
+            dir.path().join("pure.toml"),
+            "name = \"pure\"\npure = true\n",
+        )
+        .unwrap();
+        std::fs::write(
+            &path,
+            wat::parse_str(
+                r#"(module
+                    (import "host" "write_output" (func $out (param i32 i32)))
+                    (memory (export "memory") 1)
+                    (func (export "process") (param i32 i32 i32 i32) (result i32)
+                        (call $out (local.get 0) (local.get 1))
+                        (i32.const 0)))"#,
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        let id = registry.load_binary(&path, None).await.unwrap();
+        let executor = Executor::new(registry.clone());
+        let run = || executor.execute(id, "hello".to_string(), ExecutionConfig::default());
+
+        run().await.unwrap();
+        assert!(run().await.unwrap().cache_hit);
+
+        // Same path, new code: the old output must not be served
+        std::fs::write(
+            &path,
+            wat::parse_str(
+                r#"(module
+                    (import "host" "write_output" (func $out (param i32 i32)))
+                    (memory (export "memory") 1)
+                    (data (i32.const 2048) "changed")
+                    (func (export "process") (param i32 i32 i32 i32) (result i32)
+                        (call $out (i32.const 2048) (i32.const 7))
+                        (i32.const 0)))"#,
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        registry.reload_binary(&id).await.unwrap();
+        let result = run().await.unwrap();
+        assert!(!result.cache_hit);
+        assert_eq!(result.output, "changed");
+        assert_eq!(cache.len(), 1);
+
+        registry.unload_binary(&id).unwrap();
+        assert_eq!(cache.len(), 0);
+    }
 }
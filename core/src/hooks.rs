@@ -0,0 +1,24 @@
+use async_trait::async_trait;
+use uuid::Uuid;
+use wasm_shared::{ExecutionConfig, ExecutionResult};
+
+/// What a hook sees of an execution
+#[allow(dead_code)]
+pub struct HookContext {
+    pub binary_id: Uuid,
+    /// The input the plugin will receive; `before` hooks may rewrite it
+    pub input: Vec<u8>,
+    pub config: ExecutionConfig,
+}
+
+/// Code run around every execution, for logging, metrics and the like,
+/// registered with `Executor::with_hook`. Hooks run in registration order.
+#[async_trait]
+pub trait ExecutionHook: Send + Sync {
+    /// Called once the binary is found, before the plugin starts
+    async fn before(&self, _context: &mut HookContext) {}
+
+    /// Called with the finished result before it is returned. Executions
+    /// that end in an error, cancellation included, skip this.
+    async fn after(&self, _context: &HookContext, _result: &mut ExecutionResult) {}
+}
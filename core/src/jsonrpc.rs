@@ -0,0 +1,215 @@
+use serde_json::{json, Value};
+use wasm_shared::{Command, ErrorCode, ErrorResponse, Response};
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const INTERNAL_ERROR: i64 = -32603;
+/// Errors the server defines itself; `data.code` says which `ErrorCode`
+const SERVER_ERROR: i64 = -32000;
+
+/// JSON-RPC method names and the `Command` each one maps to
+const METHODS: &[(&str, &str)] = &[
+    ("auth", "Auth"),
+    ("hello", "Hello"),
+    ("load_binary", "LoadBinary"),
+    ("execute", "Execute"),
+    ("execute_by_path", "ExecuteByPath"),
+    ("execute_chain", "ExecuteChain"),
+    ("list_binaries", "ListBinaries"),
+    ("unload_binary", "UnloadBinary"),
+    ("describe_binary", "DescribeBinary"),
+    ("set_default_config", "SetDefaultConfig"),
+    ("get_audit_log", "GetAuditLog"),
+    ("get_traces", "GetTraces"),
+    ("get_history", "GetHistory"),
+    ("get_binary_bytes", "GetBinaryBytes"),
+    ("subscribe", "Subscribe"),
+    ("export_trace", "ExportTrace"),
+    ("replay", "Replay"),
+    ("reload_all", "ReloadAll"),
+    ("export_bundle", "ExportBundle"),
+    ("import_bundle", "ImportBundle"),
+    ("verify_integrity", "VerifyIntegrity"),
+    ("info", "Info"),
+    ("cancel", "Cancel"),
+    ("warmup", "Warmup"),
+];
+
+/// A decoded JSON-RPC call. `id` is `None` for notifications, which get no
+/// response.
+pub struct Request {
+    pub id: Option<Value>,
+    pub command: Command,
+}
+
+/// Whether the frame opening a connection asks for JSON-RPC rather than
+/// native commands
+pub fn is_jsonrpc(frame: &str) -> bool {
+    serde_json::from_str::<Value>(frame).is_ok_and(|value| value.get("jsonrpc").is_some())
+}
+
+/// Decode a JSON-RPC frame into a command, or the error object to answer it
+/// with. `params` are the fields of the command, by name.
+pub fn parse_request(frame: &str) -> Result<Request, Value> {
+    let value: Value = serde_json::from_str(frame)
+        .map_err(|e| error(Value::Null, PARSE_ERROR, &e.to_string(), None))?;
+    let Value::Object(mut call) = value else {
+        return Err(error(
+            Value::Null,
+            INVALID_REQUEST,
+            "Request must be a JSON object; batches are not supported",
+            None,
+        ));
+    };
+    let id = call.remove("id");
+    let reply_id = id.clone().unwrap_or(Value::Null);
+    if call.get("jsonrpc") != Some(&json!("2.0")) {
+        return Err(error(
+            reply_id,
+            INVALID_REQUEST,
+            "Only JSON-RPC 2.0 is supported",
+            None,
+        ));
+    }
+    let Some(Value::String(method)) = call.remove("method") else {
+        return Err(error(
+            reply_id,
+            INVALID_REQUEST,
+            "Request has no method",
+            None,
+        ));
+    };
+    let Some(&(_, command_type)) = METHODS.iter().find(|(name, _)| *name == method) else {
+        return Err(error(
+            reply_id,
+            METHOD_NOT_FOUND,
+            &format!("Unknown method {}", method),
+            None,
+        ));
+    };
+    let mut params = match call.remove("params") {
+        None | Some(Value::Null) => serde_json::Map::new(),
+        Some(Value::Object(params)) => params,
+        Some(_) => {
+            return Err(error(
+                reply_id,
+                INVALID_PARAMS,
+                "Params must be an object",
+                None,
+            ))
+        }
+    };
+    params.insert("type".to_string(), json!(command_type));
+    let command = serde_json::from_value(Value::Object(params)).map_err(|e| {
+        error(
+            reply_id,
+            INVALID_PARAMS,
+            &format!("Invalid params for {}: {}", method, e),
+            None,
+        )
+    })?;
+    Ok(Request { id, command })
+}
+
+/// The JSON-RPC answer to a call: the payload of a successful response as
+/// `result`, or an error object carrying the `ErrorCode` in `data.code`
+pub fn response(id: Value, response: Response) -> Value {
+    match into_result(response) {
+        Ok(result) => json!({ "jsonrpc": "2.0", "result": result, "id": id }),
+        Err(e) => {
+            let code = match e.code {
+                ErrorCode::InvalidRequest => INVALID_PARAMS,
+                ErrorCode::Internal => INTERNAL_ERROR,
+                _ => SERVER_ERROR,
+            };
+            error(id, code, &e.message, Some(json!({ "code": e.code })))
+        }
+    }
+}
+
+/// Split a native response into its payload or its error. Variants holding
+/// a `Result` serialize it as an `Ok` or `Err` field next to `type`.
+fn into_result(response: Response) -> Result<Value, ErrorResponse> {
+    if let Response::Error(e) = response {
+        return Err(e);
+    }
+    let internal = |e: serde_json::Error| ErrorResponse::new(ErrorCode::Internal, e.to_string());
+    let mut value = serde_json::to_value(&response).map_err(internal)?;
+    let Some(fields) = value.as_object_mut() else {
+        return Ok(value);
+    };
+    fields.remove("type");
+    if let Some(err) = fields.remove("Err") {
+        return Err(serde_json::from_value(err).map_err(internal)?);
+    }
+    Ok(fields.remove("Ok").unwrap_or(value))
+}
+
+fn error(id: Value, code: i64, message: &str, data: Option<Value>) -> Value {
+    let mut error = json!({ "code": code, "message": message });
+    if let Some(data) = data {
+        error["data"] = data;
+    }
+    json!({ "jsonrpc": "2.0", "error": error, "id": id })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_method_maps_to_command() {
+        let request =
+            parse_request(r#"{"jsonrpc":"2.0","method":"verify_integrity","params":{},"id":"a"}"#)
+                .unwrap();
+        assert_eq!(request.id, Some(json!("a")));
+        assert!(matches!(
+            request.command,
+            Command::VerifyIntegrity {
+                binary_id: None,
+                namespace: None
+            }
+        ));
+
+        let notification = parse_request(r#"{"jsonrpc":"2.0","method":"info"}"#).unwrap();
+        assert!(notification.id.is_none());
+        assert!(matches!(notification.command, Command::Info));
+    }
+
+    #[test]
+    fn test_bad_calls_get_standard_error_codes() {
+        let code = |frame: &str| parse_request(frame).err().unwrap()["error"]["code"].clone();
+        assert_eq!(code("{not json"), json!(PARSE_ERROR));
+        assert_eq!(code(r#"[{"jsonrpc":"2.0"}]"#), json!(INVALID_REQUEST));
+        assert_eq!(
+            code(r#"{"jsonrpc":"1.0","method":"info","id":1}"#),
+            json!(INVALID_REQUEST)
+        );
+        assert_eq!(
+            code(r#"{"jsonrpc":"2.0","method":"nope","id":1}"#),
+            json!(METHOD_NOT_FOUND)
+        );
+        assert_eq!(
+            code(r#"{"jsonrpc":"2.0","method":"replay","params":{},"id":1}"#),
+            json!(INVALID_PARAMS)
+        );
+    }
+
+    #[test]
+    fn test_error_response_keeps_error_code() {
+        let value = response(
+            json!(7),
+            Response::Execute(Err(ErrorResponse::new(
+                ErrorCode::NotFound,
+                "no such binary",
+            ))),
+        );
+        assert_eq!(value["id"], json!(7));
+        assert_eq!(value["error"]["code"], json!(SERVER_ERROR));
+        assert_eq!(value["error"]["message"], json!("no such binary"));
+        assert_eq!(value["error"]["data"]["code"], json!("not_found"));
+        assert!(value.get("result").is_none());
+    }
+}
@@ -0,0 +1,78 @@
+use std::fmt::Write;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::broadcast;
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::layer::{Context, Layer};
+use wasm_shared::{LogLevel, LogLine};
+
+/// Lines a slow `TailLogs` connection may fall behind by before it starts
+/// missing some
+const LOG_BACKLOG: usize = 1024;
+
+/// A `tracing` layer handing every event the server logs to the
+/// connections tailing it. Events are only formatted while someone listens.
+#[derive(Clone)]
+pub struct LogTail {
+    lines: broadcast::Sender<LogLine>,
+}
+
+impl LogTail {
+    pub fn new() -> Self {
+        Self {
+            lines: broadcast::channel(LOG_BACKLOG).0,
+        }
+    }
+
+    /// Receive every line logged from now on
+    pub fn subscribe(&self) -> broadcast::Receiver<LogLine> {
+        self.lines.subscribe()
+    }
+}
+
+impl<S: Subscriber> Layer<S> for LogTail {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        if self.lines.receiver_count() == 0 {
+            return;
+        }
+        let metadata = event.metadata();
+        let mut message = Message::default();
+        event.record(&mut message);
+        let timestamp_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64;
+        // Nobody listening any more is fine; the next subscriber gets the next line
+        let _ = self.lines.send(LogLine {
+            timestamp_ms,
+            level: log_level(metadata.level()),
+            target: metadata.target().to_string(),
+            message: message.0,
+        });
+    }
+}
+
+fn log_level(level: &Level) -> LogLevel {
+    match *level {
+        Level::ERROR => LogLevel::Error,
+        Level::WARN => LogLevel::Warn,
+        Level::INFO => LogLevel::Info,
+        Level::DEBUG => LogLevel::Debug,
+        Level::TRACE => LogLevel::Trace,
+    }
+}
+
+/// An event's message, then its other fields as ` name=value`
+#[derive(Default)]
+struct Message(String);
+
+impl Visit for Message {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "message" {
+            let fields = std::mem::take(&mut self.0);
+            let _ = write!(self.0, "{:?}{}", value, fields);
+        } else {
+            let _ = write!(self.0, " {}={:?}", field.name(), value);
+        }
+    }
+}
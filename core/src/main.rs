@@ -1,35 +1,258 @@
+mod audit;
 mod binary_registry;
+mod bundle;
+mod config;
+mod encoding;
 mod executor;
+mod hooks;
+mod jsonrpc;
+mod log_tail;
+mod module_cache;
+mod preprocess;
+mod result_cache;
+mod scheduler;
 mod server;
 mod socket_core;
+mod state;
+mod stats;
 mod tracer;
 
 use anyhow::Result;
-use wasmtime::{Config, Engine};
+use clap::{Parser, Subcommand};
+use std::num::NonZeroUsize;
+use std::path::PathBuf;
+use tokio::signal::unix::{signal, SignalKind};
+use tracing::Level;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::EnvFilter;
+use wasmtime::Engine;
 
+use crate::audit::AuditLog;
 use crate::binary_registry::BinaryRegistry;
+use crate::config::{CoreConfig, DEFAULT_CONFIG_PATH};
 use crate::executor::Executor;
+use crate::log_tail::LogTail;
+use crate::module_cache::ModuleCache;
+use crate::result_cache::ResultCache;
 use crate::server::Server;
 use crate::socket_core::SocketServer;
+use crate::tracer::Tracer;
+
+#[derive(Parser)]
+#[command(name = "wasm-core")]
+#[command(about = "WASM Core Server", long_about = None)]
+struct Cli {
+    /// Config file (default: $WASM_CORE_CONFIG or ./wasm-core.toml)
+    #[arg(long)]
+    config: Option<PathBuf>,
+
+    /// Unix socket path to listen on
+    #[arg(long)]
+    socket: Option<PathBuf>,
+
+    /// Default timeout in milliseconds for requests without a config
+    #[arg(long)]
+    timeout_ms: Option<u64>,
+
+    /// Default memory limit in MB for requests without a config
+    #[arg(long)]
+    memory_mb: Option<u64>,
+
+    /// Default fuel budget for requests without a config
+    #[arg(long)]
+    fuel: Option<u64>,
+
+    /// Maximum number of executions running at once
+    #[arg(long)]
+    max_concurrency: Option<usize>,
+
+    /// Hex-encoded Ed25519 public key binaries must be signed with
+    #[arg(long)]
+    trusted_key: Option<String>,
+
+    /// Append a hash-chained record of every execution to this file
+    #[arg(long)]
+    audit_log: Option<PathBuf>,
+
+    /// Directory holding precompiled `.cwasm` artifacts
+    #[arg(long)]
+    module_cache: Option<PathBuf>,
+
+    /// Load every `.wasm` file in this directory at startup
+    #[arg(long)]
+    plugin_dir: Option<PathBuf>,
+
+    /// Only allow loading binaries from inside this directory (repeatable)
+    #[arg(long = "allowed-plugin-dir", value_name = "DIR")]
+    allowed_plugin_dirs: Vec<PathBuf>,
+
+    /// Save traces and stats here on shutdown and restore them at startup
+    #[arg(long)]
+    state_dir: Option<PathBuf>,
+
+    /// Log only warnings and errors
+    #[arg(short, long, conflicts_with = "verbose")]
+    quiet: bool,
+
+    /// Log debug messages too, for troubleshooting
+    #[arg(short, long)]
+    verbose: bool,
+
+    #[command(subcommand)]
+    command: Option<CoreCommand>,
+}
+
+#[derive(Subcommand)]
+enum CoreCommand {
+    /// Compile every `.wasm` in a directory into the module cache, then exit
+    Precompile {
+        #[arg(long)]
+        dir: PathBuf,
+    },
+}
+
+impl Cli {
+    /// The most detailed level logged: warnings with `--quiet`, debug with
+    /// `--verbose`, info otherwise
+    fn log_level(&self) -> Level {
+        match (self.quiet, self.verbose) {
+            (true, _) => Level::WARN,
+            (_, true) => Level::DEBUG,
+            _ => Level::INFO,
+        }
+    }
+
+    /// What gets logged: `rust_log` (the value of `RUST_LOG`) when it is set
+    /// and valid, otherwise everything at or above `log_level`
+    fn log_filter(&self, rust_log: Option<&str>) -> EnvFilter {
+        rust_log
+            .and_then(|directives| EnvFilter::try_new(directives).ok())
+            .unwrap_or_else(|| EnvFilter::new(self.log_level().to_string()))
+    }
+
+    /// Resolve the effective config: file, then environment, then flags
+    fn load_config(&self) -> Result<CoreConfig> {
+        let mut config = match (&self.config, std::env::var_os("WASM_CORE_CONFIG")) {
+            (Some(path), _) => CoreConfig::from_file(path, true)?,
+            (None, Some(path)) => CoreConfig::from_file(path, true)?,
+            (None, None) => CoreConfig::from_file(DEFAULT_CONFIG_PATH, false)?,
+        };
+        config.apply_env()?;
+
+        if let Some(socket) = &self.socket {
+            config.socket_path = socket.clone();
+        }
+        if let Some(timeout_ms) = self.timeout_ms {
+            config.default_timeout_ms = timeout_ms;
+        }
+        if let Some(memory_mb) = self.memory_mb {
+            config.default_memory_limit_mb = memory_mb;
+        }
+        if let Some(fuel) = self.fuel {
+            config.default_fuel = Some(fuel);
+        }
+        if let Some(max_concurrency) = self.max_concurrency {
+            config.max_concurrency = max_concurrency;
+        }
+        if let Some(trusted_key) = &self.trusted_key {
+            config.trusted_key = Some(trusted_key.clone());
+        }
+        if let Some(audit_log) = &self.audit_log {
+            config.audit_log = Some(audit_log.clone());
+        }
+        if let Some(module_cache) = &self.module_cache {
+            config.module_cache = Some(module_cache.clone());
+        }
+        if let Some(plugin_dir) = &self.plugin_dir {
+            config.plugin_dir = Some(plugin_dir.clone());
+        }
+        if !self.allowed_plugin_dirs.is_empty() {
+            config.allowed_plugin_dirs = self.allowed_plugin_dirs.clone();
+        }
+        if let Some(state_dir) = &self.state_dir {
+            config.state_dir = Some(state_dir.clone());
+        }
+
+        config.validate()?;
+        Ok(config)
+    }
+}
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    tracing_subscriber::fmt()
-        .with_max_level(tracing::Level::INFO)
+    let cli = Cli::parse();
+
+    let rust_log = std::env::var("RUST_LOG").ok();
+    // Clients tailing the logs see the same lines as the console
+    let log_tail = LogTail::new();
+    tracing_subscriber::registry()
+        .with(cli.log_filter(rust_log.as_deref()))
+        .with(tracing_subscriber::fmt::layer())
+        .with(log_tail.clone())
         .init();
 
     tracing::info!("?? Starting WASM Core Server");
     tracing::info!("??????????????????????????????????????????");
 
+    let core_config = cli.load_config()?;
+    let socket_path = core_config.socket_path.clone();
+    let socket_mode = core_config.socket_mode;
+    let max_connections = core_config.max_connections;
+    let max_frame_bytes = core_config.max_frame_bytes;
+    let idle_timeout = core_config.idle_timeout();
+    tracing::info!(
+        "? Config loaded (timeout: {}ms, memory: {}MB, max concurrency: {})",
+        core_config.default_timeout_ms,
+        core_config.default_memory_limit_mb,
+        core_config.max_concurrency
+    );
+
     // Initialize Wasmtime engine
-    let mut config = Config::new();
-    config.async_support(true);
-    config.consume_fuel(true);
-    let engine = Engine::new(&config)?;
+    let engine = Engine::new(&core_config.engine_config())?;
     tracing::info!("? Wasmtime engine initialized");
 
     // Create binary registry
-    let registry = BinaryRegistry::new(engine);
+    let mut registry = BinaryRegistry::new(engine);
+    if let Some(dir) = &core_config.module_cache {
+        registry = registry.with_module_cache(ModuleCache::new(dir)?);
+        tracing::info!("? Module cache: {}", dir.display());
+    }
+    if let Some(capacity) = NonZeroUsize::new(core_config.result_cache_size) {
+        registry = registry.with_result_cache(ResultCache::new(capacity));
+    }
+
+    if let Some(CoreCommand::Precompile { dir }) = &cli.command {
+        if registry.module_cache().is_none() {
+            anyhow::bail!("precompile needs --module-cache (or module_cache in the config file)");
+        }
+        let artifacts = registry.precompile_dir(dir)?;
+        tracing::info!(
+            "? Precompiled {} binaries from {}",
+            artifacts.len(),
+            dir.display()
+        );
+        return Ok(());
+    }
+
+    if let Some(key) = core_config.verifying_key()? {
+        tracing::info!(
+            "? Signature verification enabled (key {})",
+            binary_registry::key_fingerprint(&key)
+        );
+        registry = registry.with_trusted_key(key);
+    }
+    if !core_config.allowed_plugin_dirs.is_empty() {
+        registry = registry.with_allowed_dirs(&core_config.allowed_plugin_dirs)?;
+        tracing::info!(
+            "? Binaries restricted to {} allowed directories",
+            core_config.allowed_plugin_dirs.len()
+        );
+    }
+    if let Some(dir) = &core_config.bundle_dir {
+        registry = registry.with_bundle_dir(dir)?;
+        tracing::info!("? Bundles restricted to {}", dir.display());
+    }
     tracing::info!("? Binary registry created");
 
     // Load existing binaries from metadata
@@ -37,27 +260,141 @@ async fn main() -> Result<()> {
         tracing::warn!("No existing metadata found: {}", e);
     } else {
         tracing::info!("? Loaded {} existing binaries", registry.count());
+        if let Some(cache) = registry.module_cache() {
+            tracing::info!("? {} of them from the module cache", cache.hits());
+        }
         registry.print_binaries()?;
     }
 
+    // Then everything in the plugin directory; paths already in the metadata
+    // are updated rather than registered twice
+    if let Some(dir) = &core_config.plugin_dir {
+        let ids = registry.load_directory(dir).await?;
+        tracing::info!("? Loaded {} binaries from {}", ids.len(), dir.display());
+    }
+
     // Create executor
-    let executor = Executor::new(registry.clone());
+    let tracer = Tracer::default().with_sample_rate(core_config.trace_sample_rate);
+    let mut executor = Executor::with_tracer(registry.clone(), tracer)
+        .with_max_chain_length(core_config.max_chain_length)
+        .with_slow_warning_fraction(core_config.slow_warning_fraction)
+        .with_large_output_warning_bytes(core_config.large_output_warning_bytes)
+        .with_history_size(core_config.history_size)
+        .with_fuel_yield_interval(core_config.fuel_yield_interval);
+    if let Some(budget) = core_config.chain_fuel_budget {
+        executor = executor.with_chain_fuel_budget(budget);
+    }
+
+    if let Some(path) = &core_config.audit_log {
+        executor = executor.with_audit_log(AuditLog::open(path)?);
+        tracing::info!("? Audit log enabled: {}", path.display());
+    }
     tracing::info!("? Executor created");
 
     // Create server
-    let server = Server::new(registry, executor);
+    let auth_token = core_config.auth_token.clone();
+    let server = Server::new(registry, executor, core_config).with_log_tail(log_tail);
+    // Losing the old traces and totals is better than not starting at all
+    if let Err(e) = server.restore_state().await {
+        tracing::warn!("Starting without the saved state: {:#}", e);
+    }
     tracing::info!("? Server created");
 
-    let socket_server = SocketServer::new(server);
+    if auth_token.is_some() {
+        tracing::info!("? Connections must authenticate with the configured token");
+    }
+    let socket_server = SocketServer::new(server, &socket_path)
+        .with_socket_mode(socket_mode)
+        .with_max_connections(max_connections)
+        .with_max_frame_bytes(max_frame_bytes)
+        .with_idle_timeout(idle_timeout)
+        .with_auth_token(auth_token);
     tracing::info!("? Socket server initialized");
 
     tracing::info!("??????????????????????????????????????????");
-    tracing::info!("?? Server listening on /tmp/wasm-core.sock");
+    tracing::info!("?? Server listening on {}", socket_path.display());
     tracing::info!("?? Use wasm-client to interact with the server");
     tracing::info!("??????????????????????????????????????????");
 
-    // Start listening
-    socket_server.listen().await?;
+    // Serve until SIGINT or SIGTERM, then save what should survive a restart
+    socket_server.listen_until(shutdown_signal()).await?;
+    tracing::info!("? Server stopped");
 
     Ok(())
 }
+
+async fn shutdown_signal() {
+    let mut terminate = match signal(SignalKind::terminate()) {
+        Ok(terminate) => terminate,
+        Err(e) => {
+            tracing::warn!("Cannot listen for SIGTERM: {}", e);
+            let _ = tokio::signal::ctrl_c().await;
+            return;
+        }
+    };
+    tokio::select! {
+        _ = tokio::signal::ctrl_c() => {}
+        _ = terminate.recv() => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    /// Log a debug, an info and a warning line through a subscriber set up
+    /// like the server's, returning what was written
+    fn captured_logs(cli: &Cli, rust_log: Option<&str>) -> String {
+        let buffer = Arc::new(Mutex::new(Vec::new()));
+        let writer = buffer.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_env_filter(cli.log_filter(rust_log))
+            .with_writer(move || WriteTo(writer.clone()))
+            .finish();
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::debug!("Instantiated module");
+            tracing::info!("Executing binary: 1234");
+            tracing::warn!("Execution timed out, returning partial output: 1234");
+        });
+        let bytes = buffer.lock().unwrap().clone();
+        String::from_utf8(bytes).unwrap()
+    }
+
+    struct WriteTo(Arc<Mutex<Vec<u8>>>);
+
+    impl std::io::Write for WriteTo {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_quiet_keeps_only_warnings() {
+        let cli = Cli::parse_from(["wasm-core", "--quiet"]);
+        let logs = captured_logs(&cli, None);
+        assert!(!logs.contains("Executing binary"), "{}", logs);
+        assert!(logs.contains("Execution timed out"), "{}", logs);
+
+        let cli = Cli::parse_from(["wasm-core", "--verbose"]);
+        let logs = captured_logs(&cli, None);
+        assert!(logs.contains("Instantiated module"), "{}", logs);
+        assert!(logs.contains("Executing binary"), "{}", logs);
+    }
+
+    #[test]
+    fn test_rust_log_takes_precedence() {
+        let cli = Cli::parse_from(["wasm-core", "--quiet"]);
+        let logs = captured_logs(&cli, Some("info"));
+        assert!(logs.contains("Executing binary"), "{}", logs);
+
+        let cli = Cli::parse_from(["wasm-core"]);
+        assert!(!captured_logs(&cli, None).contains("Instantiated module"));
+        assert!(Cli::try_parse_from(["wasm-core", "--quiet", "--verbose"]).is_err());
+    }
+}
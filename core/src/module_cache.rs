@@ -0,0 +1,114 @@
+use anyhow::{Context, Result};
+use sha2::{Digest, Sha256};
+use std::hash::{Hash, Hasher};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use wasmtime::component::Component;
+use wasmtime::{Engine, Module};
+
+use crate::binary_registry::BinaryKind;
+
+/// Extension of serialized compiled binaries
+pub const ARTIFACT_EXTENSION: &str = "cwasm";
+
+/// Directory of serialized compiled binaries, one `.cwasm` file per
+/// distinct wasm binary and engine configuration.
+///
+/// Artifacts are loaded without re-validation, so the directory must be as
+/// trusted as the server binary itself.
+#[derive(Clone)]
+pub struct ModuleCache {
+    dir: PathBuf,
+    hits: Arc<AtomicU64>,
+}
+
+impl ModuleCache {
+    pub fn new(dir: impl Into<PathBuf>) -> Result<Self> {
+        let dir = dir.into();
+        std::fs::create_dir_all(&dir)
+            .with_context(|| format!("Failed to create module cache: {}", dir.display()))?;
+        Ok(Self {
+            dir,
+            hits: Arc::new(AtomicU64::new(0)),
+        })
+    }
+
+    /// Where the artifact for `wasm_bytes` compiled by `engine` lives. The
+    /// name covers the engine's settings, so engines configured differently
+    /// never read each other's artifacts.
+    pub fn artifact_path(&self, engine: &Engine, wasm_bytes: &[u8]) -> PathBuf {
+        let mut engine_hash = std::collections::hash_map::DefaultHasher::new();
+        engine
+            .precompile_compatibility_hash()
+            .hash(&mut engine_hash);
+
+        let mut digest = Sha256::new();
+        digest.update(engine_hash.finish().to_le_bytes());
+        digest.update(wasm_bytes);
+        self.dir
+            .join(hex::encode(digest.finalize()))
+            .with_extension(ARTIFACT_EXTENSION)
+    }
+
+    /// The cached compilation of `wasm_bytes`, if there is a usable one
+    pub fn get(&self, engine: &Engine, wasm_bytes: &[u8]) -> Option<BinaryKind> {
+        let path = self.artifact_path(engine, wasm_bytes);
+        if !path.exists() {
+            return None;
+        }
+        match Self::deserialize(engine, &path, BinaryKind::is_component(wasm_bytes)) {
+            Ok(kind) => {
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                Some(kind)
+            }
+            Err(e) => {
+                tracing::warn!("Ignoring module cache entry {}: {:#}", path.display(), e);
+                None
+            }
+        }
+    }
+
+    fn deserialize(engine: &Engine, path: &Path, component: bool) -> Result<BinaryKind> {
+        // SAFETY: artifacts are only ever written by `put`, and the cache
+        // directory is trusted (see the type docs). Wasmtime still rejects
+        // files built by an incompatible engine or version.
+        unsafe {
+            if component {
+                Ok(BinaryKind::Component(Component::deserialize_file(
+                    engine, path,
+                )?))
+            } else {
+                Ok(BinaryKind::Core(Module::deserialize_file(engine, path)?))
+            }
+        }
+    }
+
+    /// Store the compiled form of `wasm_bytes`, returning the artifact path
+    pub fn put(&self, engine: &Engine, wasm_bytes: &[u8], kind: &BinaryKind) -> Result<PathBuf> {
+        let serialized = match kind {
+            BinaryKind::Core(module) => module.serialize(),
+            BinaryKind::Component(component) => component.serialize(),
+        }
+        .context("Failed to serialize compiled binary")?;
+
+        // Write to a file of our own, then rename, so a concurrent reader
+        // never sees half a file and concurrent writers never share one
+        let path = self.artifact_path(engine, wasm_bytes);
+        let mut partial = tempfile::NamedTempFile::new_in(&self.dir)
+            .with_context(|| format!("Failed to write module cache: {}", self.dir.display()))?;
+        partial.write_all(&serialized).with_context(|| {
+            format!("Failed to write module cache: {}", partial.path().display())
+        })?;
+        partial
+            .persist(&path)
+            .with_context(|| format!("Failed to write module cache: {}", path.display()))?;
+        Ok(path)
+    }
+
+    /// Number of binaries served from the cache instead of being compiled
+    pub fn hits(&self) -> u64 {
+        self.hits.load(Ordering::Relaxed)
+    }
+}
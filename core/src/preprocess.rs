@@ -0,0 +1,49 @@
+use unicode_normalization::UnicodeNormalization;
+use wasm_shared::{ErrorCode, ErrorResponse, Preprocessor};
+
+/// Run `input` through each of `steps` in turn. Inputs that aren't UTF-8 are
+/// rejected unless there is nothing to do.
+pub fn apply(steps: &[Preprocessor], input: Vec<u8>) -> Result<Vec<u8>, ErrorResponse> {
+    if steps.is_empty() {
+        return Ok(input);
+    }
+    let mut text = String::from_utf8(input).map_err(|_| {
+        ErrorResponse::new(
+            ErrorCode::InvalidRequest,
+            "Input preprocessing needs UTF-8 input; the decoded input is not valid UTF-8",
+        )
+    })?;
+    for step in steps {
+        text = match step {
+            Preprocessor::TrimWhitespace => text.trim().to_string(),
+            Preprocessor::ToLowercase => text.to_lowercase(),
+            Preprocessor::NfcNormalize => text.nfc().collect(),
+        };
+    }
+    Ok(text.into_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_steps_apply_in_order() {
+        let steps = [Preprocessor::TrimWhitespace, Preprocessor::ToLowercase];
+        let output = apply(&steps, b"\t  Hello WORLD \n".to_vec()).unwrap();
+        assert_eq!(output, b"hello world");
+
+        // "e" followed by a combining acute accent composes to "é"
+        let output = apply(&[Preprocessor::NfcNormalize], "Cafe\u{301}".into()).unwrap();
+        assert_eq!(output, "Caf\u{e9}".as_bytes());
+    }
+
+    #[test]
+    fn test_binary_input_only_rejected_when_preprocessing() {
+        let bytes = vec![0xff, 0xfe, b' '];
+        assert_eq!(apply(&[], bytes.clone()).unwrap(), bytes);
+
+        let err = apply(&[Preprocessor::TrimWhitespace], bytes).unwrap_err();
+        assert_eq!(err.code, ErrorCode::InvalidRequest);
+    }
+}
@@ -0,0 +1,53 @@
+use lru::LruCache;
+use std::num::NonZeroUsize;
+use std::sync::{Arc, Mutex};
+use uuid::Uuid;
+use wasm_shared::ExecutionResult;
+
+/// Recent results of pure binaries, by a key covering everything that
+/// decides the result. Held by the registry, which drops a binary's entries
+/// when it is unloaded or its code changes, and read by the executor.
+#[derive(Clone)]
+pub struct ResultCache {
+    entries: Arc<Mutex<LruCache<[u8; 32], ExecutionResult>>>,
+}
+
+impl ResultCache {
+    pub fn new(capacity: NonZeroUsize) -> Self {
+        Self {
+            entries: Arc::new(Mutex::new(LruCache::new(capacity))),
+        }
+    }
+
+    pub fn get(&self, key: &[u8; 32]) -> Option<ExecutionResult> {
+        self.entries().get(key).cloned()
+    }
+
+    pub fn put(&self, key: [u8; 32], result: ExecutionResult) {
+        self.entries().put(key, result);
+    }
+
+    /// Drop every result of `binary_id`
+    pub fn invalidate(&self, binary_id: &Uuid) {
+        let mut entries = self.entries();
+        let stale: Vec<_> = entries
+            .iter()
+            .filter(|(_, result)| result.binary_id == *binary_id)
+            .map(|(key, _)| *key)
+            .collect();
+        for key in stale {
+            entries.pop(&key);
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn len(&self) -> usize {
+        self.entries().len()
+    }
+
+    fn entries(&self) -> std::sync::MutexGuard<'_, LruCache<[u8; 32], ExecutionResult>> {
+        self.entries
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
@@ -0,0 +1,203 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::oneshot;
+
+/// A counting semaphore whose waiters are served by priority: when a permit
+/// frees up it goes to the highest-priority execution waiting, and among
+/// equal priorities to the one that has waited longest.
+pub struct Scheduler {
+    state: Mutex<State>,
+}
+
+struct State {
+    available: usize,
+    waiters: BinaryHeap<Waiter>,
+    /// Arrival order of waiters, so equal priorities stay first come, first served
+    next_seq: u64,
+}
+
+struct Waiter {
+    priority: u8,
+    seq: u64,
+    grant: oneshot::Sender<Permit>,
+}
+
+impl Ord for Waiter {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.priority
+            .cmp(&other.priority)
+            .then_with(|| other.seq.cmp(&self.seq))
+    }
+}
+
+impl PartialOrd for Waiter {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Waiter {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Waiter {}
+
+/// Held while an execution runs; dropping it hands the slot to the next waiter
+pub struct Permit {
+    /// `None` once the permit has been handed back without a release
+    scheduler: Option<Arc<Scheduler>>,
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        if let Some(scheduler) = self.scheduler.take() {
+            scheduler.release();
+        }
+    }
+}
+
+impl Scheduler {
+    pub fn new(permits: usize) -> Arc<Self> {
+        Arc::new(Self {
+            state: Mutex::new(State {
+                available: permits,
+                waiters: BinaryHeap::new(),
+                next_seq: 0,
+            }),
+        })
+    }
+
+    /// Wait for a permit. Higher `priority` goes first; 0 is the lowest.
+    pub async fn acquire(self: &Arc<Self>, priority: u8) -> Permit {
+        let granted = {
+            let mut state = self.state.lock().unwrap();
+            if state.available > 0 {
+                state.available -= 1;
+                None
+            } else {
+                let (grant, granted) = oneshot::channel();
+                let seq = state.next_seq;
+                state.next_seq += 1;
+                state.waiters.push(Waiter {
+                    priority,
+                    seq,
+                    grant,
+                });
+                Some(granted)
+            }
+        };
+        match granted {
+            None => Permit {
+                scheduler: Some(self.clone()),
+            },
+            // The sender lives in the heap until a permit is sent through it,
+            // so it is never dropped unsent
+            Some(granted) => granted.await.expect("waiter dropped without a permit"),
+        }
+    }
+
+    /// Executions waiting for a permit
+    #[cfg(test)]
+    pub fn waiting(&self) -> usize {
+        self.state.lock().unwrap().waiters.len()
+    }
+
+    fn release(self: &Arc<Self>) {
+        let mut state = self.state.lock().unwrap();
+        while let Some(waiter) = state.waiters.pop() {
+            let permit = Permit {
+                scheduler: Some(self.clone()),
+            };
+            match waiter.grant.send(permit) {
+                Ok(()) => return,
+                // The waiter gave up. Releasing the permit would take the
+                // lock again, so it is passed to the next waiter instead.
+                Err(mut permit) => permit.scheduler = None,
+            }
+        }
+        state.available += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use tokio::sync::mpsc;
+
+    /// Wait until `count` acquisitions are queued
+    async fn queued(scheduler: &Scheduler, count: usize) {
+        while scheduler.waiting() < count {
+            tokio::task::yield_now().await;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_high_priority_short_job_runs_before_queued_long_job() {
+        let scheduler = Scheduler::new(1);
+        let running = scheduler.acquire(0).await;
+        let (started, mut order) = mpsc::unbounded_channel();
+
+        let job = |name: &'static str, priority: u8, work: Duration| {
+            let scheduler = scheduler.clone();
+            let started = started.clone();
+            tokio::spawn(async move {
+                let _permit = scheduler.acquire(priority).await;
+                started.send(name).unwrap();
+                tokio::time::sleep(work).await;
+            })
+        };
+        let long = job("long", 0, Duration::from_millis(200));
+        queued(&scheduler, 1).await;
+        let short = job("short", 9, Duration::ZERO);
+        queued(&scheduler, 2).await;
+
+        drop(running);
+        assert_eq!(order.recv().await, Some("short"));
+        assert_eq!(order.recv().await, Some("long"));
+        short.await.unwrap();
+        long.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_equal_priorities_are_first_come_first_served() {
+        let scheduler = Scheduler::new(1);
+        let running = scheduler.acquire(5).await;
+        let (started, mut order) = mpsc::unbounded_channel();
+        for n in 0..3 {
+            let waiter = scheduler.clone();
+            let started = started.clone();
+            tokio::spawn(async move {
+                let _permit = waiter.acquire(5).await;
+                started.send(n).unwrap();
+            });
+            queued(&scheduler, n + 1).await;
+        }
+
+        drop(running);
+        for n in 0..3 {
+            assert_eq!(order.recv().await, Some(n));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_abandoned_wait_does_not_lose_the_permit() {
+        let scheduler = Scheduler::new(1);
+        let running = scheduler.acquire(0).await;
+        let abandoned = {
+            let scheduler = scheduler.clone();
+            tokio::spawn(async move { scheduler.acquire(9).await })
+        };
+        queued(&scheduler, 1).await;
+        abandoned.abort();
+        let _ = abandoned.await;
+
+        drop(running);
+        tokio::time::timeout(Duration::from_secs(1), scheduler.acquire(0))
+            .await
+            .expect("the permit should be free again");
+    }
+}
@@ -1,61 +1,294 @@
 use anyhow::Result;
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use std::time::{Duration, UNIX_EPOCH};
+use tokio::sync::mpsc::UnboundedSender;
+use tokio::sync::{broadcast, RwLock};
+use uuid::Uuid;
 use wasm_shared::*;
 
 use crate::binary_registry::BinaryRegistry;
-use crate::executor::Executor;
+use crate::config::CoreConfig;
+use crate::encoding;
+use crate::executor::{ExecutionContext, Executor};
+use crate::log_tail::LogTail;
+use crate::scheduler::Scheduler;
+use crate::state;
+use crate::tracer::ExecutionTrace;
+
+/// The Wasmtime release the server is built against; keep in step with the
+/// workspace's `wasmtime` requirement
+const WASMTIME_VERSION: &str = "26.0";
 
 pub struct Server {
     registry: BinaryRegistry,
     executor: Arc<RwLock<Executor>>,
+    config: CoreConfig,
+    execution_permits: Arc<Scheduler>,
+    /// Set when the server's `tracing` output feeds `TailLogs`
+    log_tail: Option<LogTail>,
 }
 
 impl Server {
-    pub fn new(registry: BinaryRegistry, executor: Executor) -> Self {
+    pub fn new(registry: BinaryRegistry, executor: Executor, config: CoreConfig) -> Self {
         Self {
             registry,
             executor: Arc::new(RwLock::new(executor)),
+            execution_permits: Scheduler::new(config.max_concurrency),
+            config,
+            log_tail: None,
         }
     }
 
+    /// Let `TailLogs` connections follow what `log_tail` receives
+    pub fn with_log_tail(mut self, log_tail: LogTail) -> Self {
+        self.log_tail = Some(log_tail);
+        self
+    }
+
     pub async fn load_binary(&self, req: LoadBinaryRequest) -> Result<LoadBinaryResponse> {
         tracing::info!("Loading binary from: {}", req.path);
-        let binary_id = self.registry.load_binary(&req.path).await?;
+        if let Some(config) = &req.default_config {
+            validate_config(config)?;
+        }
+        let binary_id = self
+            .registry
+            .load_binary_in(
+                &req.path,
+                req.signature.as_deref(),
+                req.namespace.as_deref(),
+            )
+            .await?;
+        if req.default_config.is_some() {
+            self.registry
+                .set_default_config(&binary_id, req.default_config)?;
+        }
         let binary = self.registry.get_binary(&binary_id)?;
+        let memory_pages = binary.memory_pages();
+        if binary.needs_memory_export() {
+            tracing::warn!(
+                "Binary {} does not export 'memory' and cannot be executed",
+                binary_id
+            );
+        }
         Ok(LoadBinaryResponse {
             binary_id,
             size: binary.metadata.size,
+            kind: binary.kind.name().to_string(),
+            memory_min_pages: memory_pages.map(|(min, _)| min),
+            memory_max_pages: memory_pages.and_then(|(_, max)| max),
+            exports: binary.export_names(self.registry.engine()),
+            signer: binary.metadata.signer.clone(),
+            needs_memory_export: binary.needs_memory_export(),
         })
     }
 
     pub async fn execute(&self, req: ExecuteRequest) -> Result<ExecuteResponse> {
+        self.execute_with_chunks(req, None).await
+    }
+
+    pub async fn cancel(
+        &self,
+        request_id: Uuid,
+        namespace: Option<&str>,
+    ) -> Result<CancelResponse> {
+        let executor = self.executor.read().await;
+        if !executor.cancel(&request_id, namespace) {
+            return Err(ErrorResponse::new(
+                ErrorCode::NotFound,
+                format!("No running execution with request ID {}", request_id),
+            )
+            .into());
+        }
+        tracing::info!("Cancelled request: {}", request_id);
+        Ok(CancelResponse { request_id })
+    }
+
+    /// Execute, forwarding any chunks the plugin emits to `chunks`
+    pub async fn execute_with_chunks(
+        &self,
+        req: ExecuteRequest,
+        chunks: Option<UnboundedSender<OutputChunk>>,
+    ) -> Result<ExecuteResponse> {
         tracing::info!("Executing binary: {}", req.binary_id);
-        let config = req.config.unwrap_or_default();
+        let binary = self
+            .registry
+            .get_binary_in(&req.binary_id, req.namespace.as_deref())?;
+        let config = self.resolve_config(req.config, &req.binary_id, req.namespace.as_deref())?;
+        let input = encoding::decode(req.encoding, &req.input)?;
+        if let Some(schema) = binary.metadata.input_schema() {
+            check_input_schema(schema, &input)?;
+        }
+        let _permit = self.execution_permits.acquire(req.priority).await;
         let executor = self.executor.read().await;
-        let result = executor.execute(req.binary_id, req.input, config).await?;
+        let result = executor
+            .execute_with(
+                req.binary_id,
+                input,
+                config,
+                ExecutionContext {
+                    request_id: req.request_id,
+                    chunks,
+                    env: req.env,
+                    correlation_id: req.correlation_id,
+                    encoding: req.encoding,
+                    scratch: None,
+                    deadline: req
+                        .deadline_unix_ms
+                        .map(|ms| UNIX_EPOCH + Duration::from_millis(ms)),
+                    warmup: false,
+                },
+            )
+            .await?;
         Ok(ExecuteResponse { result })
     }
 
+    /// Execute the binary at `req.path`, reusing an already loaded binary
+    /// with that path instead of reloading it
+    pub async fn execute_by_path(
+        &self,
+        req: ExecuteByPathRequest,
+    ) -> Result<ExecuteByPathResponse> {
+        let namespace = req.namespace.as_deref();
+        let binary_id = match self.registry.find_binary_by_path(&req.path, namespace) {
+            Some(binary_id) => binary_id,
+            None => {
+                self.load_binary(LoadBinaryRequest {
+                    path: req.path,
+                    signature: None,
+                    default_config: None,
+                    namespace: req.namespace.clone(),
+                })
+                .await?
+                .binary_id
+            }
+        };
+        let response = self
+            .execute(ExecuteRequest {
+                binary_id,
+                input: req.input,
+                config: req.config,
+                stream: false,
+                request_id: None,
+                env: req.env,
+                correlation_id: None,
+                namespace: req.namespace,
+                encoding: Encoding::Utf8,
+                deadline_unix_ms: None,
+                priority: 0,
+            })
+            .await?;
+        Ok(ExecuteByPathResponse {
+            binary_id,
+            result: response.result,
+        })
+    }
+
     pub async fn execute_chain(&self, req: ExecuteChainRequest) -> Result<ExecuteChainResponse> {
         tracing::info!("Executing chain: {} binaries", req.binary_ids.len());
-        let config = req.config.unwrap_or_default();
+        for binary_id in &req.binary_ids {
+            self.registry
+                .get_binary_in(binary_id, req.namespace.as_deref())?;
+        }
+        let configs = req
+            .binary_ids
+            .iter()
+            .map(|id| self.resolve_config(req.config.clone(), id, req.namespace.as_deref()))
+            .collect::<Result<Vec<_>>>()?;
+        let _permit = self.execution_permits.acquire(0).await;
+        let executor = self.executor.read().await;
+        executor
+            .execute_chain(
+                req.binary_ids,
+                req.input,
+                configs,
+                req.env,
+                req.step_env,
+                req.persist_host_state,
+            )
+            .await
+    }
+
+    /// Execute `binary_id` once in meter-only mode, under its usual limits
+    pub async fn warmup(
+        &self,
+        binary_id: Uuid,
+        sample_input: String,
+        namespace: Option<&str>,
+    ) -> Result<WarmupResponse> {
+        tracing::info!("Warming up binary: {}", binary_id);
+        let config = ExecutionConfig {
+            meter_only: true,
+            ..self.resolve_config(None, &binary_id, namespace)?
+        };
+        let _permit = self.execution_permits.acquire(0).await;
         let executor = self.executor.read().await;
-        let results = executor
-            .execute_chain(req.binary_ids, req.input, config)
+        let result = executor
+            .execute_with(
+                binary_id,
+                sample_input,
+                config,
+                ExecutionContext {
+                    warmup: true,
+                    ..Default::default()
+                },
+            )
             .await?;
-        Ok(ExecuteChainResponse { results })
+        Ok(WarmupResponse {
+            binary_id,
+            return_code: result.return_code,
+            execution_time_ms: result.execution_time_ms,
+            fuel_consumed: result.fuel_consumed,
+        })
+    }
+
+    /// The request's config if it has one, then the binary's own default,
+    /// then the server-wide default. Fails unless the binary is in `namespace`.
+    fn resolve_config(
+        &self,
+        requested: Option<ExecutionConfig>,
+        binary_id: &Uuid,
+        namespace: Option<&str>,
+    ) -> Result<ExecutionConfig> {
+        let binary = self.registry.get_binary_in(binary_id, namespace)?;
+        let config = requested
+            .or(binary.metadata.default_config)
+            .unwrap_or_else(|| self.config.default_execution_config());
+        validate_config(&config)?;
+        Ok(config)
+    }
+
+    pub async fn set_default_config(
+        &self,
+        req: SetDefaultConfigRequest,
+    ) -> Result<SetDefaultConfigResponse> {
+        if let Some(config) = &req.config {
+            validate_config(config)?;
+        }
+        self.registry
+            .get_binary_in(&req.binary_id, req.namespace.as_deref())?;
+        self.registry
+            .set_default_config(&req.binary_id, req.config.clone())?;
+        tracing::info!("Default config updated for binary: {}", req.binary_id);
+        Ok(SetDefaultConfigResponse {
+            binary_id: req.binary_id,
+            config: req.config,
+        })
     }
 
-    pub async fn list_binaries(&self, _req: ListBinariesRequest) -> Result<ListBinariesResponse> {
+    pub async fn list_binaries(&self, req: ListBinariesRequest) -> Result<ListBinariesResponse> {
         let binaries = self
             .registry
-            .list_binaries()
+            .list_loaded()
             .into_iter()
-            .map(|meta| BinaryInfo {
+            .filter(|binary| binary.metadata.namespace == req.namespace)
+            .map(|binary| (binary.needs_memory_export(), binary.metadata))
+            .map(|(needs_memory_export, meta)| BinaryInfo {
+                needs_memory_export,
                 id: meta.id,
                 path: meta.path.to_string_lossy().to_string(),
                 size: meta.size,
+                compile_time_ms: meta.compile_time_ms,
+                code_size: meta.code_size,
                 loaded_at: meta
                     .loaded_at
                     .duration_since(std::time::UNIX_EPOCH)
@@ -68,9 +301,1518 @@ impl Server {
 
     pub async fn unload_binary(&self, req: UnloadBinaryRequest) -> Result<UnloadBinaryResponse> {
         tracing::info!("Unloading binary: {}", req.binary_id);
+        self.registry
+            .get_binary_in(&req.binary_id, req.namespace.as_deref())?;
         self.registry.unload_binary(&req.binary_id)?;
+        self.executor.read().await.clear_history(&req.binary_id);
         Ok(UnloadBinaryResponse {
             message: format!("Binary {} unloaded successfully", req.binary_id),
         })
     }
+
+    pub async fn export_bundle(
+        &self,
+        path: String,
+        namespace: Option<&str>,
+    ) -> Result<ExportBundleResponse> {
+        tracing::info!("Exporting binaries to bundle: {}", path);
+        let (path, binary_count, size) = self.registry.export_bundle(&path, namespace)?;
+        Ok(ExportBundleResponse {
+            path: path.display().to_string(),
+            binary_count,
+            size,
+        })
+    }
+
+    /// The wasm bytes of a loaded binary, if `allow_binary_download` is set
+    pub async fn get_binary_bytes(
+        &self,
+        binary_id: Uuid,
+        namespace: Option<&str>,
+    ) -> Result<GetBinaryBytesResponse> {
+        if !self.config.allow_binary_download {
+            return Err(ErrorResponse::new(
+                ErrorCode::Unauthorized,
+                "Downloading binaries is disabled; set allow_binary_download to enable it",
+            )
+            .into());
+        }
+        let content_hash = self
+            .registry
+            .get_binary_in(&binary_id, namespace)?
+            .metadata
+            .content_hash;
+        let wasm_bytes = self.registry.binary_bytes(&binary_id)?;
+        Ok(GetBinaryBytesResponse {
+            binary_id,
+            wasm_base64: encoding::encode(Encoding::Base64, &wasm_bytes),
+            size: wasm_bytes.len(),
+            content_hash,
+        })
+    }
+
+    /// Restore the binaries of a bundle into `namespace`, writing their
+    /// files to a `<bundle name>-binaries` directory beside it
+    pub async fn import_bundle(
+        &self,
+        path: String,
+        namespace: Option<&str>,
+    ) -> Result<ImportBundleResponse> {
+        tracing::info!("Importing binaries from bundle: {}", path);
+        let (binary_ids, dir) = self.registry.import_bundle(&path, namespace)?;
+        Ok(ImportBundleResponse {
+            binary_ids,
+            dir: dir.display().to_string(),
+        })
+    }
+
+    /// Recompile every binary in `namespace` whose file changed since it was
+    /// loaded
+    pub async fn reload_all(&self, namespace: Option<&str>) -> Result<ReloadAllResponse> {
+        let results = self
+            .registry
+            .reload_all(namespace)
+            .await
+            .into_iter()
+            .map(|(metadata, result)| {
+                let (status, error) = match result {
+                    Ok(true) => (ReloadStatus::Reloaded, None),
+                    Ok(false) => (ReloadStatus::Unchanged, None),
+                    Err(e) => (ReloadStatus::Error, Some(format!("{:#}", e))),
+                };
+                ReloadResult {
+                    binary_id: metadata.id,
+                    path: metadata.path.display().to_string(),
+                    status,
+                    error,
+                }
+            })
+            .collect();
+        Ok(ReloadAllResponse { results })
+    }
+
+    /// Compare each loaded binary's file with the hash recorded when it was
+    /// loaded, without reloading anything
+    pub async fn verify_integrity(
+        &self,
+        binary_id: Option<Uuid>,
+        namespace: Option<&str>,
+    ) -> Result<VerifyIntegrityResponse> {
+        let results = self
+            .registry
+            .hash_sources(binary_id.as_ref(), namespace)
+            .await?
+            .into_iter()
+            .map(|(metadata, actual)| {
+                let status = match &actual {
+                    None => IntegrityStatus::Missing,
+                    Some(hash) if metadata.content_hash.as_ref() == Some(hash) => {
+                        IntegrityStatus::Ok
+                    }
+                    Some(_) => IntegrityStatus::Mismatch,
+                };
+                if status != IntegrityStatus::Ok {
+                    tracing::warn!(
+                        "Integrity check of {} ({}): {:?}",
+                        metadata.id,
+                        metadata.path.display(),
+                        status
+                    );
+                }
+                IntegrityResult {
+                    binary_id: metadata.id,
+                    path: metadata.path.display().to_string(),
+                    status,
+                    expected_sha256: metadata.content_hash,
+                    actual_sha256: actual,
+                }
+            })
+            .collect();
+        Ok(VerifyIntegrityResponse { results })
+    }
+
+    pub async fn describe_binary(
+        &self,
+        req: DescribeBinaryRequest,
+    ) -> Result<DescribeBinaryResponse> {
+        let binary = self
+            .registry
+            .get_binary_in(&req.binary_id, req.namespace.as_deref())?;
+        let memory_pages = binary.memory_pages();
+        Ok(DescribeBinaryResponse {
+            binary_id: req.binary_id,
+            path: binary.metadata.path.display().to_string(),
+            size: binary.metadata.size,
+            kind: binary.kind.name().to_string(),
+            memory_min_pages: memory_pages.map(|(min, _)| min),
+            memory_max_pages: memory_pages.and_then(|(_, max)| max),
+            exports: binary.export_names(self.registry.engine()),
+            needs_memory_export: binary.needs_memory_export(),
+            imports: binary.metadata.imports,
+            signer: binary.metadata.signer,
+            default_config: binary.metadata.default_config,
+            compile_time_ms: binary.metadata.compile_time_ms,
+            code_size: binary.metadata.code_size,
+            manifest: binary.metadata.manifest.map(Box::new),
+        })
+    }
+
+    /// Versions, engine features and defaults the server is running with
+    pub async fn info(&self) -> Result<InfoResponse> {
+        Ok(InfoResponse {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            wasmtime_version: WASMTIME_VERSION.to_string(),
+            engine_features: engine_features(self.registry.engine()),
+            default_config: self.config.default_execution_config(),
+            socket_path: self.config.socket_path.to_string_lossy().to_string(),
+            binary_count: self.registry.count(),
+        })
+    }
+
+    /// A stats snapshot every `stats_interval_ms`
+    pub async fn subscribe_stats(&self) -> broadcast::Receiver<StatsSnapshot> {
+        let interval = Duration::from_millis(self.config.stats_interval_ms);
+        self.executor.read().await.stats().subscribe(interval)
+    }
+
+    /// `snapshot` with the per-binary counts outside `namespace` dropped
+    pub fn scope_stats(
+        &self,
+        mut snapshot: StatsSnapshot,
+        namespace: Option<&str>,
+    ) -> StatsSnapshot {
+        snapshot
+            .per_binary
+            .retain(|binary_id, _| self.in_namespace(binary_id, namespace));
+        snapshot
+    }
+
+    pub fn tail_logs(&self) -> Result<broadcast::Receiver<LogLine>> {
+        match &self.log_tail {
+            Some(log_tail) => Ok(log_tail.subscribe()),
+            None => Err(ErrorResponse::new(
+                ErrorCode::InvalidRequest,
+                "This server does not make its logs available to clients",
+            )
+            .into()),
+        }
+    }
+
+    /// Load the traces and stats the last shutdown saved to `state_dir`
+    pub async fn restore_state(&self) -> Result<()> {
+        match &self.config.state_dir {
+            Some(dir) => state::restore(dir, &*self.executor.read().await).await,
+            None => Ok(()),
+        }
+    }
+
+    /// Save the traces and stats to `state_dir`, if set, for the next start
+    pub async fn save_state(&self) -> Result<()> {
+        match &self.config.state_dir {
+            Some(dir) => state::save(dir, &*self.executor.read().await).await,
+            None => Ok(()),
+        }
+    }
+
+    /// Traces matching `query`, of binaries still loaded in its namespace
+    pub async fn get_traces(&self, query: TraceQuery) -> Result<GetTracesResponse> {
+        let executor = self.executor.read().await;
+        let traces = executor.tracer().query(&query).await;
+        let namespace = query.namespace.as_deref();
+        Ok(GetTracesResponse {
+            traces: traces
+                .iter()
+                .filter(|trace| self.in_namespace(&trace.binary_id, namespace))
+                .map(ExecutionTrace::to_record)
+                .collect(),
+        })
+    }
+
+    fn in_namespace(&self, binary_id: &Uuid, namespace: Option<&str>) -> bool {
+        self.registry.get_binary_in(binary_id, namespace).is_ok()
+    }
+
+    pub async fn get_history(
+        &self,
+        binary_id: Uuid,
+        limit: Option<usize>,
+        namespace: Option<&str>,
+    ) -> Result<GetHistoryResponse> {
+        self.registry.get_binary_in(&binary_id, namespace)?;
+        let executor = self.executor.read().await;
+        Ok(GetHistoryResponse {
+            binary_id,
+            results: executor.history(&binary_id, limit),
+        })
+    }
+
+    pub async fn export_trace(
+        &self,
+        binary_id: Uuid,
+        format: TraceFormat,
+        namespace: Option<&str>,
+    ) -> Result<ExportTraceResponse> {
+        self.registry.get_binary_in(&binary_id, namespace)?;
+        let executor = self.executor.read().await;
+        let trace = executor
+            .tracer()
+            .get_trace(binary_id)
+            .await
+            .ok_or_else(|| {
+                ErrorResponse::new(
+                    ErrorCode::NotFound,
+                    format!("No trace recorded for binary: {}", binary_id),
+                )
+            })?;
+        let data = match format {
+            TraceFormat::Json => trace.to_json()?,
+            TraceFormat::Folded => trace.to_folded_stacks(),
+        };
+        Ok(ExportTraceResponse {
+            binary_id,
+            format,
+            data,
+        })
+    }
+
+    /// Run the execution recorded in trace `trace_id` again, with the input,
+    /// env, config and encoding it was given. Only `deterministic` runs are
+    /// sure to repeat exactly; others see a new timestamp and random seed.
+    /// Traces of binaries no longer loaded in `namespace` are not found.
+    pub async fn replay(&self, trace_id: Uuid, namespace: Option<&str>) -> Result<ReplayResponse> {
+        let _permit = self.execution_permits.acquire(0).await;
+        let executor = self.executor.read().await;
+        let trace = executor
+            .tracer()
+            .find(trace_id)
+            .await
+            .filter(|trace| self.in_namespace(&trace.binary_id, namespace))
+            .ok_or_else(|| {
+                ErrorResponse::new(
+                    ErrorCode::NotFound,
+                    format!("No trace recorded with ID: {}", trace_id),
+                )
+            })?;
+        let request = trace.request.ok_or_else(|| {
+            ErrorResponse::new(
+                ErrorCode::InvalidRequest,
+                format!("Trace {} did not record its request", trace_id),
+            )
+        })?;
+        tracing::info!("Replaying trace {} of binary {}", trace_id, trace.binary_id);
+        let result = executor
+            .execute_with(
+                trace.binary_id,
+                request.input,
+                request.config,
+                ExecutionContext {
+                    env: request.env,
+                    encoding: request.encoding,
+                    ..ExecutionContext::default()
+                },
+            )
+            .await?;
+        Ok(ReplayResponse {
+            trace_id,
+            binary_id: trace.binary_id,
+            original_return_code: trace.return_code,
+            result,
+        })
+    }
+
+    pub async fn get_audit_log(
+        &self,
+        since: Option<u64>,
+        namespace: Option<&str>,
+    ) -> Result<GetAuditLogResponse> {
+        let executor = self.executor.read().await;
+        let audit = executor.audit_log().ok_or_else(|| {
+            ErrorResponse::new(ErrorCode::InvalidRequest, "Audit logging is not enabled")
+        })?;
+        let mut entries = audit.entries_since(since)?;
+        entries.retain(|entry| self.in_namespace(&entry.binary_id, namespace));
+        Ok(GetAuditLogResponse { entries })
+    }
+}
+
+/// What `engine` supports. Wasmtime doesn't expose its settings, so each
+/// feature is probed: fuel by reading a store's fuel, SIMD by compiling a
+/// module that uses a vector type.
+fn engine_features(engine: &wasmtime::Engine) -> EngineFeatures {
+    let store = wasmtime::Store::new(engine, ());
+    EngineFeatures {
+        fuel: store.get_fuel().is_ok(),
+        async_support: engine.is_async(),
+        simd: wasmtime::Module::new(engine, "(module (func (param v128)))").is_ok(),
+    }
+}
+
+fn validate_config(config: &ExecutionConfig) -> Result<()> {
+    config.validate().map_err(|e| {
+        ErrorResponse::new(
+            ErrorCode::InvalidRequest,
+            format!("Invalid execution config: {}", e),
+        )
+        .into()
+    })
+}
+
+/// Reject `input` unless it is JSON matching `schema`, listing every
+/// violation with where in the input it was found
+fn check_input_schema(schema: &serde_json::Value, input: &[u8]) -> Result<()> {
+    let invalid = |message: String| ErrorResponse::new(ErrorCode::InvalidRequest, message);
+    let instance: serde_json::Value = serde_json::from_slice(input)
+        .map_err(|e| invalid(format!("Input is not valid JSON: {}", e)))?;
+    // The schema was checked when the manifest was read
+    let validator = jsonschema::validator_for(schema)
+        .map_err(|e| invalid(format!("Invalid input schema: {}", e)))?;
+    let violations: Vec<String> = validator
+        .iter_errors(&instance)
+        .map(|error| match error.instance_path.to_string() {
+            path if path.is_empty() => error.to_string(),
+            path => format!("{}: {}", path, error),
+        })
+        .collect();
+    if !violations.is_empty() {
+        return Err(invalid(format!(
+            "Input does not match the binary's input schema: {}",
+            violations.join("; ")
+        ))
+        .into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audit::AuditLog;
+    use std::path::Path;
+    use wasmtime::{Config, Engine};
+
+    #[tokio::test]
+    async fn test_execute_without_config_uses_file_defaults() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("wasm-core.toml");
+        std::fs::write(&config_path, "default_fuel = 500\n").unwrap();
+        let core_config = CoreConfig::from_file(&config_path, true).unwrap();
+
+        let mut engine_config = Config::new();
+        engine_config.async_support(true);
+        engine_config.consume_fuel(true);
+        let engine = Engine::new(&engine_config).unwrap();
+        let registry = BinaryRegistry::with_metadata_path(engine, dir.path().join("metadata.json"));
+
+        // Burns far more than 500 fuel, but nowhere near the compiled-in default
+        let wasm_path = dir.path().join("loop.wasm");
+        let wasm = wat::parse_str(
+            r#"(module
+                (memory (export "memory") 1)
+                (func (export "process") (param i32 i32 i32 i32) (result i32)
+                    (local $i i32)
+                    (loop $again
+                        (local.set $i (i32.add (local.get $i) (i32.const 1)))
+                        (br_if $again (i32.lt_u (local.get $i) (i32.const 10000))))
+                    (i32.const 0)))"#,
+        )
+        .unwrap();
+        std::fs::write(&wasm_path, wasm).unwrap();
+        let binary_id = registry.load_binary(&wasm_path, None).await.unwrap();
+
+        let executor = Executor::new(registry.clone());
+        let server = Server::new(registry, executor, core_config);
+
+        let without_config = server
+            .execute(ExecuteRequest {
+                binary_id,
+                input: String::new(),
+                config: None,
+                stream: false,
+                request_id: None,
+                env: Default::default(),
+                correlation_id: None,
+                namespace: None,
+                encoding: Encoding::Utf8,
+                deadline_unix_ms: None,
+                priority: 0,
+            })
+            .await;
+        assert!(without_config.is_err());
+
+        let with_config = server
+            .execute(ExecuteRequest {
+                binary_id,
+                input: String::new(),
+                config: Some(ExecutionConfig::default()),
+                stream: false,
+                request_id: None,
+                env: Default::default(),
+                correlation_id: None,
+                namespace: None,
+                encoding: Encoding::Utf8,
+                deadline_unix_ms: None,
+                priority: 0,
+            })
+            .await;
+        assert!(with_config.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_result_echoes_applied_defaults() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("wasm-core.toml");
+        std::fs::write(
+            &config_path,
+            "default_timeout_ms = 1500\ndefault_memory_limit_mb = 16\n",
+        )
+        .unwrap();
+        let core_config = CoreConfig::from_file(&config_path, true).unwrap();
+        let engine = Engine::new(&core_config.engine_config()).unwrap();
+        let registry = BinaryRegistry::with_metadata_path(engine, dir.path().join("metadata.json"));
+
+        let wasm_path = dir.path().join("noop.wasm");
+        let wasm = wat::parse_str(
+            r#"(module
+                (memory (export "memory") 1)
+                (func (export "process") (param i32 i32 i32 i32) (result i32)
+                    (i32.const 0)))"#,
+        )
+        .unwrap();
+        std::fs::write(&wasm_path, wasm).unwrap();
+        let binary_id = registry.load_binary(&wasm_path, None).await.unwrap();
+        let executor = Executor::new(registry.clone());
+        let server = Server::new(registry, executor, core_config);
+
+        let result = server
+            .execute(ExecuteRequest {
+                binary_id,
+                input: String::new(),
+                config: None,
+                stream: false,
+                request_id: None,
+                env: Default::default(),
+                correlation_id: None,
+                namespace: None,
+                encoding: Encoding::Utf8,
+                deadline_unix_ms: None,
+                priority: 0,
+            })
+            .await
+            .unwrap()
+            .result;
+        let applied = result.applied_config;
+        assert_eq!(applied.timeout_ms, 1500);
+        assert_eq!(applied.memory_limit_mb, 16);
+        assert_eq!(applied.fuel, ExecutionConfig::default().fuel);
+    }
+
+    #[tokio::test]
+    async fn test_binary_default_config_applies_and_persists() {
+        let dir = tempfile::tempdir().unwrap();
+        let engine = Engine::new(&CoreConfig::default().engine_config()).unwrap();
+        let metadata_path = dir.path().join("metadata.json");
+        let registry = BinaryRegistry::with_metadata_path(engine.clone(), &metadata_path);
+
+        let wasm_path = dir.path().join("loop.wasm");
+        let wasm = wat::parse_str(
+            r#"(module
+                (memory (export "memory") 1)
+                (func (export "process") (param i32 i32 i32 i32) (result i32)
+                    (local $i i32)
+                    (loop $again
+                        (local.set $i (i32.add (local.get $i) (i32.const 1)))
+                        (br_if $again (i32.lt_u (local.get $i) (i32.const 10000))))
+                    (i32.const 0)))"#,
+        )
+        .unwrap();
+        std::fs::write(&wasm_path, wasm).unwrap();
+
+        let executor = Executor::new(registry.clone());
+        let server = Server::new(registry, executor, CoreConfig::default());
+        let starved = ExecutionConfig {
+            fuel: Some(500),
+            ..ExecutionConfig::default()
+        };
+        let binary_id = server
+            .load_binary(LoadBinaryRequest {
+                path: wasm_path.display().to_string(),
+                signature: None,
+                default_config: Some(starved),
+                namespace: None,
+            })
+            .await
+            .unwrap()
+            .binary_id;
+
+        let request = ExecuteRequest {
+            binary_id,
+            input: String::new(),
+            config: None,
+            stream: false,
+            request_id: None,
+            env: Default::default(),
+            correlation_id: None,
+            namespace: None,
+            encoding: Encoding::Utf8,
+            deadline_unix_ms: None,
+            priority: 0,
+        };
+        assert!(server.execute(request.clone()).await.is_err());
+
+        let reloaded = BinaryRegistry::with_metadata_path(engine, &metadata_path);
+        reloaded.load().unwrap();
+        let stored = reloaded.get_binary(&binary_id).unwrap().metadata;
+        assert_eq!(stored.default_config.unwrap().fuel, Some(500));
+
+        server
+            .set_default_config(SetDefaultConfigRequest {
+                binary_id,
+                config: None,
+                namespace: None,
+            })
+            .await
+            .unwrap();
+        assert!(server.execute(request).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_warmup_is_not_audited_and_links_the_module() {
+        let dir = tempfile::tempdir().unwrap();
+        let engine = Engine::new(&CoreConfig::default().engine_config()).unwrap();
+        let registry = BinaryRegistry::with_metadata_path(engine, dir.path().join("metadata.json"));
+        let wasm_path = dir.path().join("echo.wasm");
+        let wasm = wat::parse_str(
+            r#"(module
+                (import "host" "write_output" (func $out (param i32 i32)))
+                (memory (export "memory") 1)
+                (func (export "process") (param i32 i32 i32 i32) (result i32)
+                    (call $out (local.get 0) (local.get 1))
+                    (i32.const 0)))"#,
+        )
+        .unwrap();
+        std::fs::write(&wasm_path, wasm).unwrap();
+        let binary_id = registry.load_binary(&wasm_path, None).await.unwrap();
+
+        let audit = AuditLog::open(dir.path().join("audit.log")).unwrap();
+        let executor = Executor::new(registry.clone()).with_audit_log(audit);
+        let server = Server::new(registry.clone(), executor, CoreConfig::default());
+        assert!(!registry.get_binary(&binary_id).unwrap().is_linked());
+
+        let warmup = server
+            .warmup(binary_id, "sample".to_string(), None)
+            .await
+            .unwrap();
+        assert_eq!(warmup.return_code, 0);
+        assert!(warmup.fuel_consumed > 0);
+        assert!(server
+            .get_audit_log(None, None)
+            .await
+            .unwrap()
+            .entries
+            .is_empty());
+        // The first real call starts from the module warmup linked
+        assert!(registry.get_binary(&binary_id).unwrap().is_linked());
+
+        for _ in 0..5 {
+            let response = server
+                .execute(ExecuteRequest {
+                    binary_id,
+                    input: "real".to_string(),
+                    config: None,
+                    stream: false,
+                    request_id: None,
+                    env: Default::default(),
+                    correlation_id: None,
+                    namespace: None,
+                    encoding: Encoding::Utf8,
+                    deadline_unix_ms: None,
+                    priority: 0,
+                })
+                .await
+                .unwrap();
+            assert_eq!(response.result.output, "real");
+        }
+        assert_eq!(
+            server
+                .get_audit_log(None, None)
+                .await
+                .unwrap()
+                .entries
+                .len(),
+            5
+        );
+    }
+
+    #[tokio::test]
+    async fn test_warmup_leaves_stats_history_and_traces_alone() {
+        let dir = tempfile::tempdir().unwrap();
+        let engine = Engine::new(&CoreConfig::default().engine_config()).unwrap();
+        let registry = BinaryRegistry::with_metadata_path(engine, dir.path().join("metadata.json"));
+        let wasm_path = dir.path().join("noop.wasm");
+        let wasm = wat::parse_str(
+            r#"(module
+                (memory (export "memory") 1)
+                (func (export "process") (param i32 i32 i32 i32) (result i32)
+                    (i32.const 0)))"#,
+        )
+        .unwrap();
+        std::fs::write(&wasm_path, wasm).unwrap();
+        let binary_id = registry.load_binary(&wasm_path, None).await.unwrap();
+        let executor = Executor::new(registry.clone()).with_history_size(10);
+        let server = Server::new(registry, executor, CoreConfig::default());
+
+        server
+            .warmup(binary_id, "sample".to_string(), None)
+            .await
+            .unwrap();
+
+        let stats = server.executor.read().await.stats().saved();
+        assert_eq!(stats.total, 0);
+        assert!(stats.per_binary.is_empty());
+        let history = server.get_history(binary_id, None, None).await.unwrap();
+        assert!(history.results.is_empty());
+        let traces = server.get_traces(TraceQuery::default()).await.unwrap();
+        assert!(traces.traces.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_execute_by_path_loads_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let engine = Engine::new(&CoreConfig::default().engine_config()).unwrap();
+        let registry = BinaryRegistry::with_metadata_path(engine, dir.path().join("metadata.json"));
+        let wasm_path = dir.path().join("echo.wasm");
+        let wasm = wat::parse_str(
+            r#"(module
+                (import "host" "write_output" (func $out (param i32 i32)))
+                (memory (export "memory") 1)
+                (func (export "process") (param i32 i32 i32 i32) (result i32)
+                    (call $out (local.get 0) (local.get 1))
+                    (i32.const 0)))"#,
+        )
+        .unwrap();
+        std::fs::write(&wasm_path, wasm).unwrap();
+
+        let executor = Executor::new(registry.clone());
+        let server = Server::new(registry.clone(), executor, CoreConfig::default());
+        let request = ExecuteByPathRequest {
+            path: wasm_path.display().to_string(),
+            input: "hi".to_string(),
+            config: None,
+            env: Default::default(),
+            namespace: None,
+        };
+
+        let first = server.execute_by_path(request.clone()).await.unwrap();
+        assert_eq!(first.result.output, "hi");
+        let second = server.execute_by_path(request).await.unwrap();
+        assert_eq!(second.binary_id, first.binary_id);
+        assert_eq!(registry.count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_namespaces_are_isolated() {
+        let dir = tempfile::tempdir().unwrap();
+        let engine = Engine::new(&CoreConfig::default().engine_config()).unwrap();
+        let registry = BinaryRegistry::with_metadata_path(engine, dir.path().join("metadata.json"));
+        let wasm_path = dir.path().join("noop.wasm");
+        let wasm = wat::parse_str(
+            r#"(module
+                (memory (export "memory") 1)
+                (func (export "process") (param i32 i32 i32 i32) (result i32)
+                    (i32.const 0)))"#,
+        )
+        .unwrap();
+        std::fs::write(&wasm_path, wasm).unwrap();
+
+        let executor = Executor::new(registry.clone());
+        let server = Server::new(registry.clone(), executor, CoreConfig::default());
+        let load = |namespace: &str| LoadBinaryRequest {
+            path: wasm_path.display().to_string(),
+            signature: None,
+            default_config: None,
+            namespace: Some(namespace.to_string()),
+        };
+        // The same file loaded by two tenants gets two entries
+        let alpha = server.load_binary(load("alpha")).await.unwrap().binary_id;
+        let beta = server.load_binary(load("beta")).await.unwrap().binary_id;
+        assert_ne!(alpha, beta);
+
+        let list = |namespace: &str| ListBinariesRequest {
+            namespace: Some(namespace.to_string()),
+        };
+        let listed = server.list_binaries(list("alpha")).await.unwrap().binaries;
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].id, alpha);
+        let listed = server.list_binaries(Default::default()).await.unwrap();
+        assert!(listed.binaries.is_empty());
+
+        let execute = |binary_id, namespace: Option<&str>| ExecuteRequest {
+            binary_id,
+            input: String::new(),
+            config: None,
+            stream: false,
+            request_id: None,
+            env: Default::default(),
+            correlation_id: None,
+            namespace: namespace.map(str::to_string),
+            encoding: Encoding::Utf8,
+            deadline_unix_ms: None,
+            priority: 0,
+        };
+        assert!(server.execute(execute(alpha, Some("alpha"))).await.is_ok());
+        for denied in [execute(beta, Some("alpha")), execute(alpha, None)] {
+            let err = server.execute(denied).await.unwrap_err();
+            let err = err.downcast_ref::<ErrorResponse>().unwrap();
+            assert_eq!(err.code, ErrorCode::NotFound);
+        }
+
+        let unload = UnloadBinaryRequest {
+            binary_id: beta,
+            namespace: Some("alpha".to_string()),
+        };
+        assert!(server.unload_binary(unload).await.is_err());
+        assert!(registry.get_binary(&beta).is_ok());
+    }
+
+    /// A server with one no-op binary loaded into the `alpha` namespace, and
+    /// `dir` as its bundle directory
+    async fn server_with_alpha_binary(dir: &Path, config: CoreConfig) -> (Server, Uuid) {
+        let engine = Engine::new(&CoreConfig::default().engine_config()).unwrap();
+        let registry = BinaryRegistry::with_metadata_path(engine, dir.join("metadata.json"))
+            .with_bundle_dir(dir)
+            .unwrap();
+        let wasm_path = dir.join("noop.wasm");
+        let wasm = wat::parse_str(
+            r#"(module
+                (memory (export "memory") 1)
+                (func (export "process") (param i32 i32 i32 i32) (result i32)
+                    (i32.const 0)))"#,
+        )
+        .unwrap();
+        std::fs::write(&wasm_path, wasm).unwrap();
+        let audit = AuditLog::open(dir.join("audit.log")).unwrap();
+        let executor = Executor::new(registry.clone())
+            .with_history_size(10)
+            .with_audit_log(audit);
+        let server = Server::new(registry, executor, config);
+        let binary_id = server
+            .load_binary(LoadBinaryRequest {
+                path: wasm_path.display().to_string(),
+                signature: None,
+                default_config: None,
+                namespace: Some("alpha".to_string()),
+            })
+            .await
+            .unwrap()
+            .binary_id;
+        (server, binary_id)
+    }
+
+    fn assert_not_found<T: std::fmt::Debug>(result: Result<T>) {
+        let err = result.unwrap_err();
+        let err = err.downcast_ref::<ErrorResponse>().unwrap();
+        assert_eq!(err.code, ErrorCode::NotFound);
+    }
+
+    #[tokio::test]
+    async fn test_history_is_namespaced() {
+        let dir = tempfile::tempdir().unwrap();
+        let (server, binary_id) = server_with_alpha_binary(dir.path(), CoreConfig::default()).await;
+
+        let history = server.get_history(binary_id, None, Some("alpha")).await;
+        assert!(history.is_ok());
+        assert_not_found(server.get_history(binary_id, None, Some("beta")).await);
+        assert_not_found(server.get_history(binary_id, None, None).await);
+    }
+
+    /// Run `binary_id` once in `alpha` so it has a trace
+    async fn execute_in_alpha(server: &Server, binary_id: Uuid) {
+        server
+            .execute(ExecuteRequest {
+                binary_id,
+                input: String::new(),
+                config: None,
+                stream: false,
+                request_id: None,
+                env: Default::default(),
+                correlation_id: None,
+                namespace: Some("alpha".to_string()),
+                encoding: Encoding::Utf8,
+                deadline_unix_ms: None,
+                priority: 0,
+            })
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_set_default_config_is_namespaced() {
+        let dir = tempfile::tempdir().unwrap();
+        let (server, binary_id) = server_with_alpha_binary(dir.path(), CoreConfig::default()).await;
+        let request = |namespace: &str| SetDefaultConfigRequest {
+            binary_id,
+            config: Some(ExecutionConfig {
+                fuel: Some(500),
+                ..ExecutionConfig::default()
+            }),
+            namespace: Some(namespace.to_string()),
+        };
+
+        assert_not_found(server.set_default_config(request("beta")).await);
+        let stored = server.registry.get_binary(&binary_id).unwrap().metadata;
+        assert!(stored.default_config.is_none());
+        assert!(server.set_default_config(request("alpha")).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_warmup_is_namespaced() {
+        let dir = tempfile::tempdir().unwrap();
+        let (server, binary_id) = server_with_alpha_binary(dir.path(), CoreConfig::default()).await;
+
+        let warmup = |namespace| server.warmup(binary_id, String::new(), namespace);
+        assert_not_found(warmup(Some("beta")).await);
+        assert_not_found(warmup(None).await);
+        assert!(warmup(Some("alpha")).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_verify_integrity_is_namespaced() {
+        let dir = tempfile::tempdir().unwrap();
+        let (server, binary_id) = server_with_alpha_binary(dir.path(), CoreConfig::default()).await;
+
+        assert_not_found(server.verify_integrity(Some(binary_id), Some("beta")).await);
+        let results = server.verify_integrity(None, Some("beta")).await.unwrap();
+        assert!(results.results.is_empty());
+        let results = server.verify_integrity(None, Some("alpha")).await.unwrap();
+        assert_eq!(results.results.len(), 1);
+        assert_eq!(results.results[0].binary_id, binary_id);
+    }
+
+    #[tokio::test]
+    async fn test_reload_all_is_namespaced() {
+        let dir = tempfile::tempdir().unwrap();
+        let (server, binary_id) = server_with_alpha_binary(dir.path(), CoreConfig::default()).await;
+
+        assert!(server
+            .reload_all(Some("beta"))
+            .await
+            .unwrap()
+            .results
+            .is_empty());
+        assert!(server.reload_all(None).await.unwrap().results.is_empty());
+        let results = server.reload_all(Some("alpha")).await.unwrap().results;
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].binary_id, binary_id);
+    }
+
+    #[tokio::test]
+    async fn test_audit_log_is_namespaced() {
+        let dir = tempfile::tempdir().unwrap();
+        let (server, binary_id) = server_with_alpha_binary(dir.path(), CoreConfig::default()).await;
+        execute_in_alpha(&server, binary_id).await;
+
+        let entries = |namespace| server.get_audit_log(None, namespace);
+        assert!(entries(Some("beta")).await.unwrap().entries.is_empty());
+        assert!(entries(None).await.unwrap().entries.is_empty());
+        let alpha = entries(Some("alpha")).await.unwrap().entries;
+        assert_eq!(alpha.len(), 1);
+        assert_eq!(alpha[0].binary_id, binary_id);
+    }
+
+    #[tokio::test]
+    async fn test_stats_are_scoped_to_namespace() {
+        let dir = tempfile::tempdir().unwrap();
+        let (server, binary_id) = server_with_alpha_binary(dir.path(), CoreConfig::default()).await;
+        let snapshot = StatsSnapshot {
+            timestamp_ms: 0,
+            executions_per_sec: 0.0,
+            active: 0,
+            total: 3,
+            per_binary: [(binary_id, 3)].into_iter().collect(),
+        };
+
+        let beta = server.scope_stats(snapshot.clone(), Some("beta"));
+        assert!(beta.per_binary.is_empty());
+        assert!(server
+            .scope_stats(snapshot.clone(), None)
+            .per_binary
+            .is_empty());
+        let alpha = server.scope_stats(snapshot, Some("alpha"));
+        assert_eq!(alpha.per_binary.get(&binary_id), Some(&3));
+    }
+
+    #[tokio::test]
+    async fn test_traces_are_namespaced() {
+        let dir = tempfile::tempdir().unwrap();
+        let (server, binary_id) = server_with_alpha_binary(dir.path(), CoreConfig::default()).await;
+        execute_in_alpha(&server, binary_id).await;
+
+        let query = |namespace: Option<&str>| TraceQuery {
+            binary_id: Some(binary_id),
+            namespace: namespace.map(str::to_string),
+            ..TraceQuery::default()
+        };
+        let traces = server.get_traces(query(Some("beta"))).await.unwrap();
+        assert!(traces.traces.is_empty());
+        let traces = server.get_traces(query(None)).await.unwrap();
+        assert!(traces.traces.is_empty());
+        let traces = server.get_traces(query(Some("alpha"))).await.unwrap();
+        assert_eq!(traces.traces.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_export_trace_is_namespaced() {
+        let dir = tempfile::tempdir().unwrap();
+        let (server, binary_id) = server_with_alpha_binary(dir.path(), CoreConfig::default()).await;
+        execute_in_alpha(&server, binary_id).await;
+
+        let export = |namespace| server.export_trace(binary_id, TraceFormat::Json, namespace);
+        assert_not_found(export(Some("beta")).await);
+        assert_not_found(export(None).await);
+        assert!(export(Some("alpha")).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_replay_is_namespaced() {
+        let dir = tempfile::tempdir().unwrap();
+        let (server, binary_id) = server_with_alpha_binary(dir.path(), CoreConfig::default()).await;
+        execute_in_alpha(&server, binary_id).await;
+        let query = TraceQuery {
+            namespace: Some("alpha".to_string()),
+            ..TraceQuery::default()
+        };
+        let trace_id = server.get_traces(query).await.unwrap().traces[0].trace_id;
+
+        assert_not_found(server.replay(trace_id, Some("beta")).await);
+        assert_not_found(server.replay(trace_id, None).await);
+        assert!(server.replay(trace_id, Some("alpha")).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_export_bundle_is_namespaced() {
+        let dir = tempfile::tempdir().unwrap();
+        let (server, _) = server_with_alpha_binary(dir.path(), CoreConfig::default()).await;
+        let bundle = "bundle.tar".to_string();
+
+        let export = server.export_bundle(bundle.clone(), Some("beta")).await;
+        assert_eq!(export.unwrap().binary_count, 0);
+        let export = server.export_bundle(bundle, Some("alpha")).await;
+        assert_eq!(export.unwrap().binary_count, 1);
+    }
+
+    #[tokio::test]
+    async fn test_info_reports_default_engine_features() {
+        let dir = tempfile::tempdir().unwrap();
+        let engine = Engine::new(&CoreConfig::default().engine_config()).unwrap();
+        let registry = BinaryRegistry::with_metadata_path(engine, dir.path().join("metadata.json"));
+        let path = dir.path().join("plugin.wasm");
+        std::fs::write(&path, wat::parse_str("(module)").unwrap()).unwrap();
+        registry.load_binary(&path, None).await.unwrap();
+
+        let executor = Executor::new(registry.clone());
+        let server = Server::new(registry, executor, CoreConfig::default());
+        let info = server.info().await.unwrap();
+
+        assert_eq!(info.version, env!("CARGO_PKG_VERSION"));
+        assert!(info.engine_features.fuel);
+        assert!(info.engine_features.async_support);
+        assert!(info.engine_features.simd);
+        assert_eq!(info.binary_count, 1);
+        assert_eq!(info.socket_path, SOCKET_PATH);
+        assert_eq!(info.default_config.timeout_ms, 5000);
+
+        // A bare engine has neither
+        let bare = engine_features(&Engine::new(&Config::new()).unwrap());
+        assert!(!bare.fuel && !bare.async_support);
+    }
+
+    #[tokio::test]
+    async fn test_reload_all_keeps_ids() {
+        let dir = tempfile::tempdir().unwrap();
+        let engine = Engine::new(&CoreConfig::default().engine_config()).unwrap();
+        let registry = BinaryRegistry::with_metadata_path(engine, dir.path().join("metadata.json"));
+        let returning = |code: i32| {
+            wat::parse_str(format!(
+                r#"(module
+                    (memory (export "memory") 1)
+                    (func (export "process") (param i32 i32 i32 i32) (result i32)
+                        (i32.const {})))"#,
+                code
+            ))
+            .unwrap()
+        };
+        let paths = [dir.path().join("a.wasm"), dir.path().join("b.wasm")];
+        let mut ids = Vec::new();
+        for path in &paths {
+            std::fs::write(path, returning(1)).unwrap();
+            ids.push(registry.load_binary(path, None).await.unwrap());
+        }
+
+        let executor = Executor::new(registry.clone());
+        let server = Server::new(registry.clone(), executor, CoreConfig::default());
+        let statuses = |response: ReloadAllResponse| {
+            response
+                .results
+                .into_iter()
+                .map(|result| (result.binary_id, result.status))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            statuses(server.reload_all(None).await.unwrap()),
+            vec![
+                (ids[0], ReloadStatus::Unchanged),
+                (ids[1], ReloadStatus::Unchanged)
+            ]
+        );
+
+        // Rebuild both plugins
+        for path in &paths {
+            std::fs::write(path, returning(2)).unwrap();
+        }
+        assert_eq!(
+            statuses(server.reload_all(None).await.unwrap()),
+            vec![
+                (ids[0], ReloadStatus::Reloaded),
+                (ids[1], ReloadStatus::Reloaded)
+            ]
+        );
+        assert_eq!(registry.count(), 2);
+        let request = ExecuteRequest {
+            binary_id: ids[1],
+            input: String::new(),
+            config: None,
+            stream: false,
+            request_id: None,
+            env: Default::default(),
+            correlation_id: None,
+            namespace: None,
+            encoding: Encoding::Utf8,
+            deadline_unix_ms: None,
+            priority: 0,
+        };
+        assert_eq!(server.execute(request).await.unwrap().result.return_code, 2);
+
+        // A file that is gone reports an error and leaves the binary loaded
+        std::fs::remove_file(&paths[0]).unwrap();
+        let response = server.reload_all(None).await.unwrap();
+        assert_eq!(response.results[0].status, ReloadStatus::Error);
+        assert!(response.results[0].error.is_some());
+        assert!(registry.get_binary(&ids[0]).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_correlation_id_reaches_trace_and_result() {
+        let dir = tempfile::tempdir().unwrap();
+        let engine = Engine::new(&CoreConfig::default().engine_config()).unwrap();
+        let registry = BinaryRegistry::with_metadata_path(engine, dir.path().join("metadata.json"));
+        let wasm_path = dir.path().join("noop.wasm");
+        let wasm = wat::parse_str(
+            r#"(module
+                (memory (export "memory") 1)
+                (func (export "process") (param i32 i32 i32 i32) (result i32)
+                    (i32.const 0)))"#,
+        )
+        .unwrap();
+        std::fs::write(&wasm_path, wasm).unwrap();
+        let binary_id = registry.load_binary(&wasm_path, None).await.unwrap();
+        let executor = Executor::new(registry.clone());
+        let server = Server::new(registry, executor, CoreConfig::default());
+
+        for correlation_id in [Some("client-req-42"), None] {
+            let response = server
+                .execute(ExecuteRequest {
+                    binary_id,
+                    input: String::new(),
+                    config: None,
+                    stream: false,
+                    request_id: None,
+                    env: Default::default(),
+                    correlation_id: correlation_id.map(String::from),
+                    namespace: None,
+                    encoding: Encoding::Utf8,
+                    deadline_unix_ms: None,
+                    priority: 0,
+                })
+                .await
+                .unwrap();
+            assert_eq!(response.result.correlation_id.as_deref(), correlation_id);
+        }
+
+        let traces = server
+            .get_traces(TraceQuery {
+                correlation_id: Some("client-req-42".to_string()),
+                ..Default::default()
+            })
+            .await
+            .unwrap()
+            .traces;
+        assert_eq!(traces.len(), 1);
+        assert_eq!(traces[0].correlation_id.as_deref(), Some("client-req-42"));
+        assert_eq!(traces[0].binary_id, binary_id);
+    }
+
+    #[tokio::test]
+    async fn test_deadline_bounds_execution() {
+        let dir = tempfile::tempdir().unwrap();
+        let engine = Engine::new(&CoreConfig::default().engine_config()).unwrap();
+        let registry = BinaryRegistry::with_metadata_path(engine, dir.path().join("metadata.json"));
+        let wasm_path = dir.path().join("spin.wasm");
+        let wasm = wat::parse_str(
+            r#"(module
+                (memory (export "memory") 1)
+                (func (export "process") (param i32 i32 i32 i32) (result i32)
+                    (loop $l (br $l))
+                    (i32.const 0)))"#,
+        )
+        .unwrap();
+        std::fs::write(&wasm_path, wasm).unwrap();
+        let binary_id = registry.load_binary(&wasm_path, None).await.unwrap();
+        let executor = Executor::new(registry.clone());
+        let server = Server::new(registry, executor, CoreConfig::default());
+        let now_ms = std::time::SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as u64;
+        let request = |deadline_unix_ms| ExecuteRequest {
+            binary_id,
+            input: String::new(),
+            config: Some(ExecutionConfig::builder().timeout(60_000).build().unwrap()),
+            stream: false,
+            request_id: None,
+            env: Default::default(),
+            correlation_id: None,
+            namespace: None,
+            encoding: Encoding::Utf8,
+            deadline_unix_ms: Some(deadline_unix_ms),
+            priority: 0,
+        };
+
+        // Already past: refused before the plugin starts, so nothing is traced
+        let start = std::time::Instant::now();
+        let err = server.execute(request(now_ms - 1_000)).await.unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<ErrorResponse>().unwrap().code,
+            ErrorCode::Timeout
+        );
+        assert!(start.elapsed() < Duration::from_millis(100));
+        let traces = server.get_traces(TraceQuery::default()).await.unwrap();
+        assert!(traces.traces.is_empty());
+
+        // A deadline closer than the timeout cuts the run short
+        let start = std::time::Instant::now();
+        let response = server.execute(request(now_ms + 200)).await.unwrap();
+        assert!(response.result.timed_out);
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[tokio::test]
+    async fn test_base64_input_and_output_carry_raw_bytes() {
+        let dir = tempfile::tempdir().unwrap();
+        let engine = Engine::new(&CoreConfig::default().engine_config()).unwrap();
+        let registry = BinaryRegistry::with_metadata_path(engine, dir.path().join("metadata.json"));
+        // Hands its input straight back
+        let wasm_path = dir.path().join("passthrough.wasm");
+        let wasm = wat::parse_str(
+            r#"(module
+                (import "host" "write_output" (func $out (param i32 i32)))
+                (memory (export "memory") 1)
+                (func (export "process") (param i32 i32 i32 i32) (result i32)
+                    (call $out (local.get 0) (local.get 1))
+                    (i32.const 0)))"#,
+        )
+        .unwrap();
+        std::fs::write(&wasm_path, wasm).unwrap();
+        let binary_id = registry.load_binary(&wasm_path, None).await.unwrap();
+        let executor = Executor::new(registry.clone());
+        let server = Server::new(registry, executor, CoreConfig::default());
+
+        let execute = |input: &str, encoding| ExecuteRequest {
+            binary_id,
+            input: input.to_string(),
+            config: None,
+            stream: false,
+            request_id: None,
+            env: Default::default(),
+            correlation_id: None,
+            namespace: None,
+            encoding,
+            deadline_unix_ms: None,
+            priority: 0,
+        };
+
+        // 0xff 0x00 0xfe 0x80: not UTF-8
+        let result = server
+            .execute(execute("/wD+gA==", Encoding::Base64))
+            .await
+            .unwrap()
+            .result;
+        assert_eq!(result.output, "/wD+gA==");
+        assert_eq!(result.encoding, Encoding::Base64);
+
+        let result = server
+            .execute(execute("ff00fe80", Encoding::Hex))
+            .await
+            .unwrap()
+            .result;
+        assert_eq!(result.output, "ff00fe80");
+
+        let err = server
+            .execute(execute("not base64!", Encoding::Base64))
+            .await
+            .unwrap_err();
+        let err = err.downcast_ref::<ErrorResponse>().unwrap();
+        assert_eq!(err.code, ErrorCode::InvalidRequest);
+    }
+
+    #[tokio::test]
+    async fn test_input_violating_schema_is_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let engine = Engine::new(&CoreConfig::default().engine_config()).unwrap();
+        let registry = BinaryRegistry::with_metadata_path(engine, dir.path().join("metadata.json"));
+        let wasm_path = dir.path().join("greeter.wasm");
+        let wasm = wat::parse_str(
+            r#"(module
+                (import "host" "write_output" (func $out (param i32 i32)))
+                (memory (export "memory") 1)
+                (func (export "process") (param i32 i32 i32 i32) (result i32)
+                    (call $out (local.get 0) (local.get 1))
+                    (i32.const 0)))"#,
+        )
+        .unwrap();
+        std::fs::write(&wasm_path, wasm).unwrap();
+        std::fs::write(
+            dir.path().join("greeter.toml"),
+            r#"
+                name = "greeter"
+
+                [input_schema]
+                type = "object"
+                required = ["name"]
+
+                [input_schema.properties.name]
+                type = "string"
+            "#,
+        )
+        .unwrap();
+        let binary_id = registry.load_binary(&wasm_path, None).await.unwrap();
+        let executor = Executor::new(registry.clone());
+        let server = Server::new(registry, executor, CoreConfig::default());
+
+        let execute = |input: &str| ExecuteRequest {
+            binary_id,
+            input: input.to_string(),
+            config: None,
+            stream: false,
+            request_id: None,
+            env: Default::default(),
+            correlation_id: None,
+            namespace: None,
+            encoding: Encoding::Utf8,
+            deadline_unix_ms: None,
+            priority: 0,
+        };
+
+        let result = server
+            .execute(execute(r#"{"name":"Ada"}"#))
+            .await
+            .unwrap()
+            .result;
+        assert_eq!(result.output, r#"{"name":"Ada"}"#);
+
+        let err = server.execute(execute(r#"{"name":42}"#)).await.unwrap_err();
+        let err = err.downcast_ref::<ErrorResponse>().unwrap();
+        assert_eq!(err.code, ErrorCode::InvalidRequest);
+        assert!(err.message.contains("input schema"), "{}", err.message);
+        assert!(err.message.contains("/name"), "{}", err.message);
+
+        let err = server.execute(execute("{}")).await.unwrap_err();
+        let err = err.downcast_ref::<ErrorResponse>().unwrap();
+        assert!(
+            err.message.contains("\"name\" is a required property"),
+            "{}",
+            err.message
+        );
+
+        let err = server.execute(execute("not json")).await.unwrap_err();
+        let err = err.downcast_ref::<ErrorResponse>().unwrap();
+        assert!(
+            err.message.starts_with("Input is not valid JSON"),
+            "{}",
+            err.message
+        );
+    }
+
+    #[tokio::test]
+    async fn test_replay_repeats_traced_execution() {
+        let dir = tempfile::tempdir().unwrap();
+        let engine = Engine::new(&CoreConfig::default().engine_config()).unwrap();
+        let registry = BinaryRegistry::with_metadata_path(engine, dir.path().join("metadata.json"));
+        // Echoes its env JSON, which holds the timestamp and seed, and
+        // returns the input length
+        let wasm_path = dir.path().join("env-echo.wasm");
+        let wasm = wat::parse_str(
+            r#"(module
+                (import "host" "write_output" (func $out (param i32 i32)))
+                (memory (export "memory") 1)
+                (func (export "process") (param i32 i32 i32 i32) (result i32)
+                    (call $out (local.get 2) (local.get 3))
+                    (local.get 1)))"#,
+        )
+        .unwrap();
+        std::fs::write(&wasm_path, wasm).unwrap();
+        let binary_id = registry.load_binary(&wasm_path, None).await.unwrap();
+        let executor = Executor::new(registry.clone());
+        let server = Server::new(registry, executor, CoreConfig::default());
+
+        let original = server
+            .execute(ExecuteRequest {
+                binary_id,
+                input: "hello".to_string(),
+                config: Some(
+                    ExecutionConfig::builder()
+                        .deterministic(true)
+                        .build()
+                        .unwrap(),
+                ),
+                stream: false,
+                request_id: None,
+                env: [("user".to_string(), "ada".to_string())].into(),
+                correlation_id: None,
+                namespace: None,
+                encoding: Encoding::Utf8,
+                deadline_unix_ms: None,
+                priority: 0,
+            })
+            .await
+            .unwrap()
+            .result;
+        assert_eq!(original.return_code, 5);
+
+        let traces = server.get_traces(TraceQuery::default()).await.unwrap();
+        let trace_id = traces.traces[0].trace_id;
+        let replay = server.replay(trace_id, None).await.unwrap();
+        assert_eq!(replay.binary_id, binary_id);
+        assert_eq!(replay.original_return_code, Some(5));
+        assert_eq!(replay.result.return_code, original.return_code);
+        assert_eq!(replay.result.output, original.output);
+        assert!(replay.result.output.contains("ada"));
+
+        let err = server.replay(Uuid::new_v4(), None).await.unwrap_err();
+        let err = err.downcast_ref::<ErrorResponse>().unwrap();
+        assert_eq!(err.code, ErrorCode::NotFound);
+    }
+
+    #[tokio::test]
+    async fn test_verify_integrity_reports_tampered_and_missing_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let engine = Engine::new(&CoreConfig::default().engine_config()).unwrap();
+        let registry = BinaryRegistry::with_metadata_path(engine, dir.path().join("metadata.json"));
+        let mut ids = Vec::new();
+        for (name, code) in [("a", 1), ("b", 2), ("c", 3)] {
+            let path = dir.path().join(format!("{}.wasm", name));
+            let wasm = wat::parse_str(format!(
+                r#"(module
+                    (memory (export "memory") 1)
+                    (func (export "process") (param i32 i32 i32 i32) (result i32)
+                        (i32.const {})))"#,
+                code
+            ))
+            .unwrap();
+            std::fs::write(&path, wasm).unwrap();
+            ids.push(registry.load_binary(&path, None).await.unwrap());
+        }
+        let executor = Executor::new(registry.clone());
+        let server = Server::new(registry, executor, CoreConfig::default());
+
+        // Swap b's bytes for c's behind the server's back, and remove c
+        std::fs::copy(dir.path().join("c.wasm"), dir.path().join("b.wasm")).unwrap();
+        std::fs::remove_file(dir.path().join("c.wasm")).unwrap();
+
+        let results = server.verify_integrity(None, None).await.unwrap().results;
+        let statuses: Vec<_> = results.iter().map(|r| (r.binary_id, r.status)).collect();
+        assert_eq!(
+            statuses,
+            vec![
+                (ids[0], IntegrityStatus::Ok),
+                (ids[1], IntegrityStatus::Mismatch),
+                (ids[2], IntegrityStatus::Missing),
+            ]
+        );
+        assert_ne!(results[1].expected_sha256, results[1].actual_sha256);
+        assert_eq!(results[2].actual_sha256, None);
+
+        // The loaded binary is untouched
+        let result = server
+            .verify_integrity(Some(ids[1]), None)
+            .await
+            .unwrap()
+            .results;
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].status, IntegrityStatus::Mismatch);
+        let executor = server.executor.read().await;
+        let run = executor
+            .execute(ids[1], String::new(), ExecutionConfig::default())
+            .await
+            .unwrap();
+        assert_eq!(run.return_code, 2);
+    }
+
+    #[tokio::test]
+    async fn test_binary_bytes_match_the_loaded_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let engine = Engine::new(&CoreConfig::default().engine_config()).unwrap();
+        let registry = BinaryRegistry::with_metadata_path(engine, dir.path().join("metadata.json"));
+        let path = dir.path().join("noop.wasm");
+        let wasm = wat::parse_str(
+            r#"(module
+                (memory (export "memory") 1)
+                (func (export "process") (param i32 i32 i32 i32) (result i32)
+                    (i32.const 0)))"#,
+        )
+        .unwrap();
+        std::fs::write(&path, &wasm).unwrap();
+        let binary_id = registry.load_binary(&path, None).await.unwrap();
+
+        // Off unless the server opts in
+        let locked = Server::new(
+            registry.clone(),
+            Executor::new(registry.clone()),
+            CoreConfig::default(),
+        );
+        let err = locked.get_binary_bytes(binary_id, None).await.unwrap_err();
+        let err = err.downcast_ref::<ErrorResponse>().unwrap();
+        assert_eq!(err.code, ErrorCode::Unauthorized);
+
+        let config = CoreConfig {
+            allow_binary_download: true,
+            ..CoreConfig::default()
+        };
+        let server = Server::new(registry.clone(), Executor::new(registry), config);
+        let response = server.get_binary_bytes(binary_id, None).await.unwrap();
+        let fetched = encoding::decode(Encoding::Base64, &response.wasm_base64).unwrap();
+        assert_eq!(fetched, wasm);
+        assert_eq!(response.size, wasm.len());
+
+        let err = server
+            .get_binary_bytes(Uuid::nil(), None)
+            .await
+            .unwrap_err();
+        let err = err.downcast_ref::<ErrorResponse>().unwrap();
+        assert_eq!(err.code, ErrorCode::NotFound);
+    }
+
+    #[tokio::test]
+    async fn test_binary_bytes_are_namespaced() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = CoreConfig {
+            allow_binary_download: true,
+            ..CoreConfig::default()
+        };
+        let (server, binary_id) = server_with_alpha_binary(dir.path(), config).await;
+
+        let bytes = server.get_binary_bytes(binary_id, Some("alpha")).await;
+        assert!(bytes.is_ok());
+        assert_not_found(server.get_binary_bytes(binary_id, Some("beta")).await);
+        assert_not_found(server.get_binary_bytes(binary_id, None).await);
+    }
 }
@@ -1,35 +1,145 @@
 use anyhow::{Context, Result};
 use futures::{SinkExt, StreamExt};
+use sha2::{Digest, Sha256};
+use std::collections::VecDeque;
+use std::future::Future;
+use std::os::unix::fs::PermissionsExt;
+use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::net::{UnixListener, UnixStream};
-use tokio_util::codec::{Framed, LinesCodec};
-use wasm_shared::{Command, ListBinariesRequest, Response, SOCKET_PATH};
+use tokio::sync::{broadcast, mpsc, Semaphore};
+use tokio_util::codec::{Framed, LinesCodec, LinesCodecError};
+use wasm_shared::{
+    Command, ErrorCode, ErrorResponse, ExecuteRequest, LogLevel, Response, SubscriptionKind,
+    PROTOCOL_VERSION,
+};
+use wasmtime::Trap;
 
+use crate::config::{
+    DEFAULT_IDLE_TIMEOUT_SECS, DEFAULT_MAX_CONNECTIONS, DEFAULT_MAX_FRAME_BYTES,
+    DEFAULT_SOCKET_MODE,
+};
+use crate::jsonrpc;
 use crate::server::Server;
 
+/// Protocol versions this server understands
+const SUPPORTED_PROTOCOLS: &[u32] = &[PROTOCOL_VERSION];
+
+/// Frames read ahead while a command runs; past this the connection isn't
+/// read until the command finishes
+const MAX_PIPELINED_FRAMES: usize = 64;
+
 pub struct SocketServer {
     server: Arc<Server>,
+    socket_path: PathBuf,
+    socket_mode: u32,
+    auth_token: Option<Arc<str>>,
+    max_connections: usize,
+    max_frame_bytes: usize,
+    idle_timeout: Option<Duration>,
 }
 
 impl SocketServer {
-    pub fn new(server: Server) -> Self {
+    pub fn new(server: Server, socket_path: impl Into<PathBuf>) -> Self {
         Self {
             server: Arc::new(server),
+            socket_path: socket_path.into(),
+            socket_mode: DEFAULT_SOCKET_MODE,
+            auth_token: None,
+            max_connections: DEFAULT_MAX_CONNECTIONS,
+            max_frame_bytes: DEFAULT_MAX_FRAME_BYTES,
+            idle_timeout: Some(Duration::from_secs(DEFAULT_IDLE_TIMEOUT_SECS)),
         }
     }
 
+    /// Permission bits to give the socket file once it is bound
+    pub fn with_socket_mode(mut self, mode: u32) -> Self {
+        self.socket_mode = mode;
+        self
+    }
+
+    /// Require every connection to open with `Command::Auth` carrying `token`
+    pub fn with_auth_token(mut self, token: Option<String>) -> Self {
+        self.auth_token = token.map(Arc::from);
+        self
+    }
+
+    /// Connections accepted beyond `max` are closed straight away
+    pub fn with_max_connections(mut self, max: usize) -> Self {
+        self.max_connections = max;
+        self
+    }
+
+    /// Lines longer than `max` bytes are answered with an error and skipped
+    pub fn with_max_frame_bytes(mut self, max: usize) -> Self {
+        self.max_frame_bytes = max;
+        self
+    }
+
+    /// Drop connections that send nothing for `timeout`; `None` keeps idle
+    /// connections open forever
+    pub fn with_idle_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.idle_timeout = timeout;
+        self
+    }
+
+    /// Serve connections until the process is killed
+    #[allow(dead_code)]
     pub async fn listen(&self) -> Result<()> {
-        let _ = std::fs::remove_file(SOCKET_PATH);
-        let listener = UnixListener::bind(SOCKET_PATH).context("Failed to bind Unix socket")?;
-        tracing::info!("?? Socket server listening on {}", SOCKET_PATH);
+        self.listen_until(std::future::pending()).await
+    }
+
+    /// Serve connections until `shutdown` completes, then stop accepting,
+    /// remove the socket file and save the server's state. Connections
+    /// already open are left to finish on their own.
+    pub async fn listen_until(&self, shutdown: impl Future<Output = ()>) -> Result<()> {
+        let _ = std::fs::remove_file(&self.socket_path);
+        let listener =
+            UnixListener::bind(&self.socket_path).context("Failed to bind Unix socket")?;
+        // Bound with the umask's permissions, which may let anyone connect
+        std::fs::set_permissions(
+            &self.socket_path,
+            std::fs::Permissions::from_mode(self.socket_mode),
+        )
+        .context("Failed to set socket permissions")?;
+        tracing::info!(
+            "?? Socket server listening on {}",
+            self.socket_path.display()
+        );
+        let connection_slots = Arc::new(Semaphore::new(self.max_connections));
+        tokio::pin!(shutdown);
         loop {
-            match listener.accept().await {
+            let accepted = tokio::select! {
+                accepted = listener.accept() => accepted,
+                () = &mut shutdown => break,
+            };
+            match accepted {
                 Ok((stream, _)) => {
+                    let Ok(slot) = Arc::clone(&connection_slots).try_acquire_owned() else {
+                        tracing::warn!(
+                            "Refusing connection: {} already open",
+                            self.max_connections
+                        );
+                        drop(stream);
+                        continue;
+                    };
                     let server = Arc::clone(&self.server);
+                    let auth_token = self.auth_token.clone();
+                    let idle_timeout = self.idle_timeout;
+                    let framed = Framed::new(
+                        stream,
+                        LinesCodec::new_with_max_length(self.max_frame_bytes),
+                    );
                     tokio::spawn(async move {
-                        if let Err(e) = handle_connection(stream, server).await {
+                        if let Err(e) =
+                            handle_connection(framed, server, auth_token, idle_timeout).await
+                        {
                             tracing::error!("Connection error: {}", e);
                         }
+                        // Whatever the connection held has been dropped with
+                        // it; free its slot for the next client
+                        drop(slot);
                     });
                 }
                 Err(e) => {
@@ -37,59 +147,1382 @@ impl SocketServer {
                 }
             }
         }
+
+        tracing::info!("?? Shutting down");
+        drop(listener);
+        let _ = std::fs::remove_file(&self.socket_path);
+        self.server.save_state().await
     }
 }
 
-async fn handle_connection(stream: UnixStream, server: Arc<Server>) -> Result<()> {
-    let mut framed = Framed::new(stream, LinesCodec::new());
-    while let Some(line) = framed.next().await {
+/// The wire format a connection speaks, fixed by its first frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Dialect {
+    Native,
+    JsonRpc,
+}
+
+impl Dialect {
+    fn detect(frame: &str) -> Self {
+        if jsonrpc::is_jsonrpc(frame) {
+            Dialect::JsonRpc
+        } else {
+            Dialect::Native
+        }
+    }
+}
+
+async fn handle_connection(
+    mut framed: Framed<UnixStream, LinesCodec>,
+    server: Arc<Server>,
+    auth_token: Option<Arc<str>>,
+    idle_timeout: Option<Duration>,
+) -> Result<()> {
+    let mut dialect = None;
+    if let Some(expected) = auth_token {
+        let Some(line) = next_line(&mut framed, idle_timeout).await else {
+            return Ok(());
+        };
         let line = line.context("Failed to read line")?;
-        let command: Command = match serde_json::from_str(&line) {
-            Ok(cmd) => cmd,
-            Err(e) => {
-                let response = Response::Error(format!("Invalid command: {}", e));
-                let json = serde_json::to_string(&response)?;
-                framed.send(json).await?;
+        let dialect = *dialect.insert(Dialect::detect(&line));
+        // A frame that doesn't decode has been answered already
+        let Some(request) = decode(&mut framed, dialect, &line).await? else {
+            tracing::warn!("Rejected unauthenticated connection");
+            return Ok(());
+        };
+        let response = match request.command {
+            Command::Auth { token } if token_matches(&expected, &token) => Response::Authenticated,
+            Command::Auth { .. } => Response::Error(ErrorResponse::new(
+                ErrorCode::Unauthorized,
+                "Invalid auth token",
+            )),
+            _ => Response::Error(ErrorResponse::new(
+                ErrorCode::Unauthorized,
+                "Authentication required: send Auth first",
+            )),
+        };
+        let authenticated = matches!(response, Response::Authenticated);
+        let id = request.id.or(Some(serde_json::Value::Null));
+        reply(&mut framed, dialect, id, response).await?;
+        if !authenticated {
+            tracing::warn!("Rejected unauthenticated connection");
+            return Ok(());
+        }
+    }
+
+    // Lines the client sent while a command was running
+    let mut pipelined = VecDeque::new();
+    loop {
+        let line = match pipelined.pop_front() {
+            Some(line) => line,
+            None => match next_line(&mut framed, idle_timeout).await {
+                Some(line) => line,
+                None => break,
+            },
+        };
+        let line = match line {
+            Ok(line) => line,
+            Err(LinesCodecError::MaxLineLengthExceeded) => {
+                let response = Response::Error(ErrorResponse::new(
+                    ErrorCode::InvalidRequest,
+                    format!(
+                        "Command exceeds the {} byte limit",
+                        framed.codec().max_length()
+                    ),
+                ));
+                let dialect = dialect.unwrap_or(Dialect::Native);
+                reply(
+                    &mut framed,
+                    dialect,
+                    Some(serde_json::Value::Null),
+                    response,
+                )
+                .await?;
+                // The codec discards the rest of the line by itself, but the
+                // stream ends the error with a `None` that doesn't mean the
+                // client has gone; swallow it and carry on reading
+                let _ = framed.next().await;
                 continue;
             }
+            Err(e) => return Err(e).context("Failed to read line"),
+        };
+        let dialect = *dialect.get_or_insert_with(|| Dialect::detect(&line));
+        let Some(jsonrpc::Request { id, command }) = decode(&mut framed, dialect, &line).await?
+        else {
+            continue;
         };
-        let response = process_command(command, &server).await;
-        let json = serde_json::to_string(&response)?;
-        framed.send(json).await?;
+        let response = match (dialect, command) {
+            (Dialect::Native, Command::Execute(req)) if req.stream => {
+                execute_streaming(req, &server, &mut framed).await?
+            }
+            (Dialect::Native, Command::Subscribe { kind, namespace }) => {
+                return subscribe(kind, namespace.as_deref(), &server, &mut framed).await
+            }
+            (Dialect::Native, Command::TailLogs { level }) => {
+                return tail_logs(level, &server, &mut framed).await
+            }
+            (Dialect::JsonRpc, Command::Subscribe { .. }) => Response::Error(ErrorResponse::new(
+                ErrorCode::InvalidRequest,
+                "Subscribe is not available over JSON-RPC",
+            )),
+            (Dialect::JsonRpc, Command::TailLogs { .. }) => Response::Error(ErrorResponse::new(
+                ErrorCode::InvalidRequest,
+                "TailLogs is not available over JSON-RPC",
+            )),
+            (_, command) => {
+                let work = process_command(command, &server);
+                match until_disconnect(&mut framed, &mut pipelined, work).await {
+                    Some(response) => response,
+                    None => {
+                        tracing::info!("Client disconnected mid-command; dropped the command");
+                        return Ok(());
+                    }
+                }
+            }
+        };
+        let unsupported_protocol =
+            matches!(&response, Response::Error(e) if e.code == ErrorCode::UnsupportedProtocol);
+        reply(&mut framed, dialect, id, response).await?;
+        if unsupported_protocol {
+            tracing::warn!("Closing connection from a client with an unsupported protocol");
+            return Ok(());
+        }
+    }
+    tracing::debug!("Client disconnected");
+    Ok(())
+}
+
+/// Run `work` while watching the connection. Gives `None`, dropping `work`
+/// and with it any execution in flight, if the client hangs up first; lines
+/// it sends meanwhile are queued on `pipelined`, up to
+/// `MAX_PIPELINED_FRAMES`. Once that many are waiting, the connection is
+/// left unread, and a hang-up unnoticed, until `work` is done.
+async fn until_disconnect<T>(
+    framed: &mut Framed<UnixStream, LinesCodec>,
+    pipelined: &mut VecDeque<Result<String, LinesCodecError>>,
+    work: impl Future<Output = T>,
+) -> Option<T> {
+    tokio::pin!(work);
+    loop {
+        tokio::select! {
+            output = &mut work => return Some(output),
+            line = framed.next(), if pipelined.len() < MAX_PIPELINED_FRAMES => match line {
+                Some(Ok(line)) => pipelined.push_back(Ok(line)),
+                // After a read error the stream yields a `None` that doesn't
+                // mean the client has gone, so stop watching
+                Some(Err(e)) => {
+                    pipelined.push_back(Err(e));
+                    return Some(work.await);
+                }
+                None => return None,
+            },
+        }
+    }
+}
+
+/// Decode a frame in the connection's dialect. Frames that aren't a valid
+/// command are answered with an error here and give `None`.
+async fn decode(
+    framed: &mut Framed<UnixStream, LinesCodec>,
+    dialect: Dialect,
+    line: &str,
+) -> Result<Option<jsonrpc::Request>> {
+    match dialect {
+        Dialect::Native => match serde_json::from_str(line) {
+            Ok(command) => Ok(Some(jsonrpc::Request { id: None, command })),
+            Err(e) => {
+                let response = Response::Error(ErrorResponse::new(
+                    ErrorCode::InvalidRequest,
+                    format!("Invalid command: {}", e),
+                ));
+                framed.send(serde_json::to_string(&response)?).await?;
+                Ok(None)
+            }
+        },
+        Dialect::JsonRpc => match jsonrpc::parse_request(line) {
+            Ok(request) => Ok(Some(request)),
+            Err(error) => {
+                framed.send(error.to_string()).await?;
+                Ok(None)
+            }
+        },
     }
+}
+
+/// Send `response` in the connection's dialect. JSON-RPC notifications,
+/// which have no `id`, are not answered.
+async fn reply(
+    framed: &mut Framed<UnixStream, LinesCodec>,
+    dialect: Dialect,
+    id: Option<serde_json::Value>,
+    response: Response,
+) -> Result<()> {
+    let frame = match (dialect, id) {
+        (Dialect::Native, _) => serde_json::to_string(&response)?,
+        (Dialect::JsonRpc, Some(id)) => jsonrpc::response(id, response).to_string(),
+        (Dialect::JsonRpc, None) => return Ok(()),
+    };
+    framed.send(frame).await?;
     Ok(())
 }
 
+/// The next line from the client, or `None` once it disconnects or has been
+/// idle for `idle_timeout`
+async fn next_line(
+    framed: &mut Framed<UnixStream, LinesCodec>,
+    idle_timeout: Option<Duration>,
+) -> Option<Result<String, LinesCodecError>> {
+    let Some(idle_timeout) = idle_timeout else {
+        return framed.next().await;
+    };
+    match tokio::time::timeout(idle_timeout, framed.next()).await {
+        Ok(line) => line,
+        Err(_) => {
+            tracing::info!("Dropping connection idle for {:?}", idle_timeout);
+            None
+        }
+    }
+}
+
+/// Run an execution, writing each emitted chunk to the connection as soon as
+/// it arrives. Returns the final response for the caller to send.
+async fn execute_streaming(
+    req: ExecuteRequest,
+    server: &Server,
+    framed: &mut Framed<UnixStream, LinesCodec>,
+) -> Result<Response> {
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let execution = server.execute_with_chunks(req, Some(tx));
+    tokio::pin!(execution);
+
+    let result = loop {
+        tokio::select! {
+            Some(chunk) = rx.recv() => {
+                framed
+                    .send(serde_json::to_string(&Response::OutputChunk(chunk))?)
+                    .await?;
+            }
+            result = &mut execution => break result,
+        }
+    };
+    // Chunks emitted just before the plugin returned may still be queued
+    while let Ok(chunk) = rx.try_recv() {
+        framed
+            .send(serde_json::to_string(&Response::OutputChunk(chunk))?)
+            .await?;
+    }
+    Ok(Response::Execute(result.map_err(error_response)))
+}
+
+/// Push updates of `kind` for binaries in `namespace` until the client
+/// disconnects. Lines the client sends meanwhile are ignored.
+async fn subscribe(
+    kind: SubscriptionKind,
+    namespace: Option<&str>,
+    server: &Server,
+    framed: &mut Framed<UnixStream, LinesCodec>,
+) -> Result<()> {
+    let SubscriptionKind::Stats = kind;
+    let mut snapshots = server.subscribe_stats().await;
+    loop {
+        tokio::select! {
+            snapshot = snapshots.recv() => match snapshot {
+                Ok(snapshot) => {
+                    let snapshot = server.scope_stats(snapshot, namespace);
+                    framed
+                        .send(serde_json::to_string(&Response::StatsSnapshot(snapshot))?)
+                        .await?;
+                }
+                // Only the latest numbers matter to a dashboard
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => return Ok(()),
+            },
+            line = framed.next() => {
+                if line.is_none() {
+                    tracing::debug!("Subscriber disconnected; dropped its subscription");
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
+
+/// Push the server's log lines at `level` or more severe until the client
+/// disconnects. Lines the client sends meanwhile are ignored.
+async fn tail_logs(
+    level: LogLevel,
+    server: &Server,
+    framed: &mut Framed<UnixStream, LinesCodec>,
+) -> Result<()> {
+    let mut lines = match server.tail_logs() {
+        Ok(lines) => lines,
+        Err(e) => {
+            let response = Response::Error(error_response(e));
+            framed.send(serde_json::to_string(&response)?).await?;
+            return Ok(());
+        }
+    };
+    loop {
+        tokio::select! {
+            line = lines.recv() => match line {
+                Ok(line) if line.level <= level => {
+                    framed.send(serde_json::to_string(&Response::LogLine(line))?).await?;
+                }
+                Ok(_) => {}
+                // Logging here would only feed the backlog further
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => return Ok(()),
+            },
+            line = framed.next() => {
+                if line.is_none() {
+                    tracing::debug!("Log tail disconnected");
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
+
+/// Compare digests so the time taken doesn't depend on how much of the
+/// token was right
+fn token_matches(expected: &str, given: &str) -> bool {
+    Sha256::digest(expected.as_bytes()) == Sha256::digest(given.as_bytes())
+}
+
+/// Answer a client's `Hello`: our version and protocols if it speaks one of
+/// them, an `unsupported_protocol` error otherwise
+fn hello(client_version: &str, protocol_version: u32) -> Response {
+    let server_version = env!("CARGO_PKG_VERSION");
+    if !SUPPORTED_PROTOCOLS.contains(&protocol_version) {
+        return Response::Error(ErrorResponse::new(
+            ErrorCode::UnsupportedProtocol,
+            format!(
+                "Client {} speaks protocol version {}, but wasm-core {} supports {:?}; \
+                 use a client and server from matching releases",
+                client_version, protocol_version, server_version, SUPPORTED_PROTOCOLS
+            ),
+        ));
+    }
+    Response::Hello {
+        server_version: server_version.to_string(),
+        supported_protocols: SUPPORTED_PROTOCOLS.to_vec(),
+    }
+}
+
 async fn process_command(command: Command, server: &Server) -> Response {
     match command {
+        // Only the first line of a connection is checked against the token
+        Command::Auth { .. } => Response::Authenticated,
+        Command::Hello {
+            client_version,
+            protocol_version,
+        } => hello(&client_version, protocol_version),
+        // Handled by the connection itself, which it then takes over
+        Command::Subscribe { .. } => Response::Error(ErrorResponse::new(
+            ErrorCode::InvalidRequest,
+            "Subscribe is only available over a socket connection",
+        )),
+        Command::TailLogs { .. } => Response::Error(ErrorResponse::new(
+            ErrorCode::InvalidRequest,
+            "TailLogs is only available over a socket connection",
+        )),
         Command::LoadBinary(req) => {
-            let result = server.load_binary(req).await.map_err(|e| e.to_string());
+            let result = server.load_binary(req).await.map_err(error_response);
             Response::LoadBinary(result)
         }
         Command::Execute(req) => {
-            let result = server.execute(req).await.map_err(|e| e.to_string());
+            let result = server.execute(req).await.map_err(error_response);
             Response::Execute(result)
         }
+        Command::ExecuteByPath(req) => {
+            let result = server.execute_by_path(req).await.map_err(error_response);
+            Response::ExecuteByPath(result)
+        }
         Command::ExecuteChain(req) => {
-            let result = server.execute_chain(req).await.map_err(|e| e.to_string());
+            let result = server.execute_chain(req).await.map_err(error_response);
             Response::ExecuteChain(result)
         }
-        Command::ListBinaries => {
-            let result = server
-                .list_binaries(ListBinariesRequest {})
-                .await
-                .map_err(|e| e.to_string());
+        Command::ListBinaries(req) => {
+            let result = server.list_binaries(req).await.map_err(error_response);
             Response::ListBinaries(result)
         }
         Command::UnloadBinary(req) => {
-            let result = server.unload_binary(req).await.map_err(|e| e.to_string());
+            let result = server.unload_binary(req).await.map_err(error_response);
             Response::UnloadBinary(result)
         }
+        Command::ReloadAll { namespace } => {
+            let result = server
+                .reload_all(namespace.as_deref())
+                .await
+                .map_err(error_response);
+            Response::ReloadAll(result)
+        }
+        Command::ExportBundle { path, namespace } => {
+            let result = server
+                .export_bundle(path, namespace.as_deref())
+                .await
+                .map_err(error_response);
+            Response::ExportBundle(result)
+        }
+        Command::ImportBundle { path, namespace } => {
+            let result = server
+                .import_bundle(path, namespace.as_deref())
+                .await
+                .map_err(error_response);
+            Response::ImportBundle(result)
+        }
+        Command::VerifyIntegrity {
+            binary_id,
+            namespace,
+        } => {
+            let result = server
+                .verify_integrity(binary_id, namespace.as_deref())
+                .await
+                .map_err(error_response);
+            Response::VerifyIntegrity(result)
+        }
+        Command::Info => {
+            let result = server.info().await.map_err(error_response);
+            Response::Info(result)
+        }
+        Command::DescribeBinary(req) => {
+            let result = server.describe_binary(req).await.map_err(error_response);
+            Response::DescribeBinary(result)
+        }
+        Command::SetDefaultConfig(req) => {
+            let result = server.set_default_config(req).await.map_err(error_response);
+            Response::SetDefaultConfig(result)
+        }
+        Command::Warmup {
+            binary_id,
+            sample_input,
+            namespace,
+        } => {
+            let result = server
+                .warmup(binary_id, sample_input, namespace.as_deref())
+                .await
+                .map_err(error_response);
+            Response::Warmup(result)
+        }
+        Command::GetTraces(query) => {
+            let result = server.get_traces(query).await.map_err(error_response);
+            Response::GetTraces(result)
+        }
+        Command::GetHistory {
+            binary_id,
+            limit,
+            namespace,
+        } => {
+            let result = server
+                .get_history(binary_id, limit, namespace.as_deref())
+                .await
+                .map_err(error_response);
+            Response::GetHistory(result)
+        }
+        Command::GetBinaryBytes {
+            binary_id,
+            namespace,
+        } => {
+            let result = server
+                .get_binary_bytes(binary_id, namespace.as_deref())
+                .await
+                .map_err(error_response);
+            Response::GetBinaryBytes(result)
+        }
+        Command::ExportTrace {
+            binary_id,
+            format,
+            namespace,
+        } => {
+            let result = server
+                .export_trace(binary_id, format, namespace.as_deref())
+                .await
+                .map_err(error_response);
+            Response::ExportTrace(result)
+        }
+        Command::Replay {
+            trace_id,
+            namespace,
+        } => {
+            let result = server
+                .replay(trace_id, namespace.as_deref())
+                .await
+                .map_err(error_response);
+            Response::Replay(result)
+        }
+        Command::Cancel {
+            request_id,
+            namespace,
+        } => {
+            let result = server
+                .cancel(request_id, namespace.as_deref())
+                .await
+                .map_err(error_response);
+            Response::Cancelled(result)
+        }
+        Command::GetAuditLog { since, namespace } => {
+            let result = server
+                .get_audit_log(since, namespace.as_deref())
+                .await
+                .map_err(error_response);
+            Response::GetAuditLog(result)
+        }
     }
 }
 
+/// Turn a failure into its wire form. Errors raised as `ErrorResponse` keep
+/// their code; fuel exhaustion is recognised from the wasm trap; anything else
+/// is `Internal`. The message is the whole context chain, outermost first, so
+/// the underlying cause reaches the client.
+fn error_response(e: anyhow::Error) -> ErrorResponse {
+    let code = e
+        .chain()
+        .find_map(|cause| {
+            if let Some(response) = cause.downcast_ref::<ErrorResponse>() {
+                return Some(response.code);
+            }
+            match cause.downcast_ref::<Trap>() {
+                Some(Trap::OutOfFuel) => Some(ErrorCode::OutOfFuel),
+                _ => None,
+            }
+        })
+        .unwrap_or(ErrorCode::Internal);
+    ErrorResponse::new(code, format!("{e:#}"))
+}
+
 impl Drop for SocketServer {
     fn drop(&mut self) {
-        let _ = std::fs::remove_file(SOCKET_PATH);
+        let _ = std::fs::remove_file(&self.socket_path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::binary_registry::BinaryRegistry;
+    use crate::config::CoreConfig;
+    use crate::executor::Executor;
+    use crate::log_tail::LogTail;
+    use tracing_subscriber::layer::SubscriberExt;
+    use tracing_subscriber::util::SubscriberInitExt;
+    use wasm_shared::{Encoding, ExecutionConfig, LoadBinaryRequest, OutputChunk, TraceQuery};
+    use wasmtime::{Config, Engine};
+
+    #[tokio::test]
+    async fn test_streamed_chunks_arrive_in_order_before_result() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut engine_config = Config::new();
+        engine_config.async_support(true);
+        engine_config.consume_fuel(true);
+        let engine = Engine::new(&engine_config).unwrap();
+        let registry = BinaryRegistry::with_metadata_path(engine, dir.path().join("metadata.json"));
+
+        let wasm_path = dir.path().join("chunks.wasm");
+        let wasm = wat::parse_str(
+            r#"(module
+                (import "host" "emit_chunk" (func $chunk (param i32 i32)))
+                (memory (export "memory") 1)
+                (data (i32.const 1024) "onetwothree")
+                (func (export "process") (param i32 i32 i32 i32) (result i32)
+                    (call $chunk (i32.const 1024) (i32.const 3))
+                    (call $chunk (i32.const 1027) (i32.const 3))
+                    (call $chunk (i32.const 1030) (i32.const 5))
+                    (i32.const 0)))"#,
+        )
+        .unwrap();
+        std::fs::write(&wasm_path, wasm).unwrap();
+        let binary_id = registry.load_binary(&wasm_path, None).await.unwrap();
+
+        let executor = Executor::new(registry.clone());
+        let server = Server::new(registry, executor, CoreConfig::default());
+        let socket_path = dir.path().join("wasm-core.sock");
+        let socket_server = SocketServer::new(server, &socket_path);
+        tokio::spawn(async move { socket_server.listen().await });
+
+        let stream = loop {
+            match UnixStream::connect(&socket_path).await {
+                Ok(stream) => break stream,
+                Err(_) => tokio::time::sleep(std::time::Duration::from_millis(10)).await,
+            }
+        };
+        let mut framed = Framed::new(stream, LinesCodec::new());
+        let command = Command::Execute(ExecuteRequest {
+            binary_id,
+            input: String::new(),
+            config: None,
+            stream: true,
+            request_id: None,
+            env: Default::default(),
+            correlation_id: None,
+            namespace: None,
+            encoding: Encoding::Utf8,
+            deadline_unix_ms: None,
+            priority: 0,
+        });
+        framed
+            .send(serde_json::to_string(&command).unwrap())
+            .await
+            .unwrap();
+
+        let mut chunks = Vec::new();
+        let result = loop {
+            let line = framed.next().await.unwrap().unwrap();
+            match serde_json::from_str::<Response>(&line).unwrap() {
+                Response::OutputChunk(OutputChunk { index, data, .. }) => {
+                    chunks.push((index, data))
+                }
+                Response::Execute(result) => break result.unwrap().result,
+                other => panic!("unexpected response: {:?}", other),
+            }
+        };
+
+        assert_eq!(
+            chunks,
+            vec![
+                (0, "one".to_string()),
+                (1, "two".to_string()),
+                (2, "three".to_string())
+            ]
+        );
+        assert_eq!(result.output, "onetwothree");
+    }
+
+    async fn test_server(dir: &tempfile::TempDir) -> Server {
+        let mut engine_config = Config::new();
+        engine_config.async_support(true);
+        engine_config.consume_fuel(true);
+        let engine = Engine::new(&engine_config).unwrap();
+        let registry = BinaryRegistry::with_metadata_path(engine, dir.path().join("metadata.json"));
+        let executor = Executor::new(registry.clone());
+        Server::new(registry, executor, CoreConfig::default())
+    }
+
+    #[tokio::test]
+    async fn test_unknown_binary_reports_not_found() {
+        let dir = tempfile::tempdir().unwrap();
+        let server = test_server(&dir).await;
+        let command = Command::Execute(ExecuteRequest {
+            binary_id: uuid::Uuid::nil(),
+            input: String::new(),
+            config: None,
+            stream: false,
+            request_id: None,
+            env: Default::default(),
+            correlation_id: None,
+            namespace: None,
+            encoding: Encoding::Utf8,
+            deadline_unix_ms: None,
+            priority: 0,
+        });
+
+        match process_command(command, &server).await {
+            Response::Execute(Err(e)) => assert_eq!(e.code, ErrorCode::NotFound),
+            other => panic!("unexpected response: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_error_response_keeps_code_under_context() {
+        let err = anyhow::Error::new(ErrorResponse::new(ErrorCode::NotFound, "no such binary"))
+            .context("Failed to execute");
+        let response = error_response(err);
+        assert_eq!(response.code, ErrorCode::NotFound);
+        assert_eq!(response.message, "Failed to execute: no such binary");
+
+        let trap = anyhow::Error::new(Trap::OutOfFuel).context("Plugin trapped");
+        assert_eq!(error_response(trap).code, ErrorCode::OutOfFuel);
+
+        let other = anyhow::anyhow!("disk on fire");
+        assert_eq!(error_response(other).code, ErrorCode::Internal);
+    }
+
+    #[tokio::test]
+    async fn test_invalid_command_reports_invalid_request() {
+        let dir = tempfile::tempdir().unwrap();
+        let socket_path = dir.path().join("wasm-core.sock");
+        let socket_server = SocketServer::new(test_server(&dir).await, &socket_path);
+        tokio::spawn(async move { socket_server.listen().await });
+
+        let stream = loop {
+            match UnixStream::connect(&socket_path).await {
+                Ok(stream) => break stream,
+                Err(_) => tokio::time::sleep(std::time::Duration::from_millis(10)).await,
+            }
+        };
+        let mut framed = Framed::new(stream, LinesCodec::new());
+        framed
+            .send(r#"{"type":"NoSuchCommand"}"#.to_string())
+            .await
+            .unwrap();
+
+        let line = framed.next().await.unwrap().unwrap();
+        match serde_json::from_str::<Response>(&line).unwrap() {
+            Response::Error(e) => {
+                assert_eq!(e.code, ErrorCode::InvalidRequest);
+                assert!(e.message.starts_with("Invalid command"));
+            }
+            other => panic!("unexpected response: {:?}", other),
+        }
+    }
+
+    /// Start a socket server requiring `auth_token` and open a connection to it
+    #[tokio::test]
+    async fn test_oversized_frame_keeps_connection_open() {
+        let dir = tempfile::tempdir().unwrap();
+        let socket_server =
+            SocketServer::new(test_server(&dir).await, dir.path().join("wasm-core.sock"))
+                .with_max_frame_bytes(1024);
+        let mut framed = connect_to(&dir, socket_server).await;
+
+        let oversized = format!(
+            r#"{{"type":"Execute","binary_id":"{}","input":"{}"}}"#,
+            uuid::Uuid::nil(),
+            "x".repeat(64 * 1024)
+        );
+        framed.send(oversized).await.unwrap();
+        let line = framed.next().await.unwrap().unwrap();
+        match serde_json::from_str::<Response>(&line).unwrap() {
+            Response::Error(e) => {
+                assert_eq!(e.code, ErrorCode::InvalidRequest);
+                assert!(e.message.contains("1024 byte limit"), "{}", e.message);
+            }
+            other => panic!("unexpected response: {:?}", other),
+        }
+
+        // The rest of the oversized line is skipped, not parsed as a command
+        assert!(matches!(
+            round_trip(&mut framed, Command::ListBinaries(Default::default())).await,
+            Response::ListBinaries(Ok(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_jsonrpc_execute_gets_jsonrpc_response() {
+        let dir = tempfile::tempdir().unwrap();
+        let server = test_server(&dir).await;
+        let wasm_path = dir.path().join("hello.wasm");
+        let wasm = wat::parse_str(
+            r#"(module
+                (import "host" "write_output" (func $write (param i32 i32)))
+                (memory (export "memory") 1)
+                (data (i32.const 1024) "hello")
+                (func (export "process") (param i32 i32 i32 i32) (result i32)
+                    (call $write (i32.const 1024) (i32.const 5))
+                    (i32.const 0)))"#,
+        )
+        .unwrap();
+        std::fs::write(&wasm_path, wasm).unwrap();
+        let binary_id = server
+            .load_binary(LoadBinaryRequest {
+                path: wasm_path.to_string_lossy().into_owned(),
+                signature: None,
+                default_config: None,
+                namespace: None,
+            })
+            .await
+            .unwrap()
+            .binary_id;
+        let socket_server = SocketServer::new(server, dir.path().join("wasm-core.sock"));
+        let mut framed = connect_to(&dir, socket_server).await;
+
+        let response = call(
+            &mut framed,
+            serde_json::json!({
+            "jsonrpc": "2.0",
+            "method": "execute",
+            "params": { "binary_id": binary_id, "input": "" },
+            "id": 1,
+            }),
+        )
+        .await;
+        assert_eq!(response["jsonrpc"], "2.0");
+        assert_eq!(response["id"], 1);
+        assert_eq!(response["result"]["result"]["output"], "hello");
+        assert!(response.get("error").is_none());
+
+        let response = call(
+            &mut framed,
+            serde_json::json!({
+            "jsonrpc": "2.0",
+            "method": "execute",
+            "params": { "binary_id": uuid::Uuid::nil(), "input": "" },
+            "id": "missing",
+            }),
+        )
+        .await;
+        assert_eq!(response["id"], "missing");
+        assert_eq!(response["error"]["data"]["code"], "not_found");
+        assert!(response.get("result").is_none());
+    }
+
+    async fn call(
+        framed: &mut Framed<UnixStream, LinesCodec>,
+        request: serde_json::Value,
+    ) -> serde_json::Value {
+        framed.send(request.to_string()).await.unwrap();
+        let line = framed.next().await.unwrap().unwrap();
+        serde_json::from_str(&line).unwrap()
+    }
+
+    async fn connect(
+        dir: &tempfile::TempDir,
+        auth_token: Option<&str>,
+    ) -> Framed<UnixStream, LinesCodec> {
+        let socket_server =
+            SocketServer::new(test_server(dir).await, dir.path().join("wasm-core.sock"))
+                .with_auth_token(auth_token.map(String::from));
+        connect_to(dir, socket_server).await
+    }
+
+    async fn connect_to(
+        dir: &tempfile::TempDir,
+        socket_server: SocketServer,
+    ) -> Framed<UnixStream, LinesCodec> {
+        let socket_path = dir.path().join("wasm-core.sock");
+        tokio::spawn(async move { socket_server.listen().await });
+
+        let stream = loop {
+            match UnixStream::connect(&socket_path).await {
+                Ok(stream) => break stream,
+                Err(_) => tokio::time::sleep(std::time::Duration::from_millis(10)).await,
+            }
+        };
+        Framed::new(stream, LinesCodec::new())
+    }
+
+    #[tokio::test]
+    async fn test_pipelined_frames_are_capped() {
+        let (client, server) = UnixStream::pair().unwrap();
+        let mut client = Framed::new(client, LinesCodec::new());
+        let mut server = Framed::new(server, LinesCodec::new());
+        for i in 0..MAX_PIPELINED_FRAMES * 2 {
+            client.send(i.to_string()).await.unwrap();
+        }
+
+        let mut pipelined = VecDeque::new();
+        let work = tokio::time::sleep(Duration::from_millis(100));
+        assert!(until_disconnect(&mut server, &mut pipelined, work)
+            .await
+            .is_some());
+        assert_eq!(pipelined.len(), MAX_PIPELINED_FRAMES);
+        // The rest stay on the connection for later
+        let next = server.next().await.unwrap().unwrap();
+        assert_eq!(next, MAX_PIPELINED_FRAMES.to_string());
+    }
+
+    async fn round_trip(framed: &mut Framed<UnixStream, LinesCodec>, command: Command) -> Response {
+        framed
+            .send(serde_json::to_string(&command).unwrap())
+            .await
+            .unwrap();
+        let line = framed.next().await.unwrap().unwrap();
+        serde_json::from_str(&line).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_matching_token_is_accepted() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut framed = connect(&dir, Some("s3cret")).await;
+
+        let auth = Command::Auth {
+            token: "s3cret".to_string(),
+        };
+        assert!(matches!(
+            round_trip(&mut framed, auth).await,
+            Response::Authenticated
+        ));
+        assert!(matches!(
+            round_trip(&mut framed, Command::ListBinaries(Default::default())).await,
+            Response::ListBinaries(Ok(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_wrong_or_missing_token_closes_connection() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut framed = connect(&dir, Some("s3cret")).await;
+        let auth = Command::Auth {
+            token: "guess".to_string(),
+        };
+        match round_trip(&mut framed, auth).await {
+            Response::Error(e) => assert_eq!(e.code, ErrorCode::Unauthorized),
+            other => panic!("unexpected response: {:?}", other),
+        }
+        assert!(framed.next().await.is_none());
+
+        let dir = tempfile::tempdir().unwrap();
+        let mut framed = connect(&dir, Some("s3cret")).await;
+        match round_trip(&mut framed, Command::ListBinaries(Default::default())).await {
+            Response::Error(e) => assert_eq!(e.code, ErrorCode::Unauthorized),
+            other => panic!("unexpected response: {:?}", other),
+        }
+        assert!(framed.next().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_no_token_configured_needs_no_auth() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut framed = connect(&dir, None).await;
+
+        assert!(matches!(
+            round_trip(&mut framed, Command::ListBinaries(Default::default())).await,
+            Response::ListBinaries(Ok(_))
+        ));
+        // Clients that always authenticate still work against an open server
+        let auth = Command::Auth {
+            token: "anything".to_string(),
+        };
+        assert!(matches!(
+            round_trip(&mut framed, auth).await,
+            Response::Authenticated
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_hello_with_matching_protocol() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut framed = connect(&dir, None).await;
+
+        let hello = Command::Hello {
+            client_version: "0.1.0".to_string(),
+            protocol_version: PROTOCOL_VERSION,
+        };
+        match round_trip(&mut framed, hello).await {
+            Response::Hello {
+                server_version,
+                supported_protocols,
+            } => {
+                assert_eq!(server_version, env!("CARGO_PKG_VERSION"));
+                assert!(supported_protocols.contains(&PROTOCOL_VERSION));
+            }
+            other => panic!("unexpected response: {:?}", other),
+        }
+        assert!(matches!(
+            round_trip(&mut framed, Command::ListBinaries(Default::default())).await,
+            Response::ListBinaries(Ok(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_hello_with_unsupported_protocol_closes_connection() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut framed = connect(&dir, None).await;
+
+        let hello = Command::Hello {
+            client_version: "9.0.0".to_string(),
+            protocol_version: PROTOCOL_VERSION + 1,
+        };
+        match round_trip(&mut framed, hello).await {
+            Response::Error(e) => {
+                assert_eq!(e.code, ErrorCode::UnsupportedProtocol);
+                assert!(e.message.contains("Client 9.0.0 speaks protocol version"));
+            }
+            other => panic!("unexpected response: {:?}", other),
+        }
+        assert!(framed.next().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_socket_gets_configured_permissions() {
+        for mode in [DEFAULT_SOCKET_MODE, 0o660] {
+            let dir = tempfile::tempdir().unwrap();
+            let socket_server =
+                SocketServer::new(test_server(&dir).await, dir.path().join("wasm-core.sock"))
+                    .with_socket_mode(mode);
+            let mut framed = connect_to(&dir, socket_server).await;
+            // A reply means the accept loop, and so the chmod before it, has run
+            round_trip(&mut framed, Command::ListBinaries(Default::default())).await;
+
+            let metadata = std::fs::metadata(dir.path().join("wasm-core.sock")).unwrap();
+            assert_eq!(metadata.permissions().mode() & 0o777, mode);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_idle_connection_is_dropped() {
+        let dir = tempfile::tempdir().unwrap();
+        let socket_server =
+            SocketServer::new(test_server(&dir).await, dir.path().join("wasm-core.sock"))
+                .with_idle_timeout(Some(Duration::from_millis(100)));
+        let mut framed = connect_to(&dir, socket_server).await;
+
+        // Activity resets the clock
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        round_trip(&mut framed, Command::ListBinaries(Default::default())).await;
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        round_trip(&mut framed, Command::ListBinaries(Default::default())).await;
+
+        let closed = tokio::time::timeout(Duration::from_secs(2), framed.next()).await;
+        assert!(closed.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_connections_beyond_cap_are_closed() {
+        let dir = tempfile::tempdir().unwrap();
+        let socket_path = dir.path().join("wasm-core.sock");
+        let socket_server =
+            SocketServer::new(test_server(&dir).await, &socket_path).with_max_connections(1);
+        let mut first = connect_to(&dir, socket_server).await;
+        round_trip(&mut first, Command::ListBinaries(Default::default())).await;
+
+        let stream = UnixStream::connect(&socket_path).await.unwrap();
+        let mut second = Framed::new(stream, LinesCodec::new());
+        let _ = second
+            .send(serde_json::to_string(&Command::ListBinaries(Default::default())).unwrap())
+            .await;
+        assert!(!matches!(second.next().await, Some(Ok(_))));
+
+        // The slot frees up once the first connection goes away
+        drop(first);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let stream = UnixStream::connect(&socket_path).await.unwrap();
+        let mut third = Framed::new(stream, LinesCodec::new());
+        assert!(matches!(
+            round_trip(&mut third, Command::ListBinaries(Default::default())).await,
+            Response::ListBinaries(Ok(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_stalled_client_is_cut_off_after_idle_timeout() {
+        let dir = tempfile::tempdir().unwrap();
+        let socket_path = dir.path().join("wasm-core.sock");
+        let socket_server = SocketServer::new(test_server(&dir).await, &socket_path)
+            .with_max_connections(1)
+            .with_idle_timeout(Some(Duration::from_millis(100)));
+
+        // One command, then silence with the socket left open, like a client
+        // that vanished without closing its end
+        let mut stalled = connect_to(&dir, socket_server).await;
+        round_trip(&mut stalled, Command::ListBinaries(Default::default())).await;
+
+        // The handler gives up on it and frees the only slot, though the
+        // client never hung up
+        let closed = tokio::time::timeout(Duration::from_secs(2), stalled.next()).await;
+        assert!(closed.unwrap().is_none());
+        let stream = UnixStream::connect(&socket_path).await.unwrap();
+        let mut next = Framed::new(stream, LinesCodec::new());
+        assert!(matches!(
+            round_trip(&mut next, Command::ListBinaries(Default::default())).await,
+            Response::ListBinaries(Ok(_))
+        ));
+        drop(stalled);
+    }
+
+    #[tokio::test]
+    async fn test_dropped_connection_releases_its_execution_and_slot() {
+        let dir = tempfile::tempdir().unwrap();
+        let engine = Engine::new(&CoreConfig::default().engine_config()).unwrap();
+        let registry = BinaryRegistry::with_metadata_path(engine, dir.path().join("metadata.json"));
+        let wasm_path = dir.path().join("spin.wasm");
+        let wasm = wat::parse_str(
+            r#"(module
+                (memory (export "memory") 1)
+                (func (export "process") (param i32 i32 i32 i32) (result i32)
+                    (loop $spin (br $spin))
+                    (i32.const 0)))"#,
+        )
+        .unwrap();
+        std::fs::write(&wasm_path, wasm).unwrap();
+        let binary_id = registry.load_binary(&wasm_path, None).await.unwrap();
+        let executor = Executor::new(registry.clone());
+        let config = CoreConfig {
+            stats_interval_ms: 20,
+            ..CoreConfig::default()
+        };
+        let server = Server::new(registry, executor, config);
+        let mut snapshots = server.subscribe_stats().await;
+        let socket_server =
+            SocketServer::new(server, dir.path().join("wasm-core.sock")).with_max_connections(1);
+        let mut client = connect_to(&dir, socket_server).await;
+
+        let execute = Command::Execute(ExecuteRequest {
+            binary_id,
+            input: String::new(),
+            config: Some(ExecutionConfig::builder().timeout(60_000).build().unwrap()),
+            stream: false,
+            request_id: None,
+            env: Default::default(),
+            correlation_id: None,
+            namespace: None,
+            encoding: Encoding::Utf8,
+            deadline_unix_ms: None,
+            priority: 0,
+        });
+        client
+            .send(serde_json::to_string(&execute).unwrap())
+            .await
+            .unwrap();
+        wait_for_active(&mut snapshots, 1).await;
+
+        // Long before the execution's own timeout
+        drop(client);
+        wait_for_active(&mut snapshots, 0).await;
+
+        // The only connection slot is free again
+        let stream = UnixStream::connect(dir.path().join("wasm-core.sock"))
+            .await
+            .unwrap();
+        let mut client = Framed::new(stream, LinesCodec::new());
+        assert!(matches!(
+            round_trip(&mut client, Command::ListBinaries(Default::default())).await,
+            Response::ListBinaries(Ok(_))
+        ));
+    }
+
+    async fn wait_for_active(
+        snapshots: &mut broadcast::Receiver<wasm_shared::StatsSnapshot>,
+        active: u64,
+    ) {
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while snapshots.recv().await.unwrap().active != active {}
+        })
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_stats_subscription_reflects_executions() {
+        let dir = tempfile::tempdir().unwrap();
+        let engine = Engine::new(&CoreConfig::default().engine_config()).unwrap();
+        let registry = BinaryRegistry::with_metadata_path(engine, dir.path().join("metadata.json"));
+        let wasm_path = dir.path().join("noop.wasm");
+        let wasm = wat::parse_str(
+            r#"(module
+                (memory (export "memory") 1)
+                (func (export "process") (param i32 i32 i32 i32) (result i32)
+                    (i32.const 0)))"#,
+        )
+        .unwrap();
+        std::fs::write(&wasm_path, wasm).unwrap();
+        let binary_id = registry.load_binary(&wasm_path, None).await.unwrap();
+        let executor = Executor::new(registry.clone());
+        let config = CoreConfig {
+            stats_interval_ms: 20,
+            ..CoreConfig::default()
+        };
+        let socket_path = dir.path().join("wasm-core.sock");
+        let socket_server =
+            SocketServer::new(Server::new(registry, executor, config), &socket_path);
+        let mut subscriber = connect_to(&dir, socket_server).await;
+        subscriber
+            .send(
+                serde_json::to_string(&Command::Subscribe {
+                    kind: SubscriptionKind::Stats,
+                    namespace: None,
+                })
+                .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        let stream = UnixStream::connect(&socket_path).await.unwrap();
+        let mut client = Framed::new(stream, LinesCodec::new());
+        for _ in 0..3 {
+            let execute = Command::Execute(ExecuteRequest {
+                binary_id,
+                input: String::new(),
+                config: None,
+                stream: false,
+                request_id: None,
+                env: Default::default(),
+                correlation_id: None,
+                namespace: None,
+                encoding: Encoding::Utf8,
+                deadline_unix_ms: None,
+                priority: 0,
+            });
+            assert!(matches!(
+                round_trip(&mut client, execute).await,
+                Response::Execute(Ok(_))
+            ));
+        }
+
+        let snapshot = tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                let line = subscriber.next().await.unwrap().unwrap();
+                match serde_json::from_str::<Response>(&line).unwrap() {
+                    Response::StatsSnapshot(snapshot) if snapshot.total == 3 => break snapshot,
+                    Response::StatsSnapshot(_) => continue,
+                    other => panic!("unexpected response: {:?}", other),
+                }
+            }
+        })
+        .await
+        .unwrap();
+        assert_eq!(snapshot.active, 0);
+        assert_eq!(snapshot.per_binary.get(&binary_id), Some(&3));
+    }
+
+    #[tokio::test]
+    async fn test_tail_logs_streams_execution_log_lines() {
+        let log_tail = LogTail::new();
+        // The runtime is single threaded, so the server logs through this too
+        let _guard = tracing_subscriber::registry()
+            .with(log_tail.clone())
+            .set_default();
+        let dir = tempfile::tempdir().unwrap();
+        let engine = Engine::new(&CoreConfig::default().engine_config()).unwrap();
+        let registry = BinaryRegistry::with_metadata_path(engine, dir.path().join("metadata.json"));
+        let wasm_path = dir.path().join("noop.wasm");
+        let wasm = wat::parse_str(
+            r#"(module
+                (memory (export "memory") 1)
+                (func (export "process") (param i32 i32 i32 i32) (result i32)
+                    (i32.const 0)))"#,
+        )
+        .unwrap();
+        std::fs::write(&wasm_path, wasm).unwrap();
+        let binary_id = registry.load_binary(&wasm_path, None).await.unwrap();
+        let executor = Executor::new(registry.clone());
+        let server = Server::new(registry, executor, CoreConfig::default()).with_log_tail(log_tail);
+        let socket_path = dir.path().join("wasm-core.sock");
+        let mut tail = connect_to(&dir, SocketServer::new(server, &socket_path)).await;
+        tail.send(
+            serde_json::to_string(&Command::TailLogs {
+                level: LogLevel::Info,
+            })
+            .unwrap(),
+        )
+        .await
+        .unwrap();
+
+        let stream = UnixStream::connect(&socket_path).await.unwrap();
+        let mut client = Framed::new(stream, LinesCodec::new());
+        let expected = format!("Executing binary: {}", binary_id);
+        // The tail may not be subscribed yet when the first execution logs
+        for _ in 0..50 {
+            let execute = Command::Execute(ExecuteRequest {
+                binary_id,
+                input: String::new(),
+                config: None,
+                stream: false,
+                request_id: None,
+                env: Default::default(),
+                correlation_id: None,
+                namespace: None,
+                encoding: Encoding::Utf8,
+                deadline_unix_ms: None,
+                priority: 0,
+            });
+            assert!(matches!(
+                round_trip(&mut client, execute).await,
+                Response::Execute(Ok(_))
+            ));
+            let line = tokio::time::timeout(Duration::from_millis(100), async {
+                loop {
+                    let line = tail.next().await.unwrap().unwrap();
+                    match serde_json::from_str::<Response>(&line).unwrap() {
+                        Response::LogLine(line) if line.message == expected => break line,
+                        Response::LogLine(_) => continue,
+                        other => panic!("unexpected response: {:?}", other),
+                    }
+                }
+            })
+            .await;
+            if let Ok(line) = line {
+                assert_eq!(line.level, LogLevel::Info);
+                assert_eq!(line.target, "wasm_core::server");
+                return;
+            }
+        }
+        panic!("no log line arrived");
+    }
+
+    #[tokio::test]
+    async fn test_traces_and_stats_survive_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let engine = Engine::new(&CoreConfig::default().engine_config()).unwrap();
+        let registry = BinaryRegistry::with_metadata_path(engine, dir.path().join("metadata.json"));
+        let wasm_path = dir.path().join("noop.wasm");
+        let wasm = wat::parse_str(
+            r#"(module
+                (memory (export "memory") 1)
+                (func (export "process") (param i32 i32 i32 i32) (result i32)
+                    (i32.const 0)))"#,
+        )
+        .unwrap();
+        std::fs::write(&wasm_path, wasm).unwrap();
+        let binary_id = registry.load_binary(&wasm_path, None).await.unwrap();
+        let config = CoreConfig {
+            stats_interval_ms: 20,
+            state_dir: Some(dir.path().join("state")),
+            ..CoreConfig::default()
+        };
+
+        let socket_path = dir.path().join("wasm-core.sock");
+        let server = Server::new(
+            registry.clone(),
+            Executor::new(registry.clone()),
+            config.clone(),
+        );
+        let socket_server = SocketServer::new(server, &socket_path);
+        let (shutdown, shutdown_received) = tokio::sync::oneshot::channel::<()>();
+        let listening = tokio::spawn(async move {
+            socket_server
+                .listen_until(async {
+                    let _ = shutdown_received.await;
+                })
+                .await
+        });
+        let stream = loop {
+            match UnixStream::connect(&socket_path).await {
+                Ok(stream) => break stream,
+                Err(_) => tokio::time::sleep(Duration::from_millis(10)).await,
+            }
+        };
+        let mut client = Framed::new(stream, LinesCodec::new());
+        for _ in 0..3 {
+            let execute = Command::Execute(ExecuteRequest {
+                binary_id,
+                input: String::new(),
+                config: None,
+                stream: false,
+                request_id: None,
+                env: Default::default(),
+                correlation_id: None,
+                namespace: None,
+                encoding: Encoding::Utf8,
+                deadline_unix_ms: None,
+                priority: 0,
+            });
+            assert!(matches!(
+                round_trip(&mut client, execute).await,
+                Response::Execute(Ok(_))
+            ));
+        }
+        shutdown.send(()).unwrap();
+        listening.await.unwrap().unwrap();
+        assert!(!socket_path.exists());
+
+        // A fresh executor starts from nothing until the state is restored
+        let restarted = Server::new(
+            registry.clone(),
+            Executor::new(registry.clone()),
+            config.clone(),
+        );
+        restarted.restore_state().await.unwrap();
+        let traces = restarted
+            .get_traces(TraceQuery::default())
+            .await
+            .unwrap()
+            .traces;
+        assert_eq!(traces.len(), 3);
+        assert!(traces.iter().all(|trace| trace.binary_id == binary_id));
+
+        let mut snapshots = restarted.subscribe_stats().await;
+        let snapshot = tokio::time::timeout(Duration::from_secs(5), snapshots.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(snapshot.total, 3);
+        assert_eq!(snapshot.per_binary.get(&binary_id), Some(&3));
+
+        // A damaged file fails the restore without leaving half of it behind
+        std::fs::write(dir.path().join("state").join("stats.json"), "{").unwrap();
+        let damaged = Server::new(registry.clone(), Executor::new(registry), config);
+        assert!(damaged.restore_state().await.is_err());
+        let traces = damaged.get_traces(TraceQuery::default()).await.unwrap();
+        assert!(traces.traces.is_empty());
     }
 }
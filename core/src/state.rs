@@ -0,0 +1,64 @@
+use anyhow::{Context, Result};
+use std::path::Path;
+
+use crate::executor::Executor;
+
+/// Traces as written by `Tracer::export_traces`
+const TRACES_FILE: &str = "traces.json";
+
+/// The executor's `SavedStats`
+const STATS_FILE: &str = "stats.json";
+
+/// Write the executor's traces and stats to `dir`, creating it if needed.
+/// Each file is written beside its final name and renamed over it.
+pub async fn save(dir: &Path, executor: &Executor) -> Result<()> {
+    std::fs::create_dir_all(dir)
+        .with_context(|| format!("Failed to create state directory: {}", dir.display()))?;
+    let traces = executor.tracer().export_traces().await?;
+    write(&dir.join(TRACES_FILE), traces.as_bytes())?;
+    let stats = serde_json::to_vec_pretty(&executor.stats().saved())
+        .context("Failed to serialize stats")?;
+    write(&dir.join(STATS_FILE), &stats)
+}
+
+/// Load what `save` wrote to `dir` into the executor. Missing files are
+/// skipped, so the first start with a new directory restores nothing. If
+/// either file can't be read or parsed, nothing is restored.
+pub async fn restore(dir: &Path, executor: &Executor) -> Result<()> {
+    let stats = match read(&dir.join(STATS_FILE))? {
+        Some(stats) => Some(
+            serde_json::from_str(&stats)
+                .with_context(|| format!("Invalid stats in {}", dir.display()))?,
+        ),
+        None => None,
+    };
+    if let Some(traces) = read(&dir.join(TRACES_FILE))? {
+        let count = executor
+            .tracer()
+            .import_traces(&traces)
+            .await
+            .with_context(|| format!("Invalid traces in {}", dir.display()))?;
+        tracing::info!("? Restored {} traces", count);
+    }
+    if let Some(stats) = stats {
+        executor.stats().restore(stats);
+        tracing::info!("? Restored execution stats");
+    }
+    Ok(())
+}
+
+fn write(path: &Path, data: &[u8]) -> Result<()> {
+    let mut temp_path = path.as_os_str().to_owned();
+    temp_path.push(".tmp");
+    std::fs::write(&temp_path, data)
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    std::fs::rename(&temp_path, path).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+fn read(path: &Path) -> Result<Option<String>> {
+    match std::fs::read_to_string(path) {
+        Ok(data) => Ok(Some(data)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    }
+}
@@ -0,0 +1,135 @@
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, OnceLock, Weak};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::broadcast;
+use uuid::Uuid;
+use wasm_shared::StatsSnapshot;
+
+/// Snapshots a slow subscriber may fall behind by before it starts missing some
+const SNAPSHOT_BACKLOG: usize = 16;
+
+/// The counters that outlive a restart: executions still active when the
+/// server stopped are not carried over
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct SavedStats {
+    pub total: u64,
+    pub per_binary: HashMap<Uuid, u64>,
+}
+
+/// Live execution counters, updated by the executor and published as
+/// periodic `StatsSnapshot`s to subscribers
+pub struct ExecutionStats {
+    active: AtomicU64,
+    total: AtomicU64,
+    per_binary: DashMap<Uuid, u64>,
+    snapshots: broadcast::Sender<StatsSnapshot>,
+    /// Set once the task publishing snapshots has been spawned
+    publisher: OnceLock<()>,
+}
+
+impl ExecutionStats {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self {
+            active: AtomicU64::new(0),
+            total: AtomicU64::new(0),
+            per_binary: DashMap::new(),
+            snapshots: broadcast::channel(SNAPSHOT_BACKLOG).0,
+            publisher: OnceLock::new(),
+        })
+    }
+
+    /// Count an execution of `binary_id` as started; it counts as active
+    /// until the returned guard is dropped
+    pub fn start(self: &Arc<Self>, binary_id: Uuid) -> ActiveExecution {
+        self.active.fetch_add(1, Ordering::Relaxed);
+        self.total.fetch_add(1, Ordering::Relaxed);
+        *self.per_binary.entry(binary_id).or_insert(0) += 1;
+        ActiveExecution {
+            stats: Arc::clone(self),
+        }
+    }
+
+    /// Receive a snapshot every `interval`. The first subscriber starts the
+    /// publishing task, which runs at that interval until the stats are dropped.
+    pub fn subscribe(self: &Arc<Self>, interval: Duration) -> broadcast::Receiver<StatsSnapshot> {
+        let receiver = self.snapshots.subscribe();
+        self.publisher.get_or_init(|| {
+            tokio::spawn(publish(Arc::downgrade(self), interval));
+        });
+        receiver
+    }
+
+    /// The totals, to be written out at shutdown
+    pub fn saved(&self) -> SavedStats {
+        SavedStats {
+            total: self.total.load(Ordering::Relaxed),
+            per_binary: self
+                .per_binary
+                .iter()
+                .map(|entry| (*entry.key(), *entry.value()))
+                .collect(),
+        }
+    }
+
+    /// Add totals saved by a previous run to the current ones
+    pub fn restore(&self, saved: SavedStats) {
+        self.total.fetch_add(saved.total, Ordering::Relaxed);
+        for (binary_id, count) in saved.per_binary {
+            *self.per_binary.entry(binary_id).or_insert(0) += count;
+        }
+    }
+
+    fn snapshot(&self, executions_per_sec: f64) -> StatsSnapshot {
+        StatsSnapshot {
+            timestamp_ms: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis() as u64,
+            executions_per_sec,
+            active: self.active.load(Ordering::Relaxed),
+            total: self.total.load(Ordering::Relaxed),
+            per_binary: self
+                .per_binary
+                .iter()
+                .map(|entry| (*entry.key(), *entry.value()))
+                .collect(),
+        }
+    }
+}
+
+/// Marks an execution as no longer active when dropped, however it ends
+pub struct ActiveExecution {
+    stats: Arc<ExecutionStats>,
+}
+
+impl Drop for ActiveExecution {
+    fn drop(&mut self) {
+        self.stats.active.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+async fn publish(stats: Weak<ExecutionStats>, interval: Duration) {
+    let mut ticker = tokio::time::interval(interval);
+    ticker.tick().await;
+    let mut last_tick = Instant::now();
+    let mut last_total = match stats.upgrade() {
+        Some(stats) => stats.total.load(Ordering::Relaxed),
+        None => return,
+    };
+    loop {
+        ticker.tick().await;
+        let Some(stats) = stats.upgrade() else {
+            return;
+        };
+        let total = stats.total.load(Ordering::Relaxed);
+        let elapsed = last_tick.elapsed().as_secs_f64();
+        let rate = (total - last_total) as f64 / elapsed;
+        last_tick = Instant::now();
+        last_total = total;
+        // No subscribers is fine; a later one gets the next snapshot
+        let _ = stats.snapshots.send(stats.snapshot(rate));
+    }
+}
@@ -3,56 +3,70 @@
 
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tokio::sync::RwLock;
 use uuid::Uuid;
+use wasm_shared::{Encoding, EnvVars, ExecutionConfig, TraceEventRecord, TraceQuery, TraceRecord};
+
+pub use wasm_shared::TraceEventType;
 
 /// Represents a trace event during WASM execution
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TraceEvent {
+    /// Microseconds since the trace started
     pub timestamp: u64,
+    /// Microseconds since the previous event (since the start for the first)
+    #[serde(default)]
+    pub delta_us: u64,
     pub event_type: TraceEventType,
     pub binary_id: Uuid,
     pub message: String,
     pub metadata: Option<serde_json::Value>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub enum TraceEventType {
-    LoadStart,
-    LoadComplete,
-    LoadError,
-    ExecutionStart,
-    ExecutionComplete,
-    ExecutionError,
-    FunctionCall,
-    HostFunctionCall,
-    MemoryOp,
-    FuelCheckpoint,
-    PluginLog,
+/// What an execution was asked to do, kept so it can be replayed
+#[derive(Debug, Clone)]
+pub struct RecordedRequest {
+    /// The input as received, before preprocessing and hooks
+    pub input: Vec<u8>,
+    pub env: EnvVars,
+    pub config: ExecutionConfig,
+    pub encoding: Encoding,
 }
 
 /// Execution trace containing all events for a single execution
 #[derive(Debug, Clone)]
 pub struct ExecutionTrace {
+    pub trace_id: Uuid,
     pub binary_id: Uuid,
+    /// Set from the request so callers can find the trace of their execution
+    pub correlation_id: Option<String>,
     pub start_time: Instant,
     pub end_time: Option<Instant>,
     pub events: Vec<TraceEvent>,
     pub success: bool,
     pub error_message: Option<String>,
+    /// Set by the executor; `None` for traces built by hand
+    pub request: Option<RecordedRequest>,
+    /// The plugin's return code, once it returned one
+    pub return_code: Option<i32>,
 }
 
 impl ExecutionTrace {
     pub fn new(binary_id: Uuid) -> Self {
         Self {
+            trace_id: Uuid::new_v4(),
             binary_id,
+            correlation_id: None,
             start_time: Instant::now(),
             end_time: None,
             events: Vec::new(),
             success: false,
             error_message: None,
+            request: None,
+            return_code: None,
         }
     }
 
@@ -63,8 +77,10 @@ impl ExecutionTrace {
         metadata: Option<serde_json::Value>,
     ) {
         let timestamp = self.start_time.elapsed().as_micros() as u64;
+        let previous = self.events.last().map_or(0, |e| e.timestamp);
         self.events.push(TraceEvent {
             timestamp,
+            delta_us: timestamp - previous,
             event_type,
             binary_id: self.binary_id,
             message,
@@ -87,7 +103,11 @@ impl ExecutionTrace {
 
     pub fn print(&self) {
         println!("\n=== Execution Trace ===");
+        println!("Trace ID: {}", self.trace_id);
         println!("Binary ID: {}", self.binary_id);
+        if let Some(correlation_id) = &self.correlation_id {
+            println!("Correlation ID: {}", correlation_id);
+        }
         println!("Duration: {:?}", self.duration());
         println!("Success: {}", self.success);
         if let Some(err) = &self.error_message {
@@ -96,8 +116,8 @@ impl ExecutionTrace {
         println!("\nEvents:");
         for event in &self.events {
             println!(
-                "  [{:>10}μs] {:?}: {}",
-                event.timestamp, event.event_type, event.message
+                "  [{:>10}μs +{:>8}μs] {:?}: {}",
+                event.timestamp, event.delta_us, event.event_type, event.message
             );
             if let Some(meta) = &event.metadata {
                 println!(
@@ -109,10 +129,86 @@ impl ExecutionTrace {
         println!("=====================\n");
     }
 
+    /// Whether this trace satisfies every criterion set in `query`
+    pub fn matches(&self, query: &TraceQuery) -> bool {
+        if query.only_failures && self.success {
+            return false;
+        }
+        if query.binary_id.is_some_and(|id| id != self.binary_id) {
+            return false;
+        }
+        if query.correlation_id.is_some() && query.correlation_id != self.correlation_id {
+            return false;
+        }
+        query.event_types.is_empty()
+            || self
+                .events
+                .iter()
+                .any(|e| query.event_types.contains(&e.event_type))
+    }
+
+    /// The wire form sent to clients
+    pub fn to_record(&self) -> TraceRecord {
+        TraceRecord {
+            trace_id: self.trace_id,
+            binary_id: self.binary_id,
+            correlation_id: self.correlation_id.clone(),
+            duration_ms: self.duration().as_millis() as u64,
+            success: self.success,
+            error_message: self.error_message.clone(),
+            events: self
+                .events
+                .iter()
+                .map(|e| TraceEventRecord {
+                    timestamp: e.timestamp,
+                    delta_us: e.delta_us,
+                    event_type: e.event_type,
+                    message: e.message.clone(),
+                    metadata: e.metadata.as_ref().map(|m| m.to_string()),
+                })
+                .collect(),
+        }
+    }
+
+    /// Render the trace in folded-stack format for flamegraph tools. The time
+    /// leading up to each event becomes a sample of `execute;<event type>`,
+    /// and the time after the last event a sample of `execute` itself, so the
+    /// counts (microseconds) add up to the trace's duration.
+    pub fn to_folded_stacks(&self) -> String {
+        let mut stacks: Vec<(String, u64)> = Vec::new();
+        let mut add = |stack: String, micros: u64| {
+            if micros == 0 {
+                return;
+            }
+            match stacks.iter_mut().find(|(s, _)| *s == stack) {
+                Some((_, total)) => *total += micros,
+                None => stacks.push((stack, micros)),
+            }
+        };
+        for event in &self.events {
+            add(
+                format!("execute;{}", frame_name(event.event_type)),
+                event.delta_us,
+            );
+        }
+        let last = self.events.last().map_or(0, |e| e.timestamp);
+        add(
+            "execute".to_string(),
+            (self.duration().as_micros() as u64).saturating_sub(last),
+        );
+
+        stacks
+            .into_iter()
+            .map(|(stack, micros)| format!("{} {}\n", stack, micros))
+            .collect()
+    }
+
     pub fn to_json(&self) -> Result<String> {
         // Convert to a serializable format
         let serializable = serde_json::json!({
+            "trace_id": self.trace_id,
             "binary_id": self.binary_id,
+            "correlation_id": self.correlation_id,
             "duration_ms": self.duration().as_millis(),
             "success": self.success,
             "error_message": self.error_message,
@@ -122,11 +218,32 @@ impl ExecutionTrace {
     }
 }
 
+/// Flamegraph frame for the span ending in an event of `event_type`
+fn frame_name(event_type: TraceEventType) -> &'static str {
+    match event_type {
+        TraceEventType::LoadStart => "load_start",
+        TraceEventType::LoadComplete => "load_complete",
+        TraceEventType::LoadError => "load_error",
+        TraceEventType::ExecutionStart => "execution_start",
+        TraceEventType::ExecutionComplete => "execution_complete",
+        TraceEventType::ExecutionError => "execution_error",
+        TraceEventType::FunctionCall => "function_call",
+        TraceEventType::HostFunctionCall => "host_function_call",
+        TraceEventType::MemoryOp => "memory_op",
+        TraceEventType::FuelCheckpoint => "fuel_checkpoint",
+        TraceEventType::PluginLog => "plugin_log",
+    }
+}
+
 /// Tracer manages execution traces
 pub struct Tracer {
     traces: Arc<RwLock<Vec<ExecutionTrace>>>,
     max_traces: usize,
     enabled: bool,
+    /// Fraction of executions that get a trace, from 0.0 to 1.0
+    sample_rate: f64,
+    /// Executions seen by `start_trace`, to pick the sampled ones
+    started: Arc<AtomicU64>,
 }
 
 impl Tracer {
@@ -135,9 +252,18 @@ impl Tracer {
             traces: Arc::new(RwLock::new(Vec::new())),
             max_traces,
             enabled,
+            sample_rate: 1.0,
+            started: Arc::new(AtomicU64::new(0)),
         }
     }
 
+    /// Trace only a `rate` fraction of executions, clamped to 0.0..=1.0.
+    /// Sampling is deterministic: with 0.25 every fourth execution is traced.
+    pub fn with_sample_rate(mut self, rate: f64) -> Self {
+        self.sample_rate = rate.clamp(0.0, 1.0);
+        self
+    }
+
     pub fn is_enabled(&self) -> bool {
         self.enabled
     }
@@ -147,12 +273,22 @@ impl Tracer {
     }
 
     pub async fn start_trace(&self, binary_id: Uuid) -> Option<ExecutionTrace> {
-        if !self.enabled {
+        if !self.enabled || !self.sampled() {
             return None;
         }
         Some(ExecutionTrace::new(binary_id))
     }
 
+    /// Whether the next execution should be traced: true each time the
+    /// running count times the rate crosses a whole number
+    fn sampled(&self) -> bool {
+        if self.sample_rate >= 1.0 {
+            return true;
+        }
+        let n = self.started.fetch_add(1, Ordering::Relaxed) as f64;
+        ((n + 1.0) * self.sample_rate).floor() > (n * self.sample_rate).floor()
+    }
+
     pub async fn complete_trace(&self, trace: ExecutionTrace) {
         if !self.enabled {
             return;
@@ -172,6 +308,17 @@ impl Tracer {
         self.traces.read().await.clone()
     }
 
+    /// Clones of the stored traces matching `query`, oldest first
+    pub async fn query(&self, query: &TraceQuery) -> Vec<ExecutionTrace> {
+        self.traces
+            .read()
+            .await
+            .iter()
+            .filter(|t| t.matches(query))
+            .cloned()
+            .collect()
+    }
+
     pub async fn get_trace(&self, binary_id: Uuid) -> Option<ExecutionTrace> {
         self.traces
             .read()
@@ -182,6 +329,16 @@ impl Tracer {
             .cloned()
     }
 
+    /// The stored trace with ID `trace_id`
+    pub async fn find(&self, trace_id: Uuid) -> Option<ExecutionTrace> {
+        self.traces
+            .read()
+            .await
+            .iter()
+            .find(|t| t.trace_id == trace_id)
+            .cloned()
+    }
+
     pub async fn clear_traces(&self) {
         self.traces.write().await.clear();
     }
@@ -196,19 +353,72 @@ impl Tracer {
 
     pub async fn export_traces(&self) -> Result<String> {
         let traces = self.traces.read().await;
-        let serializable: Vec<serde_json::Value> = traces
-            .iter()
-            .map(|t| {
-                serde_json::json!({
-                    "binary_id": t.binary_id,
-                    "duration_ms": t.duration().as_millis(),
-                    "success": t.success,
-                    "error_message": t.error_message,
-                    "events": t.events,
-                })
-            })
-            .collect();
-        Ok(serde_json::to_string_pretty(&serializable)?)
+        let exported: Vec<ExportedTrace> = traces.iter().map(ExportedTrace::from).collect();
+        Ok(serde_json::to_string_pretty(&exported)?)
+    }
+
+    /// Add traces written by `export_traces` ahead of the ones already
+    /// stored, keeping the most recent `max_traces`. Their instants are
+    /// rebuilt to end now, and they can't be replayed since the request
+    /// isn't exported. Returns how many were added.
+    pub async fn import_traces(&self, json: &str) -> Result<usize> {
+        let exported: Vec<ExportedTrace> = serde_json::from_str(json)?;
+        let count = exported.len();
+        let mut traces = self.traces.write().await;
+        let current = std::mem::take(&mut *traces);
+        let mut all: Vec<ExecutionTrace> = exported.into_iter().map(ExecutionTrace::from).collect();
+        all.extend(current);
+        let excess = all.len().saturating_sub(self.max_traces);
+        *traces = all.split_off(excess);
+        Ok(count)
+    }
+}
+
+/// A trace as `export_traces` writes it
+#[derive(Serialize, Deserialize)]
+struct ExportedTrace {
+    trace_id: Uuid,
+    binary_id: Uuid,
+    correlation_id: Option<String>,
+    duration_ms: u64,
+    success: bool,
+    error_message: Option<String>,
+    #[serde(default)]
+    return_code: Option<i32>,
+    events: Vec<TraceEvent>,
+}
+
+impl From<&ExecutionTrace> for ExportedTrace {
+    fn from(trace: &ExecutionTrace) -> Self {
+        Self {
+            trace_id: trace.trace_id,
+            binary_id: trace.binary_id,
+            correlation_id: trace.correlation_id.clone(),
+            duration_ms: trace.duration().as_millis() as u64,
+            success: trace.success,
+            error_message: trace.error_message.clone(),
+            return_code: trace.return_code,
+            events: trace.events.clone(),
+        }
+    }
+}
+
+impl From<ExportedTrace> for ExecutionTrace {
+    fn from(trace: ExportedTrace) -> Self {
+        let end_time = Instant::now();
+        let duration = Duration::from_millis(trace.duration_ms);
+        Self {
+            trace_id: trace.trace_id,
+            binary_id: trace.binary_id,
+            correlation_id: trace.correlation_id,
+            start_time: end_time.checked_sub(duration).unwrap_or(end_time),
+            end_time: Some(end_time),
+            events: trace.events,
+            success: trace.success,
+            error_message: trace.error_message,
+            request: None,
+            return_code: trace.return_code,
+        }
     }
 }
 
@@ -224,6 +434,8 @@ impl Clone for Tracer {
             traces: Arc::clone(&self.traces),
             max_traces: self.max_traces,
             enabled: self.enabled,
+            sample_rate: self.sample_rate,
+            started: Arc::clone(&self.started),
         }
     }
 }
@@ -257,4 +469,120 @@ mod tests {
         assert_eq!(traces[0].binary_id, binary_id);
         assert_eq!(traces[0].events.len(), 2);
     }
+
+    #[test]
+    fn test_event_deltas_sum_to_last_timestamp() {
+        let mut trace = ExecutionTrace::new(Uuid::new_v4());
+        for _ in 0..3 {
+            std::thread::sleep(Duration::from_millis(2));
+            trace.add_event(TraceEventType::FunctionCall, "step".to_string(), None);
+        }
+
+        let total: u64 = trace.events.iter().map(|e| e.delta_us).sum();
+        assert_eq!(total, trace.events.last().unwrap().timestamp);
+        assert!(trace.events.iter().all(|e| e.delta_us >= 2000));
+
+        let json: serde_json::Value = serde_json::from_str(&trace.to_json().unwrap()).unwrap();
+        assert_eq!(json["events"][1]["delta_us"], trace.events[1].delta_us);
+    }
+
+    #[tokio::test]
+    async fn test_query_returns_only_failures() {
+        let tracer = Tracer::new(true, 10);
+        let failing_id = Uuid::new_v4();
+        for (binary_id, success) in [
+            (Uuid::new_v4(), true),
+            (failing_id, false),
+            (Uuid::new_v4(), true),
+            (Uuid::new_v4(), false),
+        ] {
+            let mut trace = tracer.start_trace(binary_id).await.unwrap();
+            let (event_type, error) = match success {
+                true => (TraceEventType::ExecutionComplete, None),
+                false => (TraceEventType::ExecutionError, Some("trap".to_string())),
+            };
+            trace.add_event(event_type, "done".to_string(), None);
+            trace.complete(success, error);
+            tracer.complete_trace(trace).await;
+        }
+
+        let failures = tracer
+            .query(&TraceQuery {
+                only_failures: true,
+                ..Default::default()
+            })
+            .await;
+        assert_eq!(failures.len(), 2);
+        assert!(failures.iter().all(|t| !t.success));
+
+        let with_errors = tracer
+            .query(&TraceQuery {
+                event_types: vec![TraceEventType::ExecutionError],
+                ..Default::default()
+            })
+            .await;
+        assert_eq!(with_errors.len(), 2);
+
+        let one_binary = tracer
+            .query(&TraceQuery {
+                only_failures: true,
+                binary_id: Some(failing_id),
+                ..Default::default()
+            })
+            .await;
+        assert_eq!(one_binary.len(), 1);
+        assert_eq!(one_binary[0].binary_id, failing_id);
+
+        assert_eq!(tracer.query(&TraceQuery::default()).await.len(), 4);
+    }
+
+    #[test]
+    fn test_folded_stacks_add_up_to_duration() {
+        let mut trace = ExecutionTrace::new(Uuid::new_v4());
+        for event_type in [
+            TraceEventType::ExecutionStart,
+            TraceEventType::FunctionCall,
+            TraceEventType::FunctionCall,
+            TraceEventType::ExecutionComplete,
+        ] {
+            std::thread::sleep(Duration::from_millis(1));
+            trace.add_event(event_type, "step".to_string(), None);
+        }
+        std::thread::sleep(Duration::from_millis(1));
+        trace.complete(true, None);
+
+        let folded = trace.to_folded_stacks();
+        let mut total = 0;
+        let mut stacks = Vec::new();
+        for line in folded.lines() {
+            let (stack, micros) = line.rsplit_once(' ').unwrap();
+            total += micros.parse::<u64>().unwrap();
+            stacks.push(stack);
+        }
+        assert_eq!(total, trace.duration().as_micros() as u64);
+        assert_eq!(
+            stacks,
+            vec![
+                "execute;execution_start",
+                "execute;function_call",
+                "execute;execution_complete",
+                "execute"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_sample_rate_traces_a_fraction() {
+        let tracer = Tracer::new(true, 10).with_sample_rate(0.5);
+        let mut traced = 0;
+        for _ in 0..1000 {
+            if tracer.start_trace(Uuid::new_v4()).await.is_some() {
+                traced += 1;
+            }
+        }
+        assert!((450..=550).contains(&traced), "traced {}", traced);
+
+        let tracer = Tracer::new(true, 10).with_sample_rate(0.0);
+        assert!(tracer.start_trace(Uuid::new_v4()).await.is_none());
+    }
 }
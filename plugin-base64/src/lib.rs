@@ -0,0 +1,180 @@
+//! Base64 Plugin
+//!
+//! Encodes the input as standard, padded base64, or decodes it back when the
+//! env carries `"mode": "decode"`. The result goes out through
+//! `write_output`.
+//!
+//! Input and env share a 4096 byte buffer and the result is built on the
+//! stack, so input is capped at `MAX_ENCODE_INPUT` bytes when encoding and
+//! `MAX_DECODE_INPUT` characters when decoding (both yield at most 2048
+//! bytes). Larger inputs fail with return code -3.
+
+#![no_std]
+
+use core::panic::PanicInfo;
+use core::slice;
+use core::str;
+use heapless::Vec;
+use serde::Deserialize;
+
+#[link(wasm_import_module = "host")]
+extern "C" {
+    fn log(ptr: *const u8, len: usize);
+    fn write_output(ptr: *const u8, len: usize);
+}
+
+fn log_message(message: &str) {
+    unsafe {
+        log(message.as_ptr(), message.len());
+    }
+}
+
+const IO_BUFFER_SIZE: usize = 4096;
+
+/// Largest input accepted when encoding, in bytes
+const MAX_ENCODE_INPUT: usize = 1536;
+
+/// Largest input accepted when decoding, in base64 characters
+const MAX_DECODE_INPUT: usize = 2048;
+
+const OUTPUT_SIZE: usize = 2048;
+
+/// Scratch region the host writes input and env into, kept clear of the stack
+static mut IO_BUFFER: [u8; IO_BUFFER_SIZE] = [0; IO_BUFFER_SIZE];
+
+/// Reserve `len` bytes for the host's input and env writes
+#[no_mangle]
+pub extern "C" fn alloc(len: usize) -> *mut u8 {
+    if len > IO_BUFFER_SIZE {
+        return core::ptr::null_mut();
+    }
+    core::ptr::addr_of_mut!(IO_BUFFER) as *mut u8
+}
+
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+#[derive(Deserialize)]
+struct Env<'a> {
+    #[serde(default)]
+    mode: Option<&'a str>,
+}
+
+fn encode(input: &[u8], out: &mut Vec<u8, OUTPUT_SIZE>) -> Result<(), ()> {
+    for chunk in input.chunks(3) {
+        let b = [
+            chunk[0],
+            chunk.get(1).copied().unwrap_or(0),
+            chunk.get(2).copied().unwrap_or(0),
+        ];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+        for i in 0..4 {
+            let c = if i <= chunk.len() {
+                ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize]
+            } else {
+                b'='
+            };
+            out.push(c).map_err(|_| ())?;
+        }
+    }
+    Ok(())
+}
+
+fn decode_char(c: u8) -> Option<u32> {
+    match c {
+        b'A'..=b'Z' => Some((c - b'A') as u32),
+        b'a'..=b'z' => Some((c - b'a' + 26) as u32),
+        b'0'..=b'9' => Some((c - b'0' + 52) as u32),
+        b'+' => Some(62),
+        b'/' => Some(63),
+        _ => None,
+    }
+}
+
+/// Decode padded or unpadded base64, skipping ASCII whitespace
+fn decode(input: &[u8], out: &mut Vec<u8, OUTPUT_SIZE>) -> Result<(), ()> {
+    let mut acc: u32 = 0;
+    let mut bits = 0;
+    let mut padding = 0;
+    for &c in input.iter().filter(|c| !c.is_ascii_whitespace()) {
+        if c == b'=' {
+            padding += 1;
+            continue;
+        }
+        if padding > 0 {
+            return Err(());
+        }
+        acc = acc << 6 | decode_char(c).ok_or(())?;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((acc >> bits) as u8).map_err(|_| ())?;
+            acc &= (1 << bits) - 1;
+        }
+    }
+    if padding > 2 || bits >= 6 {
+        return Err(());
+    }
+    Ok(())
+}
+
+#[no_mangle]
+pub extern "C" fn process(
+    input_ptr: *const u8,
+    input_len: usize,
+    env_ptr: *const u8,
+    env_len: usize,
+) -> i32 {
+    let input = unsafe { slice::from_raw_parts(input_ptr, input_len) };
+    let env_slice = unsafe { slice::from_raw_parts(env_ptr, env_len) };
+
+    let decoding = match serde_json_core::from_slice::<Env>(env_slice) {
+        Ok((env, _)) => env.mode == Some("decode"),
+        Err(_) => {
+            log_message("[Base64] ERROR - Failed to parse env JSON");
+            return -2;
+        }
+    };
+
+    let mut out: Vec<u8, OUTPUT_SIZE> = Vec::new();
+    if decoding {
+        log_message("[Base64] Decoding");
+        if input.len() > MAX_DECODE_INPUT {
+            log_message("[Base64] ERROR - Input too large");
+            return -3;
+        }
+        if decode(input, &mut out).is_err() {
+            log_message("[Base64] ERROR - Invalid base64 input");
+            return -1;
+        }
+    } else {
+        log_message("[Base64] Encoding");
+        if input.len() > MAX_ENCODE_INPUT || encode(input, &mut out).is_err() {
+            log_message("[Base64] ERROR - Input too large");
+            return -3;
+        }
+    }
+
+    unsafe {
+        write_output(out.as_ptr(), out.len());
+    }
+    log_message("[Base64] Done");
+    0
+}
+
+#[panic_handler]
+fn panic(_info: &PanicInfo) -> ! {
+    log_message("[Base64] PANIC occurred!");
+    loop {}
+}
+
+#[global_allocator]
+static ALLOCATOR: DummyAllocator = DummyAllocator;
+
+struct DummyAllocator;
+
+unsafe impl core::alloc::GlobalAlloc for DummyAllocator {
+    unsafe fn alloc(&self, _layout: core::alloc::Layout) -> *mut u8 {
+        core::ptr::null_mut()
+    }
+    unsafe fn dealloc(&self, _ptr: *mut u8, _layout: core::alloc::Layout) {}
+}
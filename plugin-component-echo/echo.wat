@@ -0,0 +1,51 @@
+;; Echo Component Plugin
+;;
+;; A minimal component-model plugin, written in the text format so it can be
+;; built without extra tooling (`wasm-tools parse echo.wat -o echo.wasm`, or
+;; loaded directly by anything using the `wat` crate).
+;;
+;; Host bindings expected by wasm-core for components:
+;;   import log: func(message: string)
+;;   export process: func(input: string, env: string) -> tuple<s32, string>
+;;
+;; The returned tuple is (return code, output). This plugin logs a greeting
+;; and returns its input unchanged.
+(component
+  (import "log" (func $log (param "message" string)))
+
+  ;; Memory and allocator live in their own instance so the lowered `log`
+  ;; import can refer to the memory before the main module is instantiated
+  (core module $memory
+    (memory (export "memory") 1)
+    (global $bump (mut i32) (i32.const 4096))
+    (func (export "realloc") (param i32 i32 i32 i32) (result i32)
+      (local $ptr i32)
+      (local.set $ptr (global.get $bump))
+      (global.set $bump (i32.add (global.get $bump) (local.get 3)))
+      (local.get $ptr)))
+  (core instance $mem (instantiate $memory))
+
+  (core func $log_lowered (canon lower (func $log) (memory (core memory $mem "memory"))))
+
+  (core module $plugin
+    (import "host" "log" (func $log (param i32 i32)))
+    (import "env" "memory" (memory 1))
+    (data (i32.const 512) "[Echo] component ready")
+    (func (export "process") (param $input i32) (param $input_len i32)
+                             (param $env i32) (param $env_len i32) (result i32)
+      (call $log (i32.const 512) (i32.const 22))
+      ;; Return area at offset 16: (s32 code, string ptr, string len)
+      (i32.store (i32.const 16) (i32.const 0))
+      (i32.store (i32.const 20) (local.get $input))
+      (i32.store (i32.const 24) (local.get $input_len))
+      (i32.const 16)))
+  (core instance $main
+    (instantiate $plugin
+      (with "host" (instance (export "log" (func $log_lowered))))
+      (with "env" (instance $mem))))
+
+  (func (export "process") (param "input" string) (param "env" string) (result (tuple s32 string))
+    (canon lift (core func $main "process")
+      (memory (core memory $mem "memory"))
+      (realloc (core func $mem "realloc"))))
+)
@@ -1,91 +1,60 @@
-use core::slice;
-use core::str;
-use heapless::{String, Vec};
+//! Counter Plugin
+//!
+//! Counts the characters, letters, digits and whitespace in the input and
+//! writes a one-line summary as the output. The running character count is
+//! kept in the scratch buffer, so a chain that persists host state logs how
+//! many characters its steps counted together.
 
-#[link(wasm_import_module = "host")]
-extern "C" {
-    fn log(ptr: *const u8, len: usize);
-}
+#![no_std]
 
-fn log_message(message: &str) {
-    unsafe {
-        log(message.as_ptr(), message.len());
-    }
-}
+use heapless::String;
+use wasm_shared::plugin_helpers::{
+    log_message, push_u64, read_scratch, read_str, write_result, write_scratch, DummyAllocator,
+};
+use wasm_shared::{log, plugin_io_buffer, plugin_panic_handler};
+
+plugin_io_buffer!(4096);
 
-/// # Safety
-/// - This function expects valid pointers and lengths as inputs.
-/// - Callers must ensure that `input_ptr` points to a readable memory region
-/// - of at least `input_len` bytes.
 #[no_mangle]
-pub unsafe extern "C" fn process(
-    input_ptr: *const u8,
-    input_len: usize,
-    _: *const u8,
-    _: usize,
-) -> i32 {
-    log_message("[Counter] Starting character count");
-    let input_slice = unsafe { slice::from_raw_parts(input_ptr, input_len) };
-    // Normalize the input (e.g., trim whitespaces, remove delimiters)
-    let input_normalized: Vec<u8, 1024> =
-        input_slice.iter().filter(|&&c| c != 0).cloned().collect();
-    log_message("[Counter] Input normalized. Input content:");
-    log_message(str::from_utf8(&input_normalized).unwrap_or("Invalid UTF-8"));
-    let input_str = match str::from_utf8(input_slice) {
+pub extern "C" fn process(input_ptr: *const u8, input_len: usize, _: *const u8, _: usize) -> i32 {
+    log!("[Counter] Starting character count");
+    // The host passes `input_len` bytes at `input_ptr`, untouched until we return
+    let input_str = match unsafe { read_str(input_ptr, input_len) } {
         Ok(s) => s,
-        Err(_) => {
-            log_message("[Counter] ERROR - Invalid UTF-8 input");
-            return -1;
+        Err(code) => {
+            log!("[Counter] ERROR - Invalid input (code {})", code);
+            return code;
         }
     };
+    log!("[Counter] Input content:");
+    log_message(input_str);
 
     let total_chars = input_str.chars().count();
     let letters = input_str.chars().filter(|c| c.is_alphabetic()).count();
     let digits = input_str.chars().filter(|c| c.is_numeric()).count();
     let spaces = input_str.chars().filter(|c| c.is_whitespace()).count();
-    log_message("[Counter] Analysis complete");
+    log!("[Counter] Analysis complete");
+
+    let mut counted = [0u8; 8];
+    read_scratch(&mut counted);
+    let counted = u64::from_le_bytes(counted) + total_chars as u64;
+    write_scratch(&counted.to_le_bytes());
+    log!("[Counter] Characters counted so far: {}", counted);
+
     let mut output: String<256> = String::new();
-    log_message("[Counter] Memory check: Output buffer successfully created.");
     let _ = output.push_str("Total: ");
-    append_number(&mut output, total_chars);
+    let _ = push_u64(&mut output, total_chars as u64);
     let _ = output.push_str(" | Letters: ");
-    append_number(&mut output, letters);
+    let _ = push_u64(&mut output, letters as u64);
     let _ = output.push_str(" | Digits: ");
-    append_number(&mut output, digits);
+    let _ = push_u64(&mut output, digits as u64);
     let _ = output.push_str(" | Spaces: ");
-    append_number(&mut output, spaces);
-    log_message("[Counter] Result = ");
-    log_message(output.as_str());
+    let _ = push_u64(&mut output, spaces as u64);
+    write_result(output.as_str());
     0
 }
 
-fn append_number(s: &mut String<256>, n: usize) {
-    let mut buffer = [0u8; 20];
-    let mut i = 0;
-    let mut num = n;
-    if num == 0 {
-        let _ = s.push('0');
-        return;
-    }
-    while num > 0 {
-        buffer[i] = (num % 10) as u8 + b'0';
-        num /= 10;
-        i += 1;
-    }
-    while i > 0 {
-        i -= 1;
-        let _ = s.push(buffer[i] as char);
-    }
-}
+plugin_panic_handler!("Counter");
 
 #[global_allocator]
 static ALLOCATOR: DummyAllocator = DummyAllocator;
-
-struct DummyAllocator;
-
-unsafe impl core::alloc::GlobalAlloc for DummyAllocator {
-    unsafe fn alloc(&self, _layout: core::alloc::Layout) -> *mut u8 {
-        core::ptr::null_mut()
-    }
-    unsafe fn dealloc(&self, _ptr: *mut u8, _layout: core::alloc::Layout) {}
-}
@@ -0,0 +1,90 @@
+//! CRC-32 Plugin
+//!
+//! Computes the CRC-32 (IEEE, the one `zlib` and `gzip` use) of the input
+//! and writes it as 8 hex digits followed by the input length, e.g.
+//! `cbf43926 9` for `123456789`.
+//!
+//! The input is pulled from the host a buffer at a time through
+//! `read_input_chunk` instead of being written into the plugin's memory, so
+//! it can be far larger than the 64 KiB page the plugin runs in.
+
+#![no_std]
+
+use heapless::String;
+use wasm_shared::plugin_helpers::{
+    push_u64, read_input_chunk, write_result, DummyAllocator, PluginResult, ERROR_BUFFER_OVERFLOW,
+    ERROR_INVALID_INPUT,
+};
+use wasm_shared::{log, plugin_io_buffer, plugin_panic_handler};
+
+/// Bytes read from the host per `read_input_chunk` call
+const CHUNK_SIZE: usize = 4096;
+
+/// Reversed IEEE 802.3 polynomial
+const POLYNOMIAL: u32 = 0xedb8_8320;
+
+const HEX_DIGITS: &[u8; 16] = b"0123456789abcdef";
+
+static mut CHUNK: [u8; CHUNK_SIZE] = [0; CHUNK_SIZE];
+
+/// Fold `data` into a running CRC, kept inverted between calls
+fn update(mut crc: u32, data: &[u8]) -> u32 {
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (POLYNOMIAL & mask);
+        }
+    }
+    crc
+}
+
+fn format_result(crc: u32, len: u64) -> PluginResult<String<32>> {
+    let mut result = String::new();
+    for shift in (0..8).rev() {
+        let digit = (crc >> (shift * 4)) & 0x0f;
+        result
+            .push(HEX_DIGITS[digit as usize] as char)
+            .map_err(|_| ERROR_BUFFER_OVERFLOW)?;
+    }
+    result.push(' ').map_err(|_| ERROR_BUFFER_OVERFLOW)?;
+    push_u64(&mut result, len)?;
+    Ok(result)
+}
+
+// Only the env is written here; the input arrives through `read_input_chunk`
+plugin_io_buffer!(4096);
+
+#[no_mangle]
+pub extern "C" fn process(_: *const u8, input_len: usize, _: *const u8, _: usize) -> i32 {
+    log!("[CRC-32] Reading {} bytes", input_len);
+
+    let chunk = unsafe { &mut *core::ptr::addr_of_mut!(CHUNK) };
+    let mut crc = !0u32;
+    let mut total: u64 = 0;
+    loop {
+        let read = read_input_chunk(chunk);
+        if read == 0 {
+            break;
+        }
+        crc = update(crc, &chunk[..read]);
+        total += read as u64;
+    }
+    if total != input_len as u64 {
+        log!("[CRC-32] Read {} bytes, expected {}", total, input_len);
+        return ERROR_INVALID_INPUT;
+    }
+
+    match format_result(!crc, total) {
+        Ok(result) => {
+            write_result(result.as_str());
+            0
+        }
+        Err(code) => code,
+    }
+}
+
+plugin_panic_handler!("CRC-32");
+
+#[global_allocator]
+static ALLOCATOR: DummyAllocator = DummyAllocator;
@@ -24,6 +24,20 @@ fn log_message(message: &str) {
     }
 }
 
+const IO_BUFFER_SIZE: usize = 4096;
+
+/// Scratch region the host writes input and env into, kept clear of the stack
+static mut IO_BUFFER: [u8; IO_BUFFER_SIZE] = [0; IO_BUFFER_SIZE];
+
+/// Reserve `len` bytes for the host's input and env writes
+#[no_mangle]
+pub extern "C" fn alloc(len: usize) -> *mut u8 {
+    if len > IO_BUFFER_SIZE {
+        return core::ptr::null_mut();
+    }
+    core::ptr::addr_of_mut!(IO_BUFFER) as *mut u8
+}
+
 #[no_mangle]
 pub extern "C" fn process(_: *const u8, _: usize, env_ptr: *const u8, env_len: usize) -> i32 {
     log_message("[Env-Reader] Starting conversion");
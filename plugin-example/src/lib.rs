@@ -14,6 +14,7 @@ The host implements this function in the "host" module
 #[link(wasm_import_module = "host")]
 extern "C" {
     fn log(ptr: *const u8, len: usize);
+    fn write_output(ptr: *const u8, len: usize);
 }
 
 /// Helper function to safely log messages from the plugin
@@ -23,6 +24,27 @@ fn log_message(message: &str) {
     }
 }
 
+const IO_BUFFER_SIZE: usize = 4096;
+
+/// Scratch region the host writes input and env into, kept clear of the stack
+static mut IO_BUFFER: [u8; IO_BUFFER_SIZE] = [0; IO_BUFFER_SIZE];
+
+/// Reserve `len` bytes for the host's input and env writes
+#[no_mangle]
+pub extern "C" fn alloc(len: usize) -> *mut u8 {
+    if len > IO_BUFFER_SIZE {
+        return core::ptr::null_mut();
+    }
+    core::ptr::addr_of_mut!(IO_BUFFER) as *mut u8
+}
+
+/// Hand the plugin's result to the host through the output buffer
+fn write_result(result: &str) {
+    unsafe {
+        write_output(result.as_ptr(), result.len());
+    }
+}
+
 /// Main plugin entry point
 #[no_mangle]
 pub extern "C" fn process(
@@ -50,16 +72,16 @@ pub extern "C" fn process(
             return -1;
         }
     };
-    log_message("[Plugin] Env received: ");
-    // Reverse the string
+    // The env is diagnostic only, so it goes to the log and never into the result
     let mut s = String::<256>::new();
+    if write!(&mut s, "[Plugin] Env received: {}", env).is_ok() {
+        log_message(s.as_str());
+    }
+    // Reverse the string
     let reversed: String<64> = input_str.chars().rev().collect();
-    // Log the result
-    write!(&mut s, "[Plugin]: {} | {}", env, reversed).unwrap();
     log_message("[Plugin]: String reversed successfully");
-    log_message(s.as_str());
-    log_message("[Plugin]: Result = ");
-    log_message(reversed.as_str());
+    // The output buffer is the only channel carrying the result
+    write_result(reversed.as_str());
 
     // Return success code
     0
@@ -0,0 +1,93 @@
+//! Fuel Budget Plugin
+//!
+//! Copies the input to the output one chunk at a time, polling
+//! `host::fuel_remaining` between chunks. When less than `FUEL_RESERVE` is
+//! left it stops, keeps what it copied so far and returns -4 instead of
+//! trapping on an empty tank. The remaining fuel is logged at start and end.
+
+#![no_std]
+
+use core::fmt::Write;
+use core::panic::PanicInfo;
+use core::slice;
+use heapless::String;
+
+#[link(wasm_import_module = "host")]
+extern "C" {
+    fn log(ptr: *const u8, len: usize);
+    fn write_output(ptr: *const u8, len: usize);
+    fn fuel_remaining() -> i64;
+}
+
+fn log_message(message: &str) {
+    unsafe {
+        log(message.as_ptr(), message.len());
+    }
+}
+
+fn log_fuel(label: &str) {
+    let mut line: String<64> = String::new();
+    let _ = write!(line, "[Fuel-Budget] {} fuel: {}", label, unsafe {
+        fuel_remaining()
+    });
+    log_message(&line);
+}
+
+const IO_BUFFER_SIZE: usize = 4096;
+
+/// Bytes copied between fuel checks
+const CHUNK_SIZE: usize = 64;
+
+/// Fuel kept back for logging and returning once the budget runs low
+const FUEL_RESERVE: i64 = 5_000;
+
+/// Scratch region the host writes input and env into, kept clear of the stack
+static mut IO_BUFFER: [u8; IO_BUFFER_SIZE] = [0; IO_BUFFER_SIZE];
+
+/// Reserve `len` bytes for the host's input and env writes
+#[no_mangle]
+pub extern "C" fn alloc(len: usize) -> *mut u8 {
+    if len > IO_BUFFER_SIZE {
+        return core::ptr::null_mut();
+    }
+    core::ptr::addr_of_mut!(IO_BUFFER) as *mut u8
+}
+
+#[no_mangle]
+pub extern "C" fn process(input_ptr: *const u8, input_len: usize, _: *const u8, _: usize) -> i32 {
+    log_fuel("Start");
+
+    let input = unsafe { slice::from_raw_parts(input_ptr, input_len) };
+    let mut code = 0;
+    for chunk in input.chunks(CHUNK_SIZE) {
+        if unsafe { fuel_remaining() } < FUEL_RESERVE {
+            log_message("[Fuel-Budget] Budget exhausted, stopping early");
+            code = -4;
+            break;
+        }
+        unsafe {
+            write_output(chunk.as_ptr(), chunk.len());
+        }
+    }
+
+    log_fuel("End");
+    code
+}
+
+#[panic_handler]
+fn panic(_info: &PanicInfo) -> ! {
+    log_message("[Fuel-Budget] PANIC occurred!");
+    loop {}
+}
+
+#[global_allocator]
+static ALLOCATOR: DummyAllocator = DummyAllocator;
+
+struct DummyAllocator;
+
+unsafe impl core::alloc::GlobalAlloc for DummyAllocator {
+    unsafe fn alloc(&self, _layout: core::alloc::Layout) -> *mut u8 {
+        core::ptr::null_mut()
+    }
+    unsafe fn dealloc(&self, _ptr: *mut u8, _layout: core::alloc::Layout) {}
+}
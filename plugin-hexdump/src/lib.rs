@@ -0,0 +1,105 @@
+//! Hexdump Plugin
+//!
+//! Renders the input bytes the way `hexdump -C` does: 16 bytes per line, an
+//! 8-digit hex offset, the bytes in two groups of eight and the printable
+//! ones between `|` bars, with `.` for the rest. A last line holds the total
+//! length. Unlike `hexdump`, repeated lines are printed rather than folded
+//! into `*`.
+//!
+//! The input is taken as raw bytes, so send it with the `hex` or `base64`
+//! encoding to look at data that isn't UTF-8. `"Hi!\n"` gives:
+//!
+//! ```text
+//! 00000000  48 69 21 0a                                       |Hi!.|
+//! 00000004
+//! ```
+
+#![no_std]
+
+use core::slice;
+use heapless::String;
+use wasm_shared::plugin_helpers::{
+    write_result, DummyAllocator, PluginResult, ERROR_BUFFER_OVERFLOW,
+};
+use wasm_shared::{log, plugin_io_buffer, plugin_panic_handler};
+
+const BYTES_PER_LINE: usize = 16;
+
+/// Offset, two groups of eight bytes, the ASCII column and the newline
+const LINE_SIZE: usize = 80;
+
+const HEX_DIGITS: &[u8; 16] = b"0123456789abcdef";
+
+fn push(line: &mut String<LINE_SIZE>, s: &str) -> PluginResult<()> {
+    line.push_str(s).map_err(|_| ERROR_BUFFER_OVERFLOW)
+}
+
+fn push_hex_byte(line: &mut String<LINE_SIZE>, byte: u8) -> PluginResult<()> {
+    for digit in [byte >> 4, byte & 0x0f] {
+        line.push(HEX_DIGITS[digit as usize] as char)
+            .map_err(|_| ERROR_BUFFER_OVERFLOW)?;
+    }
+    Ok(())
+}
+
+fn push_offset(line: &mut String<LINE_SIZE>, offset: usize) -> PluginResult<()> {
+    for shift in (0..4).rev() {
+        push_hex_byte(line, (offset >> (shift * 8)) as u8)?;
+    }
+    Ok(())
+}
+
+/// One line of the dump: `chunk` is at most 16 bytes found at `offset`
+fn dump_line(offset: usize, chunk: &[u8]) -> PluginResult<String<LINE_SIZE>> {
+    let mut line = String::new();
+    push_offset(&mut line, offset)?;
+    push(&mut line, " ")?;
+    for i in 0..BYTES_PER_LINE {
+        if i % 8 == 0 {
+            push(&mut line, " ")?;
+        }
+        match chunk.get(i) {
+            Some(&byte) => push_hex_byte(&mut line, byte)?,
+            None => push(&mut line, "  ")?,
+        }
+        push(&mut line, " ")?;
+    }
+    push(&mut line, " |")?;
+    for &byte in chunk {
+        let shown = match byte {
+            0x20..=0x7e => byte as char,
+            _ => '.',
+        };
+        line.push(shown).map_err(|_| ERROR_BUFFER_OVERFLOW)?;
+    }
+    push(&mut line, "|\n")?;
+    Ok(line)
+}
+
+plugin_io_buffer!(4096);
+
+#[no_mangle]
+pub extern "C" fn process(input_ptr: *const u8, input_len: usize, _: *const u8, _: usize) -> i32 {
+    let input = unsafe { slice::from_raw_parts(input_ptr, input_len) };
+    log!("[Hexdump] Dumping {} bytes", input.len());
+
+    for (i, chunk) in input.chunks(BYTES_PER_LINE).enumerate() {
+        match dump_line(i * BYTES_PER_LINE, chunk) {
+            Ok(line) => write_result(line.as_str()),
+            Err(code) => return code,
+        }
+    }
+    if !input.is_empty() {
+        let mut line = String::<LINE_SIZE>::new();
+        if let Err(code) = push_offset(&mut line, input.len()).and_then(|_| push(&mut line, "\n")) {
+            return code;
+        }
+        write_result(line.as_str());
+    }
+    0
+}
+
+plugin_panic_handler!("Hexdump");
+
+#[global_allocator]
+static ALLOCATOR: DummyAllocator = DummyAllocator;
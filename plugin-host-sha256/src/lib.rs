@@ -0,0 +1,38 @@
+//! Host SHA-256 Plugin
+//!
+//! Writes the SHA-256 digest of the input as 64 lowercase hex digits, like
+//! `plugin-sha256`, but has the host compute it through `host::sha256`
+//! instead of bundling a hasher. Its manifest, `host-sha256.toml`, declares
+//! the `crypto` capability the host requires for that; copy it next to the
+//! `.wasm`.
+
+#![no_std]
+
+use core::slice;
+use wasm_shared::plugin_helpers::{host_sha256, write_result, DummyAllocator};
+use wasm_shared::{log, plugin_io_buffer, plugin_panic_handler};
+
+const HEX_DIGITS: &[u8; 16] = b"0123456789abcdef";
+
+plugin_io_buffer!(4096);
+
+#[no_mangle]
+pub extern "C" fn process(input_ptr: *const u8, input_len: usize, _: *const u8, _: usize) -> i32 {
+    let input = unsafe { slice::from_raw_parts(input_ptr, input_len) };
+    log!("[Host SHA-256] Hashing {} bytes", input.len());
+
+    let digest = host_sha256(input);
+    let mut hex = [0u8; 64];
+    for (i, byte) in digest.iter().enumerate() {
+        hex[i * 2] = HEX_DIGITS[(byte >> 4) as usize];
+        hex[i * 2 + 1] = HEX_DIGITS[(byte & 0x0f) as usize];
+    }
+    // Hex digits are always valid UTF-8
+    write_result(core::str::from_utf8(&hex).unwrap_or_default());
+    0
+}
+
+plugin_panic_handler!("Host SHA-256");
+
+#[global_allocator]
+static ALLOCATOR: DummyAllocator = DummyAllocator;
@@ -0,0 +1,243 @@
+//! JSON Transform Plugin
+//!
+//! Parses the input as a JSON object and writes back only the fields named in
+//! the env's `fields` entry, a comma-separated list (`fields=a,b`). Output
+//! fields follow the order of that list; requested fields missing from the
+//! input are left out. Values are copied verbatim, nested objects included.
+//!
+//! Keys are compared as they appear in the input, without unescaping, and the
+//! object may have at most `MAX_FIELDS` top-level fields.
+
+#![no_std]
+
+use core::panic::PanicInfo;
+use core::slice;
+use heapless::Vec;
+use serde::Deserialize;
+
+#[link(wasm_import_module = "host")]
+extern "C" {
+    fn log(ptr: *const u8, len: usize);
+    fn write_output(ptr: *const u8, len: usize);
+}
+
+fn log_message(message: &str) {
+    unsafe {
+        log(message.as_ptr(), message.len());
+    }
+}
+
+const IO_BUFFER_SIZE: usize = 4096;
+
+/// Most top-level fields an input object may have
+const MAX_FIELDS: usize = 64;
+
+/// Scratch region the host writes input and env into, kept clear of the stack
+static mut IO_BUFFER: [u8; IO_BUFFER_SIZE] = [0; IO_BUFFER_SIZE];
+
+/// Reserve `len` bytes for the host's input and env writes
+#[no_mangle]
+pub extern "C" fn alloc(len: usize) -> *mut u8 {
+    if len > IO_BUFFER_SIZE {
+        return core::ptr::null_mut();
+    }
+    core::ptr::addr_of_mut!(IO_BUFFER) as *mut u8
+}
+
+#[derive(Deserialize)]
+struct Env<'a> {
+    #[serde(default)]
+    fields: Option<&'a str>,
+}
+
+/// A top-level member: the raw key (between the quotes) and the raw value
+type Member<'a> = (&'a [u8], &'a [u8]);
+
+fn skip_whitespace(input: &[u8], mut pos: usize) -> usize {
+    while pos < input.len() && input[pos].is_ascii_whitespace() {
+        pos += 1;
+    }
+    pos
+}
+
+/// `pos` is at an opening quote; returns the position after the closing one
+fn skip_string(input: &[u8], mut pos: usize) -> Option<usize> {
+    pos += 1;
+    while pos < input.len() {
+        match input[pos] {
+            b'\\' => pos += 2,
+            b'"' => return Some(pos + 1),
+            _ => pos += 1,
+        }
+    }
+    None
+}
+
+/// Returns the position just after the value starting at `pos`
+fn skip_value(input: &[u8], pos: usize) -> Option<usize> {
+    match *input.get(pos)? {
+        b'"' => skip_string(input, pos),
+        b'{' | b'[' => {
+            let mut depth = 0usize;
+            let mut pos = pos;
+            while pos < input.len() {
+                match input[pos] {
+                    b'"' => {
+                        pos = skip_string(input, pos)?;
+                        continue;
+                    }
+                    b'{' | b'[' => depth += 1,
+                    b'}' | b']' => {
+                        depth -= 1;
+                        if depth == 0 {
+                            return Some(pos + 1);
+                        }
+                    }
+                    _ => {}
+                }
+                pos += 1;
+            }
+            None
+        }
+        _ => {
+            let start = pos;
+            let mut pos = pos;
+            while pos < input.len()
+                && !matches!(input[pos], b',' | b'}' | b']')
+                && !input[pos].is_ascii_whitespace()
+            {
+                pos += 1;
+            }
+            (pos > start).then_some(pos)
+        }
+    }
+}
+
+fn parse_object(input: &[u8]) -> Option<Vec<Member<'_>, MAX_FIELDS>> {
+    let mut members = Vec::new();
+    let mut pos = skip_whitespace(input, 0);
+    if input.get(pos) != Some(&b'{') {
+        return None;
+    }
+    pos = skip_whitespace(input, pos + 1);
+    if input.get(pos) == Some(&b'}') {
+        return (skip_whitespace(input, pos + 1) == input.len()).then_some(members);
+    }
+
+    loop {
+        if input.get(pos) != Some(&b'"') {
+            return None;
+        }
+        let key_end = skip_string(input, pos)?;
+        let key = &input[pos + 1..key_end - 1];
+
+        pos = skip_whitespace(input, key_end);
+        if input.get(pos) != Some(&b':') {
+            return None;
+        }
+        let value_start = skip_whitespace(input, pos + 1);
+        let value_end = skip_value(input, value_start)?;
+        members.push((key, &input[value_start..value_end])).ok()?;
+
+        pos = skip_whitespace(input, value_end);
+        match input.get(pos)? {
+            b',' => pos = skip_whitespace(input, pos + 1),
+            b'}' => break,
+            _ => return None,
+        }
+    }
+
+    (skip_whitespace(input, pos + 1) == input.len()).then_some(members)
+}
+
+/// Write `{"field":value,...}` for each requested field present in `members`
+fn project(
+    members: &[Member<'_>],
+    fields: &str,
+    out: &mut Vec<u8, IO_BUFFER_SIZE>,
+) -> Result<(), ()> {
+    out.push(b'{').map_err(|_| ())?;
+    let mut first = true;
+    for field in fields.split(',').map(str::trim).filter(|f| !f.is_empty()) {
+        let Some((key, value)) = members.iter().find(|(key, _)| *key == field.as_bytes()) else {
+            continue;
+        };
+        if !first {
+            out.push(b',').map_err(|_| ())?;
+        }
+        first = false;
+        out.push(b'"').map_err(|_| ())?;
+        out.extend_from_slice(key)?;
+        out.extend_from_slice(b"\":")?;
+        out.extend_from_slice(value)?;
+    }
+    out.push(b'}').map_err(|_| ())
+}
+
+#[no_mangle]
+pub extern "C" fn process(
+    input_ptr: *const u8,
+    input_len: usize,
+    env_ptr: *const u8,
+    env_len: usize,
+) -> i32 {
+    log_message("[JSON-Transform] Starting projection");
+
+    let input = unsafe { slice::from_raw_parts(input_ptr, input_len) };
+    let env_slice = unsafe { slice::from_raw_parts(env_ptr, env_len) };
+
+    let fields = match serde_json_core::from_slice::<Env>(env_slice) {
+        Ok((
+            Env {
+                fields: Some(fields),
+            },
+            _,
+        )) => fields,
+        Ok(_) => {
+            log_message("[JSON-Transform] ERROR - No `fields` in env");
+            return -2;
+        }
+        Err(_) => {
+            log_message("[JSON-Transform] ERROR - Failed to parse env JSON");
+            return -2;
+        }
+    };
+
+    let members = match parse_object(input) {
+        Some(members) => members,
+        None => {
+            log_message("[JSON-Transform] ERROR - Input is not a JSON object");
+            return -1;
+        }
+    };
+
+    let mut out: Vec<u8, IO_BUFFER_SIZE> = Vec::new();
+    if project(&members, fields, &mut out).is_err() {
+        log_message("[JSON-Transform] ERROR - Output too large");
+        return -3;
+    }
+
+    unsafe {
+        write_output(out.as_ptr(), out.len());
+    }
+    log_message("[JSON-Transform] Done");
+    0
+}
+
+#[panic_handler]
+fn panic(_info: &PanicInfo) -> ! {
+    log_message("[JSON-Transform] PANIC occurred!");
+    loop {}
+}
+
+#[global_allocator]
+static ALLOCATOR: DummyAllocator = DummyAllocator;
+
+struct DummyAllocator;
+
+unsafe impl core::alloc::GlobalAlloc for DummyAllocator {
+    unsafe fn alloc(&self, _layout: core::alloc::Layout) -> *mut u8 {
+        core::ptr::null_mut()
+    }
+    unsafe fn dealloc(&self, _ptr: *mut u8, _layout: core::alloc::Layout) {}
+}
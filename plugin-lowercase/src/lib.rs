@@ -0,0 +1,73 @@
+//! Lowercase Plugin
+//!
+//! Lowercases ASCII letters in place and hands the result back through the
+//! length-return convention instead of `write_output`: it exports
+//! `__returns_output_len`, rewrites the input where the host put it, and
+//! returns the length with the high bit set.
+
+#![no_std]
+
+use core::panic::PanicInfo;
+use core::slice;
+
+#[link(wasm_import_module = "host")]
+extern "C" {
+    fn log(ptr: *const u8, len: usize);
+}
+
+fn log_message(message: &str) {
+    unsafe {
+        log(message.as_ptr(), message.len());
+    }
+}
+
+const IO_BUFFER_SIZE: usize = 4096;
+
+/// Set on a `process` return value to mean "output length, not return code"
+const OUTPUT_LEN_FLAG: u32 = 0x8000_0000;
+
+/// Its presence tells the host to decode `OUTPUT_LEN_FLAG` return values
+#[no_mangle]
+pub static __returns_output_len: i32 = 1;
+
+/// Scratch region the host writes input and env into, kept clear of the stack
+static mut IO_BUFFER: [u8; IO_BUFFER_SIZE] = [0; IO_BUFFER_SIZE];
+
+/// Reserve `len` bytes for the host's input and env writes
+#[no_mangle]
+pub extern "C" fn alloc(len: usize) -> *mut u8 {
+    if len > IO_BUFFER_SIZE {
+        return core::ptr::null_mut();
+    }
+    core::ptr::addr_of_mut!(IO_BUFFER) as *mut u8
+}
+
+#[no_mangle]
+pub extern "C" fn process(input_ptr: *mut u8, input_len: usize, _: *const u8, _: usize) -> i32 {
+    log_message("[Lowercase] Starting conversion");
+
+    // The host reads the output from where it wrote the input
+    let input = unsafe { slice::from_raw_parts_mut(input_ptr, input_len) };
+    input.make_ascii_lowercase();
+
+    log_message("[Lowercase] Done");
+    (input_len as u32 | OUTPUT_LEN_FLAG) as i32
+}
+
+#[panic_handler]
+fn panic(_info: &PanicInfo) -> ! {
+    log_message("[Lowercase] PANIC occurred!");
+    loop {}
+}
+
+#[global_allocator]
+static ALLOCATOR: DummyAllocator = DummyAllocator;
+
+struct DummyAllocator;
+
+unsafe impl core::alloc::GlobalAlloc for DummyAllocator {
+    unsafe fn alloc(&self, _layout: core::alloc::Layout) -> *mut u8 {
+        core::ptr::null_mut()
+    }
+    unsafe fn dealloc(&self, _ptr: *mut u8, _layout: core::alloc::Layout) {}
+}
@@ -0,0 +1,62 @@
+//! Memory Grow Plugin
+//!
+//! Grows its memory by the number of 64 KiB pages given as input (1 when
+//! the input is blank) with `memory.grow`, and writes the new page count.
+//! Fails with `ERROR_MEMORY_ALLOCATION` when the host refuses, which an
+//! execution config with `allow_memory_growth = false` always does.
+
+#![no_std]
+
+use core::arch::wasm32::{memory_grow, memory_size};
+use heapless::String;
+use wasm_shared::plugin_helpers::{
+    push_u64, read_str, write_result, DummyAllocator, ERROR_INVALID_INPUT, ERROR_MEMORY_ALLOCATION,
+};
+use wasm_shared::{log, plugin_io_buffer, plugin_panic_handler};
+
+/// Most pages one execution may ask for
+const MAX_PAGES: usize = 16;
+
+plugin_io_buffer!(256);
+
+#[no_mangle]
+pub extern "C" fn process(input_ptr: *const u8, input_len: usize, _: *const u8, _: usize) -> i32 {
+    // The host passes `input_len` bytes at `input_ptr`, untouched until we return
+    let pages = match unsafe { read_str(input_ptr, input_len) }.map(str::trim) {
+        Ok("") => 1,
+        Ok(s) => match s.parse::<usize>() {
+            Ok(pages) if pages <= MAX_PAGES => pages,
+            _ => {
+                log!(
+                    "[Memory-Grow] ERROR - Expected 0 to {} pages, got {:?}",
+                    MAX_PAGES,
+                    s
+                );
+                return ERROR_INVALID_INPUT;
+            }
+        },
+        Err(code) => {
+            log!("[Memory-Grow] ERROR - Invalid input (code {})", code);
+            return code;
+        }
+    };
+
+    if memory_grow::<0>(pages) == usize::MAX {
+        log!("[Memory-Grow] ERROR - Host refused {} more pages", pages);
+        return ERROR_MEMORY_ALLOCATION;
+    }
+    let size = memory_size::<0>();
+    log!("[Memory-Grow] Grew by {} pages to {}", pages, size);
+
+    let mut output: String<20> = String::new();
+    if let Err(code) = push_u64(&mut output, size as u64) {
+        return code;
+    }
+    write_result(output.as_str());
+    0
+}
+
+plugin_panic_handler!("Memory-Grow");
+
+#[global_allocator]
+static ALLOCATOR: DummyAllocator = DummyAllocator;
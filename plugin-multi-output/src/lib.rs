@@ -0,0 +1,60 @@
+//! Multi-Output Plugin
+//!
+//! Trims its input and writes it to the `result` output channel, with byte
+//! counts for the input and the trimmed text in the `debug` channel, both
+//! through `host::write_named_output`. `"  hi "` gives
+//! `{"result": "hi", "debug": "input_bytes=5 trimmed_bytes=2"}`.
+
+#![no_std]
+
+use heapless::String;
+use wasm_shared::plugin_helpers::{push_u64, read_str, DummyAllocator, PluginResult};
+use wasm_shared::{log, plugin_io_buffer, plugin_panic_handler};
+
+#[link(wasm_import_module = "host")]
+extern "C" {
+    fn write_named_output(name_ptr: *const u8, name_len: usize, ptr: *const u8, len: usize);
+}
+
+fn write_channel(name: &str, data: &str) {
+    unsafe { write_named_output(name.as_ptr(), name.len(), data.as_ptr(), data.len()) }
+}
+
+/// Long enough for both labels and two 20-digit counts
+fn debug_line(input_bytes: usize, trimmed_bytes: usize) -> PluginResult<String<80>> {
+    let mut line = String::new();
+    let _ = line.push_str("input_bytes=");
+    push_u64(&mut line, input_bytes as u64)?;
+    let _ = line.push_str(" trimmed_bytes=");
+    push_u64(&mut line, trimmed_bytes as u64)?;
+    Ok(line)
+}
+
+plugin_io_buffer!(4096);
+
+#[no_mangle]
+pub extern "C" fn process(input_ptr: *const u8, input_len: usize, _: *const u8, _: usize) -> i32 {
+    // The host passes `input_len` bytes at `input_ptr`, untouched until we return
+    let input = match unsafe { read_str(input_ptr, input_len) } {
+        Ok(s) => s,
+        Err(code) => {
+            log!("[Multi-Output] ERROR - Invalid input (code {})", code);
+            return code;
+        }
+    };
+    let trimmed = input.trim();
+    let debug = match debug_line(input.len(), trimmed.len()) {
+        Ok(line) => line,
+        Err(code) => return code,
+    };
+
+    write_channel("result", trimmed);
+    write_channel("debug", debug.as_str());
+    log!("[Multi-Output] Wrote result and debug channels");
+    0
+}
+
+plugin_panic_handler!("Multi-Output");
+
+#[global_allocator]
+static ALLOCATOR: DummyAllocator = DummyAllocator;
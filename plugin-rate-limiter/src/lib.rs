@@ -0,0 +1,50 @@
+//! Rate Limiter Plugin
+//!
+//! Bumps the host counter named by the input (`requests` when the input is
+//! blank) through `host::counter_inc`, logs the new value and writes it as
+//! the output. Counters live in the executor, so running the plugin three
+//! times with the same key outputs `1`, `2` and `3`.
+
+#![no_std]
+
+use heapless::String;
+use wasm_shared::plugin_helpers::{push_i64, read_str, write_result, DummyAllocator};
+use wasm_shared::{log, plugin_io_buffer, plugin_panic_handler};
+
+#[link(wasm_import_module = "host")]
+extern "C" {
+    fn counter_inc(key_ptr: *const u8, key_len: usize) -> i64;
+}
+
+/// Key counted when the input is blank
+const DEFAULT_KEY: &str = "requests";
+
+plugin_io_buffer!(4096);
+
+#[no_mangle]
+pub extern "C" fn process(input_ptr: *const u8, input_len: usize, _: *const u8, _: usize) -> i32 {
+    // The host passes `input_len` bytes at `input_ptr`, untouched until we return
+    let key = match unsafe { read_str(input_ptr, input_len) } {
+        Ok(s) if s.trim().is_empty() => DEFAULT_KEY,
+        Ok(s) => s.trim(),
+        Err(code) => {
+            log!("[Rate-Limiter] ERROR - Invalid input (code {})", code);
+            return code;
+        }
+    };
+
+    let value = unsafe { counter_inc(key.as_ptr(), key.len()) };
+    log!("[Rate-Limiter] {} = {}", key, value);
+
+    let mut output: String<20> = String::new();
+    if let Err(code) = push_i64(&mut output, value) {
+        return code;
+    }
+    write_result(output.as_str());
+    0
+}
+
+plugin_panic_handler!("Rate-Limiter");
+
+#[global_allocator]
+static ALLOCATOR: DummyAllocator = DummyAllocator;
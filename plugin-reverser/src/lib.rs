@@ -0,0 +1,87 @@
+//! String Reverser Plugin
+//!
+//! Reverses the input and writes it through the `write_output` host function,
+//! so the result never mixes with log lines or the env JSON.
+
+#![no_std]
+
+use core::panic::PanicInfo;
+use core::slice;
+use core::str;
+use heapless::String;
+
+#[link(wasm_import_module = "host")]
+extern "C" {
+    fn log(ptr: *const u8, len: usize);
+    fn write_output(ptr: *const u8, len: usize);
+}
+
+fn log_message(message: &str) {
+    unsafe {
+        log(message.as_ptr(), message.len());
+    }
+}
+
+const IO_BUFFER_SIZE: usize = 4096;
+
+/// Scratch region the host writes input and env into, kept clear of the stack
+static mut IO_BUFFER: [u8; IO_BUFFER_SIZE] = [0; IO_BUFFER_SIZE];
+
+/// Reserve `len` bytes for the host's input and env writes
+#[no_mangle]
+pub extern "C" fn alloc(len: usize) -> *mut u8 {
+    if len > IO_BUFFER_SIZE {
+        return core::ptr::null_mut();
+    }
+    core::ptr::addr_of_mut!(IO_BUFFER) as *mut u8
+}
+
+fn write_result(result: &str) {
+    unsafe {
+        write_output(result.as_ptr(), result.len());
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn process(input_ptr: *const u8, input_len: usize, _: *const u8, _: usize) -> i32 {
+    log_message("[Reverser] Starting reversal");
+
+    let input_slice = unsafe { slice::from_raw_parts(input_ptr, input_len) };
+    let input_str = match str::from_utf8(input_slice) {
+        Ok(s) => s,
+        Err(_) => {
+            log_message("[Reverser] ERROR - Invalid UTF-8 input");
+            return -1;
+        }
+    };
+
+    let mut reversed: String<1024> = String::new();
+    for c in input_str.chars().rev() {
+        if reversed.push(c).is_err() {
+            log_message("[Reverser] ERROR - Input too large");
+            return -3;
+        }
+    }
+
+    write_result(reversed.as_str());
+    log_message("[Reverser] Done");
+    0
+}
+
+#[panic_handler]
+fn panic(_info: &PanicInfo) -> ! {
+    log_message("[Reverser] PANIC occurred!");
+    loop {}
+}
+
+#[global_allocator]
+static ALLOCATOR: DummyAllocator = DummyAllocator;
+
+struct DummyAllocator;
+
+unsafe impl core::alloc::GlobalAlloc for DummyAllocator {
+    unsafe fn alloc(&self, _layout: core::alloc::Layout) -> *mut u8 {
+        core::ptr::null_mut()
+    }
+    unsafe fn dealloc(&self, _ptr: *mut u8, _layout: core::alloc::Layout) {}
+}
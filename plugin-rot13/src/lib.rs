@@ -4,20 +4,10 @@
 
 #![no_std]
 
-use core::panic::PanicInfo;
-use core::slice;
-use core::str;
+use wasm_shared::plugin_helpers::{log_message, read_str, DummyAllocator};
+use wasm_shared::{log, plugin_io_buffer, plugin_panic_handler};
 
-#[link(wasm_import_module = "host")]
-extern "C" {
-    fn log(ptr: *const u8, len: usize);
-}
-
-fn log_message(message: &str) {
-    unsafe {
-        log(message.as_ptr(), message.len());
-    }
-}
+plugin_io_buffer!(4096);
 
 fn rot13_char(c: char) -> char {
     match c {
@@ -29,44 +19,29 @@ fn rot13_char(c: char) -> char {
 
 #[no_mangle]
 pub extern "C" fn process(input_ptr: *const u8, input_len: usize, _: *const u8, _: usize) -> i32 {
-    log_message("[ROT13] Starting cipher");
-
-    let input_slice = unsafe { slice::from_raw_parts(input_ptr, input_len) };
+    log!("[ROT13] Starting cipher");
 
-    let input_str = match str::from_utf8(input_slice) {
+    // The host passes `input_len` bytes at `input_ptr`, untouched until we return
+    let input_str = match unsafe { read_str(input_ptr, input_len) } {
         Ok(s) => s,
-        Err(_) => {
-            log_message("[ROT13] ERROR - Invalid UTF-8 input");
-            return -1;
+        Err(code) => {
+            log!("[ROT13] ERROR - Invalid input (code {})", code);
+            return code;
         }
     };
 
-    log_message("[ROT13] Applying ROT13 cipher");
+    log!("[ROT13] Applying ROT13 cipher");
 
     // Apply ROT13
     let encoded: heapless::String<256> = input_str.chars().map(rot13_char).collect();
 
-    log_message("[ROT13] Result = ");
+    log!("[ROT13] Result = ");
     log_message(encoded.as_str());
 
     0
 }
 
-#[panic_handler]
-fn panic(_info: &PanicInfo) -> ! {
-    log_message("[ROT13] PANIC occurred!");
-    loop {}
-}
+plugin_panic_handler!("ROT13");
 
 #[global_allocator]
 static ALLOCATOR: DummyAllocator = DummyAllocator;
-
-struct DummyAllocator;
-
-unsafe impl core::alloc::GlobalAlloc for DummyAllocator {
-    unsafe fn alloc(&self, _layout: core::alloc::Layout) -> *mut u8 {
-        core::ptr::null_mut()
-    }
-
-    unsafe fn dealloc(&self, _ptr: *mut u8, _layout: core::alloc::Layout) {}
-}
@@ -0,0 +1,77 @@
+//! SHA-256 Plugin
+//!
+//! Hashes the input and writes the digest as 64 lowercase hex digits. The
+//! work grows with the input, which makes it a steady load for `bench` and
+//! for comparing fuel use across input sizes.
+
+#![no_std]
+
+use core::panic::PanicInfo;
+use core::slice;
+use sha2::{Digest, Sha256};
+
+#[link(wasm_import_module = "host")]
+extern "C" {
+    fn log(ptr: *const u8, len: usize);
+    fn write_output(ptr: *const u8, len: usize);
+}
+
+fn log_message(message: &str) {
+    unsafe {
+        log(message.as_ptr(), message.len());
+    }
+}
+
+const IO_BUFFER_SIZE: usize = 4096;
+
+const HEX_DIGITS: &[u8; 16] = b"0123456789abcdef";
+
+/// Scratch region the host writes input and env into, kept clear of the stack
+static mut IO_BUFFER: [u8; IO_BUFFER_SIZE] = [0; IO_BUFFER_SIZE];
+
+/// Reserve `len` bytes for the host's input and env writes
+#[no_mangle]
+pub extern "C" fn alloc(len: usize) -> *mut u8 {
+    if len > IO_BUFFER_SIZE {
+        return core::ptr::null_mut();
+    }
+    core::ptr::addr_of_mut!(IO_BUFFER) as *mut u8
+}
+
+#[no_mangle]
+pub extern "C" fn process(input_ptr: *const u8, input_len: usize, _: *const u8, _: usize) -> i32 {
+    log_message("[SHA-256] Hashing input");
+
+    let input = unsafe { slice::from_raw_parts(input_ptr, input_len) };
+    let digest = Sha256::digest(input);
+
+    let mut hex = [0u8; 64];
+    for (i, byte) in digest.iter().enumerate() {
+        hex[i * 2] = HEX_DIGITS[(byte >> 4) as usize];
+        hex[i * 2 + 1] = HEX_DIGITS[(byte & 0x0f) as usize];
+    }
+    unsafe {
+        write_output(hex.as_ptr(), hex.len());
+    }
+
+    log_message("[SHA-256] Done");
+    0
+}
+
+#[panic_handler]
+fn panic(_info: &PanicInfo) -> ! {
+    log_message("[SHA-256] PANIC occurred!");
+    loop {}
+}
+
+#[global_allocator]
+static ALLOCATOR: DummyAllocator = DummyAllocator;
+
+struct DummyAllocator;
+
+unsafe impl core::alloc::GlobalAlloc for DummyAllocator {
+    unsafe fn alloc(&self, _layout: core::alloc::Layout) -> *mut u8 {
+        core::ptr::null_mut()
+    }
+    unsafe fn dealloc(&self, _ptr: *mut u8, _layout: core::alloc::Layout) {}
+}
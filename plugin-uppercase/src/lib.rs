@@ -1,53 +1,35 @@
 #![no_std]
 
-use core::panic::PanicInfo;
-use core::slice;
-use core::str;
+use wasm_shared::plugin_helpers::{log_message, read_str, try_transform, DummyAllocator};
+use wasm_shared::{log, plugin_io_buffer, plugin_panic_handler};
 
-#[link(wasm_import_module = "host")]
-extern "C" {
-    fn log(ptr: *const u8, len: usize);
-}
-
-fn log_message(message: &str) {
-    unsafe {
-        log(message.as_ptr(), message.len());
-    }
-}
+plugin_io_buffer!(4096);
 
 #[no_mangle]
 pub extern "C" fn process(input_ptr: *const u8, input_len: usize, _: *const u8, _: usize) -> i32 {
-    log_message("[Uppercase] Starting conversion");
-    let input_slice = unsafe { slice::from_raw_parts(input_ptr, input_len) };
-    let input_str = match str::from_utf8(input_slice) {
+    log!("[Uppercase] Starting conversion");
+    // The host passes `input_len` bytes at `input_ptr`, untouched until we return
+    let input_str = match unsafe { read_str(input_ptr, input_len) } {
         Ok(s) => s,
-        Err(_) => {
-            log_message("[Uppercase] ERROR - Invalid UTF-8 input");
-            return -1;
+        Err(code) => {
+            log!("[Uppercase] ERROR - Invalid input (code {})", code);
+            return code;
+        }
+    };
+    log!("[Uppercase] Converting to uppercase");
+    let uppercase = match try_transform::<64>(input_str, |c| c.to_ascii_uppercase()) {
+        Ok(uppercase) => uppercase,
+        Err(code) => {
+            log!("[Uppercase] ERROR - Input longer than 64 bytes");
+            return code;
         }
     };
-    log_message("[Uppercase] Converting to uppercase");
-    let uppercase: heapless::String<64> =
-        input_str.chars().map(|c| c.to_ascii_uppercase()).collect();
-    log_message("[Uppercase] Result = ");
+    log!("[Uppercase] Result = ");
     log_message(uppercase.as_str());
     0
 }
 
-#[panic_handler]
-fn panic(_info: &PanicInfo) -> ! {
-    log_message("[Uppercase] PANIC occurred!");
-    loop {}
-}
+plugin_panic_handler!("Uppercase");
 
 #[global_allocator]
 static ALLOCATOR: DummyAllocator = DummyAllocator;
-
-struct DummyAllocator;
-
-unsafe impl core::alloc::GlobalAlloc for DummyAllocator {
-    unsafe fn alloc(&self, _layout: core::alloc::Layout) -> *mut u8 {
-        core::ptr::null_mut()
-    }
-    unsafe fn dealloc(&self, _ptr: *mut u8, _layout: core::alloc::Layout) {}
-}
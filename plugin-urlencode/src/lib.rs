@@ -0,0 +1,135 @@
+//! URL Encode Plugin
+//!
+//! Percent-encodes the input as RFC 3986 describes, or decodes it back when
+//! the env carries `"mode": "decode"`. The result goes out through
+//! `write_output`.
+//!
+//! Only the unreserved characters (`A-Z a-z 0-9 - . _ ~`) are left as they
+//! are; every other byte, reserved delimiters like `/` and `?` included,
+//! becomes `%XX` with uppercase hex. Decoding accepts either case and leaves
+//! `+` alone, since only form encoding uses it for a space.
+//!
+//! The result is built on the stack, so input is capped at `MAX_ENCODE_INPUT`
+//! bytes when encoding (each may triple) and `MAX_DECODE_INPUT` when decoding.
+//! Larger inputs fail with return code -3.
+
+#![no_std]
+
+use core::slice;
+use heapless::Vec;
+use serde::Deserialize;
+use wasm_shared::plugin_helpers::{log_message, write_result_bytes, DummyAllocator};
+use wasm_shared::{plugin_io_buffer, plugin_panic_handler};
+
+plugin_io_buffer!(4096);
+
+/// Largest input accepted when encoding, in bytes
+const MAX_ENCODE_INPUT: usize = 1024;
+
+/// Largest input accepted when decoding, in bytes
+const MAX_DECODE_INPUT: usize = 3072;
+
+const OUTPUT_SIZE: usize = 3072;
+
+const HEX_DIGITS: &[u8; 16] = b"0123456789ABCDEF";
+
+#[derive(Deserialize)]
+struct Env<'a> {
+    #[serde(default)]
+    mode: Option<&'a str>,
+}
+
+/// RFC 3986 section 2.3: the only characters never escaped
+fn is_unreserved(c: u8) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, b'-' | b'.' | b'_' | b'~')
+}
+
+fn encode(input: &[u8], out: &mut Vec<u8, OUTPUT_SIZE>) -> Result<(), ()> {
+    for &c in input {
+        if is_unreserved(c) {
+            out.push(c).map_err(|_| ())?;
+        } else {
+            out.extend_from_slice(&[
+                b'%',
+                HEX_DIGITS[(c >> 4) as usize],
+                HEX_DIGITS[(c & 0x0f) as usize],
+            ])?;
+        }
+    }
+    Ok(())
+}
+
+fn hex_value(c: u8) -> Option<u8> {
+    match c {
+        b'0'..=b'9' => Some(c - b'0'),
+        b'a'..=b'f' => Some(c - b'a' + 10),
+        b'A'..=b'F' => Some(c - b'A' + 10),
+        _ => None,
+    }
+}
+
+/// Decode `%XX` escapes, failing on a `%` not followed by two hex digits
+fn decode(input: &[u8], out: &mut Vec<u8, OUTPUT_SIZE>) -> Result<(), ()> {
+    let mut rest = input;
+    while let Some((&c, tail)) = rest.split_first() {
+        if c != b'%' {
+            out.push(c).map_err(|_| ())?;
+            rest = tail;
+            continue;
+        }
+        let [high, low, ..] = tail else {
+            return Err(());
+        };
+        let byte = hex_value(*high).ok_or(())? << 4 | hex_value(*low).ok_or(())?;
+        out.push(byte).map_err(|_| ())?;
+        rest = &tail[2..];
+    }
+    Ok(())
+}
+
+#[no_mangle]
+pub extern "C" fn process(
+    input_ptr: *const u8,
+    input_len: usize,
+    env_ptr: *const u8,
+    env_len: usize,
+) -> i32 {
+    let input = unsafe { slice::from_raw_parts(input_ptr, input_len) };
+    let env_slice = unsafe { slice::from_raw_parts(env_ptr, env_len) };
+
+    let decoding = match serde_json_core::from_slice::<Env>(env_slice) {
+        Ok((env, _)) => env.mode == Some("decode"),
+        Err(_) => {
+            log_message("[UrlEncode] ERROR - Failed to parse env JSON");
+            return -2;
+        }
+    };
+
+    let mut out: Vec<u8, OUTPUT_SIZE> = Vec::new();
+    if decoding {
+        log_message("[UrlEncode] Decoding");
+        if input.len() > MAX_DECODE_INPUT {
+            log_message("[UrlEncode] ERROR - Input too large");
+            return -3;
+        }
+        if decode(input, &mut out).is_err() {
+            log_message("[UrlEncode] ERROR - Invalid percent-encoding");
+            return -1;
+        }
+    } else {
+        log_message("[UrlEncode] Encoding");
+        if input.len() > MAX_ENCODE_INPUT || encode(input, &mut out).is_err() {
+            log_message("[UrlEncode] ERROR - Input too large");
+            return -3;
+        }
+    }
+
+    write_result_bytes(&out);
+    log_message("[UrlEncode] Done");
+    0
+}
+
+plugin_panic_handler!("UrlEncode");
+
+#[global_allocator]
+static ALLOCATOR: DummyAllocator = DummyAllocator;
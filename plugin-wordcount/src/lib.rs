@@ -0,0 +1,153 @@
+//! Word Count Plugin
+//!
+//! Splits the input on whitespace and writes a JSON object mapping each
+//! distinct token to the number of times it appears, in order of first
+//! appearance: `"to be or not to be"` becomes
+//! `{"to":2,"be":2,"or":1,"not":1}`. Tokens are compared exactly, so case
+//! and punctuation count.
+//!
+//! At most `MAX_TOKENS` distinct tokens are counted; more fail with -3.
+
+#![no_std]
+
+use core::panic::PanicInfo;
+use core::slice;
+use heapless::{FnvIndexMap, Vec};
+
+#[link(wasm_import_module = "host")]
+extern "C" {
+    fn log(ptr: *const u8, len: usize);
+    fn write_output(ptr: *const u8, len: usize);
+}
+
+fn log_message(message: &str) {
+    unsafe {
+        log(message.as_ptr(), message.len());
+    }
+}
+
+const IO_BUFFER_SIZE: usize = 4096;
+
+/// Most distinct tokens counted; a power of two, as `FnvIndexMap` requires
+const MAX_TOKENS: usize = 128;
+
+const HEX_DIGITS: &[u8; 16] = b"0123456789abcdef";
+
+/// Scratch region the host writes input and env into, kept clear of the stack
+static mut IO_BUFFER: [u8; IO_BUFFER_SIZE] = [0; IO_BUFFER_SIZE];
+
+/// Reserve `len` bytes for the host's input and env writes
+#[no_mangle]
+pub extern "C" fn alloc(len: usize) -> *mut u8 {
+    if len > IO_BUFFER_SIZE {
+        return core::ptr::null_mut();
+    }
+    core::ptr::addr_of_mut!(IO_BUFFER) as *mut u8
+}
+
+/// Append `value` as a JSON string, quotes included
+fn push_json_string(out: &mut Vec<u8, IO_BUFFER_SIZE>, value: &str) -> Result<(), ()> {
+    out.push(b'"').map_err(|_| ())?;
+    for &byte in value.as_bytes() {
+        match byte {
+            b'"' | b'\\' => out.extend_from_slice(&[b'\\', byte])?,
+            0x00..=0x1f => out.extend_from_slice(&[
+                b'\\',
+                b'u',
+                b'0',
+                b'0',
+                HEX_DIGITS[(byte >> 4) as usize],
+                HEX_DIGITS[(byte & 0x0f) as usize],
+            ])?,
+            _ => out.push(byte).map_err(|_| ())?,
+        }
+    }
+    out.push(b'"').map_err(|_| ())
+}
+
+/// Append `value` in decimal
+fn push_count(out: &mut Vec<u8, IO_BUFFER_SIZE>, mut value: u32) -> Result<(), ()> {
+    let mut digits = [0u8; 10];
+    let mut len = 0;
+    loop {
+        digits[len] = b'0' + (value % 10) as u8;
+        len += 1;
+        value /= 10;
+        if value == 0 {
+            break;
+        }
+    }
+    digits[..len].reverse();
+    out.extend_from_slice(&digits[..len])
+}
+
+/// Write `{"token":count,...}` for every entry of `counts`
+fn write_counts(
+    counts: &FnvIndexMap<&str, u32, MAX_TOKENS>,
+    out: &mut Vec<u8, IO_BUFFER_SIZE>,
+) -> Result<(), ()> {
+    out.push(b'{').map_err(|_| ())?;
+    for (i, (token, count)) in counts.iter().enumerate() {
+        if i > 0 {
+            out.push(b',').map_err(|_| ())?;
+        }
+        push_json_string(out, token)?;
+        out.push(b':').map_err(|_| ())?;
+        push_count(out, *count)?;
+    }
+    out.push(b'}').map_err(|_| ())
+}
+
+#[no_mangle]
+pub extern "C" fn process(input_ptr: *const u8, input_len: usize, _: *const u8, _: usize) -> i32 {
+    log_message("[WordCount] Counting tokens");
+
+    let input = unsafe { slice::from_raw_parts(input_ptr, input_len) };
+    let text = match core::str::from_utf8(input) {
+        Ok(text) => text,
+        Err(_) => {
+            log_message("[WordCount] ERROR - Input is not valid UTF-8");
+            return -1;
+        }
+    };
+
+    let mut counts: FnvIndexMap<&str, u32, MAX_TOKENS> = FnvIndexMap::new();
+    for token in text.split_whitespace() {
+        if let Some(count) = counts.get_mut(token) {
+            *count += 1;
+        } else if counts.insert(token, 1).is_err() {
+            log_message("[WordCount] ERROR - Too many distinct tokens");
+            return -3;
+        }
+    }
+
+    let mut out: Vec<u8, IO_BUFFER_SIZE> = Vec::new();
+    if write_counts(&counts, &mut out).is_err() {
+        log_message("[WordCount] ERROR - Output too large");
+        return -3;
+    }
+
+    unsafe {
+        write_output(out.as_ptr(), out.len());
+    }
+    log_message("[WordCount] Done");
+    0
+}
+
+#[panic_handler]
+fn panic(_info: &PanicInfo) -> ! {
+    log_message("[WordCount] PANIC occurred!");
+    loop {}
+}
+
+#[global_allocator]
+static ALLOCATOR: DummyAllocator = DummyAllocator;
+
+struct DummyAllocator;
+
+unsafe impl core::alloc::GlobalAlloc for DummyAllocator {
+    unsafe fn alloc(&self, _layout: core::alloc::Layout) -> *mut u8 {
+        core::ptr::null_mut()
+    }
+    unsafe fn dealloc(&self, _ptr: *mut u8, _layout: core::alloc::Layout) {}
+}
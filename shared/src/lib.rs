@@ -1,7 +1,11 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
 extern crate alloc;
 
 pub mod plugin_helpers;
 
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
 use alloc::string::String;
 use alloc::vec::Vec;
 
@@ -10,10 +14,85 @@ use uuid::Uuid;
 
 pub const SOCKET_PATH: &str = "/tmp/wasm-core.sock";
 
+/// Version of the command/response protocol, sent in `Command::Hello`.
+/// Bumped whenever a change would leave an older peer unable to understand
+/// the other side.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// Environment variable holding the shared secret for `Command::Auth`, read
+/// by both the server and the client
+pub const TOKEN_ENV: &str = "WASM_CORE_TOKEN";
+
+/// Caller-supplied entries merged into the env JSON a plugin receives.
+/// Host-provided keys (`timestamp`, `random_seed`) take precedence.
+pub type EnvVars = BTreeMap<String, String>;
+
+/// Upper bound accepted for `ExecutionConfig::timeout_ms` (10 minutes)
+pub const MAX_TIMEOUT_MS: u64 = 10 * 60 * 1000;
+/// Upper bound accepted for `ExecutionConfig::memory_limit_mb` (wasm32 address space)
+pub const MAX_MEMORY_LIMIT_MB: u64 = 4096;
+/// `ExecutionResult::return_code` of an execution that hit its timeout
+pub const RETURN_CODE_TIMED_OUT: i32 = i32::MIN;
+/// Joins a plugin's logs into its output unless the config picks another
+pub const DEFAULT_OUTPUT_SEPARATOR: &str = "\n";
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ExecutionConfig {
     pub timeout_ms: u64,
     pub memory_limit_mb: u64,
+    /// Explicit fuel limit. When unset the executor derives one from `timeout_ms`.
+    #[serde(default)]
+    pub fuel: Option<u64>,
+    /// Guest memory offset for the input when the plugin exports neither
+    /// `alloc` nor `__input_base`. Defaults to 0.
+    #[serde(default)]
+    pub input_base: Option<u32>,
+    /// Run only to measure cost: logs and output are discarded, the audit log
+    /// is skipped, and the result carries just fuel and timing
+    #[serde(default)]
+    pub meter_only: bool,
+    /// Wait for other runs of the same binary that also set this, so they
+    /// never overlap. Runs of different binaries stay parallel.
+    #[serde(default)]
+    pub serialize_per_binary: bool,
+    /// Give the plugin a fixed `timestamp` and `random_seed` (both 0) so
+    /// identical requests produce identical results
+    #[serde(default)]
+    pub deterministic: bool,
+    /// Placed between log lines when the output falls back to the logs.
+    /// Defaults to `DEFAULT_OUTPUT_SEPARATOR`; a character that can't appear
+    /// in a log line, like `\u{1e}`, keeps multi-line logs apart.
+    #[serde(default)]
+    pub output_separator: Option<String>,
+    /// Rewrites applied to the input, in order, before the plugin sees it
+    #[serde(default)]
+    pub preprocess: Vec<Preprocessor>,
+    /// Output beyond this many bytes is dropped by the host and the result
+    /// is marked `output_truncated`. Unlimited when unset.
+    #[serde(default)]
+    pub max_output_bytes: Option<usize>,
+    /// When false, any `memory.grow` by the plugin fails the execution with
+    /// `memory_limit`, however much room `memory_limit_mb` leaves
+    #[serde(default = "default_allow_memory_growth")]
+    pub allow_memory_growth: bool,
+}
+
+fn default_allow_memory_growth() -> bool {
+    true
+}
+
+/// A rewrite of a text input, applied by the executor so plugins don't each
+/// have to. All of them need the input to be UTF-8.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Preprocessor {
+    /// Strip leading and trailing whitespace
+    TrimWhitespace,
+    /// Unicode lowercase
+    ToLowercase,
+    /// Unicode Normalization Form C, so composed and decomposed spellings of
+    /// the same text look alike
+    NfcNormalize,
 }
 
 impl Default for ExecutionConfig {
@@ -21,17 +100,250 @@ impl Default for ExecutionConfig {
         Self {
             timeout_ms: 5000,
             memory_limit_mb: 64,
+            fuel: None,
+            input_base: None,
+            meter_only: false,
+            serialize_per_binary: false,
+            deterministic: false,
+            output_separator: None,
+            preprocess: Vec::new(),
+            max_output_bytes: None,
+            allow_memory_growth: true,
         }
     }
 }
 
+impl ExecutionConfig {
+    pub fn builder() -> ExecutionConfigBuilder {
+        ExecutionConfigBuilder::default()
+    }
+
+    /// The separator logs are joined with
+    pub fn output_separator(&self) -> &str {
+        self.output_separator
+            .as_deref()
+            .unwrap_or(DEFAULT_OUTPUT_SEPARATOR)
+    }
+
+    /// Check that the config holds values the executor can meaningfully apply
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.timeout_ms == 0 {
+            return Err(ConfigError::ZeroTimeout);
+        }
+        if self.timeout_ms > MAX_TIMEOUT_MS {
+            return Err(ConfigError::TimeoutTooLarge(self.timeout_ms));
+        }
+        if self.memory_limit_mb == 0 {
+            return Err(ConfigError::ZeroMemoryLimit);
+        }
+        if self.memory_limit_mb > MAX_MEMORY_LIMIT_MB {
+            return Err(ConfigError::MemoryLimitTooLarge(self.memory_limit_mb));
+        }
+        if self.fuel == Some(0) {
+            return Err(ConfigError::ZeroFuel);
+        }
+        if self.max_output_bytes == Some(0) {
+            return Err(ConfigError::ZeroMaxOutputBytes);
+        }
+        Ok(())
+    }
+}
+
+/// Reasons an `ExecutionConfig` is rejected
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigError {
+    ZeroTimeout,
+    TimeoutTooLarge(u64),
+    ZeroMemoryLimit,
+    MemoryLimitTooLarge(u64),
+    ZeroFuel,
+    ZeroMaxOutputBytes,
+}
+
+impl core::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            ConfigError::ZeroTimeout => write!(f, "timeout_ms must be greater than 0"),
+            ConfigError::TimeoutTooLarge(ms) => write!(
+                f,
+                "timeout_ms {} exceeds the maximum of {}",
+                ms, MAX_TIMEOUT_MS
+            ),
+            ConfigError::ZeroMemoryLimit => write!(f, "memory_limit_mb must be greater than 0"),
+            ConfigError::MemoryLimitTooLarge(mb) => write!(
+                f,
+                "memory_limit_mb {} exceeds the maximum of {}",
+                mb, MAX_MEMORY_LIMIT_MB
+            ),
+            ConfigError::ZeroFuel => write!(f, "fuel must be greater than 0 when set"),
+            ConfigError::ZeroMaxOutputBytes => {
+                write!(f, "max_output_bytes must be greater than 0 when set")
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ConfigError {}
+
+/// Machine-readable category of a failed request
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    /// No binary (or in-flight request) with the given ID
+    NotFound,
+    /// Malformed command or invalid parameters
+    InvalidRequest,
+    /// The wasm bytes failed validation or can't be executed
+    InvalidBinary,
+    /// Signature missing or not made by the trusted key
+    VerificationFailed,
+    Timeout,
+    OutOfFuel,
+    MemoryLimit,
+    Cancelled,
+    /// Missing or wrong `Auth` token; the server closes the connection
+    Unauthorized,
+    /// Client and server share no protocol version; the server closes the
+    /// connection
+    UnsupportedProtocol,
+    Internal,
+}
+
+/// Error payload of every response: a code to branch on plus a human message
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ErrorResponse {
+    pub code: ErrorCode,
+    pub message: String,
+}
+
+impl ErrorResponse {
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+impl core::fmt::Display for ErrorResponse {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ErrorResponse {}
+
+/// Builder for `ExecutionConfig` that validates on `build()`
+#[derive(Debug, Clone, Default)]
+pub struct ExecutionConfigBuilder {
+    config: ExecutionConfig,
+}
+
+impl ExecutionConfigBuilder {
+    pub fn timeout(mut self, timeout_ms: u64) -> Self {
+        self.config.timeout_ms = timeout_ms;
+        self
+    }
+
+    pub fn memory_mb(mut self, memory_limit_mb: u64) -> Self {
+        self.config.memory_limit_mb = memory_limit_mb;
+        self
+    }
+
+    pub fn fuel(mut self, fuel: u64) -> Self {
+        self.config.fuel = Some(fuel);
+        self
+    }
+
+    pub fn meter_only(mut self, meter_only: bool) -> Self {
+        self.config.meter_only = meter_only;
+        self
+    }
+
+    pub fn serialize_per_binary(mut self, serialize: bool) -> Self {
+        self.config.serialize_per_binary = serialize;
+        self
+    }
+
+    pub fn deterministic(mut self, deterministic: bool) -> Self {
+        self.config.deterministic = deterministic;
+        self
+    }
+
+    pub fn output_separator(mut self, separator: impl Into<String>) -> Self {
+        self.config.output_separator = Some(separator.into());
+        self
+    }
+
+    pub fn max_output_bytes(mut self, bytes: usize) -> Self {
+        self.config.max_output_bytes = Some(bytes);
+        self
+    }
+
+    pub fn allow_memory_growth(mut self, allow: bool) -> Self {
+        self.config.allow_memory_growth = allow;
+        self
+    }
+
+    /// Add a step to the input preprocessing
+    pub fn preprocess(mut self, step: Preprocessor) -> Self {
+        self.config.preprocess.push(step);
+        self
+    }
+
+    pub fn build(self) -> Result<ExecutionConfig, ConfigError> {
+        self.config.validate()?;
+        Ok(self.config)
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ExecutionResult {
     pub binary_id: Uuid,
     pub return_code: i32,
+    /// Bytes written via `host::write_output`, or the joined logs if the plugin never called it
     pub output: String,
+    /// Every message the plugin sent through `host::log`
+    #[serde(default)]
+    pub logs: Vec<String>,
+    /// Whether `output` came from the output buffer rather than the logs
+    #[serde(default)]
+    pub from_output_buffer: bool,
     pub execution_time_ms: u64,
     pub fuel_consumed: u64,
+    /// The plugin was still running at the deadline. `logs` and `output` hold
+    /// whatever it produced before then and `return_code` is `RETURN_CODE_TIMED_OUT`.
+    #[serde(default)]
+    pub timed_out: bool,
+    /// `ExecuteRequest::correlation_id`, echoed back
+    #[serde(default)]
+    pub correlation_id: Option<String>,
+    /// How `output` is encoded; the request's `encoding`, echoed back
+    #[serde(default)]
+    pub encoding: Encoding,
+    /// The config the execution actually ran with, after the request's
+    /// config, the binary's default and the server's default were resolved
+    #[serde(default)]
+    pub applied_config: ExecutionConfig,
+    /// Non-fatal notes, e.g. that the run came close to its timeout or
+    /// produced an unusually large output
+    #[serde(default)]
+    pub warnings: Vec<String>,
+    /// Answered from the server's cache of pure binaries' results without
+    /// running the plugin; `fuel_consumed` is then 0
+    #[serde(default)]
+    pub cache_hit: bool,
+    /// `output` is probably incomplete: the host cut it at
+    /// `max_output_bytes`, or the input was larger than the output capacity
+    /// the plugin declares in `__output_cap`. `warnings` says which.
+    #[serde(default)]
+    pub output_truncated: bool,
+    /// Bytes written via `host::write_named_output`, by channel name and
+    /// encoded like `output`. Separate from `output`, which they never feed.
+    #[serde(default)]
+    pub outputs: BTreeMap<String, String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -40,17 +352,153 @@ pub struct BinaryInfo {
     pub path: String,
     pub size: usize,
     pub loaded_at: u64,
+    /// A core module without an exported `memory`; it cannot be executed
+    #[serde(default)]
+    pub needs_memory_export: bool,
+    /// How long the binary took to compile (or load from the module cache)
+    #[serde(default)]
+    pub compile_time_ms: u64,
+    /// Bytes of compiled code and data the server holds for the binary
+    #[serde(default)]
+    pub code_size: usize,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LoadBinaryRequest {
     pub path: String,
+    /// Detached Ed25519 signature over the wasm bytes
+    #[serde(default)]
+    pub signature: Option<Vec<u8>>,
+    /// Config for executions of this binary that don't carry their own
+    #[serde(default)]
+    pub default_config: Option<ExecutionConfig>,
+    /// Namespace to register the binary in. Only requests naming the same
+    /// namespace can list, describe, execute or unload it; `None` is the
+    /// default namespace.
+    #[serde(default)]
+    pub namespace: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LoadBinaryResponse {
     pub binary_id: Uuid,
     pub size: usize,
+    /// `"core"` for core modules, `"component"` for component-model binaries
+    #[serde(default)]
+    pub kind: String,
+    /// Declared minimum pages (64 KiB each) of the exported memory
+    #[serde(default)]
+    pub memory_min_pages: Option<u64>,
+    /// Declared maximum pages of the exported memory, if bounded
+    #[serde(default)]
+    pub memory_max_pages: Option<u64>,
+    #[serde(default)]
+    pub exports: Vec<String>,
+    /// Fingerprint of the trusted key that signed the binary, if verified
+    #[serde(default)]
+    pub signer: Option<String>,
+    /// A core module without an exported `memory`; it cannot be executed
+    #[serde(default)]
+    pub needs_memory_export: bool,
+}
+
+/// Something a binary requires from the host at instantiation
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImportInfo {
+    /// Import module, e.g. `host`. Empty for component-model imports.
+    pub module: String,
+    pub name: String,
+    /// The expected type, e.g. `func(i32, i32)`
+    pub ty: String,
+}
+
+/// Author-supplied description of a plugin, read from a TOML file shipped
+/// next to its `.wasm`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PluginManifest {
+    pub name: String,
+    #[serde(default)]
+    pub version: Option<String>,
+    #[serde(default)]
+    pub author: Option<String>,
+    /// Host features the plugin relies on, e.g. `write_output`. Informational,
+    /// except that the digest host functions need `crypto`.
+    #[serde(default)]
+    pub capabilities: Vec<String>,
+    /// Used as the binary's default config unless the load request sets one
+    #[serde(default)]
+    pub default_config: Option<ExecutionConfig>,
+    /// What the plugin expects as input; chains check it against the
+    /// previous step's `output_kind`
+    #[serde(default)]
+    pub input_kind: Option<DataKind>,
+    /// What the plugin produces
+    #[serde(default)]
+    pub output_kind: Option<DataKind>,
+    /// The same input, config and env always give the same result, so the
+    /// server may answer repeats from its result cache
+    #[serde(default)]
+    pub pure: bool,
+    /// JSON Schema the input must satisfy; the server rejects input that
+    /// doesn't before running the plugin
+    #[cfg(feature = "std")]
+    #[serde(default)]
+    pub input_schema: Option<serde_json::Value>,
+}
+
+/// Shape of the data a plugin takes or produces
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DataKind {
+    /// Plain text
+    Text,
+    /// A JSON document
+    Json,
+    /// Arbitrary bytes
+    Bytes,
+}
+
+impl DataKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            DataKind::Text => "text",
+            DataKind::Json => "json",
+            DataKind::Bytes => "bytes",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DescribeBinaryRequest {
+    pub binary_id: Uuid,
+    /// Namespace the binary must belong to; see `LoadBinaryRequest::namespace`
+    #[serde(default)]
+    pub namespace: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DescribeBinaryResponse {
+    pub binary_id: Uuid,
+    pub path: String,
+    pub size: usize,
+    pub kind: String,
+    pub memory_min_pages: Option<u64>,
+    pub memory_max_pages: Option<u64>,
+    pub exports: Vec<String>,
+    pub imports: Vec<ImportInfo>,
+    pub signer: Option<String>,
+    pub needs_memory_export: bool,
+    #[serde(default)]
+    pub default_config: Option<ExecutionConfig>,
+    #[serde(default)]
+    pub compile_time_ms: u64,
+    #[serde(default)]
+    pub code_size: usize,
+    /// The manifest found next to the binary when it was loaded. Boxed to
+    /// keep `Response` small.
+    #[serde(default)]
+    pub manifest: Option<Box<PluginManifest>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -59,6 +507,58 @@ pub struct ExecuteRequest {
     pub input: String,
     #[serde(default)]
     pub config: Option<ExecutionConfig>,
+    /// Forward `emit_chunk` output as `Response::OutputChunk` lines before the
+    /// final `Response::Execute`
+    #[serde(default)]
+    pub stream: bool,
+    /// Caller-chosen ID that `Command::Cancel` can use to abort this execution
+    #[serde(default)]
+    pub request_id: Option<Uuid>,
+    #[serde(default)]
+    pub env: EnvVars,
+    /// Caller's ID for matching this request to its server-side trace; stored
+    /// on the trace and echoed in the result
+    #[serde(default)]
+    pub correlation_id: Option<String>,
+    /// Namespace the binary must belong to; see `LoadBinaryRequest::namespace`
+    #[serde(default)]
+    pub namespace: Option<String>,
+    /// How `input` is encoded, and how the result's `output` will be
+    #[serde(default)]
+    pub encoding: Encoding,
+    /// Wall-clock time, in milliseconds since the Unix epoch, by which the
+    /// execution must finish. The run gets whichever is shorter of the time
+    /// left and `timeout_ms`; a deadline already past fails with `Timeout`
+    /// before the plugin is instantiated.
+    #[serde(default)]
+    pub deadline_unix_ms: Option<u64>,
+    /// Place in the queue while `max_concurrency` executions are already
+    /// running: waiting executions with a higher priority start first, equal
+    /// ones in arrival order. 0, the default, is the lowest.
+    #[serde(default)]
+    pub priority: u8,
+}
+
+/// How plugin input and output bytes are carried in JSON strings
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Encoding {
+    /// The string is the bytes; output that isn't valid UTF-8 is decoded lossily
+    #[default]
+    Utf8,
+    /// Standard base64 with padding
+    Base64,
+    /// Lowercase hex, two digits per byte
+    Hex,
+}
+
+/// A piece of output a plugin emitted while still running
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OutputChunk {
+    pub binary_id: Uuid,
+    /// Position of the chunk within the execution, starting at 0
+    pub index: u32,
+    pub data: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -66,30 +566,199 @@ pub struct ExecuteResponse {
     pub result: ExecutionResult,
 }
 
+/// Execute the binary at `path`, loading it first unless it already is
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExecuteByPathRequest {
+    pub path: String,
+    pub input: String,
+    #[serde(default)]
+    pub config: Option<ExecutionConfig>,
+    #[serde(default)]
+    pub env: EnvVars,
+    /// Namespace to look the path up in, and to load it into if it isn't yet
+    #[serde(default)]
+    pub namespace: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExecuteByPathResponse {
+    /// ID of the loaded (or reused) binary, usable for later executes
+    pub binary_id: Uuid,
+    pub result: ExecutionResult,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ExecuteChainRequest {
     pub binary_ids: Vec<Uuid>,
     pub input: String,
     #[serde(default)]
     pub config: Option<ExecutionConfig>,
+    /// Env entries for every step
+    #[serde(default)]
+    pub env: EnvVars,
+    /// Per-step env entries, by position in `binary_ids`, layered over `env`
+    #[serde(default)]
+    pub step_env: Vec<EnvVars>,
+    /// Namespace every binary in the chain must belong to
+    #[serde(default)]
+    pub namespace: Option<String>,
+    /// A binary appearing more than once picks up the scratch buffer its
+    /// previous step left behind, so it can accumulate state across the chain
+    #[serde(default)]
+    pub persist_host_state: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ExecuteChainResponse {
     pub results: Vec<ExecutionResult>,
+    /// Why the chain stopped before its last step, if it did
+    #[serde(default)]
+    pub halted: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ListBinariesRequest {}
+pub struct CancelResponse {
+    pub request_id: Uuid,
+}
+
+/// What `Command::ReloadAll` did with one binary
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReloadStatus {
+    /// The file changed and was recompiled under the same ID
+    Reloaded,
+    /// The file's content hash matches what is loaded; nothing was done
+    Unchanged,
+    /// The file couldn't be read, verified or compiled; the loaded binary is kept
+    Error,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReloadResult {
+    pub binary_id: Uuid,
+    pub path: String,
+    pub status: ReloadStatus,
+    /// Why the reload failed, for `ReloadStatus::Error`
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReloadAllResponse {
+    pub results: Vec<ReloadResult>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportBundleResponse {
+    pub path: String,
+    /// Binaries written to the bundle
+    pub binary_count: usize,
+    /// Size of the bundle file
+    pub size: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportBundleResponse {
+    /// The IDs the binaries had on the exporting server, which they keep
+    pub binary_ids: Vec<Uuid>,
+    /// Where the server wrote their wasm files
+    pub dir: String,
+}
+
+/// How a loaded binary compares with the file it was loaded from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IntegrityStatus {
+    /// The file's SHA-256 matches the hash recorded at load time
+    Ok,
+    /// The file changed since it was loaded, or no hash was recorded
+    Mismatch,
+    /// The file can't be read any more
+    Missing,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IntegrityResult {
+    pub binary_id: Uuid,
+    pub path: String,
+    pub status: IntegrityStatus,
+    /// Hex SHA-256 recorded when the binary was loaded
+    pub expected_sha256: Option<String>,
+    /// Hex SHA-256 of the file now; `None` when it is missing
+    pub actual_sha256: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VerifyIntegrityResponse {
+    pub results: Vec<IntegrityResult>,
+}
+
+/// Wasmtime features the server's engine was built with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EngineFeatures {
+    /// Executions are metered and bounded by `ExecutionConfig::fuel`
+    pub fuel: bool,
+    pub async_support: bool,
+    pub simd: bool,
+}
+
+/// How the running server is built and configured
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InfoResponse {
+    /// The `wasm-core` crate version
+    pub version: String,
+    pub wasmtime_version: String,
+    pub engine_features: EngineFeatures,
+    /// Applied to executions whose request and binary set no config
+    pub default_config: ExecutionConfig,
+    pub socket_path: String,
+    /// Binaries registered across all namespaces
+    pub binary_count: usize,
+}
+
+/// Cost of a warmup run; its logs and output are discarded
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WarmupResponse {
+    pub binary_id: Uuid,
+    pub return_code: i32,
+    pub execution_time_ms: u64,
+    pub fuel_consumed: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ListBinariesRequest {
+    /// Only binaries in this namespace are listed
+    #[serde(default)]
+    pub namespace: Option<String>,
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ListBinariesResponse {
     pub binaries: Vec<BinaryInfo>,
 }
 
+/// Replace a binary's default config; `None` falls back to the server default
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SetDefaultConfigRequest {
+    pub binary_id: Uuid,
+    pub config: Option<ExecutionConfig>,
+    /// Namespace the binary must belong to; see `LoadBinaryRequest::namespace`
+    #[serde(default)]
+    pub namespace: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SetDefaultConfigResponse {
+    pub binary_id: Uuid,
+    pub config: Option<ExecutionConfig>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UnloadBinaryRequest {
     pub binary_id: Uuid,
+    /// Namespace the binary must belong to; see `LoadBinaryRequest::namespace`
+    #[serde(default)]
+    pub namespace: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -97,23 +766,529 @@ pub struct UnloadBinaryResponse {
     pub message: String,
 }
 
+/// One record of the execution audit log. Digests are hex SHA-256; `hash`
+/// covers every other field plus `prev_hash`, chaining the records together.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub seq: u64,
+    pub timestamp_ms: u64,
+    pub binary_id: Uuid,
+    pub input_hash: String,
+    pub env_hash: String,
+    pub output_hash: String,
+    pub return_code: i32,
+    pub fuel_consumed: u64,
+    pub prev_hash: String,
+    pub hash: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GetAuditLogResponse {
+    pub entries: Vec<AuditEntry>,
+}
+
+/// Kind of a recorded trace event
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TraceEventType {
+    LoadStart,
+    LoadComplete,
+    LoadError,
+    ExecutionStart,
+    ExecutionComplete,
+    ExecutionError,
+    FunctionCall,
+    HostFunctionCall,
+    MemoryOp,
+    FuelCheckpoint,
+    PluginLog,
+}
+
+impl core::str::FromStr for TraceEventType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "LoadStart" => Self::LoadStart,
+            "LoadComplete" => Self::LoadComplete,
+            "LoadError" => Self::LoadError,
+            "ExecutionStart" => Self::ExecutionStart,
+            "ExecutionComplete" => Self::ExecutionComplete,
+            "ExecutionError" => Self::ExecutionError,
+            "FunctionCall" => Self::FunctionCall,
+            "HostFunctionCall" => Self::HostFunctionCall,
+            "MemoryOp" => Self::MemoryOp,
+            "FuelCheckpoint" => Self::FuelCheckpoint,
+            "PluginLog" => Self::PluginLog,
+            _ => return Err(alloc::format!("unknown trace event type: {}", s)),
+        })
+    }
+}
+
+/// Which recorded traces to return. Every set criterion must match; the
+/// default matches all traces of binaries in the default namespace.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TraceQuery {
+    /// Only traces of executions that failed
+    #[serde(default)]
+    pub only_failures: bool,
+    /// Only traces with at least one event of one of these types
+    #[serde(default)]
+    pub event_types: Vec<TraceEventType>,
+    #[serde(default)]
+    pub binary_id: Option<Uuid>,
+    /// Only traces of the request sent with this correlation ID
+    #[serde(default)]
+    pub correlation_id: Option<String>,
+    /// Namespace the traced binaries must belong to; see
+    /// `LoadBinaryRequest::namespace`
+    #[serde(default)]
+    pub namespace: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TraceEventRecord {
+    /// Microseconds since the trace started
+    pub timestamp: u64,
+    /// Microseconds since the previous event
+    pub delta_us: u64,
+    pub event_type: TraceEventType,
+    pub message: String,
+    /// The event's metadata as JSON text
+    pub metadata: Option<String>,
+}
+
+/// Wire form of one execution trace
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TraceRecord {
+    /// Pass to `Command::Replay` to run the execution again
+    #[serde(default)]
+    pub trace_id: Uuid,
+    pub binary_id: Uuid,
+    #[serde(default)]
+    pub correlation_id: Option<String>,
+    pub duration_ms: u64,
+    pub success: bool,
+    pub error_message: Option<String>,
+    pub events: Vec<TraceEventRecord>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GetTracesResponse {
+    /// Oldest first
+    pub traces: Vec<TraceRecord>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GetBinaryBytesResponse {
+    pub binary_id: Uuid,
+    /// The wasm file the binary was loaded from, base64-encoded
+    pub wasm_base64: String,
+    pub size: usize,
+    /// SHA-256 of the bytes, as recorded at load time
+    pub content_hash: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GetHistoryResponse {
+    pub binary_id: Uuid,
+    /// Oldest first
+    pub results: Vec<ExecutionResult>,
+}
+
+/// What a `Command::Subscribe` connection receives
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SubscriptionKind {
+    /// A `Response::StatsSnapshot` at the server's stats interval
+    Stats,
+}
+
+/// Executor activity at one moment
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StatsSnapshot {
+    pub timestamp_ms: u64,
+    /// Executions started per second since the previous snapshot
+    pub executions_per_sec: f64,
+    /// Executions running right now
+    pub active: u64,
+    /// Executions started since the server came up
+    pub total: u64,
+    /// Executions started since the server came up, by binary
+    pub per_binary: BTreeMap<Uuid, u64>,
+}
+
+/// Severity of a server log line, least verbose first, so a line is shown
+/// when its level is at most the one asked for
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum LogLevel {
+    Error,
+    Warn,
+    #[default]
+    Info,
+    Debug,
+    Trace,
+}
+
+impl core::str::FromStr for LogLevel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "error" | "Error" => Ok(Self::Error),
+            "warn" | "Warn" => Ok(Self::Warn),
+            "info" | "Info" => Ok(Self::Info),
+            "debug" | "Debug" => Ok(Self::Debug),
+            "trace" | "Trace" => Ok(Self::Trace),
+            _ => Err("expected error, warn, info, debug or trace".into()),
+        }
+    }
+}
+
+/// One event from the server's log, as pushed to a `Command::TailLogs`
+/// connection
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogLine {
+    pub timestamp_ms: u64,
+    pub level: LogLevel,
+    /// Module the event came from, e.g. `wasm_core::executor`
+    pub target: String,
+    /// The message followed by the event's other fields as `name=value`
+    pub message: String,
+}
+
+/// Output format of `Command::ExportTrace`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TraceFormat {
+    /// The trace as a pretty-printed JSON object
+    Json,
+    /// Brendan Gregg's folded-stack format, one `frame;frame count` line
+    /// per stack with counts in microseconds, e.g. for `inferno-flamegraph`
+    Folded,
+}
+
+impl core::str::FromStr for TraceFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" | "Json" => Ok(Self::Json),
+            "folded" | "Folded" => Ok(Self::Folded),
+            _ => Err("expected json or folded".into()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportTraceResponse {
+    pub binary_id: Uuid,
+    pub format: TraceFormat,
+    pub data: String,
+}
+
+/// A traced execution run again by `Command::Replay`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplayResponse {
+    pub trace_id: Uuid,
+    pub binary_id: Uuid,
+    /// What the recorded execution returned; `None` if it failed
+    pub original_return_code: Option<i32>,
+    pub result: ExecutionResult,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type")]
 pub enum Command {
+    /// Must be the first line on a connection when the server has a token
+    /// configured; accepted and ignored otherwise
+    Auth {
+        token: String,
+    },
+    /// Sent first (after `Auth`, when needed) to check both sides speak the
+    /// same protocol. A version the server doesn't support gets an
+    /// `unsupported_protocol` error and the connection is closed.
+    Hello {
+        client_version: String,
+        protocol_version: u32,
+    },
     LoadBinary(LoadBinaryRequest),
     Execute(ExecuteRequest),
+    ExecuteByPath(ExecuteByPathRequest),
     ExecuteChain(ExecuteChainRequest),
-    ListBinaries,
+    ListBinaries(ListBinariesRequest),
     UnloadBinary(UnloadBinaryRequest),
+    DescribeBinary(DescribeBinaryRequest),
+    SetDefaultConfig(SetDefaultConfigRequest),
+    /// Audit entries with a sequence number greater than `since` (all when unset)
+    GetAuditLog {
+        since: Option<u64>,
+        /// Only entries for binaries in this namespace; see
+        /// `LoadBinaryRequest::namespace`
+        #[serde(default)]
+        namespace: Option<String>,
+    },
+    /// Recorded execution traces matching the query
+    GetTraces(TraceQuery),
+    /// The most recent results of a binary, as returned to whoever ran it;
+    /// at most `limit` of them when set
+    GetHistory {
+        binary_id: Uuid,
+        #[serde(default)]
+        limit: Option<usize>,
+        /// Namespace the binary must belong to; see `LoadBinaryRequest::namespace`
+        #[serde(default)]
+        namespace: Option<String>,
+    },
+    /// The wasm bytes a binary was loaded from. Refused unless the server
+    /// sets `allow_binary_download`, since it hands out plugin code.
+    GetBinaryBytes {
+        binary_id: Uuid,
+        /// Namespace the binary must belong to; see `LoadBinaryRequest::namespace`
+        #[serde(default)]
+        namespace: Option<String>,
+    },
+    /// Keep the connection open and push updates of `kind` until the client
+    /// disconnects. Nothing else is read from the connection afterwards.
+    Subscribe {
+        kind: SubscriptionKind,
+        /// Only counts for binaries in this namespace; see
+        /// `LoadBinaryRequest::namespace`
+        #[serde(default)]
+        namespace: Option<String>,
+    },
+    /// Keep the connection open and push the server's log lines at `level`
+    /// or more severe until the client disconnects. Nothing else is read
+    /// from the connection afterwards.
+    TailLogs {
+        #[serde(default)]
+        level: LogLevel,
+    },
+    /// The most recent trace of a binary, rendered in `format`
+    ExportTrace {
+        binary_id: Uuid,
+        format: TraceFormat,
+        /// Namespace the binary must belong to; see `LoadBinaryRequest::namespace`
+        #[serde(default)]
+        namespace: Option<String>,
+    },
+    /// Run the execution recorded in a trace again, with the same input,
+    /// env and config
+    Replay {
+        trace_id: Uuid,
+        /// Namespace the traced binary must belong to; see
+        /// `LoadBinaryRequest::namespace`
+        #[serde(default)]
+        namespace: Option<String>,
+    },
+    /// Recompile every binary registered in `namespace` from its path,
+    /// keeping IDs
+    ReloadAll {
+        /// See `LoadBinaryRequest::namespace`
+        #[serde(default)]
+        namespace: Option<String>,
+    },
+    /// Write every binary loaded in `namespace`, bytes and metadata, to a tar
+    /// bundle at `path` on the server
+    ExportBundle {
+        path: String,
+        /// See `LoadBinaryRequest::namespace`
+        #[serde(default)]
+        namespace: Option<String>,
+    },
+    /// Register the binaries in the bundle at `path` on the server in
+    /// `namespace`, under their original IDs. Their wasm files go in a
+    /// `<bundle name>-binaries` directory beside the bundle.
+    ImportBundle {
+        path: String,
+        /// See `LoadBinaryRequest::namespace`
+        #[serde(default)]
+        namespace: Option<String>,
+    },
+    /// Hash the files of loaded binaries and compare them with the hashes
+    /// recorded at load time; every binary in `namespace` when `binary_id`
+    /// is unset
+    VerifyIntegrity {
+        #[serde(default)]
+        binary_id: Option<Uuid>,
+        /// See `LoadBinaryRequest::namespace`
+        #[serde(default)]
+        namespace: Option<String>,
+    },
+    /// Versions, engine features and configuration of the server
+    Info,
+    /// Abort the in-flight execution started with this `request_id`
+    Cancel {
+        request_id: Uuid,
+        /// Namespace the running binary must belong to; see
+        /// `LoadBinaryRequest::namespace`
+        #[serde(default)]
+        namespace: Option<String>,
+    },
+    /// Run a binary once with a throwaway input so the first real call
+    /// doesn't pay one-off setup costs. Not audited, and left out of the
+    /// stats, the history and the traces.
+    Warmup {
+        binary_id: Uuid,
+        #[serde(default)]
+        sample_input: String,
+        /// Namespace the binary must belong to; see `LoadBinaryRequest::namespace`
+        #[serde(default)]
+        namespace: Option<String>,
+    },
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type")]
 pub enum Response {
-    LoadBinary(Result<LoadBinaryResponse, String>),
-    Execute(Result<ExecuteResponse, String>),
-    ExecuteChain(Result<ExecuteChainResponse, String>),
-    ListBinaries(Result<ListBinariesResponse, String>),
-    UnloadBinary(Result<UnloadBinaryResponse, String>),
-    Error(String),
+    Authenticated,
+    Hello {
+        server_version: String,
+        supported_protocols: Vec<u32>,
+    },
+    LoadBinary(Result<LoadBinaryResponse, ErrorResponse>),
+    Execute(Result<ExecuteResponse, ErrorResponse>),
+    ExecuteByPath(Result<ExecuteByPathResponse, ErrorResponse>),
+    ExecuteChain(Result<ExecuteChainResponse, ErrorResponse>),
+    ListBinaries(Result<ListBinariesResponse, ErrorResponse>),
+    UnloadBinary(Result<UnloadBinaryResponse, ErrorResponse>),
+    DescribeBinary(Result<DescribeBinaryResponse, ErrorResponse>),
+    SetDefaultConfig(Result<SetDefaultConfigResponse, ErrorResponse>),
+    GetAuditLog(Result<GetAuditLogResponse, ErrorResponse>),
+    GetTraces(Result<GetTracesResponse, ErrorResponse>),
+    GetHistory(Result<GetHistoryResponse, ErrorResponse>),
+    GetBinaryBytes(Result<GetBinaryBytesResponse, ErrorResponse>),
+    ExportTrace(Result<ExportTraceResponse, ErrorResponse>),
+    Replay(Result<ReplayResponse, ErrorResponse>),
+    StatsSnapshot(StatsSnapshot),
+    LogLine(LogLine),
+    OutputChunk(OutputChunk),
+    ReloadAll(Result<ReloadAllResponse, ErrorResponse>),
+    ExportBundle(Result<ExportBundleResponse, ErrorResponse>),
+    ImportBundle(Result<ImportBundleResponse, ErrorResponse>),
+    VerifyIntegrity(Result<VerifyIntegrityResponse, ErrorResponse>),
+    Info(Result<InfoResponse, ErrorResponse>),
+    Cancelled(Result<CancelResponse, ErrorResponse>),
+    Warmup(Result<WarmupResponse, ErrorResponse>),
+    Error(ErrorResponse),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builder_defaults() {
+        let config = ExecutionConfig::builder().build().unwrap();
+        assert_eq!(config.timeout_ms, 5000);
+        assert_eq!(config.memory_limit_mb, 64);
+        assert_eq!(config.fuel, None);
+    }
+
+    #[test]
+    fn test_builder_sets_values() {
+        let config = ExecutionConfig::builder()
+            .timeout(100)
+            .memory_mb(16)
+            .fuel(42)
+            .build()
+            .unwrap();
+        assert_eq!(config.timeout_ms, 100);
+        assert_eq!(config.memory_limit_mb, 16);
+        assert_eq!(config.fuel, Some(42));
+    }
+
+    #[test]
+    fn test_builder_rejects_zero_timeout() {
+        let err = ExecutionConfig::builder().timeout(0).build().unwrap_err();
+        assert_eq!(err, ConfigError::ZeroTimeout);
+    }
+
+    #[test]
+    fn test_builder_rejects_huge_timeout() {
+        let err = ExecutionConfig::builder()
+            .timeout(MAX_TIMEOUT_MS + 1)
+            .build()
+            .unwrap_err();
+        assert_eq!(err, ConfigError::TimeoutTooLarge(MAX_TIMEOUT_MS + 1));
+    }
+
+    #[test]
+    fn test_builder_rejects_zero_memory() {
+        let err = ExecutionConfig::builder().memory_mb(0).build().unwrap_err();
+        assert_eq!(err, ConfigError::ZeroMemoryLimit);
+    }
+
+    #[test]
+    fn test_builder_rejects_zero_max_output() {
+        let err = ExecutionConfig::builder()
+            .max_output_bytes(0)
+            .build()
+            .unwrap_err();
+        assert_eq!(err, ConfigError::ZeroMaxOutputBytes);
+    }
+
+    #[test]
+    fn test_builder_rejects_huge_memory() {
+        let err = ExecutionConfig::builder()
+            .memory_mb(MAX_MEMORY_LIMIT_MB + 1)
+            .build()
+            .unwrap_err();
+        assert_eq!(
+            err,
+            ConfigError::MemoryLimitTooLarge(MAX_MEMORY_LIMIT_MB + 1)
+        );
+    }
+
+    #[test]
+    fn test_builder_rejects_zero_fuel() {
+        let err = ExecutionConfig::builder().fuel(0).build().unwrap_err();
+        assert_eq!(err, ConfigError::ZeroFuel);
+    }
+
+    #[test]
+    fn test_validate_deserialized_config() {
+        let config: ExecutionConfig =
+            serde_json::from_str(r#"{"timeout_ms":0,"memory_limit_mb":64}"#).unwrap();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_memory_growth_allowed_unless_disabled() {
+        let config: ExecutionConfig =
+            serde_json::from_str(r#"{"timeout_ms":100,"memory_limit_mb":64}"#).unwrap();
+        assert!(config.allow_memory_growth);
+        let config = ExecutionConfig::builder()
+            .allow_memory_growth(false)
+            .build()
+            .unwrap();
+        assert!(!config.allow_memory_growth);
+    }
+
+    #[test]
+    fn test_error_codes_survive_round_trip() {
+        let codes = [
+            ErrorCode::NotFound,
+            ErrorCode::InvalidRequest,
+            ErrorCode::InvalidBinary,
+            ErrorCode::VerificationFailed,
+            ErrorCode::Timeout,
+            ErrorCode::OutOfFuel,
+            ErrorCode::MemoryLimit,
+            ErrorCode::Cancelled,
+            ErrorCode::Unauthorized,
+            ErrorCode::UnsupportedProtocol,
+            ErrorCode::Internal,
+        ];
+        for code in codes {
+            let response = Response::Execute(Err(ErrorResponse::new(code, "boom")));
+            let json = serde_json::to_string(&response).unwrap();
+            match serde_json::from_str(&json).unwrap() {
+                Response::Execute(Err(e)) => {
+                    assert_eq!(e, ErrorResponse::new(code, "boom"));
+                }
+                other => panic!("unexpected response: {:?}", other),
+            }
+        }
+
+        let json = serde_json::to_string(&ErrorResponse::new(ErrorCode::OutOfFuel, "")).unwrap();
+        assert_eq!(json, r#"{"code":"out_of_fuel","message":""}"#);
+    }
 }
@@ -1,3 +1,10 @@
+//! Boilerplate every plugin needs: host logging, input reading, writing the
+//! result, an allocator and the `alloc` export. Plugins depend on this crate
+//! with `default-features = false, features = ["no_std"]`; the host imports
+//! only exist on `wasm32`.
+
+use core::fmt;
+
 /// Error handling utilities for WASM plugins
 /// This module provides safe error handling and reporting for no_std plugins
 /// Error codes for plugin execution
@@ -15,13 +22,393 @@ pub const SUCCESS: i32 = 0;
 /// Error result wrapper for plugin operations
 pub type PluginResult<T> = Result<T, i32>;
 
+/// Longest message `log!` sends; longer ones are cut short
+pub const LOG_BUFFER_SIZE: usize = 256;
+
+#[cfg(target_arch = "wasm32")]
+mod host {
+    #[link(wasm_import_module = "host")]
+    extern "C" {
+        pub fn log(ptr: *const u8, len: usize);
+        pub fn write_output(ptr: *const u8, len: usize);
+        pub fn read_input_chunk(ptr: *mut u8, len: usize) -> usize;
+        pub fn scratch_read(ptr: *mut u8, len: usize) -> usize;
+        pub fn scratch_write(ptr: *const u8, len: usize);
+        pub fn sha256(ptr: *const u8, len: usize, out_ptr: *mut u8);
+        pub fn blake3(ptr: *const u8, len: usize, out_ptr: *mut u8);
+    }
+}
+
+/// Send `message` to the host's log
+#[cfg(target_arch = "wasm32")]
+pub fn log_message(message: &str) {
+    unsafe {
+        host::log(message.as_ptr(), message.len());
+    }
+}
+
+/// Hand `result` to the host as the execution's output. It reaches the
+/// client and the next step of a chain as is, instead of being picked out of
+/// the logs. Calling it again appends to what was written before.
+#[cfg(target_arch = "wasm32")]
+pub fn write_result(result: &str) {
+    write_result_bytes(result.as_bytes());
+}
+
+/// `write_result` for output that needn't be UTF-8, e.g. decoded bytes
+#[cfg(target_arch = "wasm32")]
+pub fn write_result_bytes(result: &[u8]) {
+    unsafe {
+        host::write_output(result.as_ptr(), result.len());
+    }
+}
+
+/// Copy the next part of the input into `buf` and return how many bytes
+/// were copied, 0 once it has all been read. A plugin that uses this gets no
+/// input at `process`'s input pointer, only its length, so the input can be
+/// bigger than the plugin's memory.
+#[cfg(target_arch = "wasm32")]
+pub fn read_input_chunk(buf: &mut [u8]) -> usize {
+    unsafe { host::read_input_chunk(buf.as_mut_ptr(), buf.len()) }
+}
+
+/// Copy the start of the scratch buffer into `buf` and return the buffer's
+/// full length. It holds what the last `write_scratch` left there: this
+/// run's, or an earlier step's in a chain sent with `persist_host_state`.
+#[cfg(target_arch = "wasm32")]
+pub fn read_scratch(buf: &mut [u8]) -> usize {
+    unsafe { host::scratch_read(buf.as_mut_ptr(), buf.len()) }
+}
+
+/// Replace the scratch buffer with `data`, at most 64 KiB
+#[cfg(target_arch = "wasm32")]
+pub fn write_scratch(data: &[u8]) {
+    unsafe { host::scratch_write(data.as_ptr(), data.len()) }
+}
+
+/// SHA-256 of `data`, computed by the host. The plugin's manifest must list
+/// the `crypto` capability or the host refuses to run it.
+#[cfg(target_arch = "wasm32")]
+pub fn host_sha256(data: &[u8]) -> [u8; 32] {
+    let mut digest = [0; 32];
+    unsafe { host::sha256(data.as_ptr(), data.len(), digest.as_mut_ptr()) };
+    digest
+}
+
+/// BLAKE3 of `data`, computed by the host; needs `crypto` like `host_sha256`
+#[cfg(target_arch = "wasm32")]
+pub fn host_blake3(data: &[u8]) -> [u8; 32] {
+    let mut digest = [0; 32];
+    unsafe { host::blake3(data.as_ptr(), data.len(), digest.as_mut_ptr()) };
+    digest
+}
+
+/// Format `args` and log the result; what `log!` expands to
+#[cfg(target_arch = "wasm32")]
+pub fn log_args(args: fmt::Arguments<'_>) {
+    match args.as_str() {
+        Some(message) => log_message(message),
+        None => {
+            let mut buffer = FmtBuffer::<LOG_BUFFER_SIZE>::new();
+            let _ = fmt::Write::write_fmt(&mut buffer, args);
+            log_message(buffer.as_str());
+        }
+    }
+}
+
+/// `format!`-style logging through the host, without allocating:
+/// `log!("[Plugin] Read {} bytes", len)`
+#[macro_export]
+macro_rules! log {
+    ($($arg:tt)*) => {
+        $crate::plugin_helpers::log_args(::core::format_args!($($arg)*))
+    };
+}
+
+/// Fixed-capacity text buffer implementing `fmt::Write`. Text that doesn't
+/// fit is dropped, cutting only at a character boundary.
+pub struct FmtBuffer<const N: usize> {
+    bytes: [u8; N],
+    len: usize,
+}
+
+impl<const N: usize> FmtBuffer<N> {
+    pub const fn new() -> Self {
+        Self {
+            bytes: [0; N],
+            len: 0,
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        // Only whole characters are ever copied in
+        core::str::from_utf8(&self.bytes[..self.len]).unwrap_or_default()
+    }
+}
+
+impl<const N: usize> Default for FmtBuffer<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> fmt::Write for FmtBuffer<N> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let mut take = s.len().min(N - self.len);
+        while !s.is_char_boundary(take) {
+            take -= 1;
+        }
+        self.bytes[self.len..self.len + take].copy_from_slice(&s.as_bytes()[..take]);
+        self.len += take;
+        Ok(())
+    }
+}
+
+/// Append `n` in decimal to `s`, without allocating. Fails with
+/// `ERROR_BUFFER_OVERFLOW`, leaving `s` unchanged, if the digits don't fit.
+pub fn push_u64<const N: usize>(s: &mut heapless::String<N>, n: u64) -> PluginResult<()> {
+    // u64::MAX has 20 digits
+    let mut digits = [0u8; 20];
+    let mut start = digits.len();
+    let mut rest = n;
+    loop {
+        start -= 1;
+        digits[start] = b'0' + (rest % 10) as u8;
+        rest /= 10;
+        if rest == 0 {
+            break;
+        }
+    }
+    let digits = &digits[start..];
+    if s.capacity() - s.len() < digits.len() {
+        return Err(ERROR_BUFFER_OVERFLOW);
+    }
+    for &digit in digits {
+        let _ = s.push(digit as char);
+    }
+    Ok(())
+}
+
+/// `push_u64` for signed numbers: negative ones get a leading `-`
+pub fn push_i64<const N: usize>(s: &mut heapless::String<N>, n: i64) -> PluginResult<()> {
+    if n >= 0 {
+        return push_u64(s, n as u64);
+    }
+    let len = s.len();
+    s.push('-').map_err(|_| ERROR_BUFFER_OVERFLOW)?;
+    push_u64(s, n.unsigned_abs()).inspect_err(|_| s.truncate(len))
+}
+
+/// Map every character of `input` through `f` into a string of capacity
+/// `N`. Unlike `collect`, which silently stops at the capacity, this fails
+/// with `ERROR_BUFFER_OVERFLOW` when the result doesn't fit.
+pub fn try_transform<const N: usize>(
+    input: &str,
+    mut f: impl FnMut(char) -> char,
+) -> PluginResult<heapless::String<N>> {
+    let mut output = heapless::String::new();
+    for c in input.chars() {
+        output.push(f(c)).map_err(|_| ERROR_BUFFER_OVERFLOW)?;
+    }
+    Ok(output)
+}
+
+/// The `len` bytes at `ptr` as a string: `ERROR_INVALID_INPUT` if `ptr` is
+/// null (unless `len` is 0), `ERROR_INVALID_UTF8` if they aren't UTF-8.
+///
+/// Meant for the input and env pointers the host passes to `process`, which
+/// stay readable for the whole call. A plugin that takes its input at offset
+/// 0 (exporting neither `alloc` nor `__input_base`) can't use it, since that
+/// address is null.
+///
+/// # Safety
+///
+/// Unless `ptr` is null, it must point to `len` initialized bytes that are
+/// not written to while the returned string, whose lifetime the caller
+/// picks, is in use.
+pub unsafe fn read_str<'a>(ptr: *const u8, len: usize) -> PluginResult<&'a str> {
+    if ptr.is_null() {
+        return match len {
+            0 => Ok(""),
+            _ => Err(ERROR_INVALID_INPUT),
+        };
+    }
+    let bytes = core::slice::from_raw_parts(ptr, len);
+    core::str::from_utf8(bytes).map_err(|_| ERROR_INVALID_UTF8)
+}
+
+/// Global allocator for plugins that never allocate: every request fails.
+/// Install it with `#[global_allocator] static ALLOCATOR: DummyAllocator =
+/// DummyAllocator;`.
+pub struct DummyAllocator;
+
+unsafe impl core::alloc::GlobalAlloc for DummyAllocator {
+    unsafe fn alloc(&self, _layout: core::alloc::Layout) -> *mut u8 {
+        core::ptr::null_mut()
+    }
+    unsafe fn dealloc(&self, _ptr: *mut u8, _layout: core::alloc::Layout) {}
+}
+
+/// Define a `size`-byte static scratch region and export the `alloc` the
+/// host calls to place input and env there, keeping them clear of the stack
+#[macro_export]
+macro_rules! plugin_io_buffer {
+    ($size:expr) => {
+        static mut IO_BUFFER: [u8; $size] = [0; $size];
+
+        /// Reserve `len` bytes for the host's input and env writes
+        #[no_mangle]
+        pub extern "C" fn alloc(len: usize) -> *mut u8 {
+            if len > $size {
+                return ::core::ptr::null_mut();
+            }
+            ::core::ptr::addr_of_mut!(IO_BUFFER) as *mut u8
+        }
+    };
+}
+
+/// Define the plugin's panic handler, which logs `[<tag>] PANIC occurred!`
+/// and spins until the host's fuel or deadline stops it
+#[macro_export]
+macro_rules! plugin_panic_handler {
+    ($tag:literal) => {
+        #[panic_handler]
+        fn panic(_info: &::core::panic::PanicInfo) -> ! {
+            $crate::plugin_helpers::log_message(::core::concat!("[", $tag, "] PANIC occurred!"));
+            loop {}
+        }
+    };
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use core::fmt::Write;
 
     #[test]
     fn test_error_codes() {
         assert_eq!(ERROR_INVALID_UTF8, -1);
         assert_eq!(SUCCESS, 0);
     }
+
+    #[test]
+    fn test_fmt_buffer_truncates_at_char_boundary() {
+        let mut buffer = FmtBuffer::<8>::new();
+        let letters = "ab";
+        write!(buffer, "{}-{}", 12, letters).unwrap();
+        assert_eq!(buffer.as_str(), "12-ab");
+        // 'é' is two bytes and only one is left after "12-abcd"
+        buffer.write_str("cdé").unwrap();
+        assert_eq!(buffer.as_str(), "12-abcd");
+    }
+
+    #[test]
+    fn test_push_u64() {
+        let mut s: heapless::String<32> = heapless::String::new();
+        push_u64(&mut s, 0).unwrap();
+        assert_eq!(s, "0");
+
+        s.clear();
+        push_u64(&mut s, 1234).unwrap();
+        assert_eq!(s, "1234");
+
+        s.clear();
+        push_u64(&mut s, u64::MAX).unwrap();
+        assert_eq!(s, "18446744073709551615");
+
+        // Too long for what's left: nothing is written
+        let mut short: heapless::String<6> = heapless::String::new();
+        short.push_str("n=").unwrap();
+        assert_eq!(push_u64(&mut short, 12345), Err(ERROR_BUFFER_OVERFLOW));
+        assert_eq!(short, "n=");
+        push_u64(&mut short, 1234).unwrap();
+        assert_eq!(short, "n=1234");
+    }
+
+    #[test]
+    fn test_push_i64() {
+        let mut s: heapless::String<32> = heapless::String::new();
+        push_i64(&mut s, -42).unwrap();
+        assert_eq!(s, "-42");
+
+        s.clear();
+        push_i64(&mut s, 7).unwrap();
+        assert_eq!(s, "7");
+
+        s.clear();
+        push_i64(&mut s, i64::MIN).unwrap();
+        assert_eq!(s, "-9223372036854775808");
+
+        let mut short: heapless::String<3> = heapless::String::new();
+        assert_eq!(push_i64(&mut short, -123), Err(ERROR_BUFFER_OVERFLOW));
+        assert_eq!(short, "");
+    }
+
+    #[test]
+    fn test_try_transform() {
+        let upper = try_transform::<8>("héllo", |c| c.to_ascii_uppercase()).unwrap();
+        assert_eq!(upper, "HéLLO");
+        // Exactly full is fine; one more character is not
+        assert_eq!(try_transform::<4>("abcd", |c| c).unwrap(), "abcd");
+        assert_eq!(
+            try_transform::<4>("abcde", |c| c),
+            Err(ERROR_BUFFER_OVERFLOW)
+        );
+    }
+
+    #[test]
+    fn test_read_str_valid() {
+        let input = "héllo".as_bytes();
+        assert_eq!(
+            unsafe { read_str(input.as_ptr(), input.len()) },
+            Ok("héllo")
+        );
+        assert_eq!(unsafe { read_str(input.as_ptr(), 0) }, Ok(""));
+    }
+
+    #[test]
+    fn test_read_str_null_pointer() {
+        assert_eq!(
+            unsafe { read_str(core::ptr::null(), 5) },
+            Err(ERROR_INVALID_INPUT)
+        );
+        assert_eq!(unsafe { read_str(core::ptr::null(), 0) }, Ok(""));
+    }
+
+    #[test]
+    fn test_read_str_invalid_utf8() {
+        let invalid = [b'o', b'k', 0xff, 0xfe];
+        assert_eq!(
+            unsafe { read_str(invalid.as_ptr(), invalid.len()) },
+            Err(ERROR_INVALID_UTF8)
+        );
+        // A multi-byte character cut in half
+        let cut = "é".as_bytes();
+        assert_eq!(
+            unsafe { read_str(cut.as_ptr(), 1) },
+            Err(ERROR_INVALID_UTF8)
+        );
+    }
+
+    /// The helpers have to build for the target plugins run on, without std.
+    /// Building two plugins that use them covers the crate and the macros.
+    #[test]
+    fn test_helpers_build_for_wasm32() {
+        let workspace = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("..");
+        let target_dir = std::env::temp_dir().join("wasm-shared-helpers-target");
+        let cargo = std::env::var("CARGO").unwrap_or_else(|_| "cargo".to_string());
+        let output = std::process::Command::new(cargo)
+            .args(["build", "--release", "--target", "wasm32-unknown-unknown"])
+            .args(["-p", "plugin-rot13", "-p", "plugin-uppercase"])
+            .arg("--manifest-path")
+            .arg(workspace.join("Cargo.toml"))
+            .env("CARGO_TARGET_DIR", target_dir)
+            .output()
+            .unwrap();
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
 }
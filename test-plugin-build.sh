@@ -3,7 +3,7 @@
 echo "Testing plugin builds..."
 echo ""
 
-for plugin in plugin-example plugin-uppercase plugin-counter plugin-rot13 plugin-env-reader; do
+for plugin in plugin-example plugin-reverser plugin-uppercase plugin-counter plugin-rot13 plugin-env-reader plugin-base64 plugin-json-transform plugin-lowercase plugin-fuel-budget plugin-sha256 plugin-wordcount plugin-rate-limiter plugin-urlencode plugin-multi-output plugin-memory-grow plugin-hexdump plugin-crc32 plugin-host-sha256; do
     echo "=== Testing $plugin ==="
     cd "$plugin"
     
@@ -99,6 +99,34 @@ async fn test_execute_single_binary() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn test_preprocess_trims_input() -> Result<()> {
+    println!("?? Test: Input Preprocessing");
+
+    let _server = CoreServer::start()?;
+    sleep(Duration::from_secs(2)).await;
+
+    let mut client = create_client().await?;
+
+    let binary_id = client
+        .load_binary("./plugins/reverser.wasm".to_string())
+        .await?
+        .binary_id;
+
+    // Without trimming the padding would end up reversed around the word
+    let config = ExecutionConfig::builder()
+        .preprocess(Preprocessor::TrimWhitespace)
+        .build()?;
+    let response = client
+        .execute(binary_id, "  hello \n".to_string(), Some(config))
+        .await?;
+
+    assert_eq!(response.result.output, "olleh");
+    assert_eq!(response.result.return_code, 0);
+
+    Ok(())
+}
+
 #[tokio::test]
 async fn test_execute_chain() -> Result<()> {
     println!("?? Test: Execute Chain");
@@ -136,6 +164,667 @@ async fn test_execute_chain() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn test_execute_chain_through_example_plugin() -> Result<()> {
+    println!("?? Test: Execute Chain Through Example Plugin");
+
+    let _server = CoreServer::start()?;
+    sleep(Duration::from_secs(2)).await;
+
+    let mut client = create_client().await?;
+
+    let example_id = client
+        .load_binary("./plugins/example.wasm".to_string())
+        .await?
+        .binary_id;
+
+    let reverser_id = client
+        .load_binary("./plugins/reverser.wasm".to_string())
+        .await?
+        .binary_id;
+
+    // example reverses, reverser reverses back: the env must never leak into the data
+    let response = client
+        .execute_chain(vec![example_id, reverser_id], "hello".to_string(), None)
+        .await?;
+
+    assert_eq!(response.results.len(), 2);
+    assert_eq!(response.results[0].output, "olleh");
+    assert!(!response.results[0].output.contains("timestamp"));
+    assert_eq!(response.results[1].output, "hello");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_chain_persists_host_state() -> Result<()> {
+    println!("?? Test: Chain With Persisted Host State");
+
+    let _server = CoreServer::start()?;
+    sleep(Duration::from_secs(2)).await;
+
+    let mut client = create_client().await?;
+
+    let counter_id = client
+        .load_binary("./plugins/counter.wasm".to_string())
+        .await?
+        .binary_id;
+
+    let chain = |persist_host_state| ExecuteChainRequest {
+        binary_ids: vec![counter_id, counter_id],
+        input: "hello".to_string(),
+        config: None,
+        env: EnvVars::new(),
+        step_env: Vec::new(),
+        namespace: None,
+        persist_host_state,
+    };
+
+    let fresh = client.execute_chain_with(chain(false)).await?;
+    let persisted = client.execute_chain_with(chain(true)).await?;
+
+    // The second step counts the first step's summary either way
+    let summary = "Total: 45 | Letters: 24 | Digits: 4 | Spaces: 10";
+    assert_eq!(fresh.results[1].output, summary);
+    assert_eq!(persisted.results[1].output, summary);
+
+    // The running count lives in the plugin's scratch buffer: without
+    // persistence the second step starts from zero, with it from the 5
+    // characters of "hello" the first step counted
+    let counted = |result: &ExecutionResult| {
+        result
+            .logs
+            .iter()
+            .find_map(|line| line.strip_prefix("[Counter] Characters counted so far: "))
+            .map(str::to_string)
+    };
+    assert_eq!(counted(&fresh.results[1]).as_deref(), Some("45"));
+    assert_eq!(counted(&persisted.results[0]).as_deref(), Some("5"));
+    assert_eq!(counted(&persisted.results[1]).as_deref(), Some("50"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_base64_round_trip() -> Result<()> {
+    println!("?? Test: Base64 Round Trip");
+
+    let _server = CoreServer::start()?;
+    sleep(Duration::from_secs(2)).await;
+
+    let mut client = create_client().await?;
+
+    let base64_id = client
+        .load_binary("./plugins/base64.wasm".to_string())
+        .await?
+        .binary_id;
+
+    let input = "hello, wasm! \u{1F980}".to_string();
+    let mut decode = EnvVars::new();
+    decode.insert("mode".to_string(), "decode".to_string());
+    let response = client
+        .execute_chain_with(ExecuteChainRequest {
+            binary_ids: vec![base64_id, base64_id],
+            input: input.clone(),
+            config: None,
+            env: EnvVars::new(),
+            step_env: vec![EnvVars::new(), decode],
+            namespace: None,
+            persist_host_state: false,
+        })
+        .await?;
+
+    assert_eq!(response.results.len(), 2);
+    assert_eq!(response.results[0].output, "aGVsbG8sIHdhc20hIPCfpoA=");
+    assert_eq!(response.results[1].output, input);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_urlencode_round_trip() -> Result<()> {
+    println!("?? Test: URL Encode Round Trip");
+
+    let _server = CoreServer::start()?;
+    sleep(Duration::from_secs(2)).await;
+
+    let mut client = create_client().await?;
+
+    let urlencode_id = client
+        .load_binary("./plugins/urlencode.wasm".to_string())
+        .await?
+        .binary_id;
+
+    let mut decode = EnvVars::new();
+    decode.insert("mode".to_string(), "decode".to_string());
+    for input in [
+        "hello world",
+        "a/b?c=d&e=f#frag",
+        "100% sure: [x] + {y} ~ok_-.",
+        "caf\u{e9} \u{1F980}",
+    ] {
+        let response = client
+            .execute_chain_with(ExecuteChainRequest {
+                binary_ids: vec![urlencode_id, urlencode_id],
+                input: input.to_string(),
+                config: None,
+                env: EnvVars::new(),
+                step_env: vec![EnvVars::new(), decode.clone()],
+                namespace: None,
+                persist_host_state: false,
+            })
+            .await?;
+
+        let encoded = &response.results[0].output;
+        println!("? {:?} -> {}", input, encoded);
+        assert!(encoded
+            .bytes()
+            .all(|c| c.is_ascii_alphanumeric() || b"-._~%".contains(&c)));
+        assert_eq!(response.results[1].output, input);
+    }
+
+    let response = client
+        .execute_chain_with(ExecuteChainRequest {
+            binary_ids: vec![urlencode_id],
+            input: "a b/c".to_string(),
+            config: None,
+            env: EnvVars::new(),
+            step_env: Vec::new(),
+            namespace: None,
+            persist_host_state: false,
+        })
+        .await?;
+    assert_eq!(response.results[0].output, "a%20b%2Fc");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_json_transform_projects_fields() -> Result<()> {
+    println!("?? Test: JSON Transform");
+
+    let _server = CoreServer::start()?;
+    sleep(Duration::from_secs(2)).await;
+
+    let mut client = create_client().await?;
+
+    let transform_id = client
+        .load_binary("./plugins/json-transform.wasm".to_string())
+        .await?
+        .binary_id;
+
+    let mut env = EnvVars::new();
+    env.insert("fields".to_string(), "name,tags,missing".to_string());
+    let response = client
+        .execute_with(ExecuteRequest {
+            binary_id: transform_id,
+            deadline_unix_ms: None,
+            priority: 0,
+            input: r#"{"id": 7, "name": "a,b}", "tags": ["x", {"y": "]"}], "extra": null}"#
+                .to_string(),
+            config: None,
+            stream: false,
+            request_id: None,
+            env,
+            correlation_id: None,
+            namespace: None,
+            encoding: Encoding::Utf8,
+        })
+        .await?;
+
+    println!("? Output: {}", response.result.output);
+    assert_eq!(response.result.return_code, 0);
+    assert_eq!(
+        response.result.output,
+        r#"{"name":"a,b}","tags":["x", {"y": "]"}]}"#
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_deterministic_env_reader() -> Result<()> {
+    println!("?? Test: Deterministic Env Reader");
+
+    let _server = CoreServer::start()?;
+    sleep(Duration::from_secs(2)).await;
+
+    let mut client = create_client().await?;
+
+    let reader_id = client
+        .load_binary("./plugins/env-reader.wasm".to_string())
+        .await?
+        .binary_id;
+
+    let config = ExecutionConfig::builder().deterministic(true).build()?;
+    let first = client
+        .execute(reader_id, String::new(), Some(config.clone()))
+        .await?;
+    let second = client
+        .execute(reader_id, String::new(), Some(config))
+        .await?;
+
+    println!("? Output: {}", first.result.output);
+    assert_eq!(first.result.return_code, 0);
+    assert_eq!(first.result.output, second.result.output);
+    assert!(first
+        .result
+        .output
+        .ends_with("timestamp = 0 random_seed = 0"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_env_reader_with_empty_input() -> Result<()> {
+    println!("?? Test: Env Reader With Empty Input");
+
+    let _server = CoreServer::start()?;
+    sleep(Duration::from_secs(2)).await;
+
+    let mut client = create_client().await?;
+
+    let reader_id = client
+        .load_binary("./plugins/env-reader.wasm".to_string())
+        .await?
+        .binary_id;
+
+    // The env still parses when there is no input ahead of it
+    let response = client.execute(reader_id, String::new(), None).await?;
+
+    println!("? Output: {}", response.result.output);
+    assert_eq!(response.result.return_code, 0);
+    assert!(response.result.output.contains("timestamp = "));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_length_return_output() -> Result<()> {
+    println!("?? Test: Length-Return Output");
+
+    let _server = CoreServer::start()?;
+    sleep(Duration::from_secs(2)).await;
+
+    let mut client = create_client().await?;
+
+    let lowercase_id = client
+        .load_binary("./plugins/lowercase.wasm".to_string())
+        .await?
+        .binary_id;
+
+    let response = client
+        .execute(lowercase_id, "Hello WASM".to_string(), None)
+        .await?;
+
+    assert_eq!(response.result.return_code, 0);
+    assert_eq!(response.result.output, "hello wasm");
+    assert!(response.result.from_output_buffer);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_fuel_budget_plugin() -> Result<()> {
+    println!("?? Test: Fuel Budget");
+
+    let _server = CoreServer::start()?;
+    sleep(Duration::from_secs(2)).await;
+
+    let mut client = create_client().await?;
+
+    let budget_id = client
+        .load_binary("./plugins/fuel-budget.wasm".to_string())
+        .await?
+        .binary_id;
+
+    let input = "x".repeat(1000);
+    let config = ExecutionConfig::builder().fuel(1_000_000).build()?;
+    let response = client
+        .execute(budget_id, input.clone(), Some(config))
+        .await?;
+
+    println!("? Logs: {:?}", response.result.logs);
+    assert_eq!(response.result.return_code, 0);
+    assert_eq!(response.result.output, input);
+    let start_fuel: u64 = response.result.logs[0]
+        .strip_prefix("[Fuel-Budget] Start fuel: ")
+        .unwrap()
+        .parse()?;
+    assert!(start_fuel <= 1_000_000 && start_fuel > 1_000_000 - 100);
+
+    // Too little fuel for the whole input: stops cleanly instead of trapping
+    let input = "x".repeat(3000);
+    let config = ExecutionConfig::builder().fuel(7_000).build()?;
+    let response = client
+        .execute(budget_id, input.clone(), Some(config))
+        .await?;
+
+    assert_eq!(response.result.return_code, -4);
+    assert!(response.result.output.len() < input.len());
+    assert!(input.starts_with(&response.result.output));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_sha256_plugin() -> Result<()> {
+    println!("?? Test: SHA-256");
+
+    let _server = CoreServer::start()?;
+    sleep(Duration::from_secs(2)).await;
+
+    let mut client = create_client().await?;
+
+    let sha256_id = client
+        .load_binary("./plugins/sha256.wasm".to_string())
+        .await?
+        .binary_id;
+
+    let response = client
+        .execute(sha256_id, "hello world".to_string(), None)
+        .await?;
+
+    println!("? Output: {}", response.result.output);
+    assert_eq!(response.result.return_code, 0);
+    assert_eq!(
+        response.result.output,
+        "b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9"
+    );
+
+    // Hashing more input costs more fuel
+    let longer = client
+        .execute(sha256_id, "hello world".repeat(100), None)
+        .await?;
+    assert!(longer.result.fuel_consumed > response.result.fuel_consumed);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_counter_writes_result_to_output_buffer() -> Result<()> {
+    println!("?? Test: Counter Output Buffer");
+
+    let _server = CoreServer::start()?;
+    sleep(Duration::from_secs(2)).await;
+
+    let mut client = create_client().await?;
+
+    let counter_id = client
+        .load_binary("./plugins/counter.wasm".to_string())
+        .await?
+        .binary_id;
+
+    let response = client
+        .execute(counter_id, "Hello 123".to_string(), None)
+        .await?;
+
+    let summary = "Total: 9 | Letters: 5 | Digits: 3 | Spaces: 1";
+    assert_eq!(response.result.return_code, 0);
+    assert!(response.result.from_output_buffer);
+    assert_eq!(response.result.output, summary);
+    // The logs narrate the run but don't carry the result
+    assert!(!response.result.logs.is_empty());
+    assert!(!response.result.logs.iter().any(|log| log.contains(summary)));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_wordcount_plugin() -> Result<()> {
+    println!("?? Test: Word Count");
+
+    let _server = CoreServer::start()?;
+    sleep(Duration::from_secs(2)).await;
+
+    let mut client = create_client().await?;
+
+    let wordcount_id = client
+        .load_binary("./plugins/wordcount.wasm".to_string())
+        .await?
+        .binary_id;
+
+    let response = client
+        .execute(
+            wordcount_id,
+            "the cat saw the \"dog\"\n and the cat ran".to_string(),
+            None,
+        )
+        .await?;
+
+    println!("? Output: {}", response.result.output);
+    assert_eq!(response.result.return_code, 0);
+    assert_eq!(
+        response.result.output,
+        r#"{"the":3,"cat":2,"saw":1,"\"dog\"":1,"and":1,"ran":1}"#
+    );
+    let counts: serde_json::Value = serde_json::from_str(&response.result.output)?;
+    assert_eq!(counts["the"], 3);
+    assert_eq!(counts["\"dog\""], 1);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_rate_limiter_plugin() -> Result<()> {
+    println!("?? Test: Rate Limiter");
+
+    let _server = CoreServer::start()?;
+    sleep(Duration::from_secs(2)).await;
+
+    let mut client = create_client().await?;
+
+    let limiter_id = client
+        .load_binary("./plugins/rate-limiter.wasm".to_string())
+        .await?
+        .binary_id;
+
+    // The key is unique to this run so other tests can't share the counter
+    let key = Uuid::new_v4().to_string();
+    for expected in 1..=3 {
+        let response = client.execute(limiter_id, key.clone(), None).await?;
+        println!("? Logs: {:?}", response.result.logs);
+        assert_eq!(response.result.return_code, 0);
+        assert_eq!(response.result.output, expected.to_string());
+        assert_eq!(
+            response.result.logs,
+            vec![format!("[Rate-Limiter] {} = {}", key, expected)]
+        );
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_multi_output_channels() -> Result<()> {
+    println!("?? Test: Multi-Output Channels");
+
+    let _server = CoreServer::start()?;
+    sleep(Duration::from_secs(2)).await;
+
+    let mut client = create_client().await?;
+
+    let multi_id = client
+        .load_binary("./plugins/multi-output.wasm".to_string())
+        .await?
+        .binary_id;
+
+    let response = client
+        .execute(multi_id, "  hello world \n".to_string(), None)
+        .await?;
+    println!("? Outputs: {:?}", response.result.outputs);
+    assert_eq!(response.result.return_code, 0);
+    assert_eq!(response.result.outputs.len(), 2);
+    assert_eq!(response.result.outputs["result"], "hello world");
+    assert_eq!(
+        response.result.outputs["debug"],
+        "input_bytes=15 trimmed_bytes=11"
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_memory_growth_can_be_forbidden() -> Result<()> {
+    println!("?? Test: Forbidden Memory Growth");
+
+    let _server = CoreServer::start()?;
+    sleep(Duration::from_secs(2)).await;
+
+    let mut client = create_client().await?;
+
+    let grow_id = client
+        .load_binary("./plugins/memory-grow.wasm".to_string())
+        .await?
+        .binary_id;
+
+    let response = client.execute(grow_id, "2".to_string(), None).await?;
+    println!("? Logs: {:?}", response.result.logs);
+    assert_eq!(response.result.return_code, 0);
+    assert!(response.result.output.parse::<u64>()? > 2);
+
+    let config = ExecutionConfig::builder()
+        .allow_memory_growth(false)
+        .build()?;
+    let err = client
+        .execute(grow_id, "2".to_string(), Some(config))
+        .await
+        .unwrap_err();
+    let err = err.downcast_ref::<ErrorResponse>().unwrap();
+    assert_eq!(err.code, ErrorCode::MemoryLimit);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_hexdump_layout() -> Result<()> {
+    println!("?? Test: Hexdump Layout");
+
+    /// What `hexdump -C` prints for `HEXDUMP_INPUT`
+    const HEXDUMP_FIXTURE: &str = "\
+00000000  48 65 6c 6c 6f 2c 20 68  65 78 64 75 6d 70 21 0a  |Hello, hexdump!.|
+00000010  00 01 ff 7f 6f 6b                                 |....ok|
+00000016
+";
+
+    /// `Hello, hexdump!\n` followed by bytes that aren't valid UTF-8
+    const HEXDUMP_INPUT: &str = "48656c6c6f2c2068657864756d70210a0001ff7f6f6b";
+
+    let _server = CoreServer::start()?;
+    sleep(Duration::from_secs(2)).await;
+
+    let mut client = create_client().await?;
+
+    let hexdump_id = client
+        .load_binary("./plugins/hexdump.wasm".to_string())
+        .await?
+        .binary_id;
+
+    // Hex in, so the plugin sees the raw bytes; the dump comes back hex too
+    let response = client
+        .execute_with(ExecuteRequest {
+            binary_id: hexdump_id,
+            deadline_unix_ms: None,
+            priority: 0,
+            input: HEXDUMP_INPUT.to_string(),
+            config: None,
+            stream: false,
+            request_id: None,
+            env: Default::default(),
+            correlation_id: None,
+            namespace: None,
+            encoding: Encoding::Hex,
+        })
+        .await?;
+    assert_eq!(response.result.return_code, 0);
+
+    let output = &response.result.output;
+    let dump = (0..output.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&output[i..i + 2], 16))
+        .collect::<Result<Vec<u8>, _>>()?;
+    let dump = String::from_utf8(dump)?;
+    println!("? Dump:\n{}", dump);
+    assert_eq!(dump, HEXDUMP_FIXTURE);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_crc32_reads_large_input_in_chunks() -> Result<()> {
+    println!("?? Test: CRC-32 Over Chunked Input");
+
+    let _server = CoreServer::start()?;
+    sleep(Duration::from_secs(2)).await;
+
+    let mut client = create_client().await?;
+
+    let crc32_id = client
+        .load_binary("./plugins/crc32.wasm".to_string())
+        .await?
+        .binary_id;
+
+    // 1 MB: far more than the plugin's 4 KiB `alloc` buffer, or the single
+    // page of memory it starts with
+    let input = "0123456789".repeat(100_000);
+    let response = client.execute(crc32_id, input, None).await?;
+    println!("? CRC-32: {}", response.result.output);
+    assert_eq!(response.result.return_code, 0);
+    assert_eq!(response.result.output, "30e39c7f 1000000");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_host_sha256_digest() -> Result<()> {
+    println!("?? Test: SHA-256 Through The Host");
+
+    let _server = CoreServer::start()?;
+    sleep(Duration::from_secs(2)).await;
+
+    let mut client = create_client().await?;
+
+    // host-sha256.toml beside the binary grants the crypto capability
+    let sha256_id = client
+        .load_binary("./plugins/host-sha256.wasm".to_string())
+        .await?
+        .binary_id;
+
+    let response = client.execute(sha256_id, "abc".to_string(), None).await?;
+    println!("? Digest: {}", response.result.output);
+    assert_eq!(response.result.return_code, 0);
+    assert_eq!(
+        response.result.output,
+        "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_uppercase_reports_overflow() -> Result<()> {
+    println!("?? Test: Uppercase Overflow");
+
+    let _server = CoreServer::start()?;
+    sleep(Duration::from_secs(2)).await;
+
+    let mut client = create_client().await?;
+
+    let uppercase_id = client
+        .load_binary("./plugins/uppercase.wasm".to_string())
+        .await?
+        .binary_id;
+
+    // The result buffer holds 64 bytes: a longer input fails instead of
+    // coming back cut short
+    let response = client.execute(uppercase_id, "a".repeat(100), None).await?;
+
+    assert_eq!(response.result.return_code, -3);
+    assert!(!response.result.logs.iter().any(|log| log.contains("AAAA")));
+
+    Ok(())
+}
+
 #[tokio::test]
 async fn test_list_binaries() -> Result<()> {
     println!("?? Test: List Binaries");
@@ -234,6 +923,7 @@ async fn test_timeout() -> Result<()> {
     let config = Some(ExecutionConfig {
         timeout_ms: 1, // 1ms - very short
         memory_limit_mb: 64,
+        ..Default::default()
     });
 
     let result = client.execute(binary_id, "test".to_string(), config).await;
@@ -273,7 +963,12 @@ impl SocketClient {
     }
 
     pub async fn load_binary(&mut self, path: String) -> Result<LoadBinaryResponse> {
-        let command = Command::LoadBinary(LoadBinaryRequest { path });
+        let command = Command::LoadBinary(LoadBinaryRequest {
+            path,
+            signature: None,
+            default_config: None,
+            namespace: None,
+        });
         let response = {
             let this = &mut *self;
             async move {
@@ -302,11 +997,24 @@ impl SocketClient {
         input: String,
         config: Option<ExecutionConfig>,
     ) -> Result<ExecuteResponse> {
-        let command = Command::Execute(ExecuteRequest {
+        self.execute_with(ExecuteRequest {
+            stream: false,
+            request_id: None,
             binary_id,
             input,
             config,
-        });
+            env: Default::default(),
+            correlation_id: None,
+            namespace: None,
+            encoding: Encoding::Utf8,
+            deadline_unix_ms: None,
+            priority: 0,
+        })
+        .await
+    }
+
+    pub async fn execute_with(&mut self, request: ExecuteRequest) -> Result<ExecuteResponse> {
+        let command = Command::Execute(request);
         let response = {
             let this = &mut *self;
             async move {
@@ -335,11 +1043,23 @@ impl SocketClient {
         input: String,
         config: Option<ExecutionConfig>,
     ) -> Result<ExecuteChainResponse> {
-        let command = Command::ExecuteChain(ExecuteChainRequest {
+        self.execute_chain_with(ExecuteChainRequest {
             binary_ids,
             input,
             config,
-        });
+            env: Default::default(),
+            step_env: Vec::new(),
+            namespace: None,
+            persist_host_state: false,
+        })
+        .await
+    }
+
+    pub async fn execute_chain_with(
+        &mut self,
+        request: ExecuteChainRequest,
+    ) -> Result<ExecuteChainResponse> {
+        let command = Command::ExecuteChain(request);
         let response = {
             let this = &mut *self;
             async move {
@@ -363,7 +1083,7 @@ impl SocketClient {
     }
 
     pub async fn list_binaries(&mut self) -> Result<ListBinariesResponse> {
-        let command = Command::ListBinaries;
+        let command = Command::ListBinaries(ListBinariesRequest::default());
         let response = {
             let this = &mut *self;
             async move {
@@ -387,7 +1107,10 @@ impl SocketClient {
     }
 
     pub async fn unload_binary(&mut self, binary_id: Uuid) -> Result<UnloadBinaryResponse> {
-        let command = Command::UnloadBinary(UnloadBinaryRequest { binary_id });
+        let command = Command::UnloadBinary(UnloadBinaryRequest {
+            binary_id,
+            namespace: None,
+        });
         let response = {
             let this = &mut *self;
             async move {